@@ -0,0 +1,268 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Hierarchical deterministic derivation of keypairs from a master seed.
+//!
+//! Ed25519 keys are derived as specified by [SLIP-0010], which only supports hardened
+//! children, and Secp256k1 keys as specified by [BIP32]. The master seed is usually
+//! obtained from a [`Mnemonic`](super::mnemonic::Mnemonic).
+//!
+//! [SLIP-0010]: https://github.com/satoshilabs/slips/blob/master/slip-0010.md
+//! [BIP32]: https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki
+
+use super::error::DecodingError;
+use super::ed25519;
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+use std::{fmt, str::FromStr};
+use zeroize::Zeroize;
+
+/// Offset of the indices of hardened children.
+const HARDENED: u32 = 0x8000_0000;
+
+/// The index of a child key in a [`DerivationPath`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChildIndex {
+    /// A normal child, whose public key can be derived from the public key of its parent.
+    Normal(u32),
+    /// A hardened child, which can only be derived from the secret key of its parent.
+    Hardened(u32),
+}
+
+impl ChildIndex {
+    /// Returns the index as serialized in the derivation, i.e. with the high bit set
+    /// for hardened children.
+    fn to_bits(self) -> u32 {
+        match self {
+            ChildIndex::Normal(i) => i,
+            ChildIndex::Hardened(i) => i | HARDENED,
+        }
+    }
+}
+
+impl fmt::Display for ChildIndex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChildIndex::Normal(i) => write!(f, "{}", i),
+            ChildIndex::Hardened(i) => write!(f, "{}'", i),
+        }
+    }
+}
+
+/// A path from the master key to a derived key, e.g. `m/44'/0'/1'`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DerivationPath(Vec<ChildIndex>);
+
+impl DerivationPath {
+    /// Returns the path of the master key.
+    pub fn master() -> DerivationPath {
+        DerivationPath(Vec::new())
+    }
+
+    /// Returns the path of the given child of the key at this path.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is not lower than 2^31.
+    pub fn child(mut self, index: ChildIndex) -> DerivationPath {
+        match index {
+            ChildIndex::Normal(i) | ChildIndex::Hardened(i) =>
+                assert!(i < HARDENED, "child index out of range")
+        }
+        self.0.push(index);
+        self
+    }
+
+    /// Returns the indices of the path, starting from the master key.
+    pub fn indices(&self) -> &[ChildIndex] {
+        &self.0
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("m")?;
+        for index in &self.0 {
+            write!(f, "/{}", index)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = DecodingError;
+
+    /// Parses a path of the form `m/44'/0'/1`, where hardened indices are marked
+    /// with `'`, `h` or `H`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut components = s.split('/');
+        if components.next() != Some("m") {
+            return Err(DecodingError::new("Derivation path must start with `m`"))
+        }
+        components.map(|c| {
+            let hardened = c.ends_with(&['\'', 'h', 'H'][..]);
+            let digits = if hardened { &c[.. c.len() - 1] } else { c };
+            let index = digits.parse::<u32>().ok()
+                .filter(|i| i & HARDENED == 0 && digits.bytes().all(|b| b.is_ascii_digit()))
+                .ok_or_else(|| DecodingError::new(format!("Invalid child index: {}", c)))?;
+            Ok(if hardened { ChildIndex::Hardened(index) } else { ChildIndex::Normal(index) })
+        })
+        .collect::<Result<_, _>>()
+        .map(DerivationPath)
+    }
+}
+
+/// Derives the Ed25519 secret key at the given path from a master seed.
+pub(crate) fn ed25519(seed: &[u8], path: &DerivationPath) -> Result<ed25519::SecretKey, DecodingError> {
+    let (mut key, mut chain_code) = master(b"ed25519 seed", seed)?;
+    for index in path.indices() {
+        let index = match index {
+            ChildIndex::Hardened(_) => index.to_bits(),
+            ChildIndex::Normal(_) =>
+                return Err(DecodingError::new("Ed25519 keys only have hardened children"))
+        };
+        let (k, c) = split(hmac_sha512(&chain_code, &[&[0], &key, &index.to_be_bytes()]));
+        key.zeroize();
+        chain_code.zeroize();
+        key = k;
+        chain_code = c;
+    }
+    chain_code.zeroize();
+    ed25519::SecretKey::from_bytes(&mut key)
+}
+
+/// Derives the Secp256k1 secret key at the given path from a master seed.
+#[cfg(feature = "secp256k1")]
+pub(crate) fn secp256k1(seed: &[u8], path: &DerivationPath) -> Result<super::secp256k1::SecretKey, DecodingError> {
+    let invalid = || DecodingError::new("Derived an invalid Secp256k1 key");
+    let (mut key, mut chain_code) = master(b"Bitcoin seed", seed)?;
+    let mut secret = secp256k1::SecretKey::parse(&key).map_err(|_| invalid())?;
+    key.zeroize();
+    for index in path.indices() {
+        let (mut tweak, c) = match index {
+            ChildIndex::Hardened(_) => {
+                let mut key = secret.serialize();
+                let child = hmac_sha512(&chain_code, &[&[0], &key, &index.to_bits().to_be_bytes()]);
+                key.zeroize();
+                split(child)
+            }
+            ChildIndex::Normal(_) => {
+                let public = secp256k1::PublicKey::from_secret_key(&secret).serialize_compressed();
+                split(hmac_sha512(&chain_code, &[&public, &index.to_bits().to_be_bytes()]))
+            }
+        };
+        let parsed = secp256k1::SecretKey::parse(&tweak);
+        tweak.zeroize();
+        secret.tweak_add_assign(&parsed.map_err(|_| invalid())?).map_err(|_| invalid())?;
+        chain_code.zeroize();
+        chain_code = c;
+    }
+    chain_code.zeroize();
+    super::secp256k1::SecretKey::from_bytes(secret.serialize())
+}
+
+/// Computes the master key and chain code from a seed.
+fn master(curve: &[u8], seed: &[u8]) -> Result<([u8; 32], [u8; 32]), DecodingError> {
+    if !(16 ..= 64).contains(&seed.len()) {
+        return Err(DecodingError::new("Seeds must be between 16 and 64 bytes long"))
+    }
+    Ok(split(hmac_sha512(curve, &[seed])))
+}
+
+/// Splits the output of HMAC-SHA512 into a key and a chain code, zeroing it.
+fn split(mut output: [u8; 64]) -> ([u8; 32], [u8; 32]) {
+    let mut key = [0; 32];
+    let mut chain_code = [0; 32];
+    key.copy_from_slice(&output[.. 32]);
+    chain_code.copy_from_slice(&output[32 ..]);
+    output.zeroize();
+    (key, chain_code)
+}
+
+/// Computes the HMAC-SHA512 of the concatenated data with the given key.
+pub(crate) fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> [u8; 64] {
+    let mut mac = Hmac::<Sha512>::new_varkey(key).expect("HMAC accepts keys of any length");
+    for d in data {
+        mac.input(d);
+    }
+    let mut output = [0; 64];
+    output.copy_from_slice(&mac.result().code());
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: &str = "000102030405060708090a0b0c0d0e0f";
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0 .. s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i .. i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn parse_path() {
+        let path = "m/44'/0h/1H/2".parse::<DerivationPath>().unwrap();
+        assert_eq!(path, DerivationPath::master()
+            .child(ChildIndex::Hardened(44))
+            .child(ChildIndex::Hardened(0))
+            .child(ChildIndex::Hardened(1))
+            .child(ChildIndex::Normal(2)));
+        assert_eq!(path.to_string(), "m/44'/0'/1'/2");
+        assert_eq!("m".parse::<DerivationPath>().unwrap(), DerivationPath::master());
+
+        for invalid in &["", "44'", "m/", "m/-1", "m/+1", "m/2147483648", "m/1''", "m/x"] {
+            assert!(invalid.parse::<DerivationPath>().is_err(), "{}", invalid);
+        }
+    }
+
+    // Test vector 1 of SLIP-0010 for Ed25519.
+    #[test]
+    fn ed25519_test_vector() {
+        let vectors = [
+            ("m", "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"),
+            ("m/0'", "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3"),
+            ("m/0'/1'", "b1d0bad404bf35da785a64ca1ac54b2617211d2777696fbffaf208f746ae84f2"),
+            ("m/0'/1'/2'/2'/1000000000'", "8f94d394a8e8fd6b1bc2f3f49f5c47e385281d5c17e65324b0f62483e37e8793"),
+        ];
+        for (path, key) in vectors.iter() {
+            let secret = ed25519(&hex(SEED), &path.parse().unwrap()).unwrap();
+            assert_eq!(secret.as_ref(), &hex(key)[..], "{}", path);
+        }
+        assert!(ed25519(&hex(SEED), &"m/0".parse().unwrap()).is_err());
+    }
+
+    // Test vector 1 of BIP32.
+    #[test]
+    #[cfg(feature = "secp256k1")]
+    fn secp256k1_test_vector() {
+        let vectors = [
+            ("m", "e8f32e723decf4051aefac8e2c93c9c5b214313817cdb01a1494b917c8436b35"),
+            ("m/0'", "edb2e14f9ee77d26dd93b4ecede8d16ed408ce149b6cd80b0715a2d911a0afea"),
+            ("m/0'/1", "3c6cb8d0f6a264c91ea8b5030fadaa8e538b020f0a387421a12de9319dc93368"),
+            ("m/0'/1/2'", "cbce0d719ecf7431d88e6a89fa1483e02e35092af60c042b1df2ff59fa424dca"),
+            ("m/0'/1/2'/2/1000000000", "471b76e389e528d6de6d816857e012c5455051cad6660850e58372a6c3e6e7c8"),
+        ];
+        for (path, key) in vectors.iter() {
+            let secret = secp256k1(&hex(SEED), &path.parse().unwrap()).unwrap();
+            assert_eq!(&secret.to_bytes()[..], &hex(key)[..], "{}", path);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! ECDSA keys on the NIST P-256 curve.
+
+use super::error::{DecodingError, SigningError};
+use super::pkcs8::{self, PrivateKeyInfo};
+use ring::rand::SystemRandom;
+use ring::signature::{self, EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1, ECDSA_P256_SHA256_ASN1_SIGNING};
+use std::{fmt, sync::Arc};
+use zeroize::{Zeroize, Zeroizing};
+
+/// An ECDSA keypair.
+#[derive(Clone)]
+pub struct Keypair {
+    inner: Arc<EcdsaKeyPair>,
+    /// The PKCS#8 encoding of the keypair, which `ring` can't produce.
+    pkcs8: Arc<Zeroizing<Vec<u8>>>,
+}
+
+impl Keypair {
+    /// Generate a new ECDSA keypair.
+    pub fn generate() -> Keypair {
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &SystemRandom::new())
+            .expect("Failed to generate an ECDSA keypair");
+        Keypair::from_pkcs8(&mut pkcs8.as_ref().to_vec())
+            .expect("ring produces valid PKCS#8 keypairs; QED")
+    }
+
+    /// Decode a keypair from a DER-encoded secret key in PKCS#8 PrivateKeyInfo
+    /// format as defined in [RFC5915], zeroing the input on success.
+    ///
+    /// The public key must be part of the encoded secret key.
+    ///
+    /// [RFC5915]: https://tools.ietf.org/html/rfc5915#section-4
+    pub fn from_pkcs8(der: &mut [u8]) -> Result<Keypair, DecodingError> {
+        let kp = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, der)
+            .map_err(|e| DecodingError::new("ECDSA PKCS#8 PrivateKeyInfo").source(e))?;
+        let pkcs8 = Zeroizing::new(der.to_vec());
+        der.zeroize();
+        Ok(Keypair { inner: Arc::new(kp), pkcs8: Arc::new(pkcs8) })
+    }
+
+    /// Encode the keypair in DER as a PKCS#8 PrivateKeyInfo structure.
+    pub fn to_pkcs8(&self) -> Vec<u8> {
+        self.pkcs8.to_vec()
+    }
+
+    /// Decode a keypair from a DER-encoded secret key in an ECPrivateKey
+    /// structure as defined in [SEC1] and [RFC5915], zeroing the input on success.
+    ///
+    /// The public key must be part of the encoded secret key.
+    ///
+    /// [SEC1]: https://www.secg.org/sec1-v2.pdf
+    /// [RFC5915]: https://tools.ietf.org/html/rfc5915
+    pub fn from_der(der: &mut [u8]) -> Result<Keypair, DecodingError> {
+        let mut pkcs8 = PrivateKeyInfo {
+            algorithm: pkcs8::OID_EC_PUBLIC_KEY,
+            parameters: Some(pkcs8::OID_PRIME256V1),
+            private_key: der,
+        }.encode();
+        let kp = Keypair::from_pkcs8(&mut pkcs8)?;
+        der.zeroize();
+        Ok(kp)
+    }
+
+    /// Encode the keypair in DER as an ECPrivateKey structure as defined in [SEC1]
+    /// and [RFC5915], including the curve and the public key.
+    ///
+    /// [SEC1]: https://www.secg.org/sec1-v2.pdf
+    /// [RFC5915]: https://tools.ietf.org/html/rfc5915
+    pub fn to_der(&self) -> Vec<u8> {
+        let info = PrivateKeyInfo::decode(&self.pkcs8)
+            .expect("the PKCS#8 encoding has been parsed by ring; QED");
+        let (sk, _) = pkcs8::read(info.private_key, 0x30)
+            .and_then(|(key, _)| pkcs8::read(key, 0x02))
+            .and_then(|(_, rest)| pkcs8::read(rest, 0x04))
+            .expect("the PKCS#8 encoding has been parsed by ring; QED");
+
+        let mut parameters = Vec::with_capacity(10);
+        pkcs8::write(0x06, pkcs8::OID_PRIME256V1, &mut parameters);
+        let mut public = Vec::with_capacity(70);
+        let mut point = vec![0];
+        point.extend_from_slice(self.inner.public_key().as_ref());
+        pkcs8::write(0x03, &point, &mut public);
+
+        let mut key = Zeroizing::new(Vec::with_capacity(sk.len() + parameters.len() + public.len() + 16));
+        pkcs8::write(0x02, &[1], &mut key);
+        pkcs8::write(0x04, sk, &mut key);
+        pkcs8::write(0xa0, &parameters, &mut key);
+        pkcs8::write(0xa1, &public, &mut key);
+
+        let mut der = Vec::with_capacity(key.len() + 4);
+        pkcs8::write(0x30, &key, &mut der);
+        der
+    }
+
+    /// Sign a message with this keypair, producing a DER-encoded ECDSA signature
+    /// of its SHA-256 digest.
+    pub fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, SigningError> {
+        self.inner.sign(&SystemRandom::new(), msg)
+            .map(|sig| sig.as_ref().to_vec())
+            .map_err(|e| SigningError::new("ECDSA").source(e))
+    }
+
+    /// Get the public key of this keypair.
+    pub fn public(&self) -> PublicKey {
+        PublicKey(self.inner.public_key().as_ref().to_vec())
+    }
+}
+
+impl fmt::Debug for Keypair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keypair").field("public", &self.public()).finish()
+    }
+}
+
+/// An ECDSA public key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PublicKey(Vec<u8>);
+
+impl PublicKey {
+    /// Verify a DER-encoded ECDSA signature of the SHA-256 digest of a message
+    /// using the public key.
+    pub fn verify(&self, msg: &[u8], sig: &[u8]) -> bool {
+        signature::UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, &self.0)
+            .verify(msg, sig)
+            .is_ok()
+    }
+
+    /// Encode the public key as an uncompressed point, as defined in [SEC1].
+    ///
+    /// [SEC1]: https://www.secg.org/sec1-v2.pdf
+    pub fn encode(&self) -> [u8; 65] {
+        let mut encoded = [0; 65];
+        encoded.copy_from_slice(&self.0);
+        encoded
+    }
+
+    /// Decode a public key from an uncompressed point, as produced by `encode`.
+    pub fn decode(k: &[u8]) -> Result<PublicKey, DecodingError> {
+        if k.len() != 65 || k[0] != 0x04 {
+            return Err(DecodingError::new("ECDSA public key must be an uncompressed P-256 point"))
+        }
+        Ok(PublicKey(k.to_vec()))
+    }
+
+    /// Encode the public key in DER as a X.509 SubjectPublicKeyInfo structure,
+    /// as defined in [RFC5480].
+    ///
+    /// [RFC5480]: https://tools.ietf.org/html/rfc5480#section-2
+    pub fn encode_der(&self) -> Vec<u8> {
+        let mut algorithm = Vec::with_capacity(21);
+        pkcs8::write(0x06, pkcs8::OID_EC_PUBLIC_KEY, &mut algorithm);
+        pkcs8::write(0x06, pkcs8::OID_PRIME256V1, &mut algorithm);
+
+        let mut point = Vec::with_capacity(self.0.len() + 1);
+        point.push(0); // No unused bits.
+        point.extend_from_slice(&self.0);
+
+        let mut spki = Vec::with_capacity(89);
+        pkcs8::write(0x30, &algorithm, &mut spki);
+        pkcs8::write(0x03, &point, &mut spki);
+
+        let mut der = Vec::with_capacity(91);
+        pkcs8::write(0x30, &spki, &mut der);
+        der
+    }
+
+    /// Decode a public key from a DER-encoded X.509 SubjectPublicKeyInfo
+    /// structure. See also `encode_der`.
+    pub fn decode_der(k: &[u8]) -> Result<PublicKey, DecodingError> {
+        let (spki, _) = pkcs8::read(k, 0x30)?;
+        let (algorithm, rest) = pkcs8::read(spki, 0x30)?;
+        let (point, _) = pkcs8::read(rest, 0x03)?;
+
+        let (oid, parameters) = pkcs8::read(algorithm, 0x06)?;
+        let (curve, _) = pkcs8::read(parameters, 0x06)?;
+        if oid != pkcs8::OID_EC_PUBLIC_KEY || curve != pkcs8::OID_PRIME256V1 {
+            return Err(DecodingError::new("Not an ECDSA P-256 public key"))
+        }
+
+        match point.split_first() {
+            Some((0, point)) => PublicKey::decode(point),
+            _ => Err(DecodingError::new("Invalid ECDSA public key bit string"))
+        }
+    }
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PublicKey(asn.1 uncompressed): ")?;
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ecdsa_sign_verify() {
+        let kp = Keypair::generate();
+        let pk = kp.public();
+
+        let msg = "hello world".as_bytes();
+        let sig = kp.sign(msg).unwrap();
+        assert!(pk.verify(msg, &sig));
+
+        let mut invalid_sig = sig.clone();
+        invalid_sig[10] ^= 1;
+        assert!(!pk.verify(msg, &invalid_sig));
+
+        let invalid_msg = "h3ll0 w0rld".as_bytes();
+        assert!(!pk.verify(invalid_msg, &sig));
+    }
+
+    #[test]
+    fn ecdsa_encode_decode() {
+        let kp = Keypair::generate();
+        let pk = kp.public();
+        assert_eq!(PublicKey::decode(&pk.encode()).unwrap(), pk);
+        assert_eq!(PublicKey::decode_der(&pk.encode_der()).unwrap(), pk);
+
+        let mut der = kp.to_der();
+        let kp2 = Keypair::from_der(&mut der).unwrap();
+        assert_eq!(kp2.public(), pk);
+        assert!(der.iter().all(|b| *b == 0));
+
+        let kp3 = Keypair::from_pkcs8(&mut kp2.to_pkcs8()).unwrap();
+        assert_eq!(kp3.public(), pk);
+    }
+
+    #[test]
+    fn ecdsa_from_openssl_der() {
+        // openssl ecparam -name prime256v1 -genkey -noout -outform DER
+        let kp = Keypair::from_der(&mut hex(OPENSSL_EC_PRIVATE_KEY)).unwrap();
+        assert_eq!(kp.to_der(), hex(OPENSSL_EC_PRIVATE_KEY));
+        // openssl ec -inform DER -pubout -outform DER
+        assert_eq!(kp.public().encode_der(), hex(OPENSSL_PUBLIC_KEY));
+    }
+
+    const OPENSSL_EC_PRIVATE_KEY: &str = "\
+        3077020101042026f99bfb750d57945f2700ce10c1ac2766bf71fb9dbad13b523f90f25161426ba00a06082a\
+        8648ce3d030107a144034200049cc0d872551064b3c3e71bc2601505dfb863d8b6ae1ffe287701eaef6a9b9b\
+        c05f12ea70a0a72c4edff3aa9c22b9bb7402234bb6e8e60ab30b9e1800cfa8ae3b";
+    const OPENSSL_PUBLIC_KEY: &str = "\
+        3059301306072a8648ce3d020106082a8648ce3d030107034200049cc0d872551064b3c3e71bc2601505dfb8\
+        63d8b6ae1ffe287701eaef6a9b9bc05f12ea70a0a72c4edff3aa9c22b9bb7402234bb6e8e60ab30b9e1800cf\
+        a8ae3b";
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0 .. s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i .. i + 2], 16).unwrap()).collect()
+    }
+}
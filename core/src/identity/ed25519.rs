@@ -23,6 +23,7 @@
 use ed25519_dalek as ed25519;
 use rand::RngCore;
 use super::error::DecodingError;
+use super::pkcs8::{self, PrivateKeyInfo};
 use zeroize::Zeroize;
 use core::fmt;
 
@@ -50,6 +51,38 @@ impl Keypair {
             .map_err(|e| DecodingError::new("Ed25519 keypair").source(e))
     }
 
+    /// Decode a keypair from a DER-encoded secret key in PKCS#8 PrivateKeyInfo
+    /// format as defined in [RFC8410], zeroing the input on success.
+    ///
+    /// [RFC8410]: https://tools.ietf.org/html/rfc8410#section-7
+    pub fn from_pkcs8(der: &mut [u8]) -> Result<Keypair, DecodingError> {
+        let info = PrivateKeyInfo::decode(der)?;
+        if info.algorithm != pkcs8::OID_ED25519 {
+            return Err(DecodingError::new("Not an Ed25519 PKCS#8 PrivateKeyInfo"))
+        }
+        // The private key is itself wrapped in an OCTET STRING.
+        let (sk, _) = pkcs8::read(info.private_key, 0x04)?;
+        let secret = SecretKey::from_bytes(&mut sk.to_vec())?;
+        der.zeroize();
+        Ok(Keypair::from(secret))
+    }
+
+    /// Encode the keypair as a DER-encoded secret key in PKCS#8 PrivateKeyInfo
+    /// format as defined in [RFC8410].
+    ///
+    /// [RFC8410]: https://tools.ietf.org/html/rfc8410#section-7
+    pub fn to_pkcs8(&self) -> Vec<u8> {
+        let mut sk = Vec::with_capacity(34);
+        pkcs8::write(0x04, self.0.secret.as_bytes(), &mut sk);
+        let der = PrivateKeyInfo {
+            algorithm: pkcs8::OID_ED25519,
+            parameters: None,
+            private_key: &sk,
+        }.encode();
+        sk.zeroize();
+        der
+    }
+
     /// Sign a message using the private key of this keypair.
     pub fn sign(&self, msg: &[u8]) -> Vec<u8> {
         self.0.sign(msg).to_bytes().to_vec()
@@ -206,6 +239,32 @@ mod tests {
         QuickCheck::new().tests(10).quickcheck(prop as fn() -> _);
     }
 
+    #[test]
+    fn ed25519_keypair_pkcs8() {
+        let kp1 = Keypair::generate();
+        let mut der = kp1.to_pkcs8();
+        let kp2 = Keypair::from_pkcs8(&mut der).unwrap();
+        assert!(eq_keypairs(&kp1, &kp2));
+        assert!(der.iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn ed25519_keypair_from_rfc8410_pkcs8() {
+        // The example private key of RFC 8410, section 10.3.
+        let mut der = vec![
+            0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22,
+            0x04, 0x20, 0xd4, 0xee, 0x72, 0xdb, 0xf9, 0x13, 0x58, 0x4a, 0xd5, 0xb6, 0xd8, 0xf1,
+            0xf7, 0x69, 0xf8, 0xad, 0x3a, 0xfe, 0x7c, 0x28, 0xcb, 0xf1, 0xd4, 0xfb, 0xe0, 0x97,
+            0xa8, 0x8f, 0x44, 0x75, 0x58, 0x42,
+        ];
+        let kp = Keypair::from_pkcs8(&mut der).unwrap();
+        assert_eq!(kp.public().encode(), [
+            0x19, 0xbf, 0x44, 0x09, 0x69, 0x84, 0xcd, 0xfe, 0x85, 0x41, 0xba, 0xc1, 0x67, 0xdc,
+            0x3b, 0x96, 0xc8, 0x50, 0x86, 0xaa, 0x30, 0xb6, 0xb6, 0xcb, 0x0c, 0x5c, 0x38, 0xad,
+            0x70, 0x31, 0x66, 0xe1,
+        ]);
+    }
+
     #[test]
     fn ed25519_signature() {
         let kp = Keypair::generate();
@@ -82,3 +82,33 @@ impl Error for SigningError {
     }
 }
 
+
+/// An error during encoding of key material.
+#[derive(Debug)]
+pub struct EncodingError {
+    msg: String,
+    source: Option<Box<dyn Error + Send + Sync>>
+}
+
+impl EncodingError {
+    pub(crate) fn new<S: ToString>(msg: S) -> Self {
+        Self { msg: msg.to_string(), source: None }
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn source(self, source: impl Error + Send + Sync + 'static) -> Self {
+        Self { source: Some(Box::new(source)), .. self }
+    }
+}
+
+impl fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Key encoding error: {}", self.msg)
+    }
+}
+
+impl Error for EncodingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_ref().map(|s| &**s as &dyn Error)
+    }
+}
@@ -0,0 +1,216 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Files storing the identity keypair of a node, so that it survives restarts.
+//!
+//! Keystore files are PEM documents. Without a password, the keypair is stored as a PKCS#8
+//! `PRIVATE KEY`, which other tools such as OpenSSL can read as well. With a password, the
+//! protobuf encoding of the keypair is encrypted with AES-256-GCM, under a key derived from
+//! the password with PBKDF2-HMAC-SHA256, and stored as a `LIBP2P ENCRYPTED KEYPAIR`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use libp2p_core::identity::{Keypair, keystore};
+//!
+//! let keypair = keystore::load_or_generate("node.key", Some("password"), Keypair::generate_ed25519)
+//!     .expect("a valid keystore file");
+//! ```
+
+use crate::keystore_proto;
+use super::{Keypair, error::{DecodingError, EncodingError}, pkcs8};
+use prost::Message;
+use rand::RngCore;
+use ring::{aead, pbkdf2};
+use std::{fs, io, num::NonZeroU32, path::Path};
+use zeroize::{Zeroize, Zeroizing};
+
+/// The PEM label of encrypted keypairs.
+const ENCRYPTED_LABEL: &str = "LIBP2P ENCRYPTED KEYPAIR";
+
+/// Number of PBKDF2 iterations of newly encrypted keypairs.
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// Upper bound on the number of PBKDF2 iterations of decrypted keypairs, so that a
+/// tampered file can't make decryption take forever.
+const MAX_PBKDF2_ITERATIONS: u32 = 10_000_000;
+
+/// Encodes a keypair in the keystore format, encrypting it if a password is given.
+pub fn encode(keypair: &Keypair, password: Option<&str>) -> Result<String, EncodingError> {
+    let password = match password {
+        Some(password) => password,
+        None => return keypair.to_pem()
+    };
+
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; aead::NONCE_LEN];
+    let mut rng = rand::thread_rng();
+    rng.fill_bytes(&mut salt);
+    rng.fill_bytes(&mut nonce);
+
+    let key = derive_key(password, PBKDF2_ITERATIONS, &salt);
+    let mut ciphertext = keypair.to_protobuf_encoding()?;
+    key.seal_in_place_append_tag(
+        aead::Nonce::assume_unique_for_key(nonce),
+        aead::Aad::empty(),
+        &mut ciphertext
+    ).map_err(|_| EncodingError::new("Failed to encrypt the keypair"))?;
+
+    let encrypted = keystore_proto::EncryptedKeypair {
+        iterations: PBKDF2_ITERATIONS,
+        salt: salt.to_vec(),
+        nonce: nonce.to_vec(),
+        ciphertext,
+    };
+    let mut buf = Vec::with_capacity(encrypted.encoded_len());
+    encrypted.encode(&mut buf).expect("Vec<u8> provides capacity as needed");
+    Ok(pkcs8::pem_encode(ENCRYPTED_LABEL, &buf))
+}
+
+/// Decodes a keypair in the keystore format.
+///
+/// A password is required for encrypted keypairs, and ignored otherwise.
+pub fn decode(data: &str, password: Option<&str>) -> Result<Keypair, DecodingError> {
+    let (label, mut der) = pkcs8::pem_decode(data)?;
+    if label != ENCRYPTED_LABEL {
+        der.zeroize();
+        return Keypair::from_pem(data)
+    }
+
+    let password = password
+        .ok_or_else(|| DecodingError::new("A password is required to decrypt the keypair"))?;
+    let encrypted = keystore_proto::EncryptedKeypair::decode(&der[..])
+        .map_err(|e| DecodingError::new("Protobuf").source(e))?;
+    if encrypted.iterations > MAX_PBKDF2_ITERATIONS {
+        return Err(DecodingError::new("Too many PBKDF2 iterations"))
+    }
+    let nonce = aead::Nonce::try_assume_unique_for_key(&encrypted.nonce)
+        .map_err(|_| DecodingError::new("Invalid nonce length"))?;
+
+    let key = derive_key(password, encrypted.iterations, &encrypted.salt);
+    let mut ciphertext = Zeroizing::new(encrypted.ciphertext);
+    let plaintext = key.open_in_place(nonce, aead::Aad::empty(), &mut ciphertext)
+        .map_err(|_| DecodingError::new("Wrong password or corrupted keypair"))?;
+    Keypair::from_protobuf_encoding(plaintext)
+}
+
+/// Saves a keypair to a keystore file, encrypting it if a password is given.
+///
+/// The file is replaced atomically, and is only accessible to the current user on Unix.
+pub fn save(path: impl AsRef<Path>, keypair: &Keypair, password: Option<&str>) -> io::Result<()> {
+    let path = path.as_ref();
+    let data = Zeroizing::new(encode(keypair, password)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?);
+
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    {
+        use std::io::Write;
+        let mut file = options.open(&tmp)?;
+        file.write_all(data.as_bytes())?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)
+}
+
+/// Loads a keypair from a keystore file.
+///
+/// A password is required for encrypted keypairs, and ignored otherwise.
+pub fn load(path: impl AsRef<Path>, password: Option<&str>) -> io::Result<Keypair> {
+    let data = Zeroizing::new(fs::read_to_string(path)?);
+    decode(&data, password).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Loads a keypair from a keystore file, or generates one with `generate` and saves it if
+/// the file doesn't exist.
+pub fn load_or_generate(
+    path: impl AsRef<Path>,
+    password: Option<&str>,
+    generate: impl FnOnce() -> Keypair
+) -> io::Result<Keypair> {
+    match load(path.as_ref(), password) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            let keypair = generate();
+            save(path, &keypair, password)?;
+            Ok(keypair)
+        }
+        result => result
+    }
+}
+
+/// Derives the encryption key of a keypair from a password.
+fn derive_key(password: &str, iterations: u32, salt: &[u8]) -> aead::LessSafeKey {
+    let iterations = NonZeroU32::new(iterations).unwrap_or_else(|| NonZeroU32::new(1).expect("1 != 0"));
+    let mut key = [0u8; 32];
+    pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations, salt, password.as_bytes(), &mut key);
+    let unbound = aead::UnboundKey::new(&aead::AES_256_GCM, &key)
+        .expect("the key has the length of AES-256 keys; QED");
+    key.zeroize();
+    aead::LessSafeKey::new(unbound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypted_roundtrip() {
+        let keypair = Keypair::generate_ed25519();
+        let data = encode(&keypair, Some("password")).unwrap();
+        assert!(data.starts_with("-----BEGIN LIBP2P ENCRYPTED KEYPAIR-----\n"));
+
+        assert_eq!(decode(&data, Some("password")).unwrap().public(), keypair.public());
+        assert!(decode(&data, Some("wrong password")).is_err());
+        assert!(decode(&data, None).is_err());
+    }
+
+    #[test]
+    fn unencrypted_roundtrip() {
+        let keypair = Keypair::generate_ed25519();
+        let data = encode(&keypair, None).unwrap();
+        assert_eq!(data, keypair.to_pem().unwrap());
+        assert_eq!(decode(&data, Some("ignored")).unwrap().public(), keypair.public());
+    }
+
+    #[test]
+    fn load_or_generate_persists_the_keypair() {
+        let dir = std::env::temp_dir().join(format!("libp2p-keystore-{}", rand::random::<u64>()));
+        fs::create_dir(&dir).unwrap();
+        let path = dir.join("node.key");
+
+        let generated = load_or_generate(&path, Some("password"), Keypair::generate_ed25519).unwrap();
+        let loaded = load_or_generate(&path, Some("password"), || unreachable!()).unwrap();
+        assert_eq!(generated.public(), loaded.public());
+        assert_eq!(load(&path, Some("wrong")).err().unwrap().kind(), io::ErrorKind::InvalidData);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Mnemonic phrases encoding secrets as [BIP39] English words.
+//!
+//! [BIP39]: https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki
+
+use super::derivation::hmac_sha512;
+use super::error::DecodingError;
+use lazy_static::lazy_static;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Number of PBKDF2 iterations when computing the seed of a mnemonic.
+const SEED_ITERATIONS: u32 = 2048;
+
+lazy_static! {
+    /// The BIP39 English wordlist, in alphabetical order.
+    static ref WORDS: Vec<&'static str> = include_str!("bip39-english.txt").lines().collect();
+}
+
+/// A mnemonic phrase of 12 to 24 words, encoding between 16 and 32 bytes of entropy
+/// with a checksum.
+#[derive(Clone)]
+pub struct Mnemonic {
+    entropy: Zeroizing<Vec<u8>>,
+}
+
+impl Mnemonic {
+    /// Generates a mnemonic of 24 words from 32 random bytes.
+    pub fn generate() -> Mnemonic {
+        let mut entropy = Zeroizing::new(vec![0; 32]);
+        rand::thread_rng().fill_bytes(&mut entropy);
+        Mnemonic { entropy }
+    }
+
+    /// Creates the mnemonic encoding the given entropy, whose length must be a multiple
+    /// of 4 bytes between 16 and 32 bytes.
+    pub fn from_entropy(entropy: &[u8]) -> Result<Mnemonic, DecodingError> {
+        if !(16 ..= 32).contains(&entropy.len()) || entropy.len() % 4 != 0 {
+            return Err(DecodingError::new(format!("Invalid mnemonic entropy length: {}", entropy.len())))
+        }
+        Ok(Mnemonic { entropy: Zeroizing::new(entropy.to_vec()) })
+    }
+
+    /// Parses a mnemonic phrase, verifying its checksum.
+    pub fn from_phrase(phrase: &str) -> Result<Mnemonic, DecodingError> {
+        let mut bits = Zeroizing::new(Vec::with_capacity(24 * 11));
+        for word in phrase.split_whitespace() {
+            let index = WORDS.binary_search(&word)
+                .map_err(|_| DecodingError::new("Unknown mnemonic word"))?;
+            bits.extend((0 .. 11).rev().map(|i| (index >> i) & 1 == 1));
+        }
+        let words = bits.len() / 11;
+        if !(12 ..= 24).contains(&words) || words % 3 != 0 {
+            return Err(DecodingError::new(format!("Invalid number of mnemonic words: {}", words)))
+        }
+
+        let (entropy_bits, checksum_bits) = bits.split_at(words / 3 * 32);
+        let entropy = Zeroizing::new(entropy_bits.chunks(8)
+            .map(|byte| byte.iter().fold(0, |acc, &bit| acc << 1 | bit as u8))
+            .collect::<Vec<u8>>());
+        let mnemonic = Mnemonic { entropy };
+        if mnemonic.checksum_bits().ne(checksum_bits.iter().cloned()) {
+            return Err(DecodingError::new("Invalid mnemonic checksum"))
+        }
+        Ok(mnemonic)
+    }
+
+    /// Returns the entropy encoded by the mnemonic.
+    pub fn entropy(&self) -> &[u8] {
+        &self.entropy
+    }
+
+    /// Returns the words of the mnemonic, separated by spaces.
+    pub fn phrase(&self) -> String {
+        let mut bits = Zeroizing::new(self.entropy.iter()
+            .flat_map(|byte| (0 .. 8).rev().map(move |i| (byte >> i) & 1 == 1))
+            .chain(self.checksum_bits())
+            .collect::<Vec<_>>());
+        let phrase = bits.chunks(11)
+            .map(|word| WORDS[word.iter().fold(0, |acc, &bit| acc << 1 | bit as usize)])
+            .collect::<Vec<_>>()
+            .join(" ");
+        bits.zeroize();
+        phrase
+    }
+
+    /// Computes the 64 bytes seed of the mnemonic, protected by an optional passphrase,
+    /// from which keys can be derived with the [`derivation`](super::derivation) module.
+    ///
+    /// The passphrase is used as given; other implementations first normalize it to
+    /// Unicode NFKD, which only makes a difference for passphrases that are not ASCII.
+    pub fn to_seed(&self, passphrase: &str) -> Zeroizing<Vec<u8>> {
+        let phrase = Zeroizing::new(self.phrase());
+        let mut salt = Zeroizing::new(String::with_capacity(8 + passphrase.len()));
+        salt.push_str("mnemonic");
+        salt.push_str(passphrase);
+
+        // PBKDF2-HMAC-SHA512 with a single output block.
+        let mut block = hmac_sha512(phrase.as_bytes(), &[salt.as_bytes(), &1u32.to_be_bytes()]);
+        let mut seed = Zeroizing::new(block.to_vec());
+        for _ in 1 .. SEED_ITERATIONS {
+            let next = hmac_sha512(phrase.as_bytes(), &[&block]);
+            block.zeroize();
+            block = next;
+            seed.iter_mut().zip(block.iter()).for_each(|(s, b)| *s ^= b);
+        }
+        block.zeroize();
+        seed
+    }
+
+    /// Returns the checksum bits appended to the entropy.
+    fn checksum_bits(&self) -> impl Iterator<Item = bool> {
+        let hash = Sha256::digest(&self.entropy);
+        let len = self.entropy.len() / 4;
+        (0 .. len).map(move |i| (hash[i / 8] >> (7 - i % 8)) & 1 == 1)
+    }
+}
+
+impl fmt::Debug for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Mnemonic")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0 .. s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i .. i + 2], 16).unwrap()).collect()
+    }
+
+    // Test vectors of the reference implementation, with the passphrase "TREZOR".
+    #[test]
+    fn test_vectors() {
+        let vectors = [
+            ("00000000000000000000000000000000",
+             "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+             "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"),
+            ("7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+             "legal winner thank year wave sausage worth useful legal winner thank yellow",
+             "2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607"),
+            ("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+             "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo vote",
+             "dd48c104698c30cfe2b6142103248622fb7bb0ff692eebb00089b32d22484e1613912f0a5b694407be899ffd31ed3992c456cdf60f5d4564b8ba3f05a69890ad"),
+        ];
+        for (entropy, phrase, seed) in vectors.iter() {
+            let mnemonic = Mnemonic::from_entropy(&hex(entropy)).unwrap();
+            assert_eq!(&mnemonic.phrase(), phrase);
+            assert_eq!(&mnemonic.to_seed("TREZOR")[..], &hex(seed)[..]);
+            let parsed = Mnemonic::from_phrase(phrase).unwrap();
+            assert_eq!(parsed.entropy(), &hex(entropy)[..]);
+        }
+    }
+
+    #[test]
+    fn generate_roundtrip() {
+        let mnemonic = Mnemonic::generate();
+        assert_eq!(mnemonic.phrase().split(' ').count(), 24);
+        let parsed = Mnemonic::from_phrase(&mnemonic.phrase()).unwrap();
+        assert_eq!(parsed.entropy(), mnemonic.entropy());
+    }
+
+    #[test]
+    fn invalid_phrases() {
+        // Wrong checksum.
+        assert!(Mnemonic::from_phrase(&["abandon"; 12].join(" ")).is_err());
+        // Unknown word.
+        assert!(Mnemonic::from_phrase(&(["abandon"; 11].join(" ") + " libp2p")).is_err());
+        // Invalid length.
+        assert!(Mnemonic::from_phrase(&["abandon"; 11].join(" ")).is_err());
+    }
+}
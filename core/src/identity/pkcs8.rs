@@ -0,0 +1,219 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Minimal DER and PEM support for the PKCS#8 `PrivateKeyInfo` structures of [RFC5208],
+//! as far as needed to import and export the keys of all supported types.
+//!
+//! [RFC5208]: https://tools.ietf.org/html/rfc5208#section-5
+
+use super::error::DecodingError;
+use std::str;
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_NULL: u8 = 0x05;
+const TAG_OID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+
+/// The OID `rsaEncryption` (1.2.840.113549.1.1.1).
+pub(crate) const OID_RSA_ENCRYPTION: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x01];
+/// The OID `id-Ed25519` (1.3.101.112) of [RFC8410].
+///
+/// [RFC8410]: https://tools.ietf.org/html/rfc8410#section-3
+pub(crate) const OID_ED25519: &[u8] = &[0x2B, 0x65, 0x70];
+/// The OID `id-ecPublicKey` (1.2.840.10045.2.1) of [RFC5480].
+///
+/// [RFC5480]: https://tools.ietf.org/html/rfc5480#section-2.1.1
+pub(crate) const OID_EC_PUBLIC_KEY: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x02, 0x01];
+/// The OID `secp256k1` (1.3.132.0.10) of the curve of EC keys.
+pub(crate) const OID_SECP256K1: &[u8] = &[0x2B, 0x81, 0x04, 0x00, 0x0A];
+/// The OID `prime256v1` (1.2.840.10045.3.1.7) of the curve of EC keys, i.e. NIST P-256.
+pub(crate) const OID_PRIME256V1: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x03, 0x01, 0x07];
+
+/// The fields of a `PrivateKeyInfo` structure.
+pub(crate) struct PrivateKeyInfo<'a> {
+    /// The OID of the algorithm of the key.
+    pub algorithm: &'a [u8],
+    /// The parameters of the algorithm if they are an OID, e.g. the curve of EC keys.
+    pub parameters: Option<&'a [u8]>,
+    /// The algorithm-specific encoding of the private key.
+    pub private_key: &'a [u8],
+}
+
+impl<'a> PrivateKeyInfo<'a> {
+    /// Decodes a DER-encoded `PrivateKeyInfo`. Attributes and public keys of version 2
+    /// structures ([RFC5958]) are ignored.
+    ///
+    /// [RFC5958]: https://tools.ietf.org/html/rfc5958#section-2
+    pub fn decode(der: &'a [u8]) -> Result<Self, DecodingError> {
+        let (info, _) = read(der, TAG_SEQUENCE)?;
+        let (version, rest) = read(info, TAG_INTEGER)?;
+        if version != [0] && version != [1] {
+            return Err(DecodingError::new("Unsupported PKCS#8 version"))
+        }
+        let (algorithm_id, rest) = read(rest, TAG_SEQUENCE)?;
+        let (private_key, _) = read(rest, TAG_OCTET_STRING)?;
+
+        let (algorithm, parameters) = read(algorithm_id, TAG_OID)?;
+        let parameters = match parameters.first() {
+            Some(&TAG_OID) => Some(read(parameters, TAG_OID)?.0),
+            _ => None
+        };
+
+        Ok(PrivateKeyInfo { algorithm, parameters, private_key })
+    }
+
+    /// Encodes the structure in DER, as a version 1 `PrivateKeyInfo`.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut algorithm_id = Vec::new();
+        write(TAG_OID, self.algorithm, &mut algorithm_id);
+        if let Some(parameters) = self.parameters {
+            write(TAG_OID, parameters, &mut algorithm_id);
+        } else if self.algorithm == OID_RSA_ENCRYPTION {
+            // The parameters of `rsaEncryption` must be NULL rather than absent.
+            write(TAG_NULL, &[], &mut algorithm_id);
+        }
+
+        let mut info = Vec::with_capacity(self.private_key.len() + algorithm_id.len() + 16);
+        write(TAG_INTEGER, &[0], &mut info);
+        write(TAG_SEQUENCE, &algorithm_id, &mut info);
+        write(TAG_OCTET_STRING, self.private_key, &mut info);
+
+        let mut der = Vec::with_capacity(info.len() + 4);
+        write(TAG_SEQUENCE, &info, &mut der);
+        der
+    }
+}
+
+/// Reads a DER value with the given tag, returning its contents and the remaining input.
+pub(crate) fn read(input: &[u8], tag: u8) -> Result<(&[u8], &[u8]), DecodingError> {
+    let invalid = || DecodingError::new("Invalid DER encoding");
+
+    if input.first() != Some(&tag) {
+        return Err(DecodingError::new(format!("Expected DER tag {:#04x}", tag)))
+    }
+    let (len, header_len) = match *input.get(1).ok_or_else(invalid)? {
+        len if len < 0x80 => (usize::from(len), 2),
+        num if num > 0x80 && num <= 0x84 => {
+            let num = usize::from(num & 0x7f);
+            let bytes = input.get(2 .. 2 + num).ok_or_else(invalid)?;
+            let len = bytes.iter().fold(0, |len, b| (len << 8) | usize::from(*b));
+            (len, 2 + num)
+        }
+        _ => return Err(invalid())
+    };
+    let end = header_len.checked_add(len).ok_or_else(invalid)?;
+    let value = input.get(header_len .. end).ok_or_else(invalid)?;
+    Ok((value, &input[end ..]))
+}
+
+/// Appends a DER value with the given tag and contents to `out`.
+pub(crate) fn write(tag: u8, value: &[u8], out: &mut Vec<u8>) {
+    out.push(tag);
+    if value.len() < 0x80 {
+        out.push(value.len() as u8);
+    } else {
+        let len = (value.len() as u64).to_be_bytes();
+        let skip = len.iter().take_while(|b| **b == 0).count();
+        out.push(0x80 | (len.len() - skip) as u8);
+        out.extend_from_slice(&len[skip ..]);
+    }
+    out.extend_from_slice(value);
+}
+
+/// Returns the curve of a DER-encoded ECPrivateKey structure ([RFC5915]), if present.
+///
+/// [RFC5915]: https://tools.ietf.org/html/rfc5915#section-3
+pub(crate) fn ec_private_key_curve(der: &[u8]) -> Option<&[u8]> {
+    let (key, _) = read(der, TAG_SEQUENCE).ok()?;
+    let (_, rest) = read(key, TAG_INTEGER).ok()?;
+    let (_, rest) = read(rest, TAG_OCTET_STRING).ok()?;
+    let (parameters, _) = read(rest, 0xa0).ok()?;
+    read(parameters, TAG_OID).ok().map(|(curve, _)| curve)
+}
+
+/// Encodes DER-encoded data in a PEM document with the given label, as defined in [RFC7468].
+///
+/// [RFC7468]: https://tools.ietf.org/html/rfc7468
+pub(crate) fn pem_encode(label: &str, der: &[u8]) -> String {
+    let encoded = base64::encode(der);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(str::from_utf8(line).expect("base64 produces ASCII; QED"));
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+/// Decodes the first block of a PEM document, returning its label and contents.
+pub(crate) fn pem_decode(pem: &str) -> Result<(String, Vec<u8>), DecodingError> {
+    let mut lines = pem.lines()
+        .map(str::trim)
+        .skip_while(|line| !line.starts_with("-----BEGIN "));
+
+    let label = lines.next()
+        .filter(|line| line.ends_with("-----"))
+        .map(|line| &line["-----BEGIN ".len() .. line.len() - "-----".len()])
+        .ok_or_else(|| DecodingError::new("Missing PEM header"))?;
+
+    let end = format!("-----END {}-----", label);
+    let mut encoded = String::new();
+    loop {
+        match lines.next() {
+            Some(line) if line == end => break,
+            Some(line) => encoded.push_str(line),
+            None => return Err(DecodingError::new("Missing PEM footer"))
+        }
+    }
+
+    let der = base64::decode(&encoded).map_err(|e| DecodingError::new("PEM").source(e))?;
+    Ok((label.to_string(), der))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn der_lengths() {
+        for len in &[0, 1, 0x7f, 0x80, 0xff, 0x100, 0x10000] {
+            let value = vec![7; *len];
+            let mut der = Vec::new();
+            write(TAG_OCTET_STRING, &value, &mut der);
+            der.push(1);
+            assert_eq!(read(&der, TAG_OCTET_STRING).unwrap(), (&value[..], &[1][..]));
+        }
+        assert!(read(&[TAG_OCTET_STRING, 0x02, 0x00], TAG_OCTET_STRING).is_err());
+        assert!(read(&[TAG_OCTET_STRING, 0x80], TAG_OCTET_STRING).is_err());
+        assert!(read(&[TAG_INTEGER, 0x00], TAG_OCTET_STRING).is_err());
+    }
+
+    #[test]
+    fn pem_encode_decode() {
+        let der = (0 .. 200).map(|b| b as u8).collect::<Vec<_>>();
+        let pem = pem_encode("PRIVATE KEY", &der);
+        assert!(pem.lines().all(|line| line.len() <= 64));
+
+        let document = format!("Some comment\n{}\n", pem.replace('\n', "\r\n"));
+        assert_eq!(pem_decode(&document).unwrap(), ("PRIVATE KEY".to_string(), der));
+        assert!(pem_decode(&pem.replace("-----END PRIVATE KEY-----", "")).is_err());
+    }
+}
@@ -23,15 +23,20 @@
 use asn1_der::{Asn1Der, FromDerObject, IntoDerObject, DerObject, DerTag, DerValue, Asn1DerError};
 use lazy_static::lazy_static;
 use super::error::*;
+use super::pkcs8::{self, PrivateKeyInfo};
 use ring::rand::SystemRandom;
 use ring::signature::{self, RsaKeyPair, RSA_PKCS1_SHA256, RSA_PKCS1_2048_8192_SHA256};
 use ring::signature::KeyPair;
 use std::{fmt::{self, Write}, sync::Arc};
-use zeroize::Zeroize;
+use zeroize::{Zeroize, Zeroizing};
 
 /// An RSA keypair.
 #[derive(Clone)]
-pub struct Keypair(Arc<RsaKeyPair>);
+pub struct Keypair {
+    inner: Arc<RsaKeyPair>,
+    /// The PKCS#8 encoding of the keypair, which `ring` can't produce.
+    pkcs8: Arc<Zeroizing<Vec<u8>>>,
+}
 
 impl Keypair {
     /// Decode an RSA keypair from a DER-encoded private key in PKCS#8 PrivateKeyInfo
@@ -41,20 +46,50 @@ impl Keypair {
     pub fn from_pkcs8(der: &mut [u8]) -> Result<Keypair, DecodingError> {
         let kp = RsaKeyPair::from_pkcs8(&der)
             .map_err(|e| DecodingError::new("RSA PKCS#8 PrivateKeyInfo").source(e))?;
+        let pkcs8 = Zeroizing::new(der.to_vec());
         der.zeroize();
-        Ok(Keypair(Arc::new(kp)))
+        Ok(Keypair { inner: Arc::new(kp), pkcs8: Arc::new(pkcs8) })
+    }
+
+    /// Decode an RSA keypair from a DER-encoded private key in a PKCS#1 RSAPrivateKey
+    /// structure as defined in [RFC3447].
+    ///
+    /// [RFC3447]: https://tools.ietf.org/html/rfc3447#appendix-A.1.2
+    pub fn from_pkcs1(der: &mut [u8]) -> Result<Keypair, DecodingError> {
+        let kp = RsaKeyPair::from_der(der)
+            .map_err(|e| DecodingError::new("RSA PKCS#1 RSAPrivateKey").source(e))?;
+        let pkcs8 = Zeroizing::new(PrivateKeyInfo {
+            algorithm: pkcs8::OID_RSA_ENCRYPTION,
+            parameters: None,
+            private_key: der,
+        }.encode());
+        der.zeroize();
+        Ok(Keypair { inner: Arc::new(kp), pkcs8: Arc::new(pkcs8) })
+    }
+
+    /// Encode the keypair in DER as a PKCS#8 PrivateKeyInfo structure.
+    pub fn to_pkcs8(&self) -> Vec<u8> {
+        self.pkcs8.to_vec()
+    }
+
+    /// Encode the keypair in DER as a PKCS#1 RSAPrivateKey structure.
+    pub fn to_pkcs1(&self) -> Vec<u8> {
+        PrivateKeyInfo::decode(&self.pkcs8)
+            .expect("the PKCS#8 encoding has been parsed by ring; QED")
+            .private_key
+            .to_vec()
     }
 
     /// Get the public key from the keypair.
     pub fn public(&self) -> PublicKey {
-        PublicKey(self.0.public_key().as_ref().to_vec())
+        PublicKey(self.inner.public_key().as_ref().to_vec())
     }
 
     /// Sign a message with this keypair.
     pub fn sign(&self, data: &[u8]) -> Result<Vec<u8>, SigningError> {
-        let mut signature = vec![0; self.0.public_modulus_len()];
+        let mut signature = vec![0; self.inner.public_modulus_len()];
         let rng = SystemRandom::new();
-        match self.0.sign(&RSA_PKCS1_SHA256, &rng, &data, &mut signature) {
+        match self.inner.sign(&RSA_PKCS1_SHA256, &rng, &data, &mut signature) {
             Ok(()) => Ok(signature),
             Err(e) => Err(SigningError::new("RSA").source(e))
         }
@@ -249,6 +284,19 @@ mod tests {
         assert!(Keypair::from_pkcs8(&mut KEY3.to_vec()).is_ok());
     }
 
+    #[test]
+    fn rsa_pkcs1_pkcs8_encode_decode() {
+        fn prop(SomeKeypair(kp): SomeKeypair) -> bool {
+            let mut pkcs1 = kp.to_pkcs1();
+            let kp2 = Keypair::from_pkcs1(&mut pkcs1).unwrap();
+            let kp3 = Keypair::from_pkcs8(&mut kp2.to_pkcs8()).unwrap();
+            kp2.public() == kp.public()
+                && kp3.public() == kp.public()
+                && pkcs1.iter().all(|b| *b == 0)
+        }
+        QuickCheck::new().tests(10).quickcheck(prop as fn(_) -> _);
+    }
+
     #[test]
     fn rsa_x509_encode_decode() {
         fn prop(SomeKeypair(kp): SomeKeypair) -> Result<bool, String> {
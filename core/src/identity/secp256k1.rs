@@ -25,6 +25,7 @@ use rand::RngCore;
 use sha2::{Digest as ShaDigestTrait, Sha256};
 use secp256k1::{Message, Signature};
 use super::error::{DecodingError, SigningError};
+use super::pkcs8::{self, PrivateKeyInfo};
 use zeroize::Zeroize;
 use core::fmt;
 
@@ -50,6 +51,35 @@ impl Keypair {
     pub fn secret(&self) -> &SecretKey {
         &self.secret
     }
+
+    /// Decode a keypair from a DER-encoded secret key in PKCS#8 PrivateKeyInfo
+    /// format as defined in [RFC5915], zeroing the input on success.
+    ///
+    /// [RFC5915]: https://tools.ietf.org/html/rfc5915#section-4
+    pub fn from_pkcs8(der: &mut [u8]) -> Result<Keypair, DecodingError> {
+        let info = PrivateKeyInfo::decode(der)?;
+        if info.algorithm != pkcs8::OID_EC_PUBLIC_KEY || info.parameters != Some(pkcs8::OID_SECP256K1) {
+            return Err(DecodingError::new("Not a Secp256k1 PKCS#8 PrivateKeyInfo"))
+        }
+        let secret = SecretKey::from_der(info.private_key.to_vec())?;
+        der.zeroize();
+        Ok(Keypair::from(secret))
+    }
+
+    /// Encode the keypair as a DER-encoded secret key in PKCS#8 PrivateKeyInfo
+    /// format as defined in [RFC5915].
+    ///
+    /// [RFC5915]: https://tools.ietf.org/html/rfc5915#section-4
+    pub fn to_pkcs8(&self) -> Vec<u8> {
+        let mut sk = self.secret.to_der();
+        let der = PrivateKeyInfo {
+            algorithm: pkcs8::OID_EC_PUBLIC_KEY,
+            parameters: Some(pkcs8::OID_SECP256K1),
+            private_key: &sk,
+        }.encode();
+        sk.zeroize();
+        der
+    }
 }
 
 impl fmt::Debug for Keypair {
@@ -128,6 +158,26 @@ impl SecretKey {
         Ok(sk)
     }
 
+    /// Encode the secret key in DER as an ECPrivateKey structure as defined in [RFC5915].
+    ///
+    /// [RFC5915]: https://tools.ietf.org/html/rfc5915
+    pub fn to_der(&self) -> Vec<u8> {
+        let mut sk = self.to_bytes();
+        let mut parameters = Vec::with_capacity(7);
+        pkcs8::write(0x06, pkcs8::OID_SECP256K1, &mut parameters);
+
+        let mut key = Vec::with_capacity(48);
+        pkcs8::write(0x02, &[1], &mut key);
+        pkcs8::write(0x04, &sk, &mut key);
+        pkcs8::write(0xa0, &parameters, &mut key);
+        sk.zeroize();
+
+        let mut der = Vec::with_capacity(key.len() + 2);
+        pkcs8::write(0x30, &key, &mut der);
+        key.zeroize();
+        der
+    }
+
     /// Sign a message with this secret key, producing a DER-encoded
     /// ECDSA signature, as defined in [RFC3278].
     ///
@@ -200,4 +250,17 @@ mod tests {
         assert_eq!(sk1.0.serialize(), sk2.0.serialize());
         assert_eq!(sk_bytes, [0; 32]);
     }
+
+    #[test]
+    fn secp256k1_der_and_pkcs8() {
+        let sk1 = SecretKey::generate();
+        let sk2 = SecretKey::from_der(sk1.to_der()).unwrap();
+        assert_eq!(sk1.to_bytes(), sk2.to_bytes());
+
+        let kp1 = Keypair::from(sk1);
+        let mut der = kp1.to_pkcs8();
+        let kp2 = Keypair::from_pkcs8(&mut der).unwrap();
+        assert_eq!(kp1.secret().to_bytes(), kp2.secret().to_bytes());
+        assert!(der.iter().all(|b| *b == 0));
+    }
 }
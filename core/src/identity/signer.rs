@@ -0,0 +1,62 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Keypairs whose secret key is held outside of the process, e.g. by a signing daemon.
+
+use super::{Keypair, PublicKey, error::SigningError};
+use futures::future::{self, BoxFuture, FutureExt};
+
+/// Holder of the secret key of a keypair, producing signatures on request.
+///
+/// A signer is turned into a [`Keypair`] with [`Keypair::from_signer`].
+///
+/// Signing may be asynchronous, so the keypairs of signers can only sign with
+/// [`Keypair::sign_async`], which returns the future of the signer.
+pub trait Signer: Send + Sync + 'static {
+    /// Returns the public key of the keypair.
+    fn public(&self) -> PublicKey;
+
+    /// Signs a message with the secret key of the keypair, producing a signature that can be
+    /// verified with the public key.
+    fn sign(&self, msg: &[u8]) -> BoxFuture<'static, Result<Vec<u8>, SigningError>>;
+}
+
+/// A [`Signer`] backed by a keypair in memory, standing in for an external signer in tests.
+#[derive(Clone)]
+pub struct LocalSigner {
+    keypair: Keypair,
+}
+
+impl LocalSigner {
+    /// Creates a signer for the given keypair.
+    pub fn new(keypair: Keypair) -> Self {
+        LocalSigner { keypair }
+    }
+}
+
+impl Signer for LocalSigner {
+    fn public(&self) -> PublicKey {
+        self.keypair.public()
+    }
+
+    fn sign(&self, msg: &[u8]) -> BoxFuture<'static, Result<Vec<u8>, SigningError>> {
+        future::ready(self.keypair.sign(msg)).boxed()
+    }
+}
@@ -39,8 +39,23 @@ mod keys_proto {
     include!(concat!(env!("OUT_DIR"), "/keys_proto.rs"));
 }
 
+mod envelope_proto {
+    include!(concat!(env!("OUT_DIR"), "/envelope_proto.rs"));
+}
+
+mod peer_record_proto {
+    include!(concat!(env!("OUT_DIR"), "/peer_record_proto.rs"));
+}
+
+#[cfg(not(any(target_os = "emscripten", target_os = "unknown")))]
+mod keystore_proto {
+    include!(concat!(env!("OUT_DIR"), "/keystore_proto.rs"));
+}
+
 /// Multi-address re-export.
 pub use multiaddr;
+/// CID re-export.
+pub use cid;
 pub type Negotiated<T> = futures::compat::Compat01As03<multistream_select::Negotiated<futures::compat::Compat<T>>>;
 
 use std::{future::Future, pin::Pin};
@@ -52,12 +67,16 @@ pub mod either;
 pub mod identity;
 pub mod muxing;
 pub mod nodes;
+pub mod peer_record;
+pub mod signed_envelope;
 pub mod transport;
 pub mod upgrade;
 
 pub use multiaddr::Multiaddr;
-pub use muxing::StreamMuxer;
+pub use muxing::{StreamMuxer, StreamMuxerEvent};
 pub use peer_id::PeerId;
+pub use peer_record::PeerRecord;
+pub use signed_envelope::SignedEnvelope;
 pub use identity::PublicKey;
 pub use transport::Transport;
 pub use translation::address_translation;
@@ -54,7 +54,8 @@
 use fnv::FnvHashMap;
 use futures::{future, prelude::*, task::Context, task::Poll};
 use parking_lot::Mutex;
-use std::{io, ops::Deref, fmt, pin::Pin, sync::atomic::{AtomicUsize, Ordering}};
+use crate::Multiaddr;
+use std::{error, io, ops::Deref, fmt, pin::Pin, sync::atomic::{AtomicUsize, Ordering}};
 
 pub use self::singleton::SingletonMuxer;
 
@@ -64,14 +65,19 @@ mod singleton;
 ///
 /// The state of a muxer, as exposed by this API, is the following:
 ///
-/// - A connection to the remote. The `is_remote_acknowledged`, `flush_all` and `close` methods
-///   operate on this.
-/// - A list of substreams that are open. The `poll_inbound`, `poll_outbound`, `read_substream`,
+/// - A connection to the remote. The `poll_event`, `is_remote_acknowledged`, `flush_all` and
+///   `poll_close` methods operate on this.
+/// - A list of substreams that are open. The `poll_event`, `poll_outbound`, `read_substream`,
 ///   `write_substream`, `flush_substream`, `shutdown_substream` and `destroy_substream` methods
 ///   allow controlling these entries.
 /// - A list of outbound substreams being opened. The `open_outbound`, `poll_outbound` and
 ///   `destroy_outbound` methods allow controlling these entries.
 ///
+/// When a substream is terminated abruptly, reading or writing produces an error whose inner
+/// error is a `SubstreamTermination`, which can be extracted with
+/// `SubstreamTermination::from_io_error`. A substream closed gracefully by the remote produces
+/// EOF when read from.
+///
 pub trait StreamMuxer {
     /// Type of the object that represents the raw substream where data can be read and written.
     type Substream;
@@ -82,7 +88,8 @@ pub trait StreamMuxer {
     /// Error type of the muxer
     type Error: Into<io::Error>;
 
-    /// Polls for an inbound substream.
+    /// Polls for a connection-wide event, such as a new inbound substream or a change of the
+    /// address of the remote.
     ///
     /// This function behaves the same as a `Stream`.
     ///
@@ -91,7 +98,7 @@ pub trait StreamMuxer {
     /// Only the latest task that was used to call this method may be notified.
     ///
     /// An error can be generated if the connection has been closed.
-    fn poll_inbound(&self, cx: &mut Context) -> Poll<Result<Self::Substream, Self::Error>>;
+    fn poll_event(&self, cx: &mut Context) -> Poll<Result<StreamMuxerEvent<Self::Substream>, Self::Error>>;
 
     /// Opens a new outgoing substream, and produces the equivalent to a future that will be
     /// resolved when it becomes available.
@@ -125,8 +132,8 @@ pub trait StreamMuxer {
     /// If `Async::Ready(0)` is returned, the substream has been closed by the remote and should
     /// no longer be read afterwards.
     ///
-    /// An error can be generated if the connection has been closed, or if a protocol misbehaviour
-    /// happened.
+    /// An error can be generated if the substream has been reset, if the connection has been
+    /// closed, or if a protocol misbehaviour happened. See `SubstreamTermination`.
     fn read_substream(&self, cx: &mut Context, s: &mut Self::Substream, buf: &mut [u8])
         -> Poll<Result<usize, Self::Error>>;
 
@@ -186,7 +193,8 @@ pub trait StreamMuxer {
     ///
     /// After this has returned `Poll::Ready(Ok(()))`, the muxer has become useless. All
     /// subsequent reads must return either `EOF` or an error. All subsequent writes, shutdowns,
-    /// or polls must generate an error or be ignored.
+    /// or polls must generate an error or be ignored. Errors produced by substreams afterwards
+    /// should be `SubstreamTermination::ConnectionClosed`.
     ///
     /// Calling this method implies `flush_all`.
     ///
@@ -194,18 +202,123 @@ pub trait StreamMuxer {
     /// >           that the remote is properly informed of the shutdown. However, apart from
     /// >           properly informing the remote, there is no difference between this and
     /// >           immediately dropping the muxer.
-    fn close(&self, cx: &mut Context) -> Poll<Result<(), Self::Error>>;
+    fn poll_close(&self, cx: &mut Context) -> Poll<Result<(), Self::Error>>;
 
     /// Flush this `StreamMuxer`.
     ///
     /// This drains any write buffers of substreams and delivers any pending shutdown notifications
-    /// due to `shutdown_substream` or `close`. One may thus shutdown groups of substreams
+    /// due to `shutdown_substream` or `poll_close`. One may thus shutdown groups of substreams
     /// followed by a final `flush_all` instead of having to do `flush_substream` for each.
     fn flush_all(&self, cx: &mut Context) -> Poll<Result<(), Self::Error>>;
 }
 
-/// Polls for an inbound from the muxer but wraps the output in an object that
-/// implements `Read`/`Write`/`AsyncRead`/`AsyncWrite`.
+/// Event about a connection, reported by an implementation of [`StreamMuxer`].
+#[derive(Debug)]
+pub enum StreamMuxerEvent<T> {
+    /// Remote has opened a new substream. Contains the substream in question.
+    InboundSubstream(T),
+
+    /// Address to the remote has changed. The previous one is now obsolete.
+    ///
+    /// > **Note**: This can for example happen when using the QUIC protocol, where the two nodes
+    /// >           can change their IP address while retaining the same connection.
+    AddressChange(Multiaddr),
+}
+
+impl<T> StreamMuxerEvent<T> {
+    /// If `self` is a `StreamMuxerEvent::InboundSubstream`, returns the content. Otherwise
+    /// returns `None`.
+    pub fn into_inbound_substream(self) -> Option<T> {
+        if let StreamMuxerEvent::InboundSubstream(s) = self {
+            Some(s)
+        } else {
+            None
+        }
+    }
+
+    /// Map the stream within `StreamMuxerEvent::InboundSubstream` to a new type.
+    pub fn map_inbound_stream<O>(self, map: impl FnOnce(T) -> O) -> StreamMuxerEvent<O> {
+        match self {
+            StreamMuxerEvent::InboundSubstream(stream) => StreamMuxerEvent::InboundSubstream(map(stream)),
+            StreamMuxerEvent::AddressChange(addr) => StreamMuxerEvent::AddressChange(addr),
+        }
+    }
+}
+
+/// Reason why a substream can no longer be used.
+///
+/// Implementations of `StreamMuxer` put this as the inner error of the `io::Error`s they produce
+/// for substreams, so that it survives the conversion into `io::Error` and can be recovered with
+/// `SubstreamTermination::from_io_error`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SubstreamTermination {
+    /// The remote has abruptly reset the substream.
+    RemoteReset,
+    /// The substream has been reset on our side, for example because the remote sent more data
+    /// than we are willing to buffer.
+    LocalReset,
+    /// The substream has been closed gracefully, and the operation is no longer possible. For
+    /// example, writing after the writing side has been shut down.
+    Closed,
+    /// The connection the substream belongs to has been closed.
+    ConnectionClosed,
+}
+
+impl SubstreamTermination {
+    /// Extracts the `SubstreamTermination` from an error produced by a substream, if any.
+    pub fn from_io_error(err: &io::Error) -> Option<SubstreamTermination> {
+        err.get_ref()
+            .and_then(|inner| inner.downcast_ref::<SubstreamTermination>())
+            .cloned()
+    }
+
+    /// Returns the `io::ErrorKind` of the `io::Error` built from this termination.
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            SubstreamTermination::RemoteReset => io::ErrorKind::ConnectionReset,
+            SubstreamTermination::LocalReset => io::ErrorKind::ConnectionAborted,
+            SubstreamTermination::Closed => io::ErrorKind::BrokenPipe,
+            SubstreamTermination::ConnectionClosed => io::ErrorKind::NotConnected,
+        }
+    }
+}
+
+impl fmt::Display for SubstreamTermination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubstreamTermination::RemoteReset => write!(f, "Substream reset by the remote"),
+            SubstreamTermination::LocalReset => write!(f, "Substream reset locally"),
+            SubstreamTermination::Closed => write!(f, "Substream closed"),
+            SubstreamTermination::ConnectionClosed => write!(f, "Connection closed"),
+        }
+    }
+}
+
+impl error::Error for SubstreamTermination {}
+
+impl From<SubstreamTermination> for io::Error {
+    fn from(termination: SubstreamTermination) -> io::Error {
+        io::Error::new(termination.kind(), termination)
+    }
+}
+
+/// Polls for an event from the muxer and, if an inbound substream, wraps this substream in an
+/// object that implements `Read`/`Write`/`AsyncRead`/`AsyncWrite`.
+#[inline]
+pub fn event_from_ref_and_wrap<P>(
+    muxer: P,
+) -> impl Future<Output = Result<StreamMuxerEvent<SubstreamRef<P>>, <P::Target as StreamMuxer>::Error>>
+where
+    P: Deref + Clone,
+    P::Target: StreamMuxer,
+{
+    let muxer2 = muxer.clone();
+    future::poll_fn(move |cx| muxer.poll_event(cx))
+        .map_ok(|event| event.map_inbound_stream(move |substream| substream_from_ref(muxer2, substream)))
+}
+
+/// Polls for an inbound substream from the muxer, ignoring the other events, and wraps the
+/// output in an object that implements `Read`/`Write`/`AsyncRead`/`AsyncWrite`.
 #[inline]
 pub fn inbound_from_ref_and_wrap<P>(
     muxer: P,
@@ -215,8 +328,16 @@ where
     P::Target: StreamMuxer,
 {
     let muxer2 = muxer.clone();
-    future::poll_fn(move |cx| muxer.poll_inbound(cx))
-        .map_ok(|substream| substream_from_ref(muxer2, substream))
+    future::poll_fn(move |cx| loop {
+        match muxer.poll_event(cx) {
+            Poll::Ready(Ok(StreamMuxerEvent::InboundSubstream(substream))) =>
+                return Poll::Ready(Ok(substream)),
+            Poll::Ready(Ok(StreamMuxerEvent::AddressChange(_))) => {},
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+            Poll::Pending => return Poll::Pending,
+        }
+    })
+    .map_ok(|substream| substream_from_ref(muxer2, substream))
 }
 
 /// Same as `outbound_from_ref`, but wraps the output in an object that
@@ -483,8 +604,8 @@ impl StreamMuxer for StreamMuxerBox {
     type Error = io::Error;
 
     #[inline]
-    fn poll_inbound(&self, cx: &mut Context) -> Poll<Result<Self::Substream, Self::Error>> {
-        self.inner.poll_inbound(cx)
+    fn poll_event(&self, cx: &mut Context) -> Poll<Result<StreamMuxerEvent<Self::Substream>, Self::Error>> {
+        self.inner.poll_event(cx)
     }
 
     #[inline]
@@ -528,8 +649,8 @@ impl StreamMuxer for StreamMuxerBox {
     }
 
     #[inline]
-    fn close(&self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
-        self.inner.close(cx)
+    fn poll_close(&self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_close(cx)
     }
 
     #[inline]
@@ -560,16 +681,18 @@ where
     type Error = io::Error;
 
     #[inline]
-    fn poll_inbound(&self, cx: &mut Context) -> Poll<Result<Self::Substream, Self::Error>> {
-        let substream = match self.inner.poll_inbound(cx) {
+    fn poll_event(&self, cx: &mut Context) -> Poll<Result<StreamMuxerEvent<Self::Substream>, Self::Error>> {
+        let substream = match self.inner.poll_event(cx) {
             Poll::Pending => return Poll::Pending,
-            Poll::Ready(Ok(s)) => s,
+            Poll::Ready(Ok(StreamMuxerEvent::AddressChange(a))) =>
+                return Poll::Ready(Ok(StreamMuxerEvent::AddressChange(a))),
+            Poll::Ready(Ok(StreamMuxerEvent::InboundSubstream(s))) => s,
             Poll::Ready(Err(err)) => return Poll::Ready(Err(err.into())),
         };
 
         let id = self.next_substream.fetch_add(1, Ordering::Relaxed);
         self.substreams.lock().insert(id, substream);
-        Poll::Ready(Ok(id))
+        Poll::Ready(Ok(StreamMuxerEvent::InboundSubstream(id)))
     }
 
     #[inline]
@@ -634,8 +757,8 @@ where
     }
 
     #[inline]
-    fn close(&self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
-        self.inner.close(cx).map_err(|e| e.into())
+    fn poll_close(&self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_close(cx).map_err(|e| e.into())
     }
 
     #[inline]
@@ -18,7 +18,7 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::{Endpoint, muxing::StreamMuxer};
+use crate::{Endpoint, muxing::{StreamMuxer, StreamMuxerEvent}};
 use futures::prelude::*;
 use parking_lot::Mutex;
 use std::{io, pin::Pin, sync::atomic::{AtomicBool, Ordering}, task::Context, task::Poll};
@@ -67,14 +67,14 @@ where
     type OutboundSubstream = OutboundSubstream;
     type Error = io::Error;
 
-    fn poll_inbound(&self, _: &mut Context) -> Poll<Result<Self::Substream, io::Error>> {
+    fn poll_event(&self, _: &mut Context) -> Poll<Result<StreamMuxerEvent<Self::Substream>, io::Error>> {
         match self.endpoint {
             Endpoint::Dialer => return Poll::Pending,
             Endpoint::Listener => {}
         }
 
         if !self.substream_extracted.swap(true, Ordering::Relaxed) {
-            Poll::Ready(Ok(Substream {}))
+            Poll::Ready(Ok(StreamMuxerEvent::InboundSubstream(Substream {})))
         } else {
             Poll::Pending
         }
@@ -127,8 +127,8 @@ where
         self.remote_acknowledged.load(Ordering::Acquire)
     }
 
-    fn close(&self, cx: &mut Context) -> Poll<Result<(), io::Error>> {
-        // The `StreamMuxer` trait requires that `close()` implies `flush_all()`.
+    fn poll_close(&self, cx: &mut Context) -> Poll<Result<(), io::Error>> {
+        // The `StreamMuxer` trait requires that `poll_close()` implies `flush_all()`.
         self.flush_all(cx)
     }
 
@@ -18,7 +18,7 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::{PeerId, muxing::StreamMuxer};
+use crate::{Multiaddr, PeerId, muxing::StreamMuxer};
 use crate::nodes::node::{NodeEvent, NodeStream, Substream, Close};
 use std::{error, fmt, io, pin::Pin, task::Context, task::Poll};
 
@@ -53,6 +53,11 @@ pub trait NodeHandler {
     /// Injects an event coming from the outside into the handler.
     fn inject_event(&mut self, event: Self::InEvent);
 
+    /// Indicates to the handler that the address of the remote has changed.
+    ///
+    /// Does nothing by default.
+    fn inject_address_change(&mut self, _new_address: &Multiaddr) {}
+
     /// Should behave like `Stream::poll()`.
     ///
     /// Returning an error will close the connection to the remote.
@@ -234,6 +239,9 @@ where
                     let endpoint = NodeHandlerEndpoint::Dialer(user_data);
                     self.handler.inject_substream(substream, endpoint)
                 }
+                Poll::Ready(Ok(NodeEvent::AddressChange { new_address })) => {
+                    self.handler.inject_address_change(&new_address)
+                }
                 Poll::Ready(Err(err)) => return Poll::Ready(Err(HandledNodeError::Node(err))),
             }
 
@@ -19,7 +19,7 @@
 // DEALINGS IN THE SOFTWARE.
 
 use futures::prelude::*;
-use crate::muxing;
+use crate::{muxing, Multiaddr};
 use smallvec::SmallVec;
 use std::{fmt, io::Error as IoError, pin::Pin, sync::Arc, task::Context, task::Poll};
 
@@ -85,6 +85,15 @@ where
         /// destroyed or `close_graceful` is called.
         substream: Substream<TMuxer>,
     },
+
+    /// Address to the remote has changed. The previous one is now obsolete.
+    ///
+    /// > **Note**: This can for example happen when using the QUIC protocol, where the two nodes
+    /// >           can change their IP address while retaining the same connection.
+    AddressChange {
+        /// The new address of the remote.
+        new_address: Multiaddr,
+    },
 }
 
 /// Identifier for a substream being opened.
@@ -142,14 +151,19 @@ where
 
     /// Provides an API similar to `Future`.
     pub fn poll(&mut self, cx: &mut Context) -> Poll<Result<NodeEvent<TMuxer, TUserData>, IoError>> {
-        // Polling inbound substream.
-        match self.muxer.poll_inbound(cx) {
-            Poll::Ready(Ok(substream)) => {
+        // Polling inbound substream or address change.
+        match self.muxer.poll_event(cx) {
+            Poll::Ready(Ok(muxing::StreamMuxerEvent::InboundSubstream(substream))) => {
                 let substream = muxing::substream_from_ref(self.muxer.clone(), substream);
                 return Poll::Ready(Ok(NodeEvent::InboundSubstream {
                     substream,
                 }));
             }
+            Poll::Ready(Ok(muxing::StreamMuxerEvent::AddressChange(new_address))) => {
+                return Poll::Ready(Ok(NodeEvent::AddressChange {
+                    new_address,
+                }));
+            }
             Poll::Ready(Err(err)) => return Poll::Ready(Err(err.into())),
             Poll::Pending => {}
         }
@@ -214,7 +228,7 @@ where
     type Output = Result<(), IoError>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
-        match self.muxer.close(cx) {
+        match self.muxer.poll_close(cx) {
             Poll::Pending => Poll::Pending,
             Poll::Ready(Ok(())) => Poll::Ready(Ok(())),
             Poll::Ready(Err(err)) => Poll::Ready(Err(err.into())),
@@ -251,6 +265,11 @@ where
                     .field("substream", substream)
                     .finish()
             },
+            NodeEvent::AddressChange { new_address } => {
+                f.debug_struct("NodeEvent::AddressChange")
+                    .field("new_address", new_address)
+                    .finish()
+            },
         }
     }
 }
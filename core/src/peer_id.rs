@@ -20,6 +20,7 @@
 
 use crate::PublicKey;
 use bs58;
+use cid::{Cid, Codec};
 use thiserror::Error;
 use multihash;
 use std::{convert::TryFrom, fmt, hash, str::FromStr};
@@ -30,7 +31,13 @@ const MAX_INLINE_KEY_LENGTH: usize = 42;
 
 /// Identifier of a peer of the network.
 ///
-/// The data is a multihash of the public key of the peer.
+/// The data is a multihash of the public key of the peer. Short public keys, such as Ed25519
+/// keys, are inlined with the identity multihash, and can be recovered with
+/// [`PeerId::to_public_key`].
+///
+/// A `PeerId` is displayed in base-58 by default. The alternate form (`{:#}`) displays it as a
+/// CIDv1 in base-32, as produced by [`PeerId::to_cid_string`]. Both forms are accepted when
+/// parsing.
 // TODO: maybe keep things in decoded version?
 #[derive(Clone, Eq)]
 pub struct PeerId {
@@ -47,7 +54,11 @@ impl fmt::Debug for PeerId {
 
 impl fmt::Display for PeerId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.to_base58().fmt(f)
+        if f.alternate() {
+            self.to_cid_string().fmt(f)
+        } else {
+            self.to_base58().fmt(f)
+        }
     }
 }
 
@@ -78,9 +89,7 @@ impl PeerId {
     pub fn from_bytes(data: Vec<u8>) -> Result<PeerId, Vec<u8>> {
         match multihash::Multihash::from_bytes(data) {
             Ok(multihash) => {
-                if multihash.algorithm() == multihash::Hash::SHA2256
-                    || multihash.algorithm() == multihash::Hash::Identity
-                {
+                if is_valid_multihash(&multihash) {
                     Ok(PeerId { multihash })
                 } else {
                     Err(multihash.into_bytes())
@@ -93,7 +102,7 @@ impl PeerId {
     /// Turns a `Multihash` into a `PeerId`. If the multihash doesn't use the correct algorithm,
     /// returns back the data as an error.
     pub fn from_multihash(data: multihash::Multihash) -> Result<PeerId, multihash::Multihash> {
-        if data.algorithm() == multihash::Hash::SHA2256 || data.algorithm() == multihash::Hash::Identity {
+        if is_valid_multihash(&data) {
             Ok(PeerId { multihash: data })
         } else {
             Err(data)
@@ -128,6 +137,38 @@ impl PeerId {
         bs58::encode(self.multihash.as_bytes()).into_string()
     }
 
+    /// Returns the CIDv1 of this `PeerId` with the `libp2p-key` codec, encoded in base-32 with
+    /// its multibase prefix, as defined in the [peer ID specification].
+    ///
+    /// [peer ID specification]: https://github.com/libp2p/specs/blob/master/peer-ids/peer-ids.md#string-representation
+    pub fn to_cid_string(&self) -> String {
+        self.to_cid().to_string()
+    }
+
+    /// Returns the CIDv1 of this `PeerId` with the `libp2p-key` codec.
+    pub fn to_cid(&self) -> Cid {
+        Cid::new_v1(Codec::Libp2pKey, self.multihash.clone())
+    }
+
+    /// Turns a CID with the `libp2p-key` codec into a `PeerId`. If the CID has another codec or
+    /// its multihash doesn't use the correct algorithm, returns back the CID as an error.
+    pub fn from_cid(cid: Cid) -> Result<PeerId, Cid> {
+        if cid.codec() == Codec::Libp2pKey && is_valid_multihash(cid.hash()) {
+            Ok(PeerId { multihash: cid.hash().clone() })
+        } else {
+            Err(cid)
+        }
+    }
+
+    /// Returns the public key inlined in this `PeerId`, if it uses the identity multihash and
+    /// contains a valid public key.
+    pub fn to_public_key(&self) -> Option<PublicKey> {
+        if self.multihash.algorithm() != multihash::Hash::Identity {
+            return None
+        }
+        PublicKey::from_protobuf_encoding(self.multihash.digest()).ok()
+    }
+
     /// Checks whether the public key passed as parameter matches the public key of this `PeerId`.
     ///
     /// Returns `None` if this `PeerId`s hash algorithm is not supported when encoding the
@@ -143,6 +184,16 @@ impl PeerId {
     }
 }
 
+/// Checks whether `multihash` uses an algorithm supported for peer IDs, with a digest that isn't
+/// truncated.
+fn is_valid_multihash(multihash: &multihash::Multihash) -> bool {
+    match multihash.algorithm() {
+        multihash::Hash::Identity => true,
+        multihash::Hash::SHA2256 => multihash.digest().len() == usize::from(multihash::Hash::SHA2256.size()),
+        _ => false,
+    }
+}
+
 impl hash::Hash for PeerId {
     fn hash<H>(&self, state: &mut H)
     where
@@ -227,10 +278,26 @@ impl From<PeerId> for multihash::Multihash {
     }
 }
 
+impl From<PeerId> for Cid {
+    fn from(peer_id: PeerId) -> Self {
+        peer_id.to_cid()
+    }
+}
+
+impl TryFrom<Cid> for PeerId {
+    type Error = Cid;
+
+    fn try_from(value: Cid) -> Result<Self, Self::Error> {
+        PeerId::from_cid(value)
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum ParseError {
     #[error("base-58 decode error: {0}")]
     B58(#[from] bs58::decode::Error),
+    #[error("invalid CID: {0}")]
+    InvalidCid(#[from] cid::Error),
     #[error("decoding multihash failed")]
     MultiHash,
 }
@@ -238,16 +305,25 @@ pub enum ParseError {
 impl FromStr for PeerId {
     type Err = ParseError;
 
-    #[inline]
+    /// Parses a base-58 encoded multihash, or a CIDv1 with the `libp2p-key` codec encoded with
+    /// its multibase prefix.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let bytes = bs58::decode(s).into_vec()?;
-        PeerId::from_bytes(bytes).map_err(|_| ParseError::MultiHash)
+        // Base-58 multihashes always start with `1` (identity) or `Qm` (sha2-256), which no
+        // multibase prefix of a CID clashes with.
+        if s.starts_with('1') || s.starts_with('Q') {
+            let bytes = bs58::decode(s).into_vec()?;
+            return PeerId::from_bytes(bytes).map_err(|_| ParseError::MultiHash)
+        }
+
+        let cid = s.parse::<Cid>()?;
+        PeerId::from_cid(cid).map_err(|_| ParseError::MultiHash)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{PeerId, identity};
+    use super::ParseError;
     use std::{convert::TryFrom as _, hash::{self, Hasher as _}};
 
     #[test]
@@ -271,6 +347,59 @@ mod tests {
         assert_eq!(peer_id, second);
     }
 
+    #[test]
+    fn peer_id_to_cid_then_back() {
+        let peer_id = identity::Keypair::generate_ed25519().public().into_peer_id();
+        let cid = peer_id.to_cid_string();
+        assert!(cid.starts_with("bafz"));
+        assert_eq!(format!("{:#}", peer_id), cid);
+        assert_eq!(cid.parse::<PeerId>().unwrap(), peer_id);
+        assert_eq!(cid.to_uppercase().parse::<PeerId>().unwrap(), peer_id);
+        assert!(cid.replacen("bafz", "bAFZ", 1).parse::<PeerId>().is_err());
+        assert_eq!(PeerId::try_from(peer_id.to_cid()).unwrap(), peer_id);
+
+        let other_codec = cid::Cid::new_v1(cid::Codec::Raw, peer_id.clone().into());
+        assert!(PeerId::from_cid(other_codec).is_err());
+    }
+
+    #[test]
+    fn peer_id_string_representations() {
+        // The examples of the peer ID specification.
+        let peer_id: PeerId = "QmYyQSo1c1Ym7orWxLYvCrM2EmxFTANf8wXmmE7DWjhx5N".parse().unwrap();
+        assert_eq!(peer_id.to_cid_string(), "bafzbeie5745rpv2m6tjyuugywy4d5ewrqgqqhfnf445he3omzpjbx5xqxe");
+        assert_eq!("bafzbeie5745rpv2m6tjyuugywy4d5ewrqgqqhfnf445he3omzpjbx5xqxe".parse::<PeerId>().unwrap(), peer_id);
+        assert!(peer_id.to_public_key().is_none());
+
+        let peer_id: PeerId = "12D3KooWD3eckifWpRn9wQpMG9R9hX3sD158z7EqHWmweQAJU5SA".parse().unwrap();
+        assert_eq!(peer_id.to_cid_string(), "bafzaajaiaejcal72gwuz2or47oyxxn6b3rkwdmmkrxgkjxzy3rqt5kczyn7lcm3l");
+        let key = peer_id.to_public_key().unwrap();
+        assert_eq!(peer_id.is_public_key(&key), Some(true));
+        assert_eq!(key.into_peer_id().to_base58(), "12D3KooWD3eckifWpRn9wQpMG9R9hX3sD158z7EqHWmweQAJU5SA");
+
+        // A CIDv1 with another codec, here `dag-pb`.
+        assert!("bafybeie5745rpv2m6tjyuugywy4d5ewrqgqqhfnf445he3omzpjbx5xqxe".parse::<PeerId>().is_err());
+        match "xyz".parse::<PeerId>() {
+            Err(ParseError::InvalidCid(cid::Error::UnknownBase)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match "bafybeie5745rpv2m6tjyuugywy4d5ewrqgqqhfnf445he3omzpjbx5xqxe".parse::<PeerId>() {
+            Err(ParseError::MultiHash) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match "b!!!".parse::<PeerId>() {
+            Err(ParseError::InvalidCid(cid::Error::InvalidBaseString)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn inlined_public_keys() {
+        let key = identity::Keypair::generate_ed25519().public();
+        let peer_id = key.clone().into_peer_id();
+        assert_eq!(peer_id.to_public_key(), Some(key));
+        assert!(peer_id.to_base58().starts_with("12D3KooW"));
+    }
+
     #[test]
     fn random_peer_id_is_valid() {
         for _ in 0 .. 5000 {
@@ -279,6 +408,13 @@ mod tests {
         }
     }
 
+    #[test]
+    fn truncated_multihash_is_invalid() {
+        let multihash = multihash::encode(multihash::Hash::SHA2256, b"hello world").unwrap();
+        assert!(PeerId::from_multihash(multihash.truncate(20)).is_err());
+        assert!(PeerId::from_bytes(multihash.truncate(20).into_bytes()).is_err());
+    }
+
     #[test]
     fn peer_id_identity_equal_to_sha2256() {
         let random_bytes = (0..64).map(|_| rand::random::<u8>()).collect::<Vec<u8>>();
@@ -0,0 +1,272 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Self-certified addressing information of peers, as described in the [RFC 0003].
+//!
+//! A [`PeerRecord`] contains the addresses of a peer, and is always carried in a
+//! [`SignedEnvelope`] signed by the keypair of that peer. It can therefore be passed on by
+//! other nodes without having to trust them.
+//!
+//! [RFC 0003]: https://github.com/libp2p/specs/blob/master/RFC/0003-routing-records.md
+
+use crate::{
+    Multiaddr,
+    PeerId,
+    identity::{self, error::SigningError},
+    peer_record_proto,
+    signed_envelope::{ReadPayloadError, SignedEnvelope}
+};
+use futures::future::{BoxFuture, FutureExt};
+use prost::Message;
+use std::{cmp, convert::TryFrom, sync::atomic::{AtomicU64, Ordering}, time::SystemTime};
+use thiserror::Error;
+
+/// Domain of the signature of the envelopes of peer records.
+const DOMAIN_SEP: &str = "libp2p-routing-state";
+
+/// Payload type of peer records, i.e. the `libp2p-peer-record` multicodec.
+const PAYLOAD_TYPE: &[u8] = &[0x03, 0x01];
+
+/// Sequence number of the last record created with `PeerRecord::new`.
+static LAST_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// The addresses of a peer, signed by the peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerRecord {
+    peer_id: PeerId,
+    seq: u64,
+    addresses: Vec<Multiaddr>,
+    /// The envelope of the record, as it was signed.
+    envelope: SignedEnvelope,
+}
+
+impl PeerRecord {
+    /// Creates a record of the given addresses signed with `key`.
+    ///
+    /// The sequence number is the current UNIX time in nanoseconds, or one more than the
+    /// sequence number of the previous record if the clock didn't move forward, so that the new
+    /// record supersedes the previous ones.
+    pub fn new(key: &identity::Keypair, addresses: Vec<Multiaddr>) -> Result<Self, SigningError> {
+        PeerRecord::with_seq(key, next_seq(), addresses)
+    }
+
+    /// Same as [`PeerRecord::new`], but signs with [`identity::Keypair::sign_async`], which
+    /// supports the keypairs of external signers.
+    pub fn new_async(key: &identity::Keypair, addresses: Vec<Multiaddr>)
+        -> BoxFuture<'static, Result<Self, SigningError>>
+    {
+        let peer_id = key.public().into_peer_id();
+        let seq = next_seq();
+        let payload = encode_record(&peer_id, seq, &addresses);
+
+        SignedEnvelope::new_async(key, DOMAIN_SEP, PAYLOAD_TYPE.to_vec(), payload)
+            .map(move |envelope| Ok(PeerRecord { peer_id, seq, addresses, envelope: envelope? }))
+            .boxed()
+    }
+
+    /// Creates a record of the given addresses with an explicit sequence number.
+    pub fn with_seq(
+        key: &identity::Keypair,
+        seq: u64,
+        addresses: Vec<Multiaddr>,
+    ) -> Result<Self, SigningError> {
+        let peer_id = key.public().into_peer_id();
+        let payload = encode_record(&peer_id, seq, &addresses);
+        let envelope = SignedEnvelope::new(key, DOMAIN_SEP, PAYLOAD_TYPE.to_vec(), payload)?;
+
+        Ok(PeerRecord { peer_id, seq, addresses, envelope })
+    }
+
+    /// Extracts a record from an envelope, checking the signature of the envelope and that the
+    /// record has been signed by the peer it describes.
+    pub fn from_signed_envelope(envelope: SignedEnvelope) -> Result<Self, FromEnvelopeError> {
+        let (payload, signing_key) = envelope.payload_and_signing_key(DOMAIN_SEP, PAYLOAD_TYPE)?;
+        let record = peer_record_proto::PeerRecord::decode(payload)?;
+
+        let peer_id = PeerId::from_bytes(record.peer_id)
+            .map_err(|_| FromEnvelopeError::InvalidPeerId)?;
+        if peer_id != signing_key.clone().into_peer_id() {
+            return Err(FromEnvelopeError::MismatchedSignature);
+        }
+
+        let addresses = record.addresses
+            .into_iter()
+            .map(|info| Multiaddr::try_from(info.multiaddr))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(PeerRecord { peer_id, seq: record.seq, addresses, envelope })
+    }
+
+    /// Returns the envelope of the record, to be sent to other nodes.
+    pub fn to_signed_envelope(&self) -> SignedEnvelope {
+        self.envelope.clone()
+    }
+
+    /// Turns the record into its envelope, to be sent to other nodes.
+    pub fn into_signed_envelope(self) -> SignedEnvelope {
+        self.envelope
+    }
+
+    /// Returns the peer the record describes.
+    pub fn peer_id(&self) -> &PeerId {
+        &self.peer_id
+    }
+
+    /// Returns the sequence number of the record. Records with higher sequence numbers
+    /// supersede the ones with lower sequence numbers.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Returns the addresses of the peer.
+    pub fn addresses(&self) -> &[Multiaddr] {
+        &self.addresses
+    }
+}
+
+/// Returns the sequence number of a new record: the current UNIX time in nanoseconds, or one
+/// more than the sequence number of the previous record if the clock didn't move forward.
+fn next_seq() -> u64 {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("now() is never before UNIX_EPOCH; QED")
+        .as_nanos() as u64;
+    let mut last = LAST_SEQ.load(Ordering::Relaxed);
+    loop {
+        let seq = cmp::max(now, last + 1);
+        match LAST_SEQ.compare_exchange_weak(last, seq, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => return seq,
+            Err(actual) => last = actual,
+        }
+    }
+}
+
+/// Encodes the payload of the envelope of a record.
+fn encode_record(peer_id: &PeerId, seq: u64, addresses: &[Multiaddr]) -> Vec<u8> {
+    let record = peer_record_proto::PeerRecord {
+        peer_id: peer_id.clone().into_bytes(),
+        seq,
+        addresses: addresses
+            .iter()
+            .map(|addr| peer_record_proto::peer_record::AddressInfo {
+                multiaddr: addr.to_vec(),
+            })
+            .collect(),
+    };
+
+    let mut payload = Vec::with_capacity(record.encoded_len());
+    record.encode(&mut payload).expect("Vec<u8> provides capacity as needed");
+    payload
+}
+
+/// Error when extracting a [`PeerRecord`] from a [`SignedEnvelope`].
+#[derive(Debug, Error)]
+pub enum FromEnvelopeError {
+    /// The envelope doesn't contain a validly signed peer record.
+    #[error("Failed to extract the payload from the envelope")]
+    BadPayload(#[from] ReadPayloadError),
+    /// The payload is not a valid peer record.
+    #[error("Failed to decode the peer record")]
+    InvalidPeerRecord(#[from] prost::DecodeError),
+    /// The record contains an invalid peer ID.
+    #[error("Invalid peer ID")]
+    InvalidPeerId,
+    /// The record has not been signed by the peer it describes.
+    #[error("The signer of the envelope is not the peer of the record")]
+    MismatchedSignature,
+    /// The record contains an invalid address.
+    #[error("Invalid multiaddress")]
+    InvalidMultiaddr(#[from] multiaddr::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip_through_envelope() {
+        let key = identity::Keypair::generate_ed25519();
+        let addr: Multiaddr = "/ip4/1.2.3.4/tcp/5".parse().unwrap();
+        let record = PeerRecord::with_seq(&key, 7, vec![addr.clone()]).unwrap();
+
+        let bytes = record.to_signed_envelope().into_protobuf_encoding();
+        let envelope = SignedEnvelope::from_protobuf_encoding(&bytes).unwrap();
+        let decoded = PeerRecord::from_signed_envelope(envelope).unwrap();
+
+        assert_eq!(decoded, record);
+        assert_eq!(decoded.peer_id(), &key.public().into_peer_id());
+        assert_eq!(decoded.seq(), 7);
+        assert_eq!(decoded.addresses(), &[addr]);
+    }
+
+    #[test]
+    fn new_records_supersede_previous_ones() {
+        let key = identity::Keypair::generate_ed25519();
+        let first = PeerRecord::new(&key, Vec::new()).unwrap();
+        let second = PeerRecord::new(&key, Vec::new()).unwrap();
+        assert!(second.seq() > first.seq());
+    }
+
+    #[test]
+    fn records_of_external_signers() {
+        let local = identity::Keypair::generate_ed25519();
+        let key = identity::Keypair::from_signer(identity::signer::LocalSigner::new(local.clone()));
+        let addr: Multiaddr = "/ip4/1.2.3.4/tcp/5".parse().unwrap();
+        assert!(PeerRecord::new(&key, vec![addr.clone()]).is_err());
+
+        let record = futures::executor::block_on(PeerRecord::new_async(&key, vec![addr.clone()])).unwrap();
+        let decoded = PeerRecord::from_signed_envelope(record.to_signed_envelope()).unwrap();
+        assert_eq!(decoded, record);
+        assert_eq!(decoded.peer_id(), &local.public().into_peer_id());
+        assert_eq!(decoded.addresses(), &[addr]);
+    }
+
+    #[test]
+    fn record_must_be_signed_by_its_peer() {
+        let key = identity::Keypair::generate_ed25519();
+        let other = identity::Keypair::generate_ed25519();
+
+        // A record of `key` signed by `other`.
+        let record = peer_record_proto::PeerRecord {
+            peer_id: key.public().into_peer_id().into_bytes(),
+            seq: 1,
+            addresses: Vec::new(),
+        };
+        let mut payload = Vec::new();
+        record.encode(&mut payload).unwrap();
+        let envelope = SignedEnvelope::new(&other, DOMAIN_SEP, PAYLOAD_TYPE.to_vec(), payload).unwrap();
+
+        match PeerRecord::from_signed_envelope(envelope) {
+            Err(FromEnvelopeError::MismatchedSignature) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn other_envelopes_are_rejected() {
+        let key = identity::Keypair::generate_ed25519();
+        let envelope = SignedEnvelope::new(&key, "other", PAYLOAD_TYPE.to_vec(), Vec::new()).unwrap();
+
+        match PeerRecord::from_signed_envelope(envelope) {
+            Err(FromEnvelopeError::BadPayload(ReadPayloadError::InvalidSignature)) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+}
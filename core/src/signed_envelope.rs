@@ -0,0 +1,219 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Signed envelopes, as described in the [RFC 0002].
+//!
+//! An envelope carries a payload together with the public key of its signer and a signature
+//! that covers the payload, its type and a domain string. The domain string is never sent over
+//! the wire, but must be provided by the receiver, which prevents a signature produced for one
+//! purpose from being accepted for another.
+//!
+//! [RFC 0002]: https://github.com/libp2p/specs/blob/master/RFC/0002-signed-envelopes.md
+
+use crate::{PublicKey, envelope_proto, identity::{self, error::{DecodingError, SigningError}}};
+use futures::future::{BoxFuture, FutureExt};
+use prost::Message;
+use thiserror::Error;
+use unsigned_varint::encode;
+
+/// A payload signed by the keypair of a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedEnvelope {
+    key: PublicKey,
+    payload_type: Vec<u8>,
+    payload: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl SignedEnvelope {
+    /// Signs `payload` of type `payload_type` for the given domain with `key`.
+    pub fn new(
+        key: &identity::Keypair,
+        domain_separation: &str,
+        payload_type: Vec<u8>,
+        payload: Vec<u8>,
+    ) -> Result<Self, SigningError> {
+        let buffer = signature_payload(domain_separation, &payload_type, &payload);
+        let signature = key.sign(&buffer)?;
+
+        Ok(SignedEnvelope {
+            key: key.public(),
+            payload_type,
+            payload,
+            signature,
+        })
+    }
+
+    /// Same as [`SignedEnvelope::new`], but signs with [`identity::Keypair::sign_async`], which
+    /// supports the keypairs of external signers.
+    pub fn new_async(
+        key: &identity::Keypair,
+        domain_separation: &str,
+        payload_type: Vec<u8>,
+        payload: Vec<u8>,
+    ) -> BoxFuture<'static, Result<Self, SigningError>> {
+        let buffer = signature_payload(domain_separation, &payload_type, &payload);
+        let public = key.public();
+
+        key.sign_async(&buffer)
+            .map(move |signature| Ok(SignedEnvelope {
+                key: public,
+                payload_type,
+                payload,
+                signature: signature?,
+            }))
+            .boxed()
+    }
+
+    /// Returns whether the signature of the envelope is valid for the given domain.
+    pub fn verify(&self, domain_separation: &str) -> bool {
+        let buffer = signature_payload(domain_separation, &self.payload_type, &self.payload);
+        self.key.verify(&buffer, &self.signature)
+    }
+
+    /// Returns the payload of the envelope, after checking the signature for the given domain
+    /// and the type of the payload.
+    pub fn payload(
+        &self,
+        domain_separation: &str,
+        expected_payload_type: &[u8],
+    ) -> Result<&[u8], ReadPayloadError> {
+        self.payload_and_signing_key(domain_separation, expected_payload_type)
+            .map(|(payload, _)| payload)
+    }
+
+    /// Same as [`SignedEnvelope::payload`], but also returns the public key of the signer.
+    pub fn payload_and_signing_key(
+        &self,
+        domain_separation: &str,
+        expected_payload_type: &[u8],
+    ) -> Result<(&[u8], &PublicKey), ReadPayloadError> {
+        if self.payload_type != expected_payload_type {
+            return Err(ReadPayloadError::UnexpectedPayloadType {
+                expected: expected_payload_type.to_vec(),
+                got: self.payload_type.clone(),
+            });
+        }
+
+        if !self.verify(domain_separation) {
+            return Err(ReadPayloadError::InvalidSignature);
+        }
+
+        Ok((&self.payload, &self.key))
+    }
+
+    /// Returns the public key of the signer, without checking the signature.
+    pub fn signing_key(&self) -> &PublicKey {
+        &self.key
+    }
+
+    /// Encodes the envelope into a protobuf structure, for storage or exchange with other nodes.
+    pub fn into_protobuf_encoding(self) -> Vec<u8> {
+        let envelope = envelope_proto::Envelope {
+            public_key: self.key.into_protobuf_encoding(),
+            payload_type: self.payload_type,
+            payload: self.payload,
+            signature: self.signature,
+        };
+
+        let mut buf = Vec::with_capacity(envelope.encoded_len());
+        envelope.encode(&mut buf).expect("Vec<u8> provides capacity as needed");
+        buf
+    }
+
+    /// Decodes an envelope from a protobuf structure.
+    ///
+    /// The signature is not checked; see [`SignedEnvelope::payload`].
+    pub fn from_protobuf_encoding(bytes: &[u8]) -> Result<Self, DecodingError> {
+        let envelope = envelope_proto::Envelope::decode(bytes)
+            .map_err(|e| DecodingError::new("Protobuf").source(e))?;
+
+        Ok(SignedEnvelope {
+            key: PublicKey::from_protobuf_encoding(&envelope.public_key)?,
+            payload_type: envelope.payload_type,
+            payload: envelope.payload,
+            signature: envelope.signature,
+        })
+    }
+}
+
+/// Builds the buffer covered by the signature of an envelope.
+fn signature_payload(domain_separation: &str, payload_type: &[u8], payload: &[u8]) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(
+        domain_separation.len() + payload_type.len() + payload.len() + 3 * 10
+    );
+    for field in &[domain_separation.as_bytes(), payload_type, payload] {
+        buffer.extend_from_slice(encode::usize(field.len(), &mut encode::usize_buffer()));
+        buffer.extend_from_slice(field);
+    }
+    buffer
+}
+
+/// Error when reading the payload of a [`SignedEnvelope`].
+#[derive(Debug, Error)]
+pub enum ReadPayloadError {
+    /// The signature of the envelope is not valid for the domain.
+    #[error("Invalid signature")]
+    InvalidSignature,
+    /// The payload is not of the expected type.
+    #[error("Unexpected payload type, expected {expected:?} but got {got:?}")]
+    UnexpectedPayloadType {
+        /// The expected payload type.
+        expected: Vec<u8>,
+        /// The payload type of the envelope.
+        got: Vec<u8>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_verify() {
+        let key = identity::Keypair::generate_ed25519();
+        let envelope = SignedEnvelope::new(&key, "test", b"/type".to_vec(), b"data".to_vec()).unwrap();
+
+        let decoded = SignedEnvelope::from_protobuf_encoding(&envelope.clone().into_protobuf_encoding()).unwrap();
+        assert_eq!(decoded, envelope);
+        assert_eq!(decoded.payload("test", b"/type").unwrap(), b"data");
+        assert_eq!(decoded.signing_key(), &key.public());
+    }
+
+    #[test]
+    fn domain_and_type_are_checked() {
+        let key = identity::Keypair::generate_ed25519();
+        let envelope = SignedEnvelope::new(&key, "test", b"/type".to_vec(), b"data".to_vec()).unwrap();
+
+        assert!(!envelope.verify("other"));
+        match envelope.payload("other", b"/type") {
+            Err(ReadPayloadError::InvalidSignature) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+        match envelope.payload("test", b"/other") {
+            Err(ReadPayloadError::UnexpectedPayloadType { .. }) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+
+        let mut tampered = envelope.clone();
+        tampered.payload = b"other data".to_vec();
+        assert!(!tampered.verify("test"));
+    }
+}
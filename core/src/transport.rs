@@ -44,7 +44,7 @@ pub mod upgrade;
 mod optional;
 
 pub use self::choice::OrTransport;
-pub use self::memory::MemoryTransport;
+pub use self::memory::{LinkConfig, LinkHandle, MemoryTransport, SimulatedMemoryTransport};
 pub use self::optional::OptionalTransport;
 pub use self::upgrade::Upgrade;
 
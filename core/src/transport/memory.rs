@@ -20,15 +20,29 @@
 
 use crate::{Transport, transport::{TransportError, ListenerEvent}};
 use fnv::FnvHashMap;
-use futures::{future::{self, Ready}, prelude::*, channel::mpsc, task::Context, task::Poll};
+use futures::{future::{self, Ready}, prelude::*, channel::mpsc, ready, task::{Context, Poll, Waker}};
+use futures_timer::Delay;
 use lazy_static::lazy_static;
+use log::debug;
 use multiaddr::{Protocol, Multiaddr};
 use parking_lot::Mutex;
+use rand::{Rng, SeedableRng, rngs::StdRng};
 use rw_stream_sink::RwStreamSink;
-use std::{collections::hash_map::Entry, error, fmt, io, num::NonZeroU64, pin::Pin};
+use std::{
+    cmp,
+    collections::hash_map::Entry,
+    error,
+    fmt,
+    io,
+    num::NonZeroU64,
+    pin::Pin,
+    sync::Arc,
+    time::Duration
+};
+use wasm_timer::Instant;
 
 lazy_static! {
-    static ref HUB: Mutex<FnvHashMap<NonZeroU64, mpsc::Sender<Channel<Vec<u8>>>>> =
+    static ref HUB: Mutex<FnvHashMap<NonZeroU64, (mpsc::Sender<Channel<Vec<u8>>>, Option<Arc<Link>>)>> =
         Mutex::new(FnvHashMap::default());
 }
 
@@ -36,6 +50,197 @@ lazy_static! {
 #[derive(Debug, Copy, Clone, Default)]
 pub struct MemoryTransport;
 
+impl MemoryTransport {
+    /// Creates a transport whose connections are subject to the given network conditions.
+    ///
+    /// See [`SimulatedMemoryTransport`].
+    pub fn simulated(config: LinkConfig) -> SimulatedMemoryTransport {
+        SimulatedMemoryTransport::new(config)
+    }
+}
+
+/// Network conditions applied to the data sent by the endpoints of a
+/// [`SimulatedMemoryTransport`].
+///
+/// Data is transmitted in order, one write after the other, at the configured bandwidth, and
+/// is then delivered after the latency plus a random jitter. Random choices are made with an
+/// RNG seeded with `seed`, so that tests driven in the same order behave the same.
+#[derive(Debug, Clone)]
+pub struct LinkConfig {
+    latency: Duration,
+    jitter: Duration,
+    bandwidth: Option<NonZeroU64>,
+    drop_probability: f64,
+    seed: u64,
+}
+
+impl LinkConfig {
+    /// Creates a configuration of a link without latency, bandwidth limit, or connection drops.
+    pub fn new() -> Self {
+        LinkConfig {
+            latency: Duration::from_secs(0),
+            jitter: Duration::from_secs(0),
+            bandwidth: None,
+            drop_probability: 0.0,
+            seed: 0,
+        }
+    }
+
+    /// Sets the time it takes for data to arrive once it has been transmitted.
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Sets the maximum additional random delay of each write, chosen uniformly.
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets the number of bytes per second that can be transmitted, over all the connections
+    /// of an endpoint. A value of `0` removes the limit.
+    pub fn bandwidth(mut self, bytes_per_second: u64) -> Self {
+        self.bandwidth = NonZeroU64::new(bytes_per_second);
+        self
+    }
+
+    /// Sets the probability, between `0.0` and `1.0`, that a connection is dropped when writing
+    /// to it. The write then fails with `ConnectionReset` and the remote reads `BrokenPipe`.
+    pub fn drop_probability(mut self, probability: f64) -> Self {
+        assert!(probability >= 0.0 && probability <= 1.0, "probability must be between 0 and 1");
+        self.drop_probability = probability;
+        self
+    }
+
+    /// Sets the seed of the random choices.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Returns how long it takes to transmit `len` bytes.
+    fn transmission_time(&self, len: usize) -> Duration {
+        match self.bandwidth {
+            Some(bandwidth) => {
+                let nanos = len as u128 * 1_000_000_000 / u128::from(bandwidth.get());
+                Duration::from_nanos(nanos as u64)
+            }
+            None => Duration::from_secs(0)
+        }
+    }
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        LinkConfig::new()
+    }
+}
+
+/// Network link shared by the connections of a `SimulatedMemoryTransport`.
+#[derive(Debug)]
+struct Link {
+    config: LinkConfig,
+    state: Mutex<LinkState>,
+}
+
+#[derive(Debug)]
+struct LinkState {
+    /// Seeds the RNG of each connection.
+    rng: StdRng,
+    /// Time at which the link is done transmitting the data written so far.
+    busy_until: Instant,
+    partitioned: bool,
+    /// Tasks waiting for the partition to heal.
+    wakers: Vec<Waker>,
+}
+
+impl Link {
+    fn new(config: LinkConfig) -> Self {
+        let rng = StdRng::seed_from_u64(config.seed);
+        Link {
+            config,
+            state: Mutex::new(LinkState {
+                rng,
+                busy_until: Instant::now(),
+                partitioned: false,
+                wakers: Vec::new(),
+            }),
+        }
+    }
+
+    fn is_partitioned(&self) -> bool {
+        self.state.lock().partitioned
+    }
+}
+
+/// Memory transport whose connections are subject to simulated network conditions.
+///
+/// The [`LinkConfig`] applies to the data sent by the connections of this transport, whether
+/// they were dialed or accepted. Connections with a `MemoryTransport` are only subject to the
+/// conditions of this end.
+#[derive(Debug, Clone)]
+pub struct SimulatedMemoryTransport {
+    link: Arc<Link>,
+}
+
+impl SimulatedMemoryTransport {
+    /// Creates a transport whose connections are subject to the given network conditions.
+    pub fn new(config: LinkConfig) -> Self {
+        SimulatedMemoryTransport {
+            link: Arc::new(Link::new(config)),
+        }
+    }
+
+    /// Returns a handle to control the link of this transport.
+    pub fn handle(&self) -> LinkHandle {
+        LinkHandle { link: self.link.clone() }
+    }
+}
+
+impl Transport for SimulatedMemoryTransport {
+    type Output = Channel<Vec<u8>>;
+    type Error = MemoryTransportError;
+    type Listener = Listener;
+    type ListenerUpgrade = Ready<Result<Self::Output, Self::Error>>;
+    type Dial = DialFuture;
+
+    fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
+        listen_on(addr, Some(self.link))
+    }
+
+    fn dial(self, addr: Multiaddr) -> Result<DialFuture, TransportError<Self::Error>> {
+        dial(addr, Some(self.link))
+    }
+}
+
+/// Handle to control the link of a [`SimulatedMemoryTransport`].
+#[derive(Debug, Clone)]
+pub struct LinkHandle {
+    link: Arc<Link>,
+}
+
+impl LinkHandle {
+    /// Partitions the endpoint from the network, or heals the partition.
+    ///
+    /// While partitioned, dialing from or to the endpoint fails, and the data sent over its
+    /// existing connections is held back until the partition heals.
+    pub fn set_partitioned(&self, partitioned: bool) {
+        let mut state = self.link.state.lock();
+        state.partitioned = partitioned;
+        if !partitioned {
+            for waker in state.wakers.drain(..) {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Returns whether the endpoint is partitioned from the network.
+    pub fn is_partitioned(&self) -> bool {
+        self.link.is_partitioned()
+    }
+}
+
 /// Connection to a `MemoryTransport` currently being opened.
 pub struct DialFuture {
     sender: mpsc::Sender<Channel<Vec<u8>>>,
@@ -73,70 +278,85 @@ impl Transport for MemoryTransport {
     type Dial = DialFuture;
 
     fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
-        let port = if let Ok(port) = parse_memory_addr(&addr) {
-            port
-        } else {
-            return Err(TransportError::MultiaddrNotSupported(addr));
-        };
+        listen_on(addr, None)
+    }
 
-        let mut hub = (&*HUB).lock();
+    fn dial(self, addr: Multiaddr) -> Result<DialFuture, TransportError<Self::Error>> {
+        dial(addr, None)
+    }
+}
 
-        let port = if let Some(port) = NonZeroU64::new(port) {
-            port
-        } else {
-            loop {
-                let port = match NonZeroU64::new(rand::random()) {
-                    Some(p) => p,
-                    None => continue,
-                };
-                if !hub.contains_key(&port) {
-                    break port;
-                }
-            }
-        };
+fn listen_on(addr: Multiaddr, link: Option<Arc<Link>>) -> Result<Listener, TransportError<MemoryTransportError>> {
+    let port = if let Ok(port) = parse_memory_addr(&addr) {
+        port
+    } else {
+        return Err(TransportError::MultiaddrNotSupported(addr));
+    };
 
+    let mut hub = (&*HUB).lock();
 
-        let (tx, rx) = mpsc::channel(2);
-        match hub.entry(port) {
-            Entry::Occupied(_) =>
-                return Err(TransportError::Other(MemoryTransportError::Unreachable)),
-            Entry::Vacant(e) => e.insert(tx)
-        };
+    let port = if let Some(port) = NonZeroU64::new(port) {
+        port
+    } else {
+        loop {
+            let port = match NonZeroU64::new(rand::random()) {
+                Some(p) => p,
+                None => continue,
+            };
+            if !hub.contains_key(&port) {
+                break port;
+            }
+        }
+    };
 
-        let listener = Listener {
-            port,
-            addr: Protocol::Memory(port.get()).into(),
-            receiver: rx,
-            tell_listen_addr: true
-        };
 
-        Ok(listener)
-    }
+    let (tx, rx) = mpsc::channel(2);
+    match hub.entry(port) {
+        Entry::Occupied(_) =>
+            return Err(TransportError::Other(MemoryTransportError::Unreachable)),
+        Entry::Vacant(e) => e.insert((tx, link))
+    };
 
-    fn dial(self, addr: Multiaddr) -> Result<DialFuture, TransportError<Self::Error>> {
-        let port = if let Ok(port) = parse_memory_addr(&addr) {
-            if let Some(port) = NonZeroU64::new(port) {
-                port
-            } else {
-                return Err(TransportError::Other(MemoryTransportError::Unreachable));
-            }
-        } else {
-            return Err(TransportError::MultiaddrNotSupported(addr));
-        };
+    let listener = Listener {
+        port,
+        addr: Protocol::Memory(port.get()).into(),
+        receiver: rx,
+        tell_listen_addr: true
+    };
 
-        let hub = HUB.lock();
-        if let Some(sender) = hub.get(&port) {
-            let (a_tx, a_rx) = mpsc::channel(4096);
-            let (b_tx, b_rx) = mpsc::channel(4096);
-            Ok(DialFuture {
-                sender: sender.clone(),
-                channel_to_send: Some(RwStreamSink::new(Chan { incoming: a_rx, outgoing: b_tx })),
-                channel_to_return: Some(RwStreamSink::new(Chan { incoming: b_rx, outgoing: a_tx })),
+    Ok(listener)
+}
 
-            })
+fn dial(addr: Multiaddr, link: Option<Arc<Link>>) -> Result<DialFuture, TransportError<MemoryTransportError>> {
+    let port = if let Ok(port) = parse_memory_addr(&addr) {
+        if let Some(port) = NonZeroU64::new(port) {
+            port
         } else {
-            Err(TransportError::Other(MemoryTransportError::Unreachable))
+            return Err(TransportError::Other(MemoryTransportError::Unreachable));
         }
+    } else {
+        return Err(TransportError::MultiaddrNotSupported(addr));
+    };
+
+    let hub = HUB.lock();
+    if let Some((sender, remote_link)) = hub.get(&port) {
+        let partitioned = link.iter().chain(remote_link.iter()).any(|l| l.is_partitioned());
+        if partitioned {
+            debug!("Failed to dial {}: partitioned", addr);
+            return Err(TransportError::Other(MemoryTransportError::Unreachable));
+        }
+        let (a_tx, a_rx) = mpsc::channel(4096);
+        let (b_tx, b_rx) = mpsc::channel(4096);
+        let listener_chan = Chan::new(a_rx, b_tx, Vec::len, remote_link.clone(), link.clone());
+        let dialer_chan = Chan::new(b_rx, a_tx, Vec::len, link, remote_link.clone());
+        Ok(DialFuture {
+            sender: sender.clone(),
+            channel_to_send: Some(RwStreamSink::new(listener_chan)),
+            channel_to_return: Some(RwStreamSink::new(dialer_chan)),
+
+        })
+    } else {
+        Err(TransportError::Other(MemoryTransportError::Unreachable))
     }
 }
 
@@ -230,8 +450,81 @@ pub type Channel<T> = RwStreamSink<Chan<T>>;
 ///
 /// Implements `Sink` and `Stream`.
 pub struct Chan<T = Vec<u8>> {
-    incoming: mpsc::Receiver<T>,
-    outgoing: mpsc::Sender<T>,
+    incoming: mpsc::Receiver<Packet<T>>,
+    outgoing: mpsc::Sender<Packet<T>>,
+    /// Returns the number of bytes of the data sent, to simulate the bandwidth of the link.
+    data_len: fn(&T) -> usize,
+    /// Simulated network conditions, if any of the endpoints has a link.
+    simulation: Option<Box<Simulation>>,
+    /// Data received that is not to be delivered yet.
+    pending: Option<Packet<T>>,
+}
+
+/// Data sent over a `Chan`.
+struct Packet<T> {
+    data: T,
+    /// When the data arrives, if the sender has a link.
+    deliver_at: Option<Instant>,
+}
+
+/// State of a `Chan` with simulated network conditions.
+struct Simulation {
+    /// Link of this endpoint, applied to the data sent.
+    local: Option<Arc<Link>>,
+    /// Link of the remote endpoint.
+    remote: Option<Arc<Link>>,
+    rng: StdRng,
+    /// When the data sent last arrives.
+    last_delivery: Instant,
+    /// Fires when the link can transmit again.
+    send_delay: Option<Delay>,
+    /// Fires when the pending data arrives.
+    receive_delay: Option<Delay>,
+    /// Whether the connection has been dropped.
+    dropped: bool,
+}
+
+impl<T> Chan<T> {
+    fn new(
+        incoming: mpsc::Receiver<Packet<T>>,
+        outgoing: mpsc::Sender<Packet<T>>,
+        data_len: fn(&T) -> usize,
+        local: Option<Arc<Link>>,
+        remote: Option<Arc<Link>>
+    ) -> Self {
+        let simulation = if local.is_some() || remote.is_some() {
+            let seed = local.as_ref().map_or(0, |l| l.state.lock().rng.gen());
+            Some(Box::new(Simulation {
+                local,
+                remote,
+                rng: StdRng::seed_from_u64(seed),
+                last_delivery: Instant::now(),
+                send_delay: None,
+                receive_delay: None,
+                dropped: false,
+            }))
+        } else {
+            None
+        };
+        Chan { incoming, outgoing, data_len, simulation, pending: None }
+    }
+}
+
+impl Simulation {
+    /// Returns `true` and registers the task for wake up if one of the endpoints is partitioned.
+    fn poll_partitioned(&self, cx: &mut Context) -> bool {
+        let mut partitioned = false;
+        for link in self.local.iter().chain(self.remote.iter()) {
+            let mut state = link.state.lock();
+            if state.partitioned {
+                if !state.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                    state.wakers.push(cx.waker().clone());
+                }
+                partitioned = true;
+            }
+        }
+        partitioned
+    }
 }
 
 impl<T> Unpin for Chan<T> {
@@ -241,11 +534,36 @@ impl<T> Stream for Chan<T> {
     type Item = Result<T, io::Error>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
-        match Stream::poll_next(Pin::new(&mut self.incoming), cx) {
-            Poll::Pending => Poll::Pending,
-            Poll::Ready(None) => Poll::Ready(Some(Err(io::ErrorKind::BrokenPipe.into()))),
-            Poll::Ready(Some(v)) => Poll::Ready(Some(Ok(v))),
+        let this = &mut *self;
+        let packet = match this.pending.take() {
+            Some(packet) => packet,
+            None => match Stream::poll_next(Pin::new(&mut this.incoming), cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(None) => return Poll::Ready(Some(Err(io::ErrorKind::BrokenPipe.into()))),
+                Poll::Ready(Some(v)) => v,
+            }
+        };
+
+        if let Some(simulation) = this.simulation.as_mut() {
+            if let Some(deliver_at) = packet.deliver_at {
+                let now = Instant::now();
+                if deliver_at > now {
+                    let delay = simulation.receive_delay
+                        .get_or_insert_with(|| Delay::new(deliver_at - now));
+                    if Pin::new(delay).poll(cx).is_pending() {
+                        this.pending = Some(packet);
+                        return Poll::Pending
+                    }
+                }
+                simulation.receive_delay = None;
+            }
+            if simulation.poll_partitioned(cx) {
+                this.pending = Some(packet);
+                return Poll::Pending
+            }
         }
+
+        Poll::Ready(Some(Ok(packet.data)))
     }
 }
 
@@ -253,12 +571,62 @@ impl<T> Sink<T> for Chan<T> {
     type Error = io::Error;
 
     fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        if let Some(simulation) = self.simulation.as_mut() {
+            if simulation.dropped {
+                return Poll::Ready(Err(io::ErrorKind::ConnectionReset.into()))
+            }
+            if let Some(delay) = simulation.send_delay.as_mut() {
+                ready!(Pin::new(delay).poll(cx));
+                simulation.send_delay = None;
+            }
+        }
         self.outgoing.poll_ready(cx)
             .map(|v| v.map_err(|_| io::ErrorKind::BrokenPipe.into()))
     }
 
     fn start_send(mut self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
-        self.outgoing.start_send(item).map_err(|_| io::ErrorKind::BrokenPipe.into())
+        let this = &mut *self;
+        let deliver_at = match this.simulation.as_mut() {
+            Some(simulation) => match simulation.local.clone() {
+                Some(link) => {
+                    let config = &link.config;
+                    if config.drop_probability > 0.0 && simulation.rng.gen_bool(config.drop_probability) {
+                        debug!("Dropping simulated memory connection");
+                        simulation.dropped = true;
+                        this.outgoing.close_channel();
+                        this.incoming.close();
+                        return Err(io::ErrorKind::ConnectionReset.into())
+                    }
+
+                    let now = Instant::now();
+                    let transmitted_at = {
+                        let mut state = link.state.lock();
+                        state.busy_until = cmp::max(state.busy_until, now)
+                            + config.transmission_time((this.data_len)(&item));
+                        state.busy_until
+                    };
+                    if transmitted_at > now {
+                        simulation.send_delay = Some(Delay::new(transmitted_at - now));
+                    }
+
+                    let jitter = match config.jitter.as_nanos() as u64 {
+                        0 => Duration::from_secs(0),
+                        max => Duration::from_nanos(simulation.rng.gen_range(0, max + 1)),
+                    };
+                    // Data is delivered in order, as over TCP.
+                    simulation.last_delivery = cmp::max(
+                        simulation.last_delivery,
+                        transmitted_at + config.latency + jitter
+                    );
+                    Some(simulation.last_delivery)
+                }
+                None => None
+            },
+            None => None
+        };
+
+        this.outgoing.start_send(Packet { data: item, deliver_at })
+            .map_err(|_| io::ErrorKind::BrokenPipe.into())
     }
 
     fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<Result<(), Self::Error>> {
@@ -279,8 +647,6 @@ impl<T: AsRef<[u8]>> Into<RwStreamSink<Chan<T>>> for Chan<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use rand::Rng;
-    use std::io::Write;
 
     #[test]
     fn parse_memory_addr_works() {
@@ -352,4 +718,97 @@ mod tests {
 
         futures::executor::block_on(futures::future::join(listener, dialer));
     }
+
+    /// Listens on a random port and dials it, returning the dialer's and the listener's side.
+    async fn connect<L, D>(listener: L, dialer: D) -> (Channel<Vec<u8>>, Channel<Vec<u8>>)
+    where
+        L: Transport<Output = Channel<Vec<u8>>, Listener = Listener>,
+        D: Transport<Output = Channel<Vec<u8>>, Dial = DialFuture>,
+        L::Error: fmt::Debug,
+        D::Error: fmt::Debug
+    {
+        let mut listener = listener.listen_on("/memory/0".parse().unwrap()).unwrap();
+        let addr = listener.next().await.unwrap().unwrap().into_new_address().unwrap();
+        let dialer = dialer.dial(addr).unwrap().await.unwrap();
+        let (upgrade, _) = listener.next().await.unwrap().unwrap().into_upgrade().unwrap();
+        (dialer, upgrade.await.unwrap())
+    }
+
+    #[test]
+    fn latency_and_bandwidth() {
+        async_std::task::block_on(async {
+            let config = LinkConfig::new()
+                .latency(Duration::from_millis(50))
+                .bandwidth(10_000);
+            let transport = MemoryTransport::simulated(config);
+            let (mut dialer, mut listener) = connect(transport, MemoryTransport).await;
+
+            dialer.write_all(&[0; 1000]).await.unwrap();
+            let mut buf = [0; 1000];
+            listener.read_exact(&mut buf).await.unwrap();
+
+            let start = Instant::now();
+            listener.write_all(&[0; 1000]).await.unwrap();
+            listener.write_all(&[0; 1000]).await.unwrap();
+            // The second write waits for the first to be transmitted.
+            assert!(start.elapsed() >= Duration::from_millis(100));
+            dialer.read_exact(&mut buf).await.unwrap();
+            dialer.read_exact(&mut buf).await.unwrap();
+            assert!(start.elapsed() >= Duration::from_millis(250));
+        })
+    }
+
+    #[test]
+    fn drops_are_deterministic() {
+        fn writes_before_drop(seed: u64) -> usize {
+            async_std::task::block_on(async {
+                let config = LinkConfig::new().drop_probability(0.1).seed(seed);
+                let transport = MemoryTransport::simulated(config);
+                let (mut dialer, mut listener) = connect(transport, MemoryTransport).await;
+                let mut writes = 0;
+                while listener.write_all(&[1]).await.is_ok() {
+                    writes += 1;
+                }
+                let mut buf = [0; 1];
+                for _ in 0 .. writes {
+                    dialer.read_exact(&mut buf).await.unwrap();
+                }
+                assert!(dialer.read_exact(&mut buf).await.is_err());
+                writes
+            })
+        }
+
+        let writes = (0 .. 5).map(writes_before_drop).collect::<Vec<_>>();
+        assert_eq!(writes, (0 .. 5).map(writes_before_drop).collect::<Vec<_>>());
+        assert!(writes.iter().any(|w| *w != writes[0]));
+    }
+
+    #[test]
+    fn partition() {
+        async_std::task::block_on(async {
+            let transport = MemoryTransport::simulated(LinkConfig::new());
+            let handle = transport.handle();
+            let mut listener = transport.clone().listen_on("/memory/0".parse().unwrap()).unwrap();
+            let addr = listener.next().await.unwrap().unwrap().into_new_address().unwrap();
+            let (mut dialer, mut listener) = connect(transport, MemoryTransport).await;
+
+            handle.set_partitioned(true);
+            assert!(MemoryTransport.dial(addr.clone()).is_err());
+
+            dialer.write_all(&[1, 2, 3]).await.unwrap();
+            let mut buf = [0; 3];
+            let read = listener.read_exact(&mut buf);
+            futures::pin_mut!(read);
+            let timeout = Delay::new(Duration::from_millis(100));
+            match future::select(read, timeout).await {
+                future::Either::Left(_) => panic!("data was delivered during the partition"),
+                future::Either::Right((_, read)) => {
+                    handle.set_partitioned(false);
+                    read.await.unwrap();
+                }
+            }
+            assert_eq!(buf, [1, 2, 3]);
+            assert!(MemoryTransport.dial(addr).is_ok());
+        })
+    }
 }
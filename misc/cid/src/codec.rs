@@ -0,0 +1,105 @@
+/// List of content types of the data a CID refers to, from the multicodec table.
+///
+/// Content types not known to this library are represented by [`Codec::Unknown`], so that
+/// their CIDs can still be decoded and passed on unchanged.
+#[derive(PartialEq, Eq, Clone, Debug, Copy, Hash)]
+pub enum Codec {
+    /// Raw binary
+    Raw,
+    /// MerkleDAG protobuf
+    DagProtobuf,
+    /// MerkleDAG CBOR
+    DagCbor,
+    /// MerkleDAG JSON
+    DagJson,
+    /// Public key of a libp2p peer
+    Libp2pKey,
+    /// Raw Git object
+    GitRaw,
+    /// Ethereum block header (RLP)
+    EthereumBlock,
+    /// Ethereum list of block headers (RLP)
+    EthereumBlockList,
+    /// Ethereum transaction trie node (RLP)
+    EthereumTxTrie,
+    /// Ethereum transaction (RLP)
+    EthereumTx,
+    /// Ethereum transaction receipt trie node (RLP)
+    EthereumTxReceiptTrie,
+    /// Ethereum transaction receipt (RLP)
+    EthereumTxReceipt,
+    /// Ethereum state trie node (RLP)
+    EthereumStateTrie,
+    /// Ethereum account snapshot (RLP)
+    EthereumAccountSnapshot,
+    /// Ethereum contract storage trie node (RLP)
+    EthereumStorageTrie,
+    /// Bitcoin block
+    BitcoinBlock,
+    /// Bitcoin transaction
+    BitcoinTx,
+    /// Zcash block
+    ZcashBlock,
+    /// Zcash transaction
+    ZcashTx,
+    /// A content type not known to this library, with its multicodec code.
+    ///
+    /// Use [`Codec::from_code`] to build a codec from a code, which never returns this variant
+    /// for the codes of the content types above.
+    Unknown(u64),
+}
+
+impl Codec {
+    /// Get the corresponding multicodec code.
+    pub fn code(&self) -> u64 {
+        match self {
+            Codec::Raw => 0x55,
+            Codec::DagProtobuf => 0x70,
+            Codec::DagCbor => 0x71,
+            Codec::DagJson => 0x0129,
+            Codec::Libp2pKey => 0x72,
+            Codec::GitRaw => 0x78,
+            Codec::EthereumBlock => 0x90,
+            Codec::EthereumBlockList => 0x91,
+            Codec::EthereumTxTrie => 0x92,
+            Codec::EthereumTx => 0x93,
+            Codec::EthereumTxReceiptTrie => 0x94,
+            Codec::EthereumTxReceipt => 0x95,
+            Codec::EthereumStateTrie => 0x96,
+            Codec::EthereumAccountSnapshot => 0x97,
+            Codec::EthereumStorageTrie => 0x98,
+            Codec::BitcoinBlock => 0xB0,
+            Codec::BitcoinTx => 0xB1,
+            Codec::ZcashBlock => 0xC0,
+            Codec::ZcashTx => 0xC1,
+            Codec::Unknown(code) => *code,
+        }
+    }
+
+    /// Returns the content type corresponding to a code, or [`Codec::Unknown`] if no content
+    /// type is matching.
+    pub fn from_code(code: u64) -> Codec {
+        match code {
+            0x55 => Codec::Raw,
+            0x70 => Codec::DagProtobuf,
+            0x71 => Codec::DagCbor,
+            0x0129 => Codec::DagJson,
+            0x72 => Codec::Libp2pKey,
+            0x78 => Codec::GitRaw,
+            0x90 => Codec::EthereumBlock,
+            0x91 => Codec::EthereumBlockList,
+            0x92 => Codec::EthereumTxTrie,
+            0x93 => Codec::EthereumTx,
+            0x94 => Codec::EthereumTxReceiptTrie,
+            0x95 => Codec::EthereumTxReceipt,
+            0x96 => Codec::EthereumStateTrie,
+            0x97 => Codec::EthereumAccountSnapshot,
+            0x98 => Codec::EthereumStorageTrie,
+            0xB0 => Codec::BitcoinBlock,
+            0xB1 => Codec::BitcoinTx,
+            0xC0 => Codec::ZcashBlock,
+            0xC1 => Codec::ZcashTx,
+            _ => Codec::Unknown(code),
+        }
+    }
+}
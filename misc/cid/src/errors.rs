@@ -0,0 +1,54 @@
+use std::{error, fmt};
+
+/// Error that can happen when decoding a CID or a multibase string.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The multibase prefix of the string is unknown.
+    UnknownBase,
+    /// The string isn't valid for its base encoding.
+    InvalidBaseString,
+    /// The input is too short.
+    InputTooShort,
+    /// The version of the CID is unknown.
+    UnknownVersion,
+    /// A CIDv0 must be the SHA2-256 multihash of `dag-pb` data, encoded in base-58.
+    InvalidV0,
+    /// The multihash of the CID is invalid.
+    Multihash(multihash::DecodeError),
+}
+
+impl fmt::Display for Error {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::UnknownBase => write!(f, "Unknown multibase prefix"),
+            Error::InvalidBaseString => write!(f, "Invalid string for the base encoding"),
+            Error::InputTooShort => write!(f, "Input too short"),
+            Error::UnknownVersion => write!(f, "Unknown CID version"),
+            Error::InvalidV0 => write!(f, "Invalid CIDv0"),
+            Error::Multihash(err) => write!(f, "Invalid multihash: {}", err),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        if let Error::Multihash(err) = self {
+            Some(err)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<multihash::DecodeError> for Error {
+    fn from(err: multihash::DecodeError) -> Error {
+        Error::Multihash(err)
+    }
+}
+
+impl From<multihash::DecodeOwnedError> for Error {
+    fn from(err: multihash::DecodeOwnedError) -> Error {
+        Error::Multihash(err.error)
+    }
+}
@@ -0,0 +1,201 @@
+//! # CID
+//!
+//! Implementation of [CID](https://github.com/multiformats/cid) in Rust.
+//!
+//! A `Cid` is a self-describing content identifier: the multihash of some data, along with the
+//! content type of that data. The `multibase` module implements the string encodings of CIDs.
+
+pub mod multibase;
+
+mod codec;
+mod errors;
+
+use multihash::{Hash, Multihash};
+use std::{convert::TryFrom, fmt, str::FromStr};
+use unsigned_varint::{decode, encode};
+
+pub use multihash;
+
+pub use self::codec::Codec;
+pub use self::errors::Error;
+pub use self::multibase::Base;
+
+/// Version of a CID.
+#[derive(PartialEq, Eq, Clone, Debug, Copy, Hash)]
+pub enum Version {
+    /// CIDv0, which is a bare SHA2-256 multihash of `dag-pb` data.
+    V0,
+    /// CIDv1, which is prefixed with its version and content type.
+    V1,
+}
+
+impl Version {
+    /// Get the corresponding version number.
+    pub fn code(&self) -> u64 {
+        match self {
+            Version::V0 => 0,
+            Version::V1 => 1,
+        }
+    }
+
+    /// Returns the version corresponding to a version number, or `None` if it is unknown.
+    pub fn from_code(code: u64) -> Option<Version> {
+        match code {
+            0 => Some(Version::V0),
+            1 => Some(Version::V1),
+            _ => None,
+        }
+    }
+}
+
+/// Represents a valid CID.
+///
+/// A `Cid` is displayed in base-58 if it is a CIDv0, and in lowercase base-32 with its
+/// multibase prefix otherwise. Use [`Cid::to_string_of_base`] to choose another base encoding.
+#[derive(PartialEq, Eq, Clone, Debug, Hash)]
+pub struct Cid {
+    version: Version,
+    codec: Codec,
+    hash: Multihash,
+}
+
+impl Cid {
+    /// Builds a CIDv0 from the multihash of some `dag-pb` data.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the multihash isn't a non-truncated SHA2-256 multihash.
+    pub fn new_v0(hash: Multihash) -> Result<Cid, Error> {
+        if hash.algorithm() != Hash::SHA2256 || hash.digest().len() != usize::from(Hash::SHA2256.size()) {
+            return Err(Error::InvalidV0)
+        }
+        Ok(Cid { version: Version::V0, codec: Codec::DagProtobuf, hash })
+    }
+
+    /// Builds a CIDv1 from the multihash of some data and its content type.
+    pub fn new_v1(codec: Codec, hash: Multihash) -> Cid {
+        Cid { version: Version::V1, codec, hash }
+    }
+
+    /// Builds a CID of the given version.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the version is 0 and the content type isn't `dag-pb`, or the
+    /// multihash isn't a non-truncated SHA2-256 multihash.
+    pub fn new(version: Version, codec: Codec, hash: Multihash) -> Result<Cid, Error> {
+        match version {
+            Version::V0 if codec != Codec::DagProtobuf => Err(Error::InvalidV0),
+            Version::V0 => Cid::new_v0(hash),
+            Version::V1 => Ok(Cid::new_v1(codec, hash)),
+        }
+    }
+
+    /// Decodes the binary representation of a CID.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Cid, Error> {
+        // A CIDv0 is a bare SHA2-256 multihash, whose first byte can't be a CID version.
+        if bytes.first() == Some(&(Hash::SHA2256.code() as u8)) {
+            let hash = Multihash::from_bytes(bytes.to_vec())?;
+            return Cid::new_v0(hash)
+        }
+
+        let (version, rest) = decode::u64(bytes).map_err(|_| Error::InputTooShort)?;
+        if Version::from_code(version) != Some(Version::V1) {
+            return Err(Error::UnknownVersion)
+        }
+        let (codec, rest) = decode::u64(rest).map_err(|_| Error::InputTooShort)?;
+        let codec = Codec::from_code(codec);
+        let hash = Multihash::from_bytes(rest.to_vec())?;
+        Ok(Cid::new_v1(codec, hash))
+    }
+
+    /// Returns the binary representation of this CID.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self.version {
+            Version::V0 => self.hash.to_vec(),
+            Version::V1 => {
+                let mut bytes = Vec::with_capacity(self.hash.as_bytes().len() + 4);
+                bytes.extend_from_slice(encode::u64(self.version.code(), &mut encode::u64_buffer()));
+                bytes.extend_from_slice(encode::u64(self.codec.code(), &mut encode::u64_buffer()));
+                bytes.extend_from_slice(self.hash.as_bytes());
+                bytes
+            }
+        }
+    }
+
+    /// Returns the version of this CID.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Returns the content type of the data this CID refers to.
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    /// Returns the multihash of the data this CID refers to.
+    pub fn hash(&self) -> &Multihash {
+        &self.hash
+    }
+
+    /// Converts this CID into a CIDv1 referring to the same data.
+    pub fn into_v1(self) -> Cid {
+        Cid::new_v1(self.codec, self.hash)
+    }
+
+    /// Returns the multibase representation of this CID in the given base encoding.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if this is a CIDv0 and the base encoding isn't base-58, as a CIDv0
+    /// has no multibase prefix.
+    pub fn to_string_of_base(&self, base: Base) -> Result<String, Error> {
+        match self.version {
+            Version::V0 if base == Base::Base58Btc => Ok(base.encode(&self.to_bytes())),
+            Version::V0 => Err(Error::InvalidV0),
+            Version::V1 => Ok(multibase::encode(base, self.to_bytes())),
+        }
+    }
+}
+
+impl fmt::Display for Cid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let base = match self.version {
+            Version::V0 => Base::Base58Btc,
+            Version::V1 => Base::Base32Lower,
+        };
+        let string = self.to_string_of_base(base)
+            .expect("CIDs of all versions can be encoded in their default base; qed");
+        f.write_str(&string)
+    }
+}
+
+impl FromStr for Cid {
+    type Err = Error;
+
+    /// Parses a base-58 CIDv0, or a multibase CID.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Base-58 SHA2-256 multihashes are 46 characters long and start with `Qm`.
+        if s.len() == 46 && s.starts_with("Qm") {
+            let bytes = Base::Base58Btc.decode(s)?;
+            return Cid::from_bytes(&bytes)
+        }
+
+        let (_, bytes) = multibase::decode(s)?;
+        Cid::from_bytes(&bytes)
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Cid {
+    type Error = Error;
+
+    fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
+        Cid::from_bytes(value)
+    }
+}
+
+impl From<Cid> for Multihash {
+    fn from(cid: Cid) -> Self {
+        cid.hash
+    }
+}
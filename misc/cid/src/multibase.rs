@@ -0,0 +1,205 @@
+//! Implementation of [multibase](https://github.com/multiformats/multibase).
+//!
+//! A multibase string is some data in a base encoding, prefixed with a character identifying
+//! that base encoding.
+
+use crate::errors::Error;
+use data_encoding::Encoding;
+
+/// List of base encodings supported by this library.
+#[derive(PartialEq, Eq, Clone, Debug, Copy, Hash)]
+pub enum Base {
+    /// Binary (`0`)
+    Base2,
+    /// Lowercase hexadecimal (`f`)
+    Base16Lower,
+    /// Uppercase hexadecimal (`F`)
+    Base16Upper,
+    /// Lowercase RFC 4648 base-32 without padding (`b`)
+    Base32Lower,
+    /// Uppercase RFC 4648 base-32 without padding (`B`)
+    Base32Upper,
+    /// Lowercase RFC 4648 base-32 with padding (`c`)
+    Base32PadLower,
+    /// Uppercase RFC 4648 base-32 with padding (`C`)
+    Base32PadUpper,
+    /// Lowercase RFC 4648 base-32 with the extended hex alphabet, without padding (`v`)
+    Base32HexLower,
+    /// Uppercase RFC 4648 base-32 with the extended hex alphabet, without padding (`V`)
+    Base32HexUpper,
+    /// Lowercase RFC 4648 base-32 with the extended hex alphabet, with padding (`t`)
+    Base32HexPadLower,
+    /// Uppercase RFC 4648 base-32 with the extended hex alphabet, with padding (`T`)
+    Base32HexPadUpper,
+    /// Base-58 with the Bitcoin alphabet (`z`)
+    Base58Btc,
+    /// RFC 4648 base-64 without padding (`m`)
+    Base64,
+    /// RFC 4648 base-64 with padding (`M`)
+    Base64Pad,
+    /// RFC 4648 URL-safe base-64 without padding (`u`)
+    Base64Url,
+    /// RFC 4648 URL-safe base-64 with padding (`U`)
+    Base64UrlPad,
+}
+
+impl Base {
+    /// Get the prefix character of the base encoding.
+    pub fn code(&self) -> char {
+        match self {
+            Base::Base2 => '0',
+            Base::Base16Lower => 'f',
+            Base::Base16Upper => 'F',
+            Base::Base32Lower => 'b',
+            Base::Base32Upper => 'B',
+            Base::Base32PadLower => 'c',
+            Base::Base32PadUpper => 'C',
+            Base::Base32HexLower => 'v',
+            Base::Base32HexUpper => 'V',
+            Base::Base32HexPadLower => 't',
+            Base::Base32HexPadUpper => 'T',
+            Base::Base58Btc => 'z',
+            Base::Base64 => 'm',
+            Base::Base64Pad => 'M',
+            Base::Base64Url => 'u',
+            Base::Base64UrlPad => 'U',
+        }
+    }
+
+    /// Returns the base encoding corresponding to a prefix character, or `None` if no base
+    /// encoding is matching.
+    pub fn from_code(code: char) -> Option<Base> {
+        Some(match code {
+            '0' => Base::Base2,
+            'f' => Base::Base16Lower,
+            'F' => Base::Base16Upper,
+            'b' => Base::Base32Lower,
+            'B' => Base::Base32Upper,
+            'c' => Base::Base32PadLower,
+            'C' => Base::Base32PadUpper,
+            'v' => Base::Base32HexLower,
+            'V' => Base::Base32HexUpper,
+            't' => Base::Base32HexPadLower,
+            'T' => Base::Base32HexPadUpper,
+            'z' => Base::Base58Btc,
+            'm' => Base::Base64,
+            'M' => Base::Base64Pad,
+            'u' => Base::Base64Url,
+            'U' => Base::Base64UrlPad,
+            _ => return None,
+        })
+    }
+
+    /// Encodes `data` in this base encoding, without the multibase prefix.
+    pub fn encode(&self, data: &[u8]) -> String {
+        match self {
+            Base::Base2 => data.iter().map(|byte| format!("{:08b}", byte)).collect(),
+            Base::Base58Btc => bs58::encode(data).into_string(),
+            _ => {
+                let (encoding, lowercase) = self.rfc4648();
+                let encoded = encoding.encode(data);
+                if lowercase {
+                    encoded.to_ascii_lowercase()
+                } else {
+                    encoded
+                }
+            }
+        }
+    }
+
+    /// Decodes `input` from this base encoding, without the multibase prefix.
+    pub fn decode(&self, input: &str) -> Result<Vec<u8>, Error> {
+        match self {
+            Base::Base2 => {
+                if input.len() % 8 != 0 {
+                    return Err(Error::InvalidBaseString)
+                }
+                input.as_bytes()
+                    .chunks(8)
+                    .map(|bits| {
+                        bits.iter().try_fold(0u8, |byte, bit| match bit {
+                            b'0' => Ok(byte << 1),
+                            b'1' => Ok(byte << 1 | 1),
+                            _ => Err(Error::InvalidBaseString),
+                        })
+                    })
+                    .collect()
+            }
+            Base::Base58Btc => bs58::decode(input).into_vec().map_err(|_| Error::InvalidBaseString),
+            _ => {
+                let (encoding, lowercase) = self.rfc4648();
+                // The alphabets of `data_encoding` are uppercase, and each case of the
+                // alphabet is a distinct base encoding.
+                let input = if lowercase {
+                    if input.bytes().any(|b| b.is_ascii_uppercase()) {
+                        return Err(Error::InvalidBaseString)
+                    }
+                    input.to_ascii_uppercase()
+                } else {
+                    input.to_owned()
+                };
+                encoding.decode(input.as_bytes()).map_err(|_| Error::InvalidBaseString)
+            }
+        }
+    }
+
+    /// Returns the RFC 4648 encoding of this base encoding, and whether its alphabet is the
+    /// lowercase version of the one of the encoding.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the base encoding isn't defined in RFC 4648.
+    fn rfc4648(&self) -> (Encoding, bool) {
+        match self {
+            Base::Base16Lower => (data_encoding::HEXLOWER, false),
+            Base::Base16Upper => (data_encoding::HEXUPPER, false),
+            Base::Base32Lower => (data_encoding::BASE32_NOPAD, true),
+            Base::Base32Upper => (data_encoding::BASE32_NOPAD, false),
+            Base::Base32PadLower => (data_encoding::BASE32, true),
+            Base::Base32PadUpper => (data_encoding::BASE32, false),
+            Base::Base32HexLower => (data_encoding::BASE32HEX_NOPAD, true),
+            Base::Base32HexUpper => (data_encoding::BASE32HEX_NOPAD, false),
+            Base::Base32HexPadLower => (data_encoding::BASE32HEX, true),
+            Base::Base32HexPadUpper => (data_encoding::BASE32HEX, false),
+            Base::Base64 => (data_encoding::BASE64_NOPAD, false),
+            Base::Base64Pad => (data_encoding::BASE64, false),
+            Base::Base64Url => (data_encoding::BASE64URL_NOPAD, false),
+            Base::Base64UrlPad => (data_encoding::BASE64URL, false),
+            Base::Base2 | Base::Base58Btc => panic!("{:?} isn't defined in RFC 4648", self),
+        }
+    }
+}
+
+/// Encodes `data` into a multibase string.
+///
+/// # Examples
+///
+/// ```
+/// use parity_cid::multibase::{self, Base};
+///
+/// assert_eq!(multibase::encode(Base::Base32Lower, b"hello"), "bnbswy3dp");
+/// assert_eq!(multibase::encode(Base::Base58Btc, b"hello"), "zCn8eVZg");
+/// ```
+pub fn encode(base: Base, data: impl AsRef<[u8]>) -> String {
+    let mut output = String::new();
+    output.push(base.code());
+    output.push_str(&base.encode(data.as_ref()));
+    output
+}
+
+/// Decodes a multibase string, returning the base encoding that was used along with the data.
+///
+/// # Examples
+///
+/// ```
+/// use parity_cid::multibase::{self, Base};
+///
+/// assert_eq!(multibase::decode("maGVsbG8").unwrap(), (Base::Base64, b"hello".to_vec()));
+/// ```
+pub fn decode(input: &str) -> Result<(Base, Vec<u8>), Error> {
+    let mut chars = input.chars();
+    let prefix = chars.next().ok_or(Error::InputTooShort)?;
+    let base = Base::from_code(prefix).ok_or(Error::UnknownBase)?;
+    let data = base.decode(chars.as_str())?;
+    Ok((base, data))
+}
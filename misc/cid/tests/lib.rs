@@ -0,0 +1,122 @@
+use parity_cid::{multibase, Base, Cid, Codec, Error, Version};
+use parity_cid::multihash::{encode, DecodeError, Hash};
+use std::convert::TryFrom;
+
+#[test]
+fn multibase_vectors() {
+    let data = b"yes mani !";
+    let vectors = [
+        (Base::Base2, "001111001011001010111001100100000011011010110000101101110011010010010000000100001"),
+        (Base::Base16Lower, "f796573206d616e692021"),
+        (Base::Base16Upper, "F796573206D616E692021"),
+        (Base::Base32Lower, "bpfsxgidnmfxgsibb"),
+        (Base::Base32Upper, "BPFSXGIDNMFXGSIBB"),
+        (Base::Base32HexLower, "vf5in683dc5n6i811"),
+        (Base::Base32HexUpper, "VF5IN683DC5N6I811"),
+        (Base::Base58Btc, "z7paNL19xttacUY"),
+        (Base::Base64, "meWVzIG1hbmkgIQ"),
+        (Base::Base64Pad, "MeWVzIG1hbmkgIQ=="),
+        (Base::Base64Url, "ueWVzIG1hbmkgIQ"),
+        (Base::Base64UrlPad, "UeWVzIG1hbmkgIQ=="),
+    ];
+
+    for (base, encoded) in vectors.iter() {
+        assert_eq!(multibase::encode(*base, data), *encoded, "{:?} encodes correctly", base);
+        assert_eq!(multibase::decode(encoded).unwrap(), (*base, data.to_vec()), "{:?} decodes correctly", base);
+    }
+}
+
+#[test]
+fn multibase_roundtrip_padded() {
+    let data = b"hi";
+    for base in &[Base::Base32PadLower, Base::Base32PadUpper, Base::Base32HexPadLower, Base::Base32HexPadUpper] {
+        let encoded = multibase::encode(*base, data);
+        assert!(encoded.ends_with('='));
+        assert_eq!(multibase::decode(&encoded).unwrap(), (*base, data.to_vec()));
+    }
+}
+
+#[test]
+fn multibase_invalid() {
+    assert_eq!(multibase::decode(""), Err(Error::InputTooShort));
+    assert_eq!(multibase::decode("?abc"), Err(Error::UnknownBase));
+    // Lowercase base-32 can't contain uppercase characters, and vice versa.
+    assert_eq!(multibase::decode("bPFSXGIDNMFXGSIBB"), Err(Error::InvalidBaseString));
+    assert_eq!(multibase::decode("Bpfsxgidnmfxgsibb"), Err(Error::InvalidBaseString));
+    assert_eq!(multibase::decode("0101"), Err(Error::InvalidBaseString));
+    assert_eq!(multibase::decode("z0OIl"), Err(Error::InvalidBaseString));
+}
+
+#[test]
+fn cid_v0() {
+    let cid: Cid = "QmY7Yh4UquoXHLPFo2XbhXkhBvFoPwmQUSa92pxnxjQuPU".parse().unwrap();
+    assert_eq!(cid.version(), Version::V0);
+    assert_eq!(cid.codec(), Codec::DagProtobuf);
+    assert_eq!(cid.hash().algorithm(), Hash::SHA2256);
+    assert_eq!(cid.to_string(), "QmY7Yh4UquoXHLPFo2XbhXkhBvFoPwmQUSa92pxnxjQuPU");
+    assert_eq!(cid.to_bytes(), cid.hash().to_vec());
+    assert_eq!(Cid::try_from(&cid.to_bytes()[..]).unwrap(), cid);
+    assert_eq!(cid.to_string_of_base(Base::Base32Lower), Err(Error::InvalidV0));
+
+    let v1 = cid.into_v1();
+    assert_eq!(v1.version(), Version::V1);
+    assert_eq!(v1.to_string(), "bafybeierhgbz4zp2x2u67urqrgfnrnlukciupzenpqpipiz5nwtq7uxpx4");
+}
+
+#[test]
+fn cid_v0_requires_sha2256() {
+    let hash = encode(Hash::SHA2256, b"hello world").unwrap();
+    assert!(Cid::new_v0(hash.clone()).is_ok());
+    assert_eq!(Cid::new_v0(hash.truncate(20)), Err(Error::InvalidV0));
+    assert_eq!(Cid::new(Version::V0, Codec::Raw, hash), Err(Error::InvalidV0));
+    let blake3 = encode(Hash::Blake3, b"hello world").unwrap();
+    assert_eq!(Cid::new_v0(blake3), Err(Error::InvalidV0));
+}
+
+#[test]
+fn cid_v1() {
+    let hash = encode(Hash::SHA2256, b"hello world").unwrap();
+    let cid = Cid::new_v1(Codec::Raw, hash.clone());
+    assert_eq!(&cid.to_bytes()[.. 2], &[0x01, 0x55]);
+    assert_eq!(&cid.to_bytes()[2 ..], hash.as_bytes());
+    assert_eq!(Cid::from_bytes(&cid.to_bytes()).unwrap(), cid);
+    assert!(cid.to_string().starts_with("bafkrei"));
+
+    for base in &[Base::Base2, Base::Base16Upper, Base::Base32HexLower, Base::Base58Btc, Base::Base64Url] {
+        let string = cid.to_string_of_base(*base).unwrap();
+        assert_eq!(string.parse::<Cid>().unwrap(), cid);
+    }
+
+    // Codecs with multi-byte varints.
+    let cid = Cid::new_v1(Codec::DagJson, hash);
+    assert_eq!(&cid.to_bytes()[.. 3], &[0x01, 0xA9, 0x02]);
+    assert_eq!(cid.to_string().parse::<Cid>().unwrap(), cid);
+}
+
+#[test]
+fn cid_unknown_codec() {
+    let hash = encode(Hash::SHA2256, b"hello world").unwrap();
+    let mut bytes = vec![0x01, 0x7F];
+    bytes.extend_from_slice(hash.as_bytes());
+    let cid = Cid::from_bytes(&bytes).unwrap();
+    assert_eq!(cid.codec(), Codec::Unknown(0x7F));
+    assert_eq!(cid.to_bytes(), bytes);
+    assert_eq!(cid.to_string().parse::<Cid>().unwrap(), cid);
+
+    assert_eq!(Codec::from_code(0x55), Codec::Raw);
+    assert_eq!(Codec::from_code(0x0300), Codec::Unknown(0x0300));
+    assert_eq!(Codec::Unknown(0x0300).code(), 0x0300);
+}
+
+#[test]
+fn cid_invalid() {
+    let hash = encode(Hash::SHA2256, b"hello world").unwrap();
+    let mut bytes = vec![0x02, 0x55];
+    bytes.extend_from_slice(hash.as_bytes());
+    assert_eq!(Cid::from_bytes(&bytes), Err(Error::UnknownVersion));
+    bytes[0] = 0x01;
+    bytes[1] = 0x55;
+    bytes.pop();
+    assert_eq!(Cid::from_bytes(&bytes), Err(Error::Multihash(DecodeError::BadInputLength)));
+    assert_eq!(Cid::from_bytes(&[]), Err(Error::InputTooShort));
+}
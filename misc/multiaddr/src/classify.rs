@@ -0,0 +1,168 @@
+use crate::{Multiaddr, Protocol};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Classification of addresses.
+///
+/// The IP-based predicates look at the first IP address of the multiaddress, which for
+/// a relayed address is the address of the relay. Addresses without IP address, e.g.
+/// those with a DNS name, don't fall in any of these categories.
+impl Multiaddr {
+    /// Returns true if the address is a loopback address, e.g. `127.0.0.1` or `::1`.
+    pub fn is_loopback(&self) -> bool {
+        self.ip().map(classify) == Some(Scope::Loopback)
+    }
+
+    /// Returns true if the address belongs to a private network, e.g. `192.168.0.0/16`,
+    /// the shared address space of carrier-grade NATs or IPv6 unique local addresses.
+    pub fn is_private(&self) -> bool {
+        self.ip().map(classify) == Some(Scope::Private)
+    }
+
+    /// Returns true if the address is link-local, e.g. `169.254.0.0/16` or `fe80::/10`.
+    pub fn is_link_local(&self) -> bool {
+        self.ip().map(classify) == Some(Scope::LinkLocal)
+    }
+
+    /// Returns true if the address is globally reachable, i.e. is neither loopback,
+    /// private, link-local, unspecified, multicast nor reserved for documentation or
+    /// other special purposes.
+    pub fn is_global(&self) -> bool {
+        self.ip().map(classify) == Some(Scope::Global)
+    }
+
+    /// Returns true if the address goes through a relay, i.e. contains `/p2p-circuit`.
+    pub fn is_relayed(&self) -> bool {
+        self.iter().any(|p| p == Protocol::P2pCircuit)
+    }
+
+    /// Returns true if the address ends with the peer id of the peer it reaches,
+    /// i.e. with `/p2p/<peer id>`.
+    pub fn has_peer_id(&self) -> bool {
+        match self.iter().last() {
+            Some(Protocol::P2p(_)) => true,
+            _ => false
+        }
+    }
+
+    /// Returns the first IP address of the multiaddress.
+    fn ip(&self) -> Option<IpAddr> {
+        self.iter().find_map(|p| match p {
+            Protocol::Ip4(ip) => Some(IpAddr::V4(ip)),
+            Protocol::Ip6(ip) => Some(IpAddr::V6(ip)),
+            _ => None
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Scope {
+    Loopback,
+    Private,
+    LinkLocal,
+    Global,
+    /// Unspecified, multicast, documentation, and other special-purpose addresses.
+    Special,
+}
+
+fn classify(ip: IpAddr) -> Scope {
+    match ip {
+        IpAddr::V4(ip) => classify_v4(ip),
+        IpAddr::V6(ip) => classify_v6(ip),
+    }
+}
+
+fn classify_v4(ip: Ipv4Addr) -> Scope {
+    let octets = ip.octets();
+    if ip.is_loopback() {
+        Scope::Loopback
+    } else if ip.is_private() || (octets[0] == 100 && octets[1] & 0xc0 == 64) {
+        Scope::Private
+    } else if ip.is_link_local() {
+        Scope::LinkLocal
+    } else if octets[0] == 0
+        || octets[0] >= 224
+        || ip.is_documentation()
+        || (octets[0] == 192 && octets[1] == 0 && octets[2] == 0)
+        || (octets[0] == 198 && octets[1] & 0xfe == 18)
+    {
+        // "This network", multicast, reserved and broadcast, documentation,
+        // IETF protocol assignments and benchmarking.
+        Scope::Special
+    } else {
+        Scope::Global
+    }
+}
+
+fn classify_v6(ip: Ipv6Addr) -> Scope {
+    let segments = ip.segments();
+    if segments[.. 6] == [0, 0, 0, 0, 0, 0xffff] {
+        // IPv4-mapped address.
+        let [a, b] = segments[6].to_be_bytes();
+        let [c, d] = segments[7].to_be_bytes();
+        return classify_v4(Ipv4Addr::new(a, b, c, d))
+    }
+    if ip.is_loopback() {
+        Scope::Loopback
+    } else if segments[0] & 0xfe00 == 0xfc00 {
+        Scope::Private
+    } else if segments[0] & 0xffc0 == 0xfe80 {
+        Scope::LinkLocal
+    } else if ip.is_unspecified()
+        || ip.is_multicast()
+        || segments[0] & 0xe000 != 0x2000
+        || (segments[0] == 0x2001 && segments[1] == 0xdb8)
+    {
+        // Unspecified, multicast, outside of the global unicast range and documentation.
+        Scope::Special
+    } else {
+        Scope::Global
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Multiaddr;
+
+    fn addr(s: &str) -> Multiaddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn classify_ip_addresses() {
+        for loopback in &["/ip4/127.0.0.1/tcp/1", "/ip6/::1", "/ip6/::ffff:127.0.0.2"] {
+            assert!(addr(loopback).is_loopback(), "{}", loopback);
+            assert!(!addr(loopback).is_global(), "{}", loopback);
+        }
+        for private in &["/ip4/10.0.0.1", "/ip4/172.31.0.1", "/ip4/192.168.1.1", "/ip4/100.64.0.1", "/ip6/fd00::1"] {
+            assert!(addr(private).is_private(), "{}", private);
+            assert!(!addr(private).is_global(), "{}", private);
+        }
+        for link_local in &["/ip4/169.254.1.1", "/ip6/fe80::1/tcp/1"] {
+            assert!(addr(link_local).is_link_local(), "{}", link_local);
+            assert!(!addr(link_local).is_global(), "{}", link_local);
+        }
+        for global in &["/ip4/1.1.1.1/tcp/1", "/ip4/100.128.0.1", "/ip6/2606:4700::1111", "/ip6/::ffff:8.8.8.8"] {
+            assert!(addr(global).is_global(), "{}", global);
+            assert!(!addr(global).is_private(), "{}", global);
+        }
+        for special in &["/ip4/0.0.0.0", "/ip4/224.0.0.1", "/ip4/255.255.255.255", "/ip4/192.0.2.1",
+                         "/ip6/::", "/ip6/ff02::1", "/ip6/2001:db8::1", "/dns4/example.com/tcp/1"] {
+            let a = addr(special);
+            assert!(!a.is_global() && !a.is_private() && !a.is_loopback() && !a.is_link_local(), "{}", special);
+        }
+    }
+
+    #[test]
+    fn relayed_and_peer_id() {
+        let peer = "QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC";
+        let relayed = addr(&format!("/ip4/1.1.1.1/tcp/1/p2p/{0}/p2p-circuit/p2p/{0}", peer));
+        assert!(relayed.is_relayed());
+        assert!(relayed.has_peer_id());
+        assert!(relayed.is_global());
+
+        let direct = addr("/ip4/1.1.1.1/tcp/1");
+        assert!(!direct.is_relayed());
+        assert!(!direct.has_peer_id());
+        assert!(!addr(&format!("/p2p/{}/tcp/1", peer)).has_peer_id());
+    }
+}
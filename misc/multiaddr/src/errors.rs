@@ -15,6 +15,7 @@ pub enum Error {
     ParsingError(Box<dyn error::Error + Send + Sync>),
     UnknownProtocolId(u32),
     UnknownProtocolString(String),
+    PushAfterUnknown,
     #[doc(hidden)]
     __Nonexhaustive
 }
@@ -29,6 +30,7 @@ impl fmt::Display for Error {
             Error::ParsingError(e) => write!(f, "failed to parse: {}", e),
             Error::UnknownProtocolId(id) => write!(f, "unknown protocol id: {}", id),
             Error::UnknownProtocolString(string) => write!(f, "unknown protocol string: {}", string),
+            Error::PushAfterUnknown => f.write_str("can't push a protocol after an unknown protocol"),
             Error::__Nonexhaustive => f.write_str("__Nonexhaustive")
         }
     }
@@ -6,6 +6,8 @@ mod protocol;
 mod onion_addr;
 mod errors;
 mod from_url;
+mod classify;
+mod pattern;
 
 use serde::{
     Deserialize,
@@ -28,6 +30,7 @@ pub use self::errors::{Result, Error};
 pub use self::from_url::{FromUrlErr, from_url, from_url_lossy};
 pub use self::protocol::Protocol;
 pub use self::onion_addr::Onion3Addr;
+pub use self::pattern::Pattern;
 
 static_assertions::const_assert! {
     // This check is most certainly overkill right now, but done here
@@ -37,17 +40,22 @@ static_assertions::const_assert! {
 
 /// Representation of a Multiaddr.
 #[derive(PartialEq, Eq, Clone, Hash)]
-pub struct Multiaddr { bytes: Arc<Vec<u8>> }
+pub struct Multiaddr {
+    bytes: Arc<Vec<u8>>,
+    /// Whether the last component is a [`Protocol::Unknown`], whose data extends to the end of
+    /// the address, so that nothing can be appended to it.
+    ends_with_unknown: bool
+}
 
 impl Multiaddr {
     /// Create a new, empty multiaddress.
     pub fn empty() -> Self {
-        Self { bytes: Arc::new(Vec::new()) }
+        Self { bytes: Arc::new(Vec::new()), ends_with_unknown: false }
     }
 
     /// Create a new, empty multiaddress with the given capacity.
     pub fn with_capacity(n: usize) -> Self {
-        Self { bytes: Arc::new(Vec::with_capacity(n)) }
+        Self { bytes: Arc::new(Vec::with_capacity(n)), ends_with_unknown: false }
     }
 
     /// Return the length in bytes of this multiaddress.
@@ -72,10 +80,41 @@ impl Multiaddr {
     /// assert_eq!(address, "/ip4/127.0.0.1/tcp/10000".parse().unwrap());
     /// ```
     ///
+    /// # Panics
+    ///
+    /// Panics if the last component of this multiaddr is a [`Protocol::Unknown`], whose data
+    /// extends to the end of the address. Addresses obtained from
+    /// [`Multiaddr::from_bytes_passthrough`] can end with one and should be extended with
+    /// [`Multiaddr::try_push`] instead.
+    ///
     pub fn push(&mut self, p: Protocol<'_>) {
+        self.try_push(p).expect("Can't push a protocol after an unknown protocol")
+    }
+
+    /// Like [`push`] but returns [`Error::PushAfterUnknown`] instead of panicking if the
+    /// last component of this multiaddr is a [`Protocol::Unknown`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use parity_multiaddr::{Multiaddr, Protocol};
+    ///
+    /// let bytes = vec![0x04, 127, 0, 0, 1, 0xe7, 0x07, 0x01, 0x02];
+    /// let mut address = Multiaddr::from_bytes_passthrough(bytes).unwrap();
+    /// assert!(address.try_push(Protocol::Tcp(10000)).is_err());
+    /// ```
+    ///
+    pub fn try_push(&mut self, p: Protocol<'_>) -> Result<()> {
+        if self.ends_with_unknown {
+            return Err(Error::PushAfterUnknown)
+        }
+        if let Protocol::Unknown(..) = p {
+            self.ends_with_unknown = true
+        }
         let mut w = io::Cursor::<&mut Vec<u8>>::new(Arc::make_mut(&mut self.bytes));
         w.set_position(w.get_ref().len() as u64);
-        p.write_bytes(&mut w).expect("Writing to a `io::Cursor<&mut Vec<u8>>` never fails.")
+        p.write_bytes(&mut w).expect("Writing to a `io::Cursor<&mut Vec<u8>>` never fails.");
+        Ok(())
     }
 
     /// Pops the last `Protocol` of this multiaddr, or `None` if the multiaddr is empty.
@@ -94,7 +133,7 @@ impl Multiaddr {
             return None
         }
         let protocol = loop {
-            let (p, s) = Protocol::from_bytes(slice).expect("`slice` is a valid `Protocol`.");
+            let (p, s) = Protocol::from_bytes_passthrough(slice).expect("`slice` is a valid `Protocol`.");
             if s.is_empty() {
                 break p.acquire()
             }
@@ -102,17 +141,52 @@ impl Multiaddr {
         };
         let remaining_len = self.bytes.len() - slice.len();
         Arc::make_mut(&mut self.bytes).truncate(remaining_len);
+        // Only the last component can be unknown.
+        self.ends_with_unknown = false;
         Some(protocol)
     }
 
     /// Like [`push`] but consumes `self`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the last component of this multiaddr is a [`Protocol::Unknown`].
     pub fn with(mut self, p: Protocol<'_>) -> Self {
-        let mut w = io::Cursor::<&mut Vec<u8>>::new(Arc::make_mut(&mut self.bytes));
-        w.set_position(w.get_ref().len() as u64);
-        p.write_bytes(&mut w).expect("Writing to a `io::Cursor<&mut Vec<u8>>` never fails.");
+        self.push(p);
         self
     }
 
+    /// Like [`try_push`] but consumes `self`.
+    pub fn try_with(mut self, p: Protocol<'_>) -> Result<Self> {
+        self.try_push(p)?;
+        Ok(self)
+    }
+
+    /// Decodes a multiaddress from its byte representation, preserving the components
+    /// of protocols unknown to this implementation as [`Protocol::Unknown`] instead of
+    /// failing, so that the address can be passed on unchanged.
+    ///
+    /// Nothing can be appended to an address ending with a [`Protocol::Unknown`], see
+    /// [`Multiaddr::try_push`].
+    pub fn from_bytes_passthrough(bytes: Vec<u8>) -> Result<Self> {
+        let mut slice = &bytes[..];
+        let mut ends_with_unknown = false;
+        while !slice.is_empty() {
+            let (p, s) = Protocol::from_bytes_passthrough(slice)?;
+            if let Protocol::Unknown(..) = p {
+                ends_with_unknown = true
+            }
+            slice = s
+        }
+        Ok(Multiaddr { bytes: Arc::new(bytes), ends_with_unknown })
+    }
+
+    /// Returns whether the last component of this multiaddr is a [`Protocol::Unknown`], in which
+    /// case [`Multiaddr::try_push`] fails.
+    pub fn ends_with_unknown(&self) -> bool {
+        self.ends_with_unknown
+    }
+
     /// Returns the components of this multiaddress.
     ///
     /// # Example
@@ -138,8 +212,9 @@ impl Multiaddr {
     /// The parameter `at` denotes the index of the protocol at which the function
     /// `by` will be applied to the current protocol, returning an optional replacement.
     ///
-    /// If `at` is out of bounds or `by` does not yield a replacement value,
-    /// `None` will be returned. Otherwise a copy of this `Multiaddr` with the
+    /// If `at` is out of bounds, `by` does not yield a replacement value or
+    /// yields a [`Protocol::Unknown`] that is not the last component, `None`
+    /// will be returned. Otherwise a copy of this `Multiaddr` with the
     /// updated `Protocol` at position `at` will be returned.
     pub fn replace<'a, F>(&self, at: usize, by: F) -> Option<Multiaddr>
     where
@@ -153,13 +228,13 @@ impl Multiaddr {
             if i == at {
                 let f = fun.take().expect("i == at only happens once");
                 if let Some(q) = f(&p) {
-                    address = address.with(q);
+                    address = address.try_with(q).ok()?;
                     replaced = true;
                     continue
                 }
                 return None
             }
-            address = address.with(p)
+            address = address.try_with(p).ok()?
         }
 
         if replaced { Some(address) } else { None }
@@ -208,15 +283,19 @@ impl<'a> IntoIterator for &'a Multiaddr {
 }
 
 impl<'a> FromIterator<Protocol<'a>> for Multiaddr {
+    /// # Panics
+    ///
+    /// Like [`Multiaddr::push`], panics if a [`Protocol::Unknown`] is followed by another
+    /// component.
     fn from_iter<T>(iter: T) -> Self
     where
         T: IntoIterator<Item = Protocol<'a>>,
     {
-        let mut writer = Vec::new();
+        let mut address = Multiaddr::empty();
         for cmp in iter {
-            cmp.write_bytes(&mut writer).expect("Writing to a `Vec` never fails.");
+            address.push(cmp)
         }
-        Multiaddr { bytes: Arc::new(writer) }
+        address
     }
 }
 
@@ -225,6 +304,7 @@ impl FromStr for Multiaddr {
 
     fn from_str(input: &str) -> Result<Self> {
         let mut writer = Vec::new();
+        let mut ends_with_unknown = false;
         let mut parts = input.split('/').peekable();
 
         if Some("") != parts.next() {
@@ -234,10 +314,17 @@ impl FromStr for Multiaddr {
 
         while parts.peek().is_some() {
             let p = Protocol::from_str_parts(&mut parts)?;
+            // The data of an unknown protocol extends to the end of the address.
+            if let Protocol::Unknown(..) = p {
+                if parts.peek().is_some() {
+                    return Err(Error::InvalidMultiaddr)
+                }
+                ends_with_unknown = true
+            }
             p.write_bytes(&mut writer).expect("Writing to a `Vec` never fails.");
         }
 
-        Ok(Multiaddr { bytes: Arc::new(writer) })
+        Ok(Multiaddr { bytes: Arc::new(writer), ends_with_unknown })
     }
 }
 
@@ -253,7 +340,7 @@ impl<'a> Iterator for Iter<'a> {
         }
 
         let (p, next_data) =
-            Protocol::from_bytes(self.0).expect("`Multiaddr` is known to be valid.");
+            Protocol::from_bytes_passthrough(self.0).expect("`Multiaddr` is known to be valid.");
 
         self.0 = next_data;
         Some(p)
@@ -262,9 +349,9 @@ impl<'a> Iterator for Iter<'a> {
 
 impl<'a> From<Protocol<'a>> for Multiaddr {
     fn from(p: Protocol<'a>) -> Multiaddr {
-        let mut w = Vec::new();
-        p.write_bytes(&mut w).expect("Writing to a `Vec` never fails.");
-        Multiaddr { bytes: Arc::new(w) }
+        let mut address = Multiaddr::empty();
+        address.push(p);
+        address
     }
 }
 
@@ -299,7 +386,7 @@ impl TryFrom<Vec<u8>> for Multiaddr {
             let (_, s) = Protocol::from_bytes(slice)?;
             slice = s
         }
-        Ok(Multiaddr { bytes: Arc::new(v) })
+        Ok(Multiaddr { bytes: Arc::new(v), ends_with_unknown: false })
     }
 }
 
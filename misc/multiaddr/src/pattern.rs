@@ -0,0 +1,175 @@
+use crate::{Error, Multiaddr, Protocol, Result};
+use std::{iter, str::FromStr};
+
+/// A template of multiaddresses, matching addresses against a sequence of protocols
+/// whose values may be left open.
+///
+/// Patterns are written like multiaddresses, except that:
+///
+/// - a value can be replaced by `*` to match any value of the protocol, e.g. `/tcp/*`;
+/// - alternative protocols can be separated by `|`, e.g. `/ip4|ip6/*`;
+/// - a final `/**` matches any remaining protocols.
+///
+/// # Example
+///
+/// ```
+/// use parity_multiaddr::{Multiaddr, Pattern, Protocol};
+///
+/// let pattern: Pattern = "/ip4|ip6/*/tcp/*/ws".parse().unwrap();
+/// let address: Multiaddr = "/ip4/127.0.0.1/tcp/8080/ws".parse().unwrap();
+/// assert!(pattern.matches(&address));
+///
+/// // The values matched by wildcards can be extracted.
+/// let captures = pattern.captures(&address).unwrap();
+/// assert_eq!(captures, vec![Protocol::Ip4([127, 0, 0, 1].into()), Protocol::Tcp(8080)]);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    components: Vec<Component>,
+    /// Whether the pattern ends with `/**`.
+    any_suffix: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Component {
+    /// Matches a protocol equal to one of the alternatives.
+    Exact(Vec<Protocol<'static>>),
+    /// Matches any value of the protocols with one of the given tags.
+    Any(Vec<String>),
+}
+
+impl Pattern {
+    /// Returns true if the address matches the pattern.
+    pub fn matches(&self, address: &Multiaddr) -> bool {
+        self.captures(address).is_some()
+    }
+
+    /// Matches the address against the pattern, returning the protocols matched by
+    /// the wildcards, in order, or `None` if the address doesn't match.
+    pub fn captures<'a>(&self, address: &'a Multiaddr) -> Option<Vec<Protocol<'a>>> {
+        let mut captures = Vec::new();
+        let mut protocols = address.iter();
+        for component in &self.components {
+            let protocol = protocols.next()?;
+            match component {
+                Component::Exact(alternatives) => {
+                    if !alternatives.iter().any(|p| p == &protocol) {
+                        return None
+                    }
+                }
+                Component::Any(tags) => {
+                    if !tags.iter().any(|t| t == protocol.tag()) {
+                        return None
+                    }
+                    captures.push(protocol)
+                }
+            }
+        }
+        if !self.any_suffix && protocols.next().is_some() {
+            return None
+        }
+        Some(captures)
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let mut parts = input.split('/').peekable();
+        if Some("") != parts.next() {
+            return Err(Error::InvalidMultiaddr)
+        }
+
+        let mut components = Vec::new();
+        let mut any_suffix = false;
+        while let Some(part) = parts.next() {
+            if part == "**" {
+                if parts.peek().is_some() {
+                    return Err(Error::InvalidProtocolString)
+                }
+                any_suffix = true;
+                break
+            }
+
+            // Protocols without value parse on their own, the others require one.
+            let mut tags = Vec::new();
+            let mut valueless = Vec::new();
+            for tag in part.split('|') {
+                match Protocol::from_str_parts(iter::once(tag)) {
+                    Ok(p) => valueless.push(p.acquire()),
+                    Err(Error::InvalidProtocolString) => tags.push(tag),
+                    Err(e) => return Err(e)
+                }
+            }
+
+            let component = match (valueless.is_empty(), tags.is_empty()) {
+                (false, true) => Component::Exact(valueless),
+                (true, false) => match parts.next().ok_or(Error::InvalidProtocolString)? {
+                    "*" => Component::Any(tags.into_iter().map(String::from).collect()),
+                    value => Component::Exact(tags.into_iter()
+                        .map(|t| Protocol::from_str_parts(iter::once(t).chain(iter::once(value)))
+                            .map(Protocol::acquire))
+                        .collect::<Result<_>>()?),
+                },
+                // Mixing protocols with and without value.
+                _ => return Err(Error::InvalidProtocolString)
+            };
+            components.push(component)
+        }
+
+        Ok(Pattern { components, any_suffix })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv6Addr;
+
+    fn pattern(s: &str) -> Pattern {
+        s.parse().unwrap()
+    }
+
+    fn addr(s: &str) -> Multiaddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn match_patterns() {
+        let tcp = pattern("/ip4|ip6/*/tcp/*");
+        assert!(tcp.matches(&addr("/ip4/127.0.0.1/tcp/1234")));
+        assert!(tcp.matches(&addr("/ip6/::1/tcp/1234")));
+        assert!(!tcp.matches(&addr("/ip4/127.0.0.1/udp/1234")));
+        assert!(!tcp.matches(&addr("/ip4/127.0.0.1/tcp/1234/ws")));
+        assert!(!tcp.matches(&addr("/ip4/127.0.0.1")));
+        assert!(!tcp.matches(&addr("/dns4/example.com/tcp/1234")));
+
+        let ws = pattern("/ip4/*/tcp/*/ws/**");
+        assert!(ws.matches(&addr("/ip4/127.0.0.1/tcp/1234/ws")));
+        assert!(ws.matches(&addr("/ip4/127.0.0.1/tcp/1234/ws/p2p/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC")));
+        assert!(!ws.matches(&addr("/ip4/127.0.0.1/tcp/1234/wss")));
+
+        let exact = pattern("/ip4/*/tcp|udp/443");
+        assert!(exact.matches(&addr("/ip4/1.2.3.4/tcp/443")));
+        assert!(exact.matches(&addr("/ip4/1.2.3.4/udp/443")));
+        assert!(!exact.matches(&addr("/ip4/1.2.3.4/tcp/80")));
+
+        assert!(pattern("").matches(&Multiaddr::empty()));
+        assert!(pattern("/**").matches(&addr("/ip4/1.2.3.4")));
+    }
+
+    #[test]
+    fn capture_values() {
+        let address = addr("/ip6/::1/udp/4001/quic-v1/webtransport");
+        let captures = pattern("/ip6/*/udp/*/quic-v1/**").captures(&address).unwrap();
+        assert_eq!(captures, vec![Protocol::Ip6(Ipv6Addr::LOCALHOST), Protocol::Udp(4001)]);
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        for invalid in &["ip4/*", "/ip4", "/ip4|ws/*", "/foo/*", "/*", "/**/tcp/*", "/tcp/x"] {
+            assert!(invalid.parse::<Pattern>().is_err(), "{}", invalid);
+        }
+    }
+}
@@ -3,7 +3,7 @@ use arrayref::array_ref;
 use bs58;
 use byteorder::{BigEndian, ByteOrder, ReadBytesExt, WriteBytesExt};
 use crate::{Result, Error};
-use data_encoding::BASE32;
+use data_encoding::{BASE32, BASE32_NOPAD, BASE64, BASE64URL_NOPAD, BASE64_NOPAD, HEXLOWER, HEXLOWER_PERMISSIVE};
 use multihash::Multihash;
 use std::{
     borrow::Cow,
@@ -16,29 +16,40 @@ use std::{
 use unsigned_varint::{encode, decode};
 use crate::onion_addr::Onion3Addr;
 
+const CERTHASH: u32 = 466;
 const DCCP: u32 = 33;
+const DNS: u32 = 53;
 const DNS4: u32 = 54;
 const DNS6: u32 = 55;
 const DNSADDR: u32 = 56;
+const GARLIC32: u32 = 447;
+const GARLIC64: u32 = 446;
 const HTTP: u32 = 480;
 const HTTPS: u32 = 443;
 const IP4: u32 = 4;
 const IP6: u32 = 41;
+const IP6ZONE: u32 = 42;
+const IPCIDR: u32 = 43;
 const P2P_WEBRTC_DIRECT: u32 = 276;
 const P2P_WEBRTC_STAR: u32 = 275;
 const P2P_WEBSOCKET_STAR: u32 = 479;
 const MEMORY: u32 = 777;
+const NOISE: u32 = 454;
 const ONION: u32 = 444;
 const ONION3: u32 = 445;
 const P2P: u32 = 421;
 const P2P_CIRCUIT: u32 = 290;
 const QUIC: u32 = 460;
+const QUIC_V1: u32 = 461;
 const SCTP: u32 = 132;
+const SNI: u32 = 449;
 const TCP: u32 = 6;
+const TLS: u32 = 448;
 const UDP: u32 = 273;
 const UDT: u32 = 301;
 const UNIX: u32 = 400;
 const UTP: u32 = 302;
+const WEBTRANSPORT: u32 = 465;
 const WS: u32 = 477;
 const WS_WITH_PATH: u32 = 4770;         // Note: not standard
 const WSS: u32 = 478;
@@ -65,32 +76,57 @@ const PATH_SEGMENT_ENCODE_SET: &percent_encoding::AsciiSet = &percent_encoding::
 /// happen separately.
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub enum Protocol<'a> {
+    /// The hash of a certificate, as a multihash.
+    Certhash(Multihash),
     Dccp(u16),
+    Dns(Cow<'a, str>),
     Dns4(Cow<'a, str>),
     Dns6(Cow<'a, str>),
     Dnsaddr(Cow<'a, str>),
+    /// An I2P address in its base-32 form, i.e. the hash of a destination.
+    Garlic32(Cow<'a, [u8]>),
+    /// An I2P destination.
+    Garlic64(Cow<'a, [u8]>),
     Http,
     Https,
     Ip4(Ipv4Addr),
     Ip6(Ipv6Addr),
+    /// The zone of the IPv6 address that follows, e.g. the network interface
+    /// of a link-local address.
+    Ip6zone(Cow<'a, str>),
+    /// The prefix length of the preceding IP address, making it a network address.
+    Ipcidr(u8),
     P2pWebRtcDirect,
     P2pWebRtcStar,
     P2pWebSocketStar,
     /// Contains the "port" to contact. Similar to TCP or UDP, 0 means "assign me a port".
     Memory(u64),
+    Noise,
     Onion(Cow<'a, [u8; 10]>, u16),
     Onion3(Onion3Addr<'a>),
     P2p(Multihash),
     P2pCircuit,
     Quic,
+    QuicV1,
     Sctp(u16),
+    /// The server name to indicate in the TLS handshake.
+    Sni(Cow<'a, str>),
     Tcp(u16),
+    Tls,
     Udp(u16),
     Udt,
     Unix(Cow<'a, str>),
     Utp,
+    WebTransport,
     Ws(Cow<'a, str>),
     Wss(Cow<'a, str>),
+    /// A protocol not known to this implementation, with the remainder of the
+    /// address, whose layout depends on that protocol.
+    ///
+    /// Unknown protocols are only produced by [`Protocol::from_bytes_passthrough`].
+    /// As the length of their data is unknown, nothing can follow them in an
+    /// address: protocols pushed after them become part of their data.
+    Unknown(u32, Cow<'a, [u8]>),
 }
 
 impl<'a> Protocol<'a> {
@@ -125,6 +161,10 @@ impl<'a> Protocol<'a> {
                 let s = iter.next().ok_or(Error::InvalidProtocolString)?;
                 Ok(Protocol::Ip6(Ipv6Addr::from_str(s)?))
             }
+            "dns" => {
+                let s = iter.next().ok_or(Error::InvalidProtocolString)?;
+                Ok(Protocol::Dns(Cow::Borrowed(s)))
+            }
             "dns4" => {
                 let s = iter.next().ok_or(Error::InvalidProtocolString)?;
                 Ok(Protocol::Dns4(Cow::Borrowed(s)))
@@ -185,6 +225,62 @@ impl<'a> Protocol<'a> {
                 let s = iter.next().ok_or(Error::InvalidProtocolString)?;
                 Ok(Protocol::Memory(s.parse()?))
             }
+            "quic-v1" => Ok(Protocol::QuicV1),
+            "webtransport" => Ok(Protocol::WebTransport),
+            "certhash" => {
+                let s = iter.next().ok_or(Error::InvalidProtocolString)?;
+                Ok(Protocol::Certhash(Multihash::from_bytes(multibase_decode(s)?)?))
+            }
+            "tls" => Ok(Protocol::Tls),
+            "sni" => {
+                let s = iter.next().ok_or(Error::InvalidProtocolString)?;
+                let decoded = percent_encoding::percent_decode(s.as_bytes()).decode_utf8()?;
+                Ok(Protocol::Sni(decoded))
+            }
+            "noise" => Ok(Protocol::Noise),
+            "ip6zone" => {
+                let s = iter.next().ok_or(Error::InvalidProtocolString)?;
+                if s.is_empty() {
+                    return Err(Error::InvalidProtocolString)
+                }
+                let decoded = percent_encoding::percent_decode(s.as_bytes()).decode_utf8()?;
+                Ok(Protocol::Ip6zone(decoded))
+            }
+            "ipcidr" => {
+                let s = iter.next().ok_or(Error::InvalidProtocolString)?;
+                Ok(Protocol::Ipcidr(s.parse()?))
+            }
+            "garlic64" => {
+                let s = iter.next().ok_or(Error::InvalidProtocolString)?;
+                // I2P uses the base-64 alphabet with `-` and `~` instead of `+` and `/`.
+                if s.contains('+') {
+                    return Err(Error::InvalidMultiaddr)
+                }
+                let decoded = BASE64.decode(s.replace('-', "+").replace('~', "/").as_bytes())
+                    .map_err(|_| Error::InvalidMultiaddr)?;
+                check_garlic64(&decoded)?;
+                Ok(Protocol::Garlic64(Cow::Owned(decoded)))
+            }
+            "garlic32" => {
+                let s = iter.next().ok_or(Error::InvalidProtocolString)?;
+                let decoded = BASE32_NOPAD.decode(s.to_uppercase().as_bytes())
+                    .map_err(|_| Error::InvalidMultiaddr)?;
+                check_garlic32(&decoded)?;
+                Ok(Protocol::Garlic32(Cow::Owned(decoded)))
+            }
+            "x-unknown" => {
+                let code = iter.next().ok_or(Error::InvalidProtocolString)?.parse()?;
+                let s = iter.next().ok_or(Error::InvalidProtocolString)?;
+                let data = HEXLOWER_PERMISSIVE.decode(s.as_bytes())
+                    .map_err(|_| Error::InvalidProtocolString)?;
+                // A known protocol must not be smuggled in as an unknown one.
+                let mut bytes = Vec::new();
+                encode_with_data(code, &data, false, &mut bytes)?;
+                match Protocol::from_bytes(&bytes) {
+                    Err(Error::UnknownProtocolId(_)) => Ok(Protocol::Unknown(code, Cow::Owned(data))),
+                    _ => Err(Error::InvalidProtocolString)
+                }
+            }
             unknown => Err(Error::UnknownProtocolString(unknown.to_string()))
         }
     }
@@ -206,6 +302,11 @@ impl<'a> Protocol<'a> {
                 let num = rdr.read_u16::<BigEndian>()?;
                 Ok((Protocol::Dccp(num), rest))
             }
+            DNS => {
+                let (n, input) = decode::usize(input)?;
+                let (data, rest) = split_at(n, input)?;
+                Ok((Protocol::Dns(Cow::Borrowed(str::from_utf8(data)?)), rest))
+            }
             DNS4 => {
                 let (n, input) = decode::usize(input)?;
                 let (data, rest) = split_at(n, input)?;
@@ -310,10 +411,61 @@ impl<'a> Protocol<'a> {
                 let (data, rest) = split_at(n, input)?;
                 Ok((Protocol::Wss(Cow::Borrowed(str::from_utf8(data)?)), rest))
             }
+            QUIC_V1 => Ok((Protocol::QuicV1, input)),
+            WEBTRANSPORT => Ok((Protocol::WebTransport, input)),
+            CERTHASH => {
+                let (n, input) = decode::usize(input)?;
+                let (data, rest) = split_at(n, input)?;
+                Ok((Protocol::Certhash(Multihash::from_bytes(data.to_owned())?), rest))
+            }
+            TLS => Ok((Protocol::Tls, input)),
+            SNI => {
+                let (n, input) = decode::usize(input)?;
+                let (data, rest) = split_at(n, input)?;
+                Ok((Protocol::Sni(Cow::Borrowed(str::from_utf8(data)?)), rest))
+            }
+            NOISE => Ok((Protocol::Noise, input)),
+            IP6ZONE => {
+                let (n, input) = decode::usize(input)?;
+                let (data, rest) = split_at(n, input)?;
+                if data.is_empty() {
+                    return Err(Error::InvalidMultiaddr)
+                }
+                Ok((Protocol::Ip6zone(Cow::Borrowed(str::from_utf8(data)?)), rest))
+            }
+            IPCIDR => {
+                let (data, rest) = split_at(1, input)?;
+                Ok((Protocol::Ipcidr(data[0]), rest))
+            }
+            GARLIC64 => {
+                let (n, input) = decode::usize(input)?;
+                let (data, rest) = split_at(n, input)?;
+                check_garlic64(data)?;
+                Ok((Protocol::Garlic64(Cow::Borrowed(data)), rest))
+            }
+            GARLIC32 => {
+                let (n, input) = decode::usize(input)?;
+                let (data, rest) = split_at(n, input)?;
+                check_garlic32(data)?;
+                Ok((Protocol::Garlic32(Cow::Borrowed(data)), rest))
+            }
             _ => Err(Error::UnknownProtocolId(id))
         }
     }
 
+    /// Same as [`Protocol::from_bytes`], except that a protocol that is not known to
+    /// this implementation is returned as a [`Protocol::Unknown`] containing the
+    /// remainder of the input, instead of failing.
+    pub fn from_bytes_passthrough(input: &'a [u8]) -> Result<(Self, &'a [u8])> {
+        match Protocol::from_bytes(input) {
+            Err(Error::UnknownProtocolId(id)) => {
+                let (_, data) = decode::u32(input)?;
+                Ok((Protocol::Unknown(id, Cow::Borrowed(data)), &[]))
+            }
+            other => other
+        }
+    }
+
     /// Encode this protocol by writing its binary representation into
     /// the given `Write` impl.
     pub fn write_bytes<W: Write>(&self, w: &mut W) -> Result<()> {
@@ -345,6 +497,12 @@ impl<'a> Protocol<'a> {
                 w.write_all(encode::u32(SCTP, &mut buf))?;
                 w.write_u16::<BigEndian>(*port)?
             }
+            Protocol::Dns(s) => {
+                w.write_all(encode::u32(DNS, &mut buf))?;
+                let bytes = s.as_bytes();
+                w.write_all(encode::usize(bytes.len(), &mut encode::usize_buffer()))?;
+                w.write_all(&bytes)?
+            }
             Protocol::Dns4(s) => {
                 w.write_all(encode::u32(DNS4, &mut buf))?;
                 let bytes = s.as_bytes();
@@ -412,15 +570,77 @@ impl<'a> Protocol<'a> {
                 w.write_all(encode::u32(MEMORY, &mut buf))?;
                 w.write_u64::<BigEndian>(*port)?
             }
+            Protocol::QuicV1 => w.write_all(encode::u32(QUIC_V1, &mut buf))?,
+            Protocol::WebTransport => w.write_all(encode::u32(WEBTRANSPORT, &mut buf))?,
+            Protocol::Certhash(multihash) => encode_with_data(CERTHASH, multihash.as_bytes(), true, w)?,
+            Protocol::Tls => w.write_all(encode::u32(TLS, &mut buf))?,
+            Protocol::Sni(s) => encode_with_data(SNI, s.as_bytes(), true, w)?,
+            Protocol::Noise => w.write_all(encode::u32(NOISE, &mut buf))?,
+            Protocol::Ip6zone(s) => encode_with_data(IP6ZONE, s.as_bytes(), true, w)?,
+            Protocol::Ipcidr(len) => {
+                w.write_all(encode::u32(IPCIDR, &mut buf))?;
+                w.write_u8(*len)?
+            }
+            Protocol::Garlic64(data) => encode_with_data(GARLIC64, data, true, w)?,
+            Protocol::Garlic32(data) => encode_with_data(GARLIC32, data, true, w)?,
+            Protocol::Unknown(code, data) => encode_with_data(*code, data, false, w)?,
         }
         Ok(())
     }
 
+    /// Returns the name of the protocol in the string representation of multiaddresses,
+    /// e.g. `"ip4"` or `"tcp"`.
+    pub fn tag(&self) -> &'static str {
+        use self::Protocol::*;
+        match self {
+            Certhash(_) => "certhash",
+            Dccp(_) => "dccp",
+            Dns(_) => "dns",
+            Dns4(_) => "dns4",
+            Dns6(_) => "dns6",
+            Dnsaddr(_) => "dnsaddr",
+            Garlic32(_) => "garlic32",
+            Garlic64(_) => "garlic64",
+            Http => "http",
+            Https => "https",
+            Ip4(_) => "ip4",
+            Ip6(_) => "ip6",
+            Ip6zone(_) => "ip6zone",
+            Ipcidr(_) => "ipcidr",
+            P2pWebRtcDirect => "p2p-webrtc-direct",
+            P2pWebRtcStar => "p2p-webrtc-star",
+            P2pWebSocketStar => "p2p-websocket-star",
+            Memory(_) => "memory",
+            Noise => "noise",
+            Onion(..) => "onion",
+            Onion3(_) => "onion3",
+            P2p(_) => "p2p",
+            P2pCircuit => "p2p-circuit",
+            Quic => "quic",
+            QuicV1 => "quic-v1",
+            Sctp(_) => "sctp",
+            Sni(_) => "sni",
+            Tcp(_) => "tcp",
+            Tls => "tls",
+            Udp(_) => "udp",
+            Udt => "udt",
+            Unix(_) => "unix",
+            Utp => "utp",
+            WebTransport => "webtransport",
+            Ws(ref s) if s == "/" => "ws",
+            Ws(_) => "x-parity-ws",
+            Wss(ref s) if s == "/" => "wss",
+            Wss(_) => "x-parity-wss",
+            Unknown(..) => "x-unknown",
+        }
+    }
+
     /// Turn this `Protocol` into one that owns its data, thus being valid for any lifetime.
     pub fn acquire<'b>(self) -> Protocol<'b> {
         use self::Protocol::*;
         match self {
             Dccp(a) => Dccp(a),
+            Dns(cow) => Dns(Cow::Owned(cow.into_owned())),
             Dns4(cow) => Dns4(Cow::Owned(cow.into_owned())),
             Dns6(cow) => Dns6(Cow::Owned(cow.into_owned())),
             Dnsaddr(cow) => Dnsaddr(Cow::Owned(cow.into_owned())),
@@ -445,6 +665,17 @@ impl<'a> Protocol<'a> {
             Utp => Utp,
             Ws(cow) => Ws(Cow::Owned(cow.into_owned())),
             Wss(cow) => Wss(Cow::Owned(cow.into_owned())),
+            QuicV1 => QuicV1,
+            WebTransport => WebTransport,
+            Certhash(a) => Certhash(a),
+            Tls => Tls,
+            Sni(cow) => Sni(Cow::Owned(cow.into_owned())),
+            Noise => Noise,
+            Ip6zone(cow) => Ip6zone(Cow::Owned(cow.into_owned())),
+            Ipcidr(a) => Ipcidr(a),
+            Garlic64(cow) => Garlic64(Cow::Owned(cow.into_owned())),
+            Garlic32(cow) => Garlic32(Cow::Owned(cow.into_owned())),
+            Unknown(code, cow) => Unknown(code, Cow::Owned(cow.into_owned())),
         }
     }
 }
@@ -454,6 +685,7 @@ impl<'a> fmt::Display for Protocol<'a> {
         use self::Protocol::*;
         match self {
             Dccp(port) => write!(f, "/dccp/{}", port),
+            Dns(s) => write!(f, "/dns/{}", s),
             Dns4(s) => write!(f, "/dns4/{}", s),
             Dns6(s) => write!(f, "/dns6/{}", s),
             Dnsaddr(s) => write!(f, "/dnsaddr/{}", s),
@@ -492,6 +724,26 @@ impl<'a> fmt::Display for Protocol<'a> {
                 let encoded = percent_encoding::percent_encode(s.as_bytes(), PATH_SEGMENT_ENCODE_SET);
                 write!(f, "/x-parity-wss/{}", encoded)
             },
+            QuicV1 => f.write_str("/quic-v1"),
+            WebTransport => f.write_str("/webtransport"),
+            Certhash(c) => write!(f, "/certhash/u{}", BASE64URL_NOPAD.encode(c.as_bytes())),
+            Tls => f.write_str("/tls"),
+            Sni(s) => {
+                let encoded = percent_encoding::percent_encode(s.as_bytes(), PATH_SEGMENT_ENCODE_SET);
+                write!(f, "/sni/{}", encoded)
+            }
+            Noise => f.write_str("/noise"),
+            Ip6zone(s) => {
+                let encoded = percent_encoding::percent_encode(s.as_bytes(), PATH_SEGMENT_ENCODE_SET);
+                write!(f, "/ip6zone/{}", encoded)
+            }
+            Ipcidr(len) => write!(f, "/ipcidr/{}", len),
+            Garlic64(data) => {
+                let s = BASE64.encode(data).replace('+', "-").replace('/', "~");
+                write!(f, "/garlic64/{}", s)
+            }
+            Garlic32(data) => write!(f, "/garlic32/{}", BASE32_NOPAD.encode(data).to_lowercase()),
+            Unknown(code, data) => write!(f, "/x-unknown/{}/{}", code, HEXLOWER.encode(data)),
         }
     }
 }
@@ -565,4 +817,50 @@ read_onion_impl!(read_onion, 10, 16);
 // Parse a version 3 onion address and return its binary representation.
 //
 // Format: <base-32 address> ":" <port number>
-read_onion_impl!(read_onion3, 35, 56);
\ No newline at end of file
+read_onion_impl!(read_onion3, 35, 56);
+
+/// Writes the code of a protocol followed by its data, prefixed with its length
+/// if `length_prefixed` is set.
+fn encode_with_data<W: Write>(code: u32, data: &[u8], length_prefixed: bool, w: &mut W) -> Result<()> {
+    w.write_all(encode::u32(code, &mut encode::u32_buffer()))?;
+    if length_prefixed {
+        w.write_all(encode::usize(data.len(), &mut encode::usize_buffer()))?;
+    }
+    w.write_all(data)?;
+    Ok(())
+}
+
+// Decode a multibase-encoded string, as used for certificate hashes.
+fn multibase_decode(s: &str) -> Result<Vec<u8>> {
+    let mut chars = s.chars();
+    let base = chars.next().ok_or(Error::InvalidMultiaddr)?;
+    let data = chars.as_str();
+    let decoded = match base {
+        'u' => BASE64URL_NOPAD.decode(data.as_bytes()),
+        'm' => BASE64_NOPAD.decode(data.as_bytes()),
+        'b' => BASE32_NOPAD.decode(data.to_uppercase().as_bytes()),
+        'B' => BASE32_NOPAD.decode(data.as_bytes()),
+        'f' | 'F' => HEXLOWER_PERMISSIVE.decode(data.as_bytes()),
+        'z' => return Ok(bs58::decode(data).into_vec()?),
+        _ => return Err(Error::InvalidMultiaddr)
+    };
+    decoded.map_err(|_| Error::InvalidMultiaddr)
+}
+
+// An I2P destination contains 256 and 128 bytes keys followed by a certificate.
+// Like other implementations, accept destinations of at least 386 bytes.
+fn check_garlic64(data: &[u8]) -> Result<()> {
+    if data.len() < 386 {
+        return Err(Error::InvalidMultiaddr)
+    }
+    Ok(())
+}
+
+// An I2P base-32 address is either a 32 bytes hash or, for encrypted lease sets,
+// at least 35 bytes long.
+fn check_garlic32(data: &[u8]) -> Result<()> {
+    if data.len() < 35 && data.len() != 32 {
+        return Err(Error::InvalidMultiaddr)
+    }
+    Ok(())
+}
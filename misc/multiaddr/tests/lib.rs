@@ -76,7 +76,7 @@ struct Proto(Protocol<'static>);
 impl Arbitrary for Proto {
     fn arbitrary<G: Gen>(g: &mut G) -> Self {
         use Protocol::*;
-        match g.gen_range(0, 24) { // TODO: Add Protocol::Quic
+        match g.gen_range(0, 36) { // TODO: Add Protocol::Quic
              0 => Proto(Dccp(g.gen())),
              1 => Proto(Dns4(Cow::Owned(SubString::arbitrary(g).0))),
              2 => Proto(Dns6(Cow::Owned(SubString::arbitrary(g).0))),
@@ -110,6 +110,26 @@ impl Arbitrary for Proto {
                 g.fill_bytes(&mut a);
                 Proto(Onion3((a, g.gen()).into()))
             },
+            24 => Proto(Dns(Cow::Owned(SubString::arbitrary(g).0))),
+            25 => Proto(QuicV1),
+            26 => Proto(WebTransport),
+            27 => Proto(Certhash(multihash("QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC"))),
+            28 => Proto(Tls),
+            29 => Proto(Sni(Cow::Owned(SubString::arbitrary(g).0))),
+            30 => Proto(Noise),
+            31 => Proto(Ip6zone(Cow::Owned(format!("z{}", SubString::arbitrary(g).0)))),
+            32 => Proto(Ipcidr(g.gen())),
+            33 => {
+                let mut a = vec![0; g.gen_range(386, 400)];
+                g.fill_bytes(&mut a);
+                Proto(Garlic64(Cow::Owned(a)))
+            },
+            34 => {
+                let mut a = vec![0; 32];
+                g.fill_bytes(&mut a);
+                Proto(Garlic32(Cow::Owned(a)))
+            },
+            35 => Proto(Udp(g.gen())),
              _ => panic!("outside range")
         }
     }
@@ -224,6 +244,11 @@ fn construct_success() {
         "BD03ADADEC040BE047F9658668B11A504F3155001F231A37F54C4476C07FB4CC139ED7E30304D2",
         vec![Onion3(([173, 173, 236, 4, 11, 224, 71, 249, 101, 134, 104, 177, 26, 80, 79, 49, 85, 0, 31, 35, 26, 55, 245, 76, 68, 118, 192, 127, 180, 204, 19, 158, 215, 227, 3], 1234).into())],
     );
+    ma_valid(
+        "/dns/example.com/tcp/443",
+        "350B6578616D706C652E636F6D0601BB",
+        vec![Dns(Cow::Borrowed("example.com")), Tcp(443)]
+    );
     ma_valid(
         "/dnsaddr/sjc-1.bootstrap.libp2p.io",
         "3819736A632D312E626F6F7473747261702E6C69627032702E696F",
@@ -234,6 +259,28 @@ fn construct_success() {
         "3819736A632D312E626F6F7473747261702E6C69627032702E696F0604D2A50322122006B3608AA000274049EB28AD8E793A26FF6FAB281A7D3BD77CD18EB745DFAABB",
         vec![Dnsaddr(Cow::Borrowed("sjc-1.bootstrap.libp2p.io")), Tcp(1234), P2p(multihash("QmNnooDu7bfjPFoTZYxMNLWUQJyrVwtbZg5gBMjTezGAJN"))]
     );
+    ma_valid(
+        "/ip4/127.0.0.1/udp/1234/quic-v1/webtransport/certhash/uEiDDq4_xNyDorZBH3TlGazyJdOWSwvo4PUo5YHFMrvDE8g",
+        "047F000001910204D2CD03D103D203221220C3AB8FF13720E8AD9047DD39466B3C8974E592C2FA383D4A3960714CAEF0C4F2",
+        vec![Ip4(local.clone()), Udp(1234), QuicV1, WebTransport,
+             Certhash(Multihash::from_bytes(HEXUPPER.decode(b"1220C3AB8FF13720E8AD9047DD39466B3C8974E592C2FA383D4A3960714CAEF0C4F2").unwrap()).unwrap())]
+    );
+    ma_valid(
+        "/dns/example.com/tcp/443/tls/sni/example.com/noise",
+        "350B6578616D706C652E636F6D0601BBC003C1030B6578616D706C652E636F6DC603",
+        vec![Dns(Cow::Borrowed("example.com")), Tcp(443), Tls, Sni(Cow::Borrowed("example.com")), Noise]
+    );
+    ma_valid(
+        "/ip6zone/eth0/ip6/fe80::1",
+        "2A046574683029FE800000000000000000000000000001",
+        vec![Ip6zone(Cow::Borrowed("eth0")), Ip6("fe80::1".parse().unwrap())]
+    );
+    ma_valid("/ip4/192.168.0.0/ipcidr/16", "04C0A800002B10", vec![Ip4(Ipv4Addr::new(192, 168, 0, 0)), Ipcidr(16)]);
+    ma_valid(
+        "/garlic32/aaaqeayeaudaocajbifqydiob4ibceqtcqkrmfyydenbwha5dypq",
+        "BF0320000102030405060708090A0B0C0D0E0F101112131415161718191A1B1C1D1E1F",
+        vec![Garlic32(Cow::Owned((0 .. 32).collect()))]
+    );
 }
 
 #[test]
@@ -269,7 +316,18 @@ fn construct_fail() {
         "/ip4/127.0.0.1/tcp",
         "/ip4/127.0.0.1/p2p",
         "/ip4/127.0.0.1/p2p/tcp",
-        "/p2p-circuit/50"
+        "/p2p-circuit/50",
+        "/certhash",
+        "/certhash/xEiDDq4_xNyDorZBH3TlGazyJdOWSwvo4PUo5YHFMrvDE8g",
+        "/ip6zone",
+        "/ip6zone/",
+        "/ipcidr/256",
+        "/garlic32/aaaqeayeaudaocajbifqydiob4ibceqtcqkrmfyydenbwha5dy",
+        "/garlic64/AAAA",
+        "/quic-v1/1234",
+        "/x-unknown/999",
+        "/x-unknown/6/04d2",
+        "/x-unknown/999/00/tcp/1234"
     ];
 
     for address in &addresses {
@@ -374,3 +432,77 @@ fn unknown_protocol_string() {
         }
     }
 }
+
+#[test]
+fn unknown_protocol_passthrough() {
+    // `/ip4/127.0.0.1/tcp/1234` followed by the unassigned protocol 999 and two bytes.
+    let bytes = HEXUPPER.decode(b"047F0000010604D2E7070102").unwrap();
+    match Multiaddr::try_from(bytes.clone()) {
+        Err(crate::Error::UnknownProtocolId(999)) => {}
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    let addr = Multiaddr::from_bytes_passthrough(bytes.clone()).unwrap();
+    assert_eq!(addr.to_vec(), bytes);
+    assert_eq!(addr.iter().collect::<Vec<_>>(), vec![
+        Protocol::Ip4(Ipv4Addr::new(127, 0, 0, 1)),
+        Protocol::Tcp(1234),
+        Protocol::Unknown(999, Cow::Borrowed(&[1, 2]))
+    ]);
+    assert_eq!(addr.to_string(), "/ip4/127.0.0.1/tcp/1234/x-unknown/999/0102");
+    assert_eq!(addr.to_string().parse::<Multiaddr>().unwrap(), addr);
+
+    let mut popped = addr.clone();
+    assert_eq!(popped.pop(), Some(Protocol::Unknown(999, Cow::Owned(vec![1, 2]))));
+    assert_eq!(popped, "/ip4/127.0.0.1/tcp/1234".parse().unwrap());
+}
+
+#[test]
+fn push_after_unknown_protocol() {
+    let bytes = HEXUPPER.decode(b"047F000001E7070102").unwrap();
+    let mut addr = Multiaddr::from_bytes_passthrough(bytes).unwrap();
+    assert!(addr.ends_with_unknown());
+    match addr.try_push(Protocol::Tcp(1234)) {
+        Err(crate::Error::PushAfterUnknown) => {}
+        other => panic!("unexpected result: {:?}", other)
+    }
+    assert!(addr.clone().try_with(Protocol::Tcp(1234)).is_err());
+    assert_eq!(addr.to_string(), "/ip4/127.0.0.1/x-unknown/999/0102");
+
+    let collected = addr.iter().collect::<Multiaddr>();
+    assert_eq!(collected, addr);
+    assert!(collected.ends_with_unknown());
+
+    addr.pop();
+    assert!(!addr.ends_with_unknown());
+    addr.push(Protocol::Tcp(1234));
+    assert_eq!(addr, "/ip4/127.0.0.1/tcp/1234".parse().unwrap());
+}
+
+#[test]
+#[should_panic]
+fn push_after_unknown_protocol_panics() {
+    let bytes = HEXUPPER.decode(b"047F000001E7070102").unwrap();
+    let mut addr = Multiaddr::from_bytes_passthrough(bytes).unwrap();
+    addr.push(Protocol::Tcp(1234));
+}
+
+#[test]
+#[should_panic]
+fn collect_after_unknown_protocol_panics() {
+    let bytes = HEXUPPER.decode(b"047F000001E7070102").unwrap();
+    let addr = Multiaddr::from_bytes_passthrough(bytes).unwrap();
+    let _ = addr.iter().chain(std::iter::once(Protocol::Tcp(1234))).collect::<Multiaddr>();
+}
+
+#[test]
+fn sni_and_ip6zone_are_escaped() {
+    let addr = Multiaddr::empty()
+        .with(Protocol::Ip6zone(Cow::Borrowed("eth/0")))
+        .with(Protocol::Ip6("fe80::1".parse().unwrap()))
+        .with(Protocol::Tcp(443))
+        .with(Protocol::Tls)
+        .with(Protocol::Sni(Cow::Borrowed("a/b%c")));
+    assert_eq!(addr.to_string(), "/ip6zone/eth%2F0/ip6/fe80::1/tcp/443/tls/sni/a%2Fb%25c");
+    assert_eq!(addr.to_string().parse::<Multiaddr>().unwrap(), addr);
+}
@@ -64,3 +64,24 @@ impl fmt::Display for DecodeOwnedError {
 }
 
 impl error::Error for DecodeOwnedError {}
+
+/// Error that can happen when registering a custom hash algorithm.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RegisterError {
+    /// The code is already used by another hash algorithm.
+    CodeInUse,
+    /// The size of the digests is invalid.
+    InvalidSize,
+}
+
+impl fmt::Display for RegisterError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            RegisterError::CodeInUse => write!(f, "Code already in use"),
+            RegisterError::InvalidSize => write!(f, "Invalid digest size"),
+        }
+    }
+}
+
+impl error::Error for RegisterError {}
@@ -0,0 +1,209 @@
+use crate::hashes::Hash;
+use sha2::digest::{self, VariableOutput};
+
+/// Incrementally computes the digest of some data.
+///
+/// A `Hasher` for any supported algorithm can be obtained with [`Hash::hasher`]. Applications
+/// can implement this trait themselves in order to register hash algorithms that this library
+/// doesn't know about (see [`Hash::register`]).
+///
+/// # Examples
+///
+/// ```
+/// use parity_multihash::{encode, Hash, Multihash};
+///
+/// let mut hasher = Hash::SHA2256.hasher();
+/// hasher.update(b"hello ");
+/// hasher.update(b"world");
+/// let multihash = Multihash::wrap(Hash::SHA2256, &hasher.finalize()).unwrap();
+/// assert_eq!(multihash, encode(Hash::SHA2256, b"hello world").unwrap());
+/// ```
+pub trait Hasher: Send {
+    /// Feeds some data to the hasher.
+    fn update(&mut self, input: &[u8]);
+
+    /// Returns the digest of all the data fed so far.
+    ///
+    /// The hasher isn't reset and can be fed more data afterwards.
+    fn finalize(&self) -> Vec<u8>;
+
+    /// Resets the hasher to its initial state.
+    fn reset(&mut self);
+}
+
+/// Builds a hasher for one of the algorithms built into this library.
+///
+/// # Panics
+///
+/// Panics if `hash` is a custom algorithm.
+pub(crate) fn builtin(hash: Hash) -> Box<dyn Hasher> {
+    match hash {
+        Hash::Identity => Box::new(Identity::default()),
+        Hash::SHA1 => Box::new(DigestHasher::<sha1::Sha1>::default()),
+        Hash::SHA2256 => Box::new(DigestHasher::<sha2::Sha256>::default()),
+        Hash::SHA2512 => Box::new(DigestHasher::<sha2::Sha512>::default()),
+        Hash::SHA3224 => Box::new(DigestHasher::<sha3::Sha3_224>::default()),
+        Hash::SHA3256 => Box::new(DigestHasher::<sha3::Sha3_256>::default()),
+        Hash::SHA3384 => Box::new(DigestHasher::<sha3::Sha3_384>::default()),
+        Hash::SHA3512 => Box::new(DigestHasher::<sha3::Sha3_512>::default()),
+        Hash::Keccak224 => Box::new(DigestHasher::<sha3::Keccak224>::default()),
+        Hash::Keccak256 => Box::new(DigestHasher::<sha3::Keccak256>::default()),
+        Hash::Keccak384 => Box::new(DigestHasher::<sha3::Keccak384>::default()),
+        Hash::Keccak512 => Box::new(DigestHasher::<sha3::Keccak512>::default()),
+        Hash::Blake2b512 => Box::new(DigestHasher::<blake2::Blake2b>::default()),
+        Hash::Blake2b256 => Box::new(DigestHasher::<Blake2b256>::default()),
+        Hash::Blake2s256 => Box::new(DigestHasher::<blake2::Blake2s>::default()),
+        Hash::Blake2s128 => Box::new(DigestHasher::<Blake2s128>::default()),
+        Hash::Blake3 => Box::new(Blake3::default()),
+        Hash::SHA2256Trunc254Padded => Box::new(SHA2256Trunc254Padded::default()),
+        Hash::Custom(_) => panic!("custom hash algorithms aren't built in"),
+    }
+}
+
+/// Buffers the data, as the identity "digest" is the data itself.
+#[derive(Debug, Default, Clone)]
+struct Identity(Vec<u8>);
+
+impl Hasher for Identity {
+    fn update(&mut self, input: &[u8]) {
+        self.0.extend_from_slice(input)
+    }
+
+    fn finalize(&self) -> Vec<u8> {
+        self.0.clone()
+    }
+
+    fn reset(&mut self) {
+        self.0.clear()
+    }
+}
+
+/// Hasher for any implementation of the `Digest` trait.
+#[derive(Debug, Default, Clone)]
+struct DigestHasher<D>(D);
+
+impl<D> Hasher for DigestHasher<D>
+where
+    D: digest::Digest + Clone + Send,
+{
+    fn update(&mut self, input: &[u8]) {
+        self.0.input(input)
+    }
+
+    fn finalize(&self) -> Vec<u8> {
+        self.0.clone().result().to_vec()
+    }
+
+    fn reset(&mut self) {
+        self.0.reset()
+    }
+}
+
+/// BLAKE3 (32-byte hash size)
+#[derive(Debug, Default, Clone)]
+struct Blake3(blake3::Hasher);
+
+impl Hasher for Blake3 {
+    fn update(&mut self, input: &[u8]) {
+        self.0.update(input);
+    }
+
+    fn finalize(&self) -> Vec<u8> {
+        self.0.finalize().as_bytes().to_vec()
+    }
+
+    fn reset(&mut self) {
+        self.0.reset();
+    }
+}
+
+/// SHA2-256 with the two most significant bits of the last byte zeroed, so that the digest
+/// fits in 254 bits (32-byte hash size).
+#[derive(Debug, Default, Clone)]
+struct SHA2256Trunc254Padded(sha2::Sha256);
+
+impl Hasher for SHA2256Trunc254Padded {
+    fn update(&mut self, input: &[u8]) {
+        digest::Digest::input(&mut self.0, input)
+    }
+
+    fn finalize(&self) -> Vec<u8> {
+        let mut digest = digest::Digest::result(self.0.clone()).to_vec();
+        digest[31] &= 0b0011_1111;
+        digest
+    }
+
+    fn reset(&mut self) {
+        digest::Digest::reset(&mut self.0)
+    }
+}
+
+/// BLAKE2b-256 (32-byte hash size)
+#[derive(Debug, Clone)]
+struct Blake2b256(blake2::VarBlake2b);
+
+impl Default for Blake2b256 {
+    fn default() -> Self {
+        Blake2b256(blake2::VarBlake2b::new(32).unwrap())
+    }
+}
+
+impl digest::Input for Blake2b256 {
+    fn input<B: AsRef<[u8]>>(&mut self, data: B) {
+        self.0.input(data)
+    }
+}
+
+impl digest::FixedOutput for Blake2b256 {
+    type OutputSize = digest::generic_array::typenum::U32;
+
+    fn fixed_result(self) -> digest::generic_array::GenericArray<u8, Self::OutputSize> {
+        let mut out = digest::generic_array::GenericArray::default();
+        self.0.variable_result(|slice| {
+            assert_eq!(slice.len(), 32);
+            out.copy_from_slice(slice)
+        });
+        out
+    }
+}
+
+impl digest::Reset for Blake2b256 {
+    fn reset(&mut self) {
+        self.0.reset()
+    }
+}
+
+/// BLAKE2s-128 (16-byte hash size)
+#[derive(Debug, Clone)]
+struct Blake2s128(blake2::VarBlake2s);
+
+impl Default for Blake2s128 {
+    fn default() -> Self {
+        Blake2s128(blake2::VarBlake2s::new(16).unwrap())
+    }
+}
+
+impl digest::Input for Blake2s128 {
+    fn input<B: AsRef<[u8]>>(&mut self, data: B) {
+        self.0.input(data)
+    }
+}
+
+impl digest::FixedOutput for Blake2s128 {
+    type OutputSize = digest::generic_array::typenum::U16;
+
+    fn fixed_result(self) -> digest::generic_array::GenericArray<u8, Self::OutputSize> {
+        let mut out = digest::generic_array::GenericArray::default();
+        self.0.variable_result(|slice| {
+            assert_eq!(slice.len(), 16);
+            out.copy_from_slice(slice)
+        });
+        out
+    }
+}
+
+impl digest::Reset for Blake2s128 {
+    fn reset(&mut self) {
+        self.0.reset()
+    }
+}
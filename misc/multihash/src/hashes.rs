@@ -1,6 +1,24 @@
+use crate::errors::RegisterError;
+use crate::hasher::{self, Hasher};
+use lazy_static::lazy_static;
+use std::{collections::HashMap, sync::RwLock};
+
+lazy_static! {
+    /// Hash algorithms registered by the application, indexed by code.
+    static ref CUSTOM: RwLock<HashMap<u16, Registration>> = RwLock::new(HashMap::new());
+}
+
+/// A hash algorithm registered with [`Hash::register`].
+#[derive(Copy, Clone)]
+struct Registration {
+    size: u8,
+    hasher: fn() -> Box<dyn Hasher>,
+}
+
 /// List of types currently supported in the multihash spec.
 ///
-/// Not all hash types are supported by this library.
+/// Not all hash types are supported by this library. Applications can add the ones they need
+/// with [`Hash::register`].
 #[derive(PartialEq, Eq, Clone, Debug, Copy, Hash)]
 pub enum Hash {
     /// Identity (Raw binary )
@@ -35,6 +53,23 @@ pub enum Hash {
     Blake2s256,
     /// BLAKE2s-128 (16-byte hash size)
     Blake2s128,
+    /// BLAKE3 (32-byte hash size)
+    Blake3,
+    /// SHA2-256 with the two most significant bits of the digest zeroed (32-byte hash size)
+    SHA2256Trunc254Padded,
+    /// Hash algorithm registered by the application.
+    Custom(CustomHash),
+}
+
+/// Hash algorithm registered by the application with [`Hash::register`].
+#[derive(PartialEq, Eq, Clone, Debug, Copy, Hash)]
+pub struct CustomHash(u16);
+
+impl CustomHash {
+    /// Returns the code of the hash algorithm.
+    pub fn code(&self) -> u16 {
+        self.0
+    }
 }
 
 impl Hash {
@@ -57,6 +92,9 @@ impl Hash {
             Hash::Blake2b256 => 0xB220,
             Hash::Blake2s256 => 0xB260,
             Hash::Blake2s128 => 0xB250,
+            Hash::Blake3 => 0x1E,
+            Hash::SHA2256Trunc254Padded => 0x1012,
+            Hash::Custom(custom) => custom.0,
         }
     }
 
@@ -79,11 +117,22 @@ impl Hash {
             Hash::Blake2b256 => 32,
             Hash::Blake2s256 => 32,
             Hash::Blake2s128 => 16,
+            Hash::Blake3 => 32,
+            Hash::SHA2256Trunc254Padded => 32,
+            Hash::Custom(custom) => registration(custom.0)
+                .expect("custom hashes are only created once registered")
+                .size,
         }
     }
 
     /// Returns the algorithm corresponding to a code, or `None` if no algorithm is matching.
     pub fn from_code(code: u16) -> Option<Hash> {
+        Hash::from_builtin_code(code)
+            .or_else(|| registration(code).map(|_| Hash::Custom(CustomHash(code))))
+    }
+
+    /// Same as `from_code`, but ignores the algorithms registered by the application.
+    fn from_builtin_code(code: u16) -> Option<Hash> {
         Some(match code {
             0x00 => Hash::Identity,
             0x11 => Hash::SHA1,
@@ -101,7 +150,69 @@ impl Hash {
             0xB220 => Hash::Blake2b256,
             0xB260 => Hash::Blake2s256,
             0xB250 => Hash::Blake2s128,
+            0x1E => Hash::Blake3,
+            0x1012 => Hash::SHA2256Trunc254Padded,
             _ => return None,
         })
     }
+
+    /// Returns a `Hasher` computing digests with this algorithm.
+    pub fn hasher(&self) -> Box<dyn Hasher> {
+        match self {
+            Hash::Custom(custom) => {
+                let registration = registration(custom.0)
+                    .expect("custom hashes are only created once registered");
+                (registration.hasher)()
+            }
+            hash => hasher::builtin(*hash),
+        }
+    }
+
+    /// Registers a hash algorithm unknown to this library, so that multihashes using it can be
+    /// encoded and decoded.
+    ///
+    /// `size` is the length in bytes of the digests produced by the hashers that `hasher`
+    /// builds. Registrations are global to the process and can't be undone.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `code` is already used by another algorithm, or if `size` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use parity_multihash::{encode, Hash, Hasher, MultihashRef};
+    ///
+    /// #[derive(Default)]
+    /// struct Xor(u8);
+    ///
+    /// impl Hasher for Xor {
+    ///     fn update(&mut self, input: &[u8]) {
+    ///         self.0 = input.iter().fold(self.0, |acc, byte| acc ^ byte);
+    ///     }
+    ///     fn finalize(&self) -> Vec<u8> { vec![self.0] }
+    ///     fn reset(&mut self) { self.0 = 0 }
+    /// }
+    ///
+    /// let xor = Hash::register(0x3001, 1, || Box::new(Xor::default())).unwrap();
+    /// let multihash = encode(xor, b"hello world").unwrap();
+    /// assert_eq!(MultihashRef::from_slice(multihash.as_bytes()).unwrap().algorithm(), xor);
+    /// ```
+    pub fn register(code: u16, size: u8, hasher: fn() -> Box<dyn Hasher>) -> Result<Hash, RegisterError> {
+        if size == 0 {
+            return Err(RegisterError::InvalidSize);
+        }
+
+        let mut custom = CUSTOM.write().expect("the registry lock is never poisoned");
+        if Hash::from_builtin_code(code).is_some() || custom.contains_key(&code) {
+            return Err(RegisterError::CodeInUse);
+        }
+        custom.insert(code, Registration { size, hasher });
+        Ok(Hash::Custom(CustomHash(code)))
+    }
+}
+
+/// Returns the registration of the custom hash algorithm with the given code, if any.
+fn registration(code: u16) -> Option<Registration> {
+    CUSTOM.read().expect("the registry lock is never poisoned").get(&code).cloned()
 }
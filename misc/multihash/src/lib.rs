@@ -6,44 +6,26 @@
 //! A `MultihashRef` is the same as a `Multihash`, except that it doesn't own its data.
 
 mod errors;
+mod hasher;
 mod hashes;
 
 use std::{convert::TryFrom, fmt::Write};
 
 use bytes::{BufMut, Bytes, BytesMut};
 use rand::RngCore;
-use sha2::digest::{self, VariableOutput};
 use unsigned_varint::{decode, encode};
 
-pub use self::errors::{DecodeError, DecodeOwnedError, EncodeError};
-pub use self::hashes::Hash;
-
-/// Helper function for encoding input into output using given `Digest`
-fn digest_encode<D: digest::Digest>(input: &[u8], output: &mut [u8]) {
-    output.copy_from_slice(&D::digest(input))
-}
-
-// And another one to keep the matching DRY
-macro_rules! match_encoder {
-    ($hash_id:ident for ($input:expr, $output:expr) {
-        $( $hashtype:ident => $hash_ty:path, )*
-    }) => ({
-        match $hash_id {
-            $(
-                Hash::$hashtype => digest_encode::<$hash_ty>($input, $output),
-            )*
-
-            _ => return Err(EncodeError::UnsupportedType)
-        }
-    })
-}
+pub use self::errors::{DecodeError, DecodeOwnedError, EncodeError, RegisterError};
+pub use self::hasher::Hasher;
+pub use self::hashes::{CustomHash, Hash};
 
 /// Encodes data into a multihash.
 ///
+/// Use [`Hash::hasher`] instead in order to hash data that isn't entirely available at once.
+///
 /// # Errors
 ///
-/// Will return an error if the specified hash type is not supported. See the docs for `Hash`
-/// to see what is supported.
+/// Will return an error if the input is too large for the identity hash.
 ///
 /// # Examples
 ///
@@ -58,134 +40,28 @@ macro_rules! match_encoder {
 /// ```
 ///
 pub fn encode(hash: Hash, input: &[u8]) -> Result<Multihash, EncodeError> {
-    // Custom length encoding for the identity multihash
-    if let Hash::Identity = hash {
-        if u64::from(std::u32::MAX) < as_u64(input.len()) {
-            return Err(EncodeError::UnsupportedInputLength);
-        }
-        let mut buf = encode::u16_buffer();
-        let code = encode::u16(hash.code(), &mut buf);
-        let mut len_buf = encode::u32_buffer();
-        let size = encode::u32(input.len() as u32, &mut len_buf);
-
-        let total_len = code.len() + size.len() + input.len();
-
-        let mut output = BytesMut::with_capacity(total_len);
-        output.put_slice(code);
-        output.put_slice(size);
-        output.put_slice(input);
-        Ok(Multihash {
-            bytes: output.freeze(),
-        })
-    } else {
-        let (offset, mut output) = encode_hash(hash);
-        match_encoder!(hash for (input, &mut output[offset ..]) {
-            SHA1 => sha1::Sha1,
-            SHA2256 => sha2::Sha256,
-            SHA2512 => sha2::Sha512,
-            SHA3224 => sha3::Sha3_224,
-            SHA3256 => sha3::Sha3_256,
-            SHA3384 => sha3::Sha3_384,
-            SHA3512 => sha3::Sha3_512,
-            Keccak224 => sha3::Keccak224,
-            Keccak256 => sha3::Keccak256,
-            Keccak384 => sha3::Keccak384,
-            Keccak512 => sha3::Keccak512,
-            Blake2b512 => blake2::Blake2b,
-            Blake2b256 => Blake2b256,
-            Blake2s256 => blake2::Blake2s,
-            Blake2s128 => Blake2s128,
-        });
-        Ok(Multihash {
-            bytes: output.freeze(),
-        })
-    }
+    let mut hasher = hash.hasher();
+    hasher.update(input);
+    Multihash::wrap(hash, &hasher.finalize())
 }
 
-// Encode the given [`Hash`] value and ensure the returned [`BytesMut`]
+// Encode the given [`Hash`] value and digest length, and ensure the returned [`BytesMut`]
 // has enough capacity to hold the actual digest.
-fn encode_hash(hash: Hash) -> (usize, BytesMut) {
+fn encode_hash(hash: Hash, digest_len: u32) -> (usize, BytesMut) {
     let mut buf = encode::u16_buffer();
     let code = encode::u16(hash.code(), &mut buf);
+    let mut len_buf = encode::u32_buffer();
+    let size = encode::u32(digest_len, &mut len_buf);
 
-    let len = code.len() + 1 + usize::from(hash.size());
+    let offset = code.len() + size.len();
+    let len = offset + digest_len as usize;
 
     let mut output = BytesMut::with_capacity(len);
     output.put_slice(code);
-    output.put_u8(hash.size());
+    output.put_slice(size);
     output.resize(len, 0);
 
-    (code.len() + 1, output)
-}
-
-/// BLAKE2b-256 (32-byte hash size)
-#[derive(Debug, Clone)]
-struct Blake2b256(blake2::VarBlake2b);
-
-impl Default for Blake2b256 {
-    fn default() -> Self {
-        Blake2b256(blake2::VarBlake2b::new(32).unwrap())
-    }
-}
-
-impl digest::Input for Blake2b256 {
-    fn input<B: AsRef<[u8]>>(&mut self, data: B) {
-        self.0.input(data)
-    }
-}
-
-impl digest::FixedOutput for Blake2b256 {
-    type OutputSize = digest::generic_array::typenum::U32;
-
-    fn fixed_result(self) -> digest::generic_array::GenericArray<u8, Self::OutputSize> {
-        let mut out = digest::generic_array::GenericArray::default();
-        self.0.variable_result(|slice| {
-            assert_eq!(slice.len(), 32);
-            out.copy_from_slice(slice)
-        });
-        out
-    }
-}
-
-impl digest::Reset for Blake2b256 {
-    fn reset(&mut self) {
-        self.0.reset()
-    }
-}
-
-/// BLAKE2s-128 (16-byte hash size)
-#[derive(Debug, Clone)]
-struct Blake2s128(blake2::VarBlake2s);
-
-impl Default for Blake2s128 {
-    fn default() -> Self {
-        Blake2s128(blake2::VarBlake2s::new(16).unwrap())
-    }
-}
-
-impl digest::Input for Blake2s128 {
-    fn input<B: AsRef<[u8]>>(&mut self, data: B) {
-        self.0.input(data)
-    }
-}
-
-impl digest::FixedOutput for Blake2s128 {
-    type OutputSize = digest::generic_array::typenum::U16;
-
-    fn fixed_result(self) -> digest::generic_array::GenericArray<u8, Self::OutputSize> {
-        let mut out = digest::generic_array::GenericArray::default();
-        self.0.variable_result(|slice| {
-            assert_eq!(slice.len(), 16);
-            out.copy_from_slice(slice)
-        });
-        out
-    }
-}
-
-impl digest::Reset for Blake2s128 {
-    fn reset(&mut self) {
-        self.0.reset()
-    }
+    (offset, output)
 }
 
 /// Represents a valid multihash.
@@ -201,9 +77,34 @@ impl Multihash {
         Ok(Multihash { bytes: Bytes::from(bytes) })
     }
 
+    /// Builds a `Multihash` from a digest computed with the given algorithm.
+    ///
+    /// The digest can be shorter than the size of the algorithm, in which case it is considered
+    /// as truncated.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the digest is longer than the size of the algorithm, or if it is
+    /// too large for the identity hash.
+    pub fn wrap(hash: Hash, digest: &[u8]) -> Result<Multihash, EncodeError> {
+        // The identity "digest" can have any length.
+        let max_len = if hash == Hash::Identity {
+            u64::from(std::u32::MAX)
+        } else {
+            u64::from(hash.size())
+        };
+        if as_u64(digest.len()) > max_len {
+            return Err(EncodeError::UnsupportedInputLength);
+        }
+
+        let (offset, mut bytes) = encode_hash(hash, digest.len() as u32);
+        bytes[offset ..].copy_from_slice(digest);
+        Ok(Multihash { bytes: bytes.freeze() })
+    }
+
     /// Generates a random `Multihash` from a cryptographically secure PRNG.
     pub fn random(hash: Hash) -> Multihash {
-        let (offset, mut bytes) = encode_hash(hash);
+        let (offset, mut bytes) = encode_hash(hash, u32::from(hash.size()));
         rand::thread_rng().fill_bytes(&mut bytes[offset ..]);
         Multihash { bytes: bytes.freeze() }
     }
@@ -237,6 +138,19 @@ impl Multihash {
     pub fn digest(&self) -> &[u8] {
         self.as_ref().digest()
     }
+
+    /// Returns a multihash with the same algorithm, whose digest only contains the first `len`
+    /// bytes of this one.
+    ///
+    /// Returns an identical multihash if the digest isn't longer than `len`.
+    pub fn truncate(&self, len: usize) -> Multihash {
+        let digest = self.digest();
+        if digest.len() <= len {
+            return self.clone();
+        }
+        Multihash::wrap(self.algorithm(), &digest[.. len])
+            .expect("a truncated digest of a valid multihash is valid")
+    }
 }
 
 impl AsRef<[u8]> for Multihash {
@@ -275,24 +189,15 @@ impl<'a> MultihashRef<'a> {
         let (code, bytes) = decode::u16(&input).map_err(|_| DecodeError::BadInputLength)?;
 
         let alg = Hash::from_code(code).ok_or(DecodeError::UnknownCode)?;
+        let (hash_len, bytes) = decode::u32(&bytes).map_err(|_| DecodeError::BadInputLength)?;
 
-        // handle the identity case
-        if alg == Hash::Identity {
-            let (hash_len, bytes) = decode::u32(&bytes).map_err(|_| DecodeError::BadInputLength)?;
-            if as_u64(bytes.len()) != u64::from(hash_len) {
-                return Err(DecodeError::BadInputLength);
-            }
-            return Ok(MultihashRef { bytes: input });
-        }
-
-        let hash_len = usize::from(alg.size());
-
-        // Length of input after hash code should be exactly hash_len + 1
-        if bytes.len() != hash_len + 1 {
+        // The length of the digest can be shorter than the size of the algorithm if the digest
+        // is truncated, except for the identity hash whose digest can have any length.
+        if alg != Hash::Identity && hash_len > u32::from(alg.size()) {
             return Err(DecodeError::BadInputLength);
         }
 
-        if usize::from(bytes[0]) != hash_len {
+        if as_u64(bytes.len()) != u64::from(hash_len) {
             return Err(DecodeError::BadInputLength);
         }
 
@@ -312,7 +217,9 @@ impl<'a> MultihashRef<'a> {
         let bytes = decode::u16(&self.bytes)
             .expect("multihash is known to be valid digest")
             .1;
-        &bytes[1 ..]
+        decode::u32(bytes)
+            .expect("multihash is known to be valid digest")
+            .1
     }
 
     /// Builds a `Multihash` that owns the data.
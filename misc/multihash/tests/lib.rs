@@ -42,6 +42,8 @@ fn multihash_encode() {
         Blake2b256, b"hello world", "a0e40220256c83b297114d201b30179f3f0ef0cace9783622da5974326b436178aeef610";
         Blake2s256, b"hello world", "e0e402209aec6806794561107e594b1f6a8a6b0c92a0cba9acf5e5e93cca06f781813b0b";
         Blake2s128, b"hello world", "d0e4021037deae0226c30da2ab424a7b8ee14e83";
+        Blake3, b"hello world", "1e20d74981efa70a0c880b8d8c1985d075dbcbf679b99a5f9914e5aaf96b831a9e24";
+        SHA2256Trunc254Padded, b"hello world", "922020b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcd29";
     }
 }
 
@@ -77,6 +79,8 @@ fn assert_decode() {
         Blake2b256, "a0e40220256c83b297114d201b30179f3f0ef0cace9783622da5974326b436178aeef610";
         Blake2s256, "e0e402209aec6806794561107e594b1f6a8a6b0c92a0cba9acf5e5e93cca06f781813b0b";
         Blake2s128, "d0e4021037deae0226c30da2ab424a7b8ee14e83";
+        Blake3, "1e20d74981efa70a0c880b8d8c1985d075dbcbf679b99a5f9914e5aaf96b831a9e24";
+        SHA2256Trunc254Padded, "922020b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcd29";
     }
 }
 
@@ -98,7 +102,7 @@ macro_rules! assert_roundtrip {
 fn assert_roundtrip() {
     assert_roundtrip!(
         SHA1, SHA2256, SHA2512, SHA3224, SHA3256, SHA3384, SHA3512, Keccak224, Keccak256,
-        Keccak384, Keccak512, Blake2b512, Blake2s256
+        Keccak384, Keccak512, Blake2b512, Blake2s256, Blake3, SHA2256Trunc254Padded
     );
 }
 
@@ -119,4 +123,84 @@ fn hash_types() {
     assert_eq!(Hash::Blake2b256.size(), 32);
     assert_eq!(Hash::Blake2s256.size(), 32);
     assert_eq!(Hash::Blake2s128.size(), 16);
+    assert_eq!(Hash::Blake3.size(), 32);
+    assert_eq!(Hash::SHA2256Trunc254Padded.size(), 32);
+}
+
+#[test]
+fn hasher_matches_encode() {
+    let data = (0 .. 5000).map(|n| n as u8).collect::<Vec<_>>();
+
+    for code in 0 .. u16::max_value() {
+        let hash = match Hash::from_code(code) {
+            Some(hash) => hash,
+            None => continue,
+        };
+
+        let mut hasher = hash.hasher();
+        for chunk in data.chunks(333) {
+            hasher.update(chunk);
+        }
+        let multihash = Multihash::wrap(hash, &hasher.finalize()).unwrap();
+        assert_eq!(multihash, encode(hash, &data).unwrap(), "{:?} hashes incrementally", hash);
+
+        hasher.reset();
+        hasher.update(b"hello world");
+        assert_eq!(hasher.finalize(), encode(hash, b"hello world").unwrap().digest());
+    }
+}
+
+#[test]
+fn truncated_digests() {
+    let multihash = encode(Hash::SHA2256, b"hello world").unwrap();
+    let truncated = multihash.truncate(20);
+    assert_eq!(truncated.algorithm(), Hash::SHA2256);
+    assert_eq!(truncated.digest(), &multihash.digest()[.. 20]);
+    assert_eq!(truncated.as_bytes()[1], 20);
+    assert_eq!(Multihash::from_bytes(truncated.to_vec()).unwrap(), truncated);
+    assert_eq!(multihash.truncate(64), multihash);
+
+    assert_eq!(Multihash::wrap(Hash::SHA2256, &[0; 33]), Err(EncodeError::UnsupportedInputLength));
+
+    // A digest longer than the size of the algorithm is invalid.
+    let mut too_long = multihash.to_vec();
+    too_long[1] = 33;
+    too_long.push(0);
+    assert_eq!(MultihashRef::from_slice(&too_long), Err(DecodeError::BadInputLength));
+}
+
+#[derive(Default)]
+struct Sum(u32);
+
+impl Hasher for Sum {
+    fn update(&mut self, input: &[u8]) {
+        self.0 = input.iter().fold(self.0, |acc, &byte| acc.wrapping_add(u32::from(byte)));
+    }
+
+    fn finalize(&self) -> Vec<u8> {
+        self.0.to_be_bytes().to_vec()
+    }
+
+    fn reset(&mut self) {
+        self.0 = 0
+    }
+}
+
+#[test]
+fn custom_hash() {
+    assert_eq!(Hash::from_code(0x3000), None);
+    assert_eq!(MultihashRef::from_slice(&[0x80, 0x60, 4, 0, 0, 0, 6]), Err(DecodeError::UnknownCode));
+
+    let sum = Hash::register(0x3000, 4, || Box::new(Sum::default())).unwrap();
+    assert_eq!(sum.code(), 0x3000);
+    assert_eq!(sum.size(), 4);
+    assert_eq!(Hash::from_code(0x3000), Some(sum));
+
+    let multihash = encode(sum, &[1, 2, 3]).unwrap();
+    assert_eq!(multihash.as_bytes(), &[0x80, 0x60, 4, 0, 0, 0, 6]);
+    assert_eq!(MultihashRef::from_slice(multihash.as_bytes()).unwrap().algorithm(), sum);
+
+    assert_eq!(Hash::register(0x3000, 4, || Box::new(Sum::default())), Err(RegisterError::CodeInUse));
+    assert_eq!(Hash::register(0x12, 4, || Box::new(Sum::default())), Err(RegisterError::CodeInUse));
+    assert_eq!(Hash::register(0x3001, 0, || Box::new(Sum::default())), Err(RegisterError::InvalidSize));
 }
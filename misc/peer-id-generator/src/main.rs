@@ -18,8 +18,14 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use libp2p_core::identity;
-use std::{env, str, thread, time::Duration};
+use libp2p_core::identity::{self, derivation::{ChildIndex, DerivationPath}, mnemonic::Mnemonic};
+use std::{env, process, str, thread, time::Duration};
+
+/// Environment variable holding the mnemonic from which peer ids are derived.
+const MNEMONIC_VAR: &str = "PEER_ID_MNEMONIC";
+
+/// Number of hardened child indices, i.e. of keys that can be derived from a mnemonic.
+const MAX_CHILDREN: u32 = 1 << 31;
 
 fn main() {
     // Due to the fact that a peer id uses a SHA-256 multihash, it always starts with the
@@ -28,51 +34,81 @@ fn main() {
 
     let prefix =
         match env::args().nth(1) {
+            Some(ref arg) if arg == "derive" => {
+                match env::args().nth(2).and_then(|count| count.parse().ok()) {
+                    Some(count) if count <= MAX_CHILDREN => derive(count),
+                    Some(_) => {
+                        eprintln!("At most {} peer ids can be derived", MAX_CHILDREN);
+                        process::exit(1)
+                    }
+                    None => {
+                        eprintln!("Usage: derive <count>");
+                        process::exit(1)
+                    }
+                }
+                return;
+            }
             Some(prefix) => prefix,
             None => {
-                println!(
-                "Usage: {} <prefix>\n\n\
-                 Generates a peer id that starts with the chosen prefix using a secp256k1 public \
-                 key.\n\n\
+                eprintln!(
+                "Usage: {0} <prefix>\n       {0} derive <count>\n\n\
+                 Generates a peer id that starts with the chosen prefix using an Ed25519 public \
+                 key derived from a new mnemonic.\n\n\
                  Prefix must be a sequence of characters in the base58 \
-                 alphabet, and must start with one of the following: {}",
+                 alphabet, and must start with one of the following: {1}\n\n\
+                 With `derive`, derives the Ed25519 keys of <count> peer ids at the paths m/<i>' \
+                 from the mnemonic in the {2} environment variable, or from a new mnemonic if \
+                 it is not set.",
                 env::current_exe().unwrap().file_name().unwrap().to_str().unwrap(),
-                str::from_utf8(ALLOWED_FIRST_BYTE).unwrap()
+                str::from_utf8(ALLOWED_FIRST_BYTE).unwrap(),
+                MNEMONIC_VAR
             );
-                return;
+                process::exit(1);
             }
         };
 
     // The base58 alphabet is not necessarily obvious.
     const ALPHABET: &'static [u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
     if prefix.as_bytes().iter().any(|c| !ALPHABET.contains(c)) {
-        println!("Prefix {} is not valid base58", prefix);
-        return;
+        eprintln!("Prefix {} is not valid base58", prefix);
+        process::exit(1);
     }
 
     // Checking conformity to ALLOWED_FIRST_BYTE.
     if !prefix.is_empty() {
         if !ALLOWED_FIRST_BYTE.contains(&prefix.as_bytes()[0]) {
-            println!("Prefix {} is not reachable", prefix);
-            println!(
+            eprintln!("Prefix {} is not reachable", prefix);
+            eprintln!(
                 "Only the following bytes are possible as first byte: {}",
                 str::from_utf8(ALLOWED_FIRST_BYTE).unwrap()
             );
-            return;
+            process::exit(1);
         }
     }
 
-    // Find peer IDs in a multithreaded fashion.
+    // Find peer IDs in a multithreaded fashion. Each thread derives keys from its own mnemonic,
+    // so that the keys found can be derived again with `derive`.
     for _ in 0..num_cpus::get() {
         let prefix = prefix.clone();
         thread::spawn(move || loop {
-            let keypair = identity::ed25519::Keypair::generate();
-            let secret = keypair.secret();
-            let peer_id = identity::PublicKey::Ed25519(keypair.public()).into_peer_id();
-            let base58 = peer_id.to_base58();
-            if base58[2..].starts_with(&prefix) {
-                println!("Found {:?}", peer_id);
-                println!("=> Private key = {:?}", secret.as_ref());
+            let mnemonic = Mnemonic::generate();
+            let seed = mnemonic.to_seed("");
+            for i in 0 .. MAX_CHILDREN {
+                let path = DerivationPath::master().child(ChildIndex::Hardened(i));
+                let keypair = identity::Keypair::derive_ed25519(&seed, &path)
+                    .expect("Seeds of mnemonics are 64 bytes long and Ed25519 paths are hardened");
+                let peer_id = keypair.public().into_peer_id();
+                let base58 = peer_id.to_base58();
+                if base58[2..].starts_with(&prefix) {
+                    let secret = match &keypair {
+                        identity::Keypair::Ed25519(pair) => pair.secret(),
+                        _ => unreachable!("derive_ed25519 returns Ed25519 keypairs"),
+                    };
+                    println!("Found {:?}", peer_id);
+                    println!("=> Private key = {:?}", secret.as_ref());
+                    println!("=> Mnemonic = {}", mnemonic.phrase());
+                    println!("=> Path = {}", path);
+                }
             }
         });
     }
@@ -81,3 +117,26 @@ fn main() {
         thread::sleep(Duration::from_secs(3600));
     }
 }
+
+/// Prints the peer ids of the first `count` Ed25519 keys derived from a mnemonic.
+fn derive(count: u32) {
+    let mnemonic = match env::var(MNEMONIC_VAR) {
+        Ok(phrase) => Mnemonic::from_phrase(&phrase).unwrap_or_else(|e| {
+            eprintln!("Invalid mnemonic in {}: {}", MNEMONIC_VAR, e);
+            process::exit(1)
+        }),
+        Err(_) => {
+            let mnemonic = Mnemonic::generate();
+            println!("Mnemonic = {}", mnemonic.phrase());
+            mnemonic
+        }
+    };
+
+    let seed = mnemonic.to_seed("");
+    for i in 0 .. count {
+        let path = DerivationPath::master().child(ChildIndex::Hardened(i));
+        let keypair = identity::Keypair::derive_ed25519(&seed, &path)
+            .expect("Seeds of mnemonics are 64 bytes long and Ed25519 paths are hardened");
+        println!("{} => {}", path, keypair.public().into_peer_id());
+    }
+}
@@ -0,0 +1,593 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Test harness for [`NetworkBehaviour`]s.
+//!
+//! A [`SwarmTest`] builds a number of [`Swarm`]s that communicate over the
+//! [`MemoryTransport`], authenticated with the plaintext protocol and multiplexed
+//! with mplex. The identities of the nodes are derived from a seed, the nodes are
+//! connected according to a [`Topology`], and all the swarms as well as the
+//! background tasks of their connections are driven from the current thread.
+//!
+//! # Example
+//!
+//! ```
+//! use libp2p_ping::{Ping, PingConfig, PingEvent};
+//! use libp2p_swarm::SwarmEvent;
+//! use libp2p_swarm_test::{SwarmTest, Topology};
+//!
+//! let mut test = SwarmTest::new(3, |_| Ping::new(PingConfig::new().with_keep_alive(true)));
+//! test.connect_topology(Topology::Line);
+//!
+//! let pinged = test.peer_id(1).clone();
+//! test.wait_for_event(|index, event| match event {
+//!     SwarmEvent::Behaviour(PingEvent { peer, result: Ok(_) }) if index == 0 && peer == pinged => Some(()),
+//!     _ => None,
+//! });
+//! ```
+//!
+//! [`MemoryTransport`]: libp2p_core::transport::MemoryTransport
+
+use futures::{prelude::*, future::BoxFuture, stream::FuturesUnordered};
+use futures_timer::Delay;
+use libp2p_core::{
+    InboundUpgrade, Multiaddr, Negotiated, OutboundUpgrade, PeerId, ProtocolName, Transport,
+    UpgradeInfo,
+    identity::{self, ed25519},
+    multiaddr::Protocol,
+    muxing::StreamMuxerBox,
+    nodes::Substream,
+    transport::{MemoryTransport, boxed::Boxed},
+    upgrade
+};
+use libp2p_mplex::MplexConfig;
+use libp2p_plaintext::PlainText2Config;
+use libp2p_swarm::{IntoProtocolsHandler, NetworkBehaviour, ProtocolsHandler, SwarmBuilder};
+use parking_lot::Mutex;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::{
+    collections::{HashSet, VecDeque},
+    error,
+    io,
+    mem,
+    ops::{Index, IndexMut},
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration
+};
+
+pub use libp2p_swarm::{Swarm, SwarmEvent};
+
+/// Transport of the swarms of a [`SwarmTest`].
+pub type TestTransport = Boxed<(PeerId, StreamMuxerBox), io::Error>;
+
+/// Swarm of a [`SwarmTest`].
+pub type TestSwarm<B> = Swarm<TestTransport, B>;
+
+/// Time after which waiting for an event fails, unless changed with
+/// [`SwarmTest::set_timeout`].
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+type TestSubstream = Negotiated<Substream<StreamMuxerBox>>;
+
+/// A [`NetworkBehaviour`] that can be run by a [`SwarmTest`].
+///
+/// Implemented for all the behaviours whose connection handlers, and everything they produce,
+/// can be sent to the background tasks of the connections.
+pub trait TestBehaviour: NetworkBehaviour<ProtocolsHandler = <Self as TestBehaviour>::IntoHandler> {
+    /// Same as `NetworkBehaviour::ProtocolsHandler`.
+    type IntoHandler: IntoProtocolsHandler<Handler = Self::Handler> + Send + 'static;
+    /// The handler of the connections of the behaviour.
+    type Handler: TestHandler;
+}
+
+impl<B> TestBehaviour for B
+where
+    B: NetworkBehaviour,
+    B::ProtocolsHandler: Send + 'static,
+    <B::ProtocolsHandler as IntoProtocolsHandler>::Handler: TestHandler,
+{
+    type IntoHandler = B::ProtocolsHandler;
+    type Handler = <B::ProtocolsHandler as IntoProtocolsHandler>::Handler;
+}
+
+/// The handler of the connections of a [`TestBehaviour`].
+pub trait TestHandler: ProtocolsHandler<
+    Substream = Substream<StreamMuxerBox>,
+    InEvent = <Self as TestHandler>::Input,
+    OutEvent = <Self as TestHandler>::Output,
+    Error = <Self as TestHandler>::Failure,
+    OutboundOpenInfo = <Self as TestHandler>::OpenInfo,
+    InboundProtocol = <Self as TestHandler>::Inbound,
+    OutboundProtocol = <Self as TestHandler>::Outbound,
+> + Send + 'static {
+    /// Same as `ProtocolsHandler::InEvent`.
+    type Input: Send + 'static;
+    /// Same as `ProtocolsHandler::OutEvent`.
+    type Output: Send + 'static;
+    /// Same as `ProtocolsHandler::Error`.
+    type Failure: error::Error + Send + 'static;
+    /// Same as `ProtocolsHandler::OutboundOpenInfo`.
+    type OpenInfo: Send + 'static;
+    /// Same as `ProtocolsHandler::InboundProtocol`.
+    type Inbound: TestInboundUpgrade;
+    /// Same as `ProtocolsHandler::OutboundProtocol`.
+    type Outbound: TestOutboundUpgrade;
+}
+
+impl<H> TestHandler for H
+where
+    H: ProtocolsHandler<Substream = Substream<StreamMuxerBox>> + Send + 'static,
+    H::InEvent: Send + 'static,
+    H::OutEvent: Send + 'static,
+    H::Error: Send + 'static,
+    H::OutboundOpenInfo: Send + 'static,
+    H::InboundProtocol: TestInboundUpgrade,
+    H::OutboundProtocol: TestOutboundUpgrade,
+{
+    type Input = H::InEvent;
+    type Output = H::OutEvent;
+    type Failure = H::Error;
+    type OpenInfo = H::OutboundOpenInfo;
+    type Inbound = H::InboundProtocol;
+    type Outbound = H::OutboundProtocol;
+}
+
+/// The protocols of a [`TestHandler`].
+pub trait TestUpgradeInfo: UpgradeInfo<
+    Info = <Self as TestUpgradeInfo>::Protocol,
+    InfoIter = <Self as TestUpgradeInfo>::Protocols,
+> + Send + 'static {
+    /// Same as `UpgradeInfo::Info`.
+    type Protocol: ProtocolName + Clone + Send + 'static;
+    /// Same as `UpgradeInfo::InfoIter`.
+    type Protocols: IntoIterator<Item = Self::Protocol, IntoIter = Self::ProtocolsIter> + Send + 'static;
+    /// The iterator of `Protocols`.
+    type ProtocolsIter: Iterator<Item = Self::Protocol> + Send + 'static;
+}
+
+impl<U> TestUpgradeInfo for U
+where
+    U: UpgradeInfo + Send + 'static,
+    U::Info: Send + 'static,
+    U::InfoIter: Send + 'static,
+    <U::InfoIter as IntoIterator>::IntoIter: Send + 'static,
+{
+    type Protocol = U::Info;
+    type Protocols = U::InfoIter;
+    type ProtocolsIter = <U::InfoIter as IntoIterator>::IntoIter;
+}
+
+/// The inbound protocol of a [`TestHandler`].
+pub trait TestInboundUpgrade: InboundUpgrade<
+    TestSubstream,
+    Error = <Self as TestInboundUpgrade>::UpgradeError,
+    Future = <Self as TestInboundUpgrade>::UpgradeFuture,
+> + TestUpgradeInfo {
+    /// Same as `InboundUpgrade::Error`.
+    type UpgradeError: Send + 'static;
+    /// Same as `InboundUpgrade::Future`.
+    type UpgradeFuture: Send + 'static;
+}
+
+impl<U> TestInboundUpgrade for U
+where
+    U: InboundUpgrade<TestSubstream> + TestUpgradeInfo,
+    U::Error: Send + 'static,
+    U::Future: Send + 'static,
+{
+    type UpgradeError = U::Error;
+    type UpgradeFuture = U::Future;
+}
+
+/// The outbound protocol of a [`TestHandler`].
+pub trait TestOutboundUpgrade: OutboundUpgrade<
+    TestSubstream,
+    Error = <Self as TestOutboundUpgrade>::UpgradeError,
+    Future = <Self as TestOutboundUpgrade>::UpgradeFuture,
+> + TestUpgradeInfo {
+    /// Same as `OutboundUpgrade::Error`.
+    type UpgradeError: Send + 'static;
+    /// Same as `OutboundUpgrade::Future`.
+    type UpgradeFuture: Send + 'static;
+}
+
+impl<U> TestOutboundUpgrade for U
+where
+    U: OutboundUpgrade<TestSubstream> + TestUpgradeInfo,
+    U::Error: Send + 'static,
+    U::Future: Send + 'static,
+{
+    type UpgradeError = U::Error;
+    type UpgradeFuture = U::Future;
+}
+
+/// How the nodes of a [`SwarmTest`] are connected with each other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Topology {
+    /// Every node is connected to every other node.
+    FullMesh,
+    /// Node `i` is connected to node `i + 1`.
+    Line,
+    /// Like `Line`, with the last node also connected to the first one.
+    Ring,
+    /// Every node is connected to node 0.
+    Star,
+    /// For every `(a, b)`, node `a` dials node `b`.
+    Edges(Vec<(usize, usize)>),
+}
+
+impl Topology {
+    /// Returns the `(dialer, listener)` pairs of the topology for `num` nodes.
+    pub fn edges(&self, num: usize) -> Vec<(usize, usize)> {
+        match self {
+            Topology::FullMesh =>
+                (0 .. num).flat_map(|a| (a + 1 .. num).map(move |b| (a, b))).collect(),
+            Topology::Line =>
+                (1 .. num).map(|b| (b - 1, b)).collect(),
+            Topology::Ring => {
+                let mut edges = Topology::Line.edges(num);
+                if num > 2 {
+                    edges.push((num - 1, 0));
+                }
+                edges
+            }
+            Topology::Star =>
+                (1 .. num).map(|a| (a, 0)).collect(),
+            Topology::Edges(edges) => edges.clone(),
+        }
+    }
+}
+
+/// A number of swarms connected over the memory transport.
+///
+/// The swarms can be accessed by index, e.g. `test[0]`. They are only polled while one of the
+/// `wait_for_event` or `connect` methods is running.
+pub struct SwarmTest<B: NetworkBehaviour> {
+    nodes: Vec<Node<B>>,
+    /// Background tasks of the connections of all the nodes.
+    tasks: Tasks,
+    /// Events that have been produced while establishing connections and that have yet to be
+    /// passed to `wait_for_event`.
+    events: VecDeque<(usize, SwarmEvent<B::OutEvent>)>,
+    /// Node to poll first, so that a busy node doesn't prevent the others from making progress.
+    next_node: usize,
+    timeout: Duration,
+}
+
+struct Node<B: NetworkBehaviour> {
+    swarm: TestSwarm<B>,
+    peer_id: PeerId,
+    /// Address the node listens on, once reported by the swarm.
+    addr: Option<Multiaddr>,
+}
+
+impl<B: TestBehaviour> SwarmTest<B> {
+    /// Builds `num` unconnected nodes, whose identities are derived from the seed `0`.
+    ///
+    /// The behaviour of every node is created by `behaviour` from the keypair of the node.
+    pub fn new<F>(num: usize, behaviour: F) -> Self
+    where
+        F: FnMut(&identity::Keypair) -> B
+    {
+        SwarmTest::with_seed(num, 0, behaviour)
+    }
+
+    /// Builds `num` unconnected nodes, whose identities are derived from `seed`.
+    ///
+    /// The behaviour of every node is created by `behaviour` from the keypair of the node.
+    pub fn with_seed<F>(num: usize, seed: u64, mut behaviour: F) -> Self
+    where
+        F: FnMut(&identity::Keypair) -> B
+    {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let tasks = Tasks::default();
+
+        let nodes = (0 .. num)
+            .map(|_| {
+                let secret = ed25519::SecretKey::from_bytes(rng.gen::<[u8; 32]>())
+                    .expect("any 32 bytes are a valid ed25519 secret key; QED");
+                let keypair = identity::Keypair::Ed25519(secret.into());
+                let peer_id = keypair.public().into_peer_id();
+                let mut swarm = build_swarm(&keypair, behaviour(&keypair), &tasks);
+                Swarm::listen_on(&mut swarm, Protocol::Memory(0).into())
+                    .expect("the memory transport supports /memory/0; QED");
+                Node { swarm, peer_id, addr: None }
+            })
+            .collect();
+
+        let mut test = SwarmTest {
+            nodes,
+            tasks,
+            events: VecDeque::new(),
+            next_node: 0,
+            timeout: DEFAULT_TIMEOUT,
+        };
+
+        // The addresses are recorded by `poll_swarms`.
+        while test.nodes.iter().any(|node| node.addr.is_none()) {
+            test.wait_matching("listen addresses", |_, event| match event {
+                SwarmEvent::NewListenAddr(_) => true,
+                _ => false,
+            });
+        }
+
+        test
+    }
+
+    /// Sets the time after which waiting for an event or for connections fails.
+    ///
+    /// Defaults to [`DEFAULT_TIMEOUT`].
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Returns the number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if there are no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the `PeerId` of a node.
+    pub fn peer_id(&self, index: usize) -> &PeerId {
+        &self.nodes[index].peer_id
+    }
+
+    /// Returns the address a node listens on.
+    pub fn addr(&self, index: usize) -> &Multiaddr {
+        self.nodes[index].addr.as_ref().expect("addresses are known after construction; QED")
+    }
+
+    /// Returns the swarms of all the nodes.
+    pub fn swarms_mut(&mut self) -> impl Iterator<Item = &mut TestSwarm<B>> {
+        self.nodes.iter_mut().map(|node| &mut node.swarm)
+    }
+
+    /// Makes `dialer` connect to `listener` and waits for both nodes to report the connection.
+    ///
+    /// Panics if the connection can't be established before the timeout.
+    pub fn connect(&mut self, dialer: usize, listener: usize) {
+        self.connect_topology(Topology::Edges(vec![(dialer, listener)]))
+    }
+
+    /// Connects every node to every other node and waits for the connections.
+    ///
+    /// Panics if the connections can't be established before the timeout.
+    pub fn connect_all(&mut self) {
+        self.connect_topology(Topology::FullMesh)
+    }
+
+    /// Connects the nodes according to `topology` and waits for all the connections to be
+    /// reported by both of their nodes.
+    ///
+    /// The nodes of an edge must not be connected already. Events that are not related to the
+    /// new connections remain available to [`SwarmTest::wait_for_event`].
+    ///
+    /// Panics if a connection fails or can't be established before the timeout.
+    pub fn connect_topology(&mut self, topology: Topology) {
+        let mut dialed = HashSet::new();
+        let mut expected = HashSet::new();
+
+        for (dialer, listener) in topology.edges(self.nodes.len()) {
+            assert_ne!(dialer, listener, "node {} can not connect to itself", dialer);
+            let addr = self.addr(listener).clone();
+            Swarm::dial_addr(&mut self.nodes[dialer].swarm, addr.clone())
+                .expect("the memory transport supports the addresses of the nodes; QED");
+            dialed.insert((dialer, addr));
+            expected.insert((dialer, self.nodes[listener].peer_id.clone()));
+            expected.insert((listener, self.nodes[dialer].peer_id.clone()));
+        }
+
+        while !expected.is_empty() {
+            let (index, event) = self.wait_matching("connections", |index, event| match event {
+                SwarmEvent::Connected(peer) => expected.contains(&(index, peer.clone())),
+                SwarmEvent::UnreachableAddr { address, .. } =>
+                    dialed.contains(&(index, address.clone())),
+                _ => false,
+            });
+
+            match event {
+                SwarmEvent::Connected(peer) => {
+                    expected.remove(&(index, peer));
+                }
+                SwarmEvent::UnreachableAddr { address, error, .. } =>
+                    panic!("node {} failed to connect to {}: {}", index, address, error),
+                _ => unreachable!("only connections and dialing errors are matched"),
+            }
+        }
+    }
+
+    /// Drives all the nodes and passes their events to `f`, along with the index of the node
+    /// that produced them, until `f` returns `Some`.
+    ///
+    /// Events for which `f` returns `None` are discarded.
+    ///
+    /// Panics if `f` doesn't return `Some` before the timeout.
+    pub fn wait_for_event<F, T>(&mut self, f: F) -> T
+    where
+        F: FnMut(usize, SwarmEvent<B::OutEvent>) -> Option<T>
+    {
+        let timeout = self.timeout;
+        self.try_wait_for_event(timeout, f)
+            .unwrap_or_else(|| panic!("no matching event within {:?}", timeout))
+    }
+
+    /// Same as [`SwarmTest::wait_for_event`], but returns `None` if `f` doesn't return `Some`
+    /// within `timeout`.
+    pub fn try_wait_for_event<F, T>(&mut self, timeout: Duration, mut f: F) -> Option<T>
+    where
+        F: FnMut(usize, SwarmEvent<B::OutEvent>) -> Option<T>
+    {
+        while let Some((index, event)) = self.events.pop_front() {
+            if let Some(out) = f(index, event) {
+                return Some(out)
+            }
+        }
+
+        self.run(timeout, |this, cx| loop {
+            let (index, event) = futures::ready!(this.poll_swarms(cx));
+            if let Some(out) = f(index, event) {
+                return Poll::Ready(out)
+            }
+        })
+    }
+
+    /// Drives all the nodes until none of them has an event to report without waiting, e.g. for
+    /// a timer. The events produced meanwhile remain available to
+    /// [`SwarmTest::wait_for_event`].
+    pub fn run_until_idle(&mut self) {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        while let Poll::Ready(event) = self.poll_swarms(&mut cx) {
+            self.events.push_back(event);
+        }
+    }
+
+    /// Waits for an event that matches `pred`. The other events are kept for `wait_for_event`.
+    ///
+    /// Panics if there is no such event before the timeout.
+    fn wait_matching<F>(&mut self, what: &str, mut pred: F) -> (usize, SwarmEvent<B::OutEvent>)
+    where
+        F: FnMut(usize, &SwarmEvent<B::OutEvent>) -> bool
+    {
+        if let Some(pos) = self.events.iter().position(|(index, event)| pred(*index, event)) {
+            return self.events.remove(pos).expect("the position was just found; QED")
+        }
+
+        let timeout = self.timeout;
+        self.run(timeout, |this, cx| loop {
+            let (index, event) = futures::ready!(this.poll_swarms(cx));
+            if pred(index, &event) {
+                return Poll::Ready((index, event))
+            }
+            this.events.push_back((index, event));
+        })
+        .unwrap_or_else(|| panic!("timed out after {:?} waiting for {}", timeout, what))
+    }
+
+    /// Blocks the current thread on `poll` for at most `timeout`.
+    fn run<F, T>(&mut self, timeout: Duration, mut poll: F) -> Option<T>
+    where
+        F: FnMut(&mut Self, &mut Context) -> Poll<T>
+    {
+        let mut delay = Delay::new(timeout);
+        futures::executor::block_on(future::poll_fn(|cx| {
+            if let Poll::Ready(out) = poll(self, cx) {
+                return Poll::Ready(Some(out))
+            }
+            if let Poll::Ready(()) = delay.poll_unpin(cx) {
+                return Poll::Ready(None)
+            }
+            Poll::Pending
+        }))
+    }
+
+    /// Polls the background tasks and the swarms, and returns the next event of any node.
+    fn poll_swarms(&mut self, cx: &mut Context) -> Poll<(usize, SwarmEvent<B::OutEvent>)> {
+        let num = self.nodes.len();
+
+        loop {
+            self.tasks.poll(cx);
+
+            for offset in 0 .. num {
+                let index = (self.next_node + offset) % num;
+                let node = &mut self.nodes[index];
+                let event = {
+                    let next = node.swarm.next_event();
+                    futures::pin_mut!(next);
+                    next.poll(cx)
+                };
+
+                if let Poll::Ready(event) = event {
+                    if let SwarmEvent::NewListenAddr(addr) = &event {
+                        if node.addr.is_none() {
+                            node.addr = Some(addr.clone());
+                        }
+                    }
+                    self.next_node = (index + 1) % num;
+                    return Poll::Ready((index, event))
+                }
+            }
+
+            // Polling the swarms may have spawned new tasks, which have to be polled once
+            // before they can wake us up.
+            if !self.tasks.has_spawned() {
+                return Poll::Pending
+            }
+        }
+    }
+}
+
+impl<B: NetworkBehaviour> Index<usize> for SwarmTest<B> {
+    type Output = TestSwarm<B>;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.nodes[index].swarm
+    }
+}
+
+impl<B: NetworkBehaviour> IndexMut<usize> for SwarmTest<B> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.nodes[index].swarm
+    }
+}
+
+/// Tasks spawned by the swarms, which are polled by the `SwarmTest` instead of a thread pool.
+#[derive(Default)]
+struct Tasks {
+    /// Tasks spawned since the last call to `poll`.
+    spawned: Arc<Mutex<Vec<BoxFuture<'static, ()>>>>,
+    running: FuturesUnordered<BoxFuture<'static, ()>>,
+}
+
+impl Tasks {
+    /// Returns `true` if tasks have been spawned since the last call to `poll`.
+    fn has_spawned(&self) -> bool {
+        !self.spawned.lock().is_empty()
+    }
+
+    /// Polls the running tasks, including the ones that have just been spawned.
+    fn poll(&mut self, cx: &mut Context) {
+        for task in mem::replace(&mut *self.spawned.lock(), Vec::new()) {
+            self.running.push(task);
+        }
+        while let Poll::Ready(Some(())) = self.running.poll_next_unpin(cx) {}
+    }
+}
+
+fn build_swarm<B: TestBehaviour>(keypair: &identity::Keypair, behaviour: B, tasks: &Tasks) -> TestSwarm<B> {
+    let local_public_key = keypair.public();
+    let transport = MemoryTransport::default()
+        .upgrade(upgrade::Version::V1)
+        .authenticate(PlainText2Config { local_public_key: local_public_key.clone() })
+        .multiplex(MplexConfig::new())
+        .map(|(peer, muxer), _| (peer, StreamMuxerBox::new(muxer)))
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+        .boxed();
+
+    let spawned = tasks.spawned.clone();
+    SwarmBuilder::new(transport, behaviour, local_public_key.into_peer_id())
+        .executor_fn(move |task| spawned.lock().push(task))
+        .build()
+}
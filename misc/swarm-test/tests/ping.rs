@@ -0,0 +1,83 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use libp2p_core::{PeerId, muxing::StreamMuxerBox, nodes::Substream};
+use libp2p_ping::{Ping, PingConfig, PingEvent, PingSuccess};
+use libp2p_swarm_test::{SwarmEvent, SwarmTest, Topology};
+use std::{collections::HashSet, time::Duration};
+
+fn build(num: usize, seed: u64) -> SwarmTest<Ping<Substream<StreamMuxerBox>>> {
+    SwarmTest::with_seed(num, seed, |_| Ping::new(PingConfig::new().with_keep_alive(true)))
+}
+
+#[test]
+fn topology_edges() {
+    assert_eq!(Topology::FullMesh.edges(3), vec![(0, 1), (0, 2), (1, 2)]);
+    assert_eq!(Topology::Line.edges(3), vec![(0, 1), (1, 2)]);
+    assert_eq!(Topology::Ring.edges(3), vec![(0, 1), (1, 2), (2, 0)]);
+    assert_eq!(Topology::Ring.edges(2), vec![(0, 1)]);
+    assert_eq!(Topology::Star.edges(3), vec![(1, 0), (2, 0)]);
+    assert!(Topology::FullMesh.edges(1).is_empty());
+}
+
+#[test]
+fn identities_are_derived_from_the_seed() {
+    let peers = |test: &SwarmTest<_>| (0 .. test.len()).map(|i| test.peer_id(i).clone()).collect::<Vec<_>>();
+
+    let a = build(3, 7);
+    let b = build(3, 7);
+    let c = build(3, 8);
+    assert_eq!(peers(&a), peers(&b));
+    assert_ne!(peers(&a), peers(&c));
+    assert_eq!(peers(&a).into_iter().collect::<HashSet<_>>().len(), 3);
+}
+
+#[test]
+fn nodes_ping_their_neighbours() {
+    for topology in vec![Topology::FullMesh, Topology::Line, Topology::Ring, Topology::Star] {
+        let mut test = build(4, 0);
+        test.connect_topology(topology.clone());
+
+        let mut expected = HashSet::<(PeerId, PeerId)>::new();
+        for (a, b) in topology.edges(test.len()) {
+            expected.insert((test.peer_id(a).clone(), test.peer_id(b).clone()));
+            expected.insert((test.peer_id(b).clone(), test.peer_id(a).clone()));
+        }
+
+        while !expected.is_empty() {
+            let pinged = test.wait_for_event(|index, event| match event {
+                SwarmEvent::Behaviour(PingEvent { peer, result: Ok(PingSuccess::Ping { .. }) }) =>
+                    Some((index, peer)),
+                _ => None,
+            });
+            expected.remove(&(test.peer_id(pinged.0).clone(), pinged.1));
+        }
+    }
+}
+
+#[test]
+fn waiting_times_out() {
+    let mut test = build(2, 0);
+    let event = test.try_wait_for_event(Duration::from_millis(100), |_, event| match event {
+        SwarmEvent::Behaviour(ev) => Some(ev),
+        _ => None,
+    });
+    assert!(event.is_none());
+}
@@ -21,18 +21,20 @@
 mod codec;
 
 use std::{cmp, iter, mem, pin::Pin, task::Context, task::Poll};
-use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::collections::VecDeque;
+use std::io::Error as IoError;
 use std::sync::Arc;
 use std::task::Waker;
 use bytes::Bytes;
 use libp2p_core::{
     Endpoint,
     StreamMuxer,
+    muxing::{StreamMuxerEvent, SubstreamTermination},
     upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo},
 };
 use log::{debug, trace};
 use parking_lot::Mutex;
-use fnv::FnvHashSet;
+use fnv::FnvHashMap;
 use futures::{prelude::*, future, ready, stream::Fuse};
 use futures::task::{ArcWake, waker_ref};
 use futures_codec::Framed;
@@ -42,9 +44,9 @@ use futures_codec::Framed;
 pub struct MplexConfig {
     /// Maximum number of simultaneously-open substreams.
     max_substreams: usize,
-    /// Maximum number of elements in the internal buffer.
+    /// Maximum number of frames buffered per substream.
     max_buffer_len: usize,
-    /// Behaviour when the buffer size limit is reached.
+    /// Behaviour when the buffer size limit of a substream is reached.
     max_buffer_behaviour: MaxBufferBehaviour,
     /// When sending data, split it into frames whose maximum size is this value
     /// (max 1MByte, as per the Mplex spec).
@@ -58,8 +60,8 @@ impl MplexConfig {
         Default::default()
     }
 
-    /// Sets the maximum number of simultaneously opened substreams, after which an error is
-    /// generated and the connection closes.
+    /// Sets the maximum number of simultaneously opened substreams. Substreams opened by the
+    /// remote beyond this limit are immediately reset.
     ///
     /// A limit is necessary in order to avoid DoS attacks.
     #[inline]
@@ -68,16 +70,18 @@ impl MplexConfig {
         self
     }
 
-    /// Sets the maximum number of pending incoming messages.
+    /// Sets the maximum number of frames that can be buffered for each substream before they
+    /// are read.
     ///
-    /// A limit is necessary in order to avoid DoS attacks.
+    /// The limit applies to each substream individually. A limit is necessary in order to
+    /// avoid DoS attacks.
     #[inline]
     pub fn max_buffer_len(&mut self, max: usize) -> &mut Self {
         self.max_buffer_len = max;
         self
     }
 
-    /// Sets the behaviour when the maximum buffer length has been reached.
+    /// Sets the behaviour when the maximum buffer length of a substream has been reached.
     ///
     /// See the documentation of `MaxBufferBehaviour`.
     #[inline]
@@ -99,15 +103,17 @@ impl MplexConfig {
     where
         C: AsyncRead + AsyncWrite + Unpin
     {
-        let max_buffer_len = self.max_buffer_len;
         Multiplex {
             inner: Mutex::new(MultiplexInner {
                 error: Ok(()),
                 inner: Framed::new(i, codec::Codec::new()).fuse(),
                 config: self,
-                buffer: Vec::with_capacity(cmp::min(max_buffer_len, 512)),
-                opened_substreams: Default::default(),
+                pending_inbound: VecDeque::new(),
+                substreams: Default::default(),
                 next_outbound_stream_id: 0,
+                pending_frames: VecDeque::new(),
+                events: VecDeque::new(),
+                events_waker: None,
                 notifier_read: Arc::new(Notifier {
                     to_wake: Mutex::new(Default::default()),
                 }),
@@ -126,25 +132,80 @@ impl Default for MplexConfig {
     fn default() -> MplexConfig {
         MplexConfig {
             max_substreams: 128,
-            max_buffer_len: 4096,
-            max_buffer_behaviour: MaxBufferBehaviour::CloseAll,
+            max_buffer_len: 32,
+            max_buffer_behaviour: MaxBufferBehaviour::ResetStream,
             split_send_size: 1024,
         }
     }
 }
 
-/// Behaviour when the maximum length of the buffer is reached.
+/// Behaviour when the maximum length of the buffer of a substream is reached.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum MaxBufferBehaviour {
-    /// Produce an error on all the substreams.
-    CloseAll,
-    /// No new message will be read from the underlying connection if the buffer is full.
+    /// Reset the substream whose buffer is full. The other substreams are not affected.
+    ///
+    /// The data buffered before the reset can still be read from the substream, after which
+    /// reading produces an error whose `SubstreamTermination` is `LocalReset`. A
+    /// `MplexEvent::SubstreamReset` is generated.
+    ResetStream,
+    /// Pause the substream whose buffer is full until it is read from. The other substreams are
+    /// not affected.
     ///
-    /// This can potentially introduce a deadlock if you are waiting for a message from a substream
-    /// before processing the messages received on another substream.
+    /// Mplex has no flow control of its own, so the remote can't be asked to stop sending on a
+    /// single substream. The frames it keeps sending on paused substreams are held back in an
+    /// overflow area shared by all the substreams of the connection, which can hold
+    /// `max_buffer_len` frames. Only once this area is full too does the connection stop being
+    /// read, which pauses every substream until the backlogged ones are read from.
     Block,
 }
 
+/// Identifier of a substream within a connection.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SubstreamId {
+    num: u32,
+    endpoint: Endpoint,
+}
+
+impl SubstreamId {
+    /// Returns the number of the substream, as transmitted on the wire.
+    #[inline]
+    pub fn num(&self) -> u32 {
+        self.num
+    }
+
+    /// Returns which side opened the substream, from our point of view.
+    #[inline]
+    pub fn endpoint(&self) -> Endpoint {
+        self.endpoint
+    }
+}
+
+/// Event about one of the substreams of a connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MplexEvent {
+    /// A substream has been reset. Writing to it will produce an error, and reading from it will
+    /// produce an error once the data that was received before the reset has been read.
+    SubstreamReset {
+        /// The substream that has been reset.
+        id: SubstreamId,
+        /// Why the substream has been reset.
+        reason: ResetReason,
+    },
+}
+
+/// Reason why a substream has been reset.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ResetReason {
+    /// The remote has reset the substream.
+    Remote,
+    /// The remote has sent more frames than the buffer of the substream can hold. Only happens
+    /// with `MaxBufferBehaviour::ResetStream`.
+    BufferOverflow,
+    /// The remote has opened a substream while the maximum number of substreams was already
+    /// reached.
+    TooManySubstreams,
+}
+
 impl UpgradeInfo for MplexConfig {
     type Info = &'static [u8];
     type InfoIter = iter::Once<Self::Info>;
@@ -186,6 +247,22 @@ pub struct Multiplex<C> {
     inner: Mutex<MultiplexInner<C>>,
 }
 
+impl<C> Multiplex<C> {
+    /// Returns the next event about the substreams of this connection.
+    ///
+    /// Events are generated while frames are being read from the connection, which happens when
+    /// polling the connection for events or reading from substreams.
+    pub fn poll_substream_event(&self, cx: &mut Context) -> Poll<MplexEvent> {
+        let mut inner = self.inner.lock();
+        if let Some(event) = inner.events.pop_front() {
+            return Poll::Ready(event);
+        }
+
+        inner.events_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
 // Struct shared throughout the implementation.
 struct MultiplexInner<C> {
     // Error that happened earlier. Should poison any attempt to use this `MultiplexError`.
@@ -194,15 +271,21 @@ struct MultiplexInner<C> {
     inner: Fuse<Framed<C, codec::Codec>>,
     /// The original configuration.
     config: MplexConfig,
-    // Buffer of elements pulled from the stream but not processed yet.
-    buffer: Vec<codec::Elem>,
-    // List of Ids of opened substreams. Used to filter out messages that don't belong to any
-    // substream. Note that this is handled exclusively by `next_match`.
-    // The `Endpoint` value denotes who initiated the substream from our point of view
-    // (see note [StreamId]).
-    opened_substreams: FnvHashSet<(u32, Endpoint)>,
+    /// Numbers of the substreams opened by the remote that haven't been returned by
+    /// `poll_event` yet.
+    pending_inbound: VecDeque<u32>,
+    /// Receiving side of the substreams that are open. Note that the `Endpoint` denotes who
+    /// initiated the substream from our point of view (see note [StreamId]).
+    substreams: FnvHashMap<SubstreamId, SubstreamState>,
     // Id of the next outgoing substream.
     next_outbound_stream_id: u32,
+    /// Frames generated by the multiplexer itself, such as resets, that must be sent to the
+    /// remote.
+    pending_frames: VecDeque<codec::Elem>,
+    /// Events waiting to be returned by `poll_substream_event`.
+    events: VecDeque<MplexEvent>,
+    /// Task to wake up when a new event is pushed to `events`.
+    events_waker: Option<Waker>,
     /// List of wakers to wake when a read event happens on the underlying stream.
     notifier_read: Arc<Notifier>,
     /// List of wakers to wake when a write event happens on the underlying stream.
@@ -214,6 +297,17 @@ struct MultiplexInner<C> {
     is_acknowledged: bool,
 }
 
+/// Receiving side of a substream.
+#[derive(Debug, Default)]
+struct SubstreamState {
+    /// Data received from the remote but not read yet.
+    buffer: VecDeque<Bytes>,
+    /// If true, the remote has closed its writing side.
+    remote_closed: bool,
+    /// If `Some`, the substream has been reset.
+    reset: Option<ResetReason>,
+}
+
 struct Notifier {
     /// List of wakers to wake.
     to_wake: Mutex<Vec<Waker>>,
@@ -247,87 +341,180 @@ impl ArcWake for Notifier {
 // entry has been stored as `(<u32>, Dialer)`. So, when looking up streams based on frames
 // received, we have to invert the `Endpoint`, except for `Open`.
 
-/// Processes elements in `inner` until one matching `filter` is found.
+/// Reads the next frame from the underlying stream and dispatches it to the substream it
+/// belongs to.
 ///
 /// If `Pending` is returned, the waker is kept and notified later, just like with any `Poll`.
-/// `Ready(Ok())` is almost always returned. An error is returned if the stream is EOF.
-fn next_match<C, F, O>(inner: &mut MultiplexInner<C>, cx: &mut Context, mut filter: F) -> Poll<Result<O, IoError>>
-where C: AsyncRead + AsyncWrite + Unpin,
-      F: FnMut(&codec::Elem) -> Option<O>,
+/// An error is returned if the stream is EOF or if an error happened earlier.
+fn poll_next_frame<C>(inner: &mut MultiplexInner<C>, cx: &mut Context) -> Poll<Result<(), IoError>>
+where C: AsyncRead + AsyncWrite + Unpin
 {
     // If an error happened earlier, immediately return it.
     if let Err(ref err) = inner.error {
         return Poll::Ready(Err(IoError::new(err.kind(), err.to_string())));
     }
 
-    if let Some((offset, out)) = inner.buffer.iter().enumerate().filter_map(|(n, v)| filter(v).map(|v| (n, v))).next() {
-        // Found a matching entry in the existing buffer!
-
-        // The buffer was full and no longer is, so let's notify everything.
-        if inner.buffer.len() == inner.config.max_buffer_len {
-            ArcWake::wake_by_ref(&inner.notifier_read);
+    // Try to send the resets we have generated, even if nobody is writing on the connection.
+    // Errors will be reported to the next writer.
+    if !inner.pending_frames.is_empty() {
+        if let Poll::Ready(Err(err)) = poll_flush(inner, cx) {
+            debug!("Failed to send pending mplex frames: {:?}", err);
         }
-
-        inner.buffer.remove(offset);
-        return Poll::Ready(Ok(out));
     }
 
-    loop {
-        // Check if we reached max buffer length first.
-        debug_assert!(inner.buffer.len() <= inner.config.max_buffer_len);
-        if inner.buffer.len() == inner.config.max_buffer_len {
+    // With `MaxBufferBehaviour::Block`, stop reading as long as the overflow area of the paused
+    // substreams is full.
+    if inner.config.max_buffer_behaviour == MaxBufferBehaviour::Block {
+        let max_buffer_len = inner.config.max_buffer_len;
+        let overflow: usize = inner.substreams.values()
+            .map(|s| s.buffer.len().saturating_sub(max_buffer_len))
+            .sum();
+        if overflow >= max_buffer_len {
             debug!("Reached mplex maximum buffer length");
-            match inner.config.max_buffer_behaviour {
-                MaxBufferBehaviour::CloseAll => {
-                    inner.error = Err(IoError::new(IoErrorKind::Other, "reached maximum buffer length"));
-                    return Poll::Ready(Err(IoError::new(IoErrorKind::Other, "reached maximum buffer length")));
-                },
-                MaxBufferBehaviour::Block => {
-                    inner.notifier_read.insert(cx.waker());
-                    return Poll::Pending
-                },
-            }
+            inner.notifier_read.insert(cx.waker());
+            return Poll::Pending
         }
+    }
 
-        inner.notifier_read.insert(cx.waker());
-        let elem = match Stream::poll_next(Pin::new(&mut inner.inner), &mut Context::from_waker(&waker_ref(&inner.notifier_read))) {
-            Poll::Ready(Some(Ok(item))) => item,
-            Poll::Ready(None) => return Poll::Ready(Err(IoErrorKind::BrokenPipe.into())),
-            Poll::Pending => return Poll::Pending,
-            Poll::Ready(Some(Err(err))) => {
-                let err2 = IoError::new(err.kind(), err.to_string());
-                inner.error = Err(err);
-                return Poll::Ready(Err(err2));
-            },
-        };
-
-        trace!("Received message: {:?}", elem);
-        inner.is_acknowledged = true;
-
-        // Handle substreams opening/closing.
-        match elem {
-            codec::Elem::Open { substream_id } => {
-                if !inner.opened_substreams.insert((substream_id, Endpoint::Listener)) {
-                    debug!("Received open message for substream {} which was already open", substream_id)
+    inner.notifier_read.insert(cx.waker());
+    let elem = match Stream::poll_next(Pin::new(&mut inner.inner), &mut Context::from_waker(&waker_ref(&inner.notifier_read))) {
+        Poll::Ready(Some(Ok(item))) => item,
+        Poll::Ready(None) => return Poll::Ready(Err(SubstreamTermination::ConnectionClosed.into())),
+        Poll::Pending => return Poll::Pending,
+        Poll::Ready(Some(Err(err))) => {
+            let err2 = IoError::new(err.kind(), err.to_string());
+            inner.error = Err(err);
+            return Poll::Ready(Err(err2));
+        },
+    };
+
+    trace!("Received message: {:?}", elem);
+    inner.is_acknowledged = true;
+
+    match elem {
+        codec::Elem::Open { substream_id } => {
+            let id = SubstreamId { num: substream_id, endpoint: Endpoint::Listener };
+            if inner.substreams.contains_key(&id) {
+                debug!("Received open message for substream {} which was already open", substream_id)
+            } else if inner.substreams.len() >= inner.config.max_substreams {
+                debug!("Refused substream {}; reached maximum number of substreams {}",
+                    substream_id, inner.config.max_substreams);
+                queue_reset(inner, id);
+                push_event(inner, MplexEvent::SubstreamReset { id, reason: ResetReason::TooManySubstreams });
+            } else {
+                inner.substreams.insert(id, SubstreamState::default());
+                inner.pending_inbound.push_back(substream_id);
+            }
+        }
+        codec::Elem::Data { substream_id, endpoint, data } => {
+            let id = SubstreamId { num: substream_id, endpoint: !endpoint }; // see note [StreamId]
+            let max_buffer_len = inner.config.max_buffer_len;
+            let reset_on_overflow = inner.config.max_buffer_behaviour == MaxBufferBehaviour::ResetStream;
+            let overflow = match inner.substreams.get_mut(&id) {
+                Some(state) if state.reset.is_none() && !state.remote_closed => {
+                    if reset_on_overflow && state.buffer.len() >= max_buffer_len {
+                        // The data already buffered stays readable up to the reset.
+                        state.reset = Some(ResetReason::BufferOverflow);
+                        true
+                    } else {
+                        state.buffer.push_back(data);
+                        false
+                    }
+                }
+                _ => {
+                    debug!("Ignored data for substream {} because it isn't open", substream_id);
+                    false
                 }
+            };
+
+            if overflow {
+                debug!("Resetting substream {} because its buffer is full", substream_id);
+                queue_reset(inner, id);
+                push_event(inner, MplexEvent::SubstreamReset { id, reason: ResetReason::BufferOverflow });
             }
-            codec::Elem::Close { substream_id, endpoint, .. } | codec::Elem::Reset { substream_id, endpoint, .. } => {
-                inner.opened_substreams.remove(&(substream_id, !endpoint));
+        }
+        codec::Elem::Close { substream_id, endpoint } => {
+            let id = SubstreamId { num: substream_id, endpoint: !endpoint }; // see note [StreamId]
+            match inner.substreams.get_mut(&id) {
+                Some(state) => state.remote_closed = true,
+                None => debug!("Ignored close for substream {} because it isn't open", substream_id),
             }
-            _ => ()
         }
-
-        if let Some(out) = filter(&elem) {
-            return Poll::Ready(Ok(out));
-        } else {
-            let endpoint = elem.endpoint().unwrap_or(Endpoint::Dialer);
-            if inner.opened_substreams.contains(&(elem.substream_id(), !endpoint)) || elem.is_open_msg() {
-                inner.buffer.push(elem);
-            } else if !elem.is_close_or_reset_msg() {
-                debug!("Ignored message {:?} because the substream wasn't open", elem);
+        codec::Elem::Reset { substream_id, endpoint } => {
+            let id = SubstreamId { num: substream_id, endpoint: !endpoint }; // see note [StreamId]
+            if let Some(pos) = inner.pending_inbound.iter().position(|n| *n == substream_id && id.endpoint == Endpoint::Listener) {
+                // Nobody knows about this substream yet; simply forget it.
+                inner.pending_inbound.remove(pos);
+                inner.substreams.remove(&id);
+            } else if let Some(state) = inner.substreams.get_mut(&id) {
+                if state.reset.is_none() {
+                    state.reset = Some(ResetReason::Remote);
+                    push_event(inner, MplexEvent::SubstreamReset { id, reason: ResetReason::Remote });
+                }
             }
         }
     }
+
+    Poll::Ready(Ok(()))
+}
+
+/// Schedules sending a `Reset` frame for the given substream.
+///
+/// The frame is sent the next time the connection is written to, read from or flushed.
+fn queue_reset<C>(inner: &mut MultiplexInner<C>, id: SubstreamId) {
+    if inner.is_shutdown {
+        return
+    }
+
+    // The number of pending frames is bounded in order to avoid a remote forcing us to
+    // allocate memory by opening substreams while not reading from the connection.
+    if inner.pending_frames.len() >= inner.config.max_substreams {
+        debug!("Too many pending frames; not sending reset for substream {}", id.num);
+        return
+    }
+
+    inner.pending_frames.push_back(codec::Elem::Reset { substream_id: id.num, endpoint: id.endpoint });
+    ArcWake::wake_by_ref(&inner.notifier_write);
+}
+
+/// Adds an event to the queue returned by `Multiplex::poll_substream_event`.
+fn push_event<C>(inner: &mut MultiplexInner<C>, event: MplexEvent) {
+    // The queue is bounded in case nobody ever polls for events.
+    if inner.events.len() >= inner.config.max_substreams {
+        inner.events.pop_front();
+    }
+
+    inner.events.push_back(event);
+    if let Some(waker) = inner.events_waker.take() {
+        waker.wake();
+    }
+}
+
+/// Writes the frames of `pending_frames` to the underlying stream, without flushing it.
+fn poll_send_pending<C>(inner: &mut MultiplexInner<C>, cx: &mut Context) -> Poll<Result<(), IoError>>
+where C: AsyncRead + AsyncWrite + Unpin
+{
+    if inner.is_shutdown {
+        inner.pending_frames.clear();
+        return Poll::Ready(Ok(()))
+    }
+
+    inner.notifier_write.insert(cx.waker());
+
+    while !inner.pending_frames.is_empty() {
+        match Sink::poll_ready(Pin::new(&mut inner.inner), &mut Context::from_waker(&waker_ref(&inner.notifier_write))) {
+            Poll::Ready(Ok(())) => {
+                let elem = inner.pending_frames.pop_front().expect("pending_frames is not empty");
+                if let Err(err) = Sink::start_send(Pin::new(&mut inner.inner), elem) {
+                    return Poll::Ready(Err(err))
+                }
+            },
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(err))
+        }
+    }
+
+    Poll::Ready(Ok(()))
 }
 
 // Small convenience function that tries to write `elem` to the stream.
@@ -335,10 +522,15 @@ fn poll_send<C>(inner: &mut MultiplexInner<C>, cx: &mut Context, elem: codec::El
 where C: AsyncRead + AsyncWrite + Unpin
 {
     if inner.is_shutdown {
-        return Poll::Ready(Err(IoError::new(IoErrorKind::Other, "connection is shut down")))
+        return Poll::Ready(Err(SubstreamTermination::ConnectionClosed.into()))
     }
 
-    inner.notifier_write.insert(cx.waker());
+    // Frames generated by the multiplexer go first.
+    match poll_send_pending(inner, cx) {
+        Poll::Ready(Ok(())) => (),
+        Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+        Poll::Pending => return Poll::Pending,
+    }
 
     match Sink::poll_ready(Pin::new(&mut inner.inner), &mut Context::from_waker(&waker_ref(&inner.notifier_write))) {
         Poll::Ready(Ok(())) => {
@@ -352,6 +544,33 @@ where C: AsyncRead + AsyncWrite + Unpin
     }
 }
 
+// Small convenience function that writes the pending frames and flushes the stream.
+fn poll_flush<C>(inner: &mut MultiplexInner<C>, cx: &mut Context) -> Poll<Result<(), IoError>>
+where C: AsyncRead + AsyncWrite + Unpin
+{
+    match poll_send_pending(inner, cx) {
+        Poll::Ready(Ok(())) => (),
+        Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+        Poll::Pending => return Poll::Pending,
+    }
+
+    if inner.is_shutdown {
+        return Poll::Ready(Ok(()))
+    }
+
+    inner.notifier_write.insert(cx.waker());
+    Sink::poll_flush(Pin::new(&mut inner.inner), &mut Context::from_waker(&waker_ref(&inner.notifier_write)))
+}
+
+/// Error returned when using a substream that has been reset, or whose state no longer exists.
+fn reset_error(state: Option<&SubstreamState>) -> IoError {
+    match state.and_then(|s| s.reset) {
+        Some(ResetReason::Remote) => SubstreamTermination::RemoteReset.into(),
+        Some(ResetReason::BufferOverflow) | Some(ResetReason::TooManySubstreams) | None =>
+            SubstreamTermination::LocalReset.into(),
+    }
+}
+
 impl<C> StreamMuxer for Multiplex<C>
 where C: AsyncRead + AsyncWrite + Unpin
 {
@@ -359,35 +578,20 @@ where C: AsyncRead + AsyncWrite + Unpin
     type OutboundSubstream = OutboundSubstream;
     type Error = IoError;
 
-    fn poll_inbound(&self, cx: &mut Context) -> Poll<Result<Self::Substream, IoError>> {
+    fn poll_event(&self, cx: &mut Context) -> Poll<Result<StreamMuxerEvent<Self::Substream>, IoError>> {
         let mut inner = self.inner.lock();
 
-        if inner.opened_substreams.len() >= inner.config.max_substreams {
-            debug!("Refused substream; reached maximum number of substreams {}", inner.config.max_substreams);
-            return Poll::Ready(Err(IoError::new(IoErrorKind::ConnectionRefused,
-                                    "exceeded maximum number of open substreams")));
-        }
-
-        let num = ready!(next_match(&mut inner, cx, |elem| {
-            match elem {
-                codec::Elem::Open { substream_id } => Some(*substream_id),
-                _ => None,
+        loop {
+            if let Some(num) = inner.pending_inbound.pop_front() {
+                debug!("Successfully opened inbound substream {}", num);
+                let substream = Substream::new(num, Endpoint::Listener);
+                return Poll::Ready(Ok(StreamMuxerEvent::InboundSubstream(substream)));
             }
-        }));
-
-        let num = match num {
-            Ok(n) => n,
-            Err(err) => return Poll::Ready(Err(err)),
-        };
 
-        debug!("Successfully opened inbound substream {}", num);
-        Poll::Ready(Ok(Substream {
-            current_data: Bytes::new(),
-            num,
-            endpoint: Endpoint::Listener,
-            local_open: true,
-            remote_open: true,
-        }))
+            if let Err(err) = ready!(poll_next_frame(&mut inner, cx)) {
+                return Poll::Ready(Err(err));
+            }
+        }
     }
 
     fn open_outbound(&self) -> Self::OutboundSubstream {
@@ -401,7 +605,8 @@ where C: AsyncRead + AsyncWrite + Unpin
             n
         };
 
-        inner.opened_substreams.insert((substream_id, Endpoint::Dialer));
+        let id = SubstreamId { num: substream_id, endpoint: Endpoint::Dialer };
+        inner.substreams.insert(id, SubstreamState::default());
 
         OutboundSubstream {
             num: substream_id,
@@ -419,11 +624,9 @@ where C: AsyncRead + AsyncWrite + Unpin
                 },
                 OutboundSubstreamState::Flush => {
                     if inner.is_shutdown {
-                        return Poll::Ready(Err(IoError::new(IoErrorKind::Other, "connection is shut down")))
+                        return Poll::Ready(Err(SubstreamTermination::ConnectionClosed.into()))
                     }
-                    let inner = &mut *inner; // Avoids borrow errors
-                    inner.notifier_write.insert(cx.waker());
-                    Sink::poll_flush(Pin::new(&mut inner.inner), &mut Context::from_waker(&waker_ref(&inner.notifier_write)))
+                    poll_flush(&mut inner, cx)
                 },
                 OutboundSubstreamState::Done => {
                     panic!("Polling outbound substream after it's been succesfully open");
@@ -435,9 +638,7 @@ where C: AsyncRead + AsyncWrite + Unpin
                 Poll::Pending => return Poll::Pending,
                 Poll::Ready(Err(err)) => {
                     debug!("Failed to open outbound substream {}", substream.num);
-                    inner.buffer.retain(|elem| {
-                        elem.substream_id() != substream.num || elem.endpoint() == Some(Endpoint::Dialer)
-                    });
+                    inner.substreams.remove(&SubstreamId { num: substream.num, endpoint: Endpoint::Dialer });
                     return Poll::Ready(Err(err));
                 },
             };
@@ -452,25 +653,32 @@ where C: AsyncRead + AsyncWrite + Unpin
                 OutboundSubstreamState::Flush => {
                     debug!("Successfully opened outbound substream {}", substream.num);
                     substream.state = OutboundSubstreamState::Done;
-                    return Poll::Ready(Ok(Substream {
-                        num: substream.num,
-                        current_data: Bytes::new(),
-                        endpoint: Endpoint::Dialer,
-                        local_open: true,
-                        remote_open: true,
-                    }));
+                    return Poll::Ready(Ok(Substream::new(substream.num, Endpoint::Dialer)));
                 },
                 OutboundSubstreamState::Done => unreachable!(),
             }
         }
     }
 
-    #[inline]
-    fn destroy_outbound(&self, _substream: Self::OutboundSubstream) {
-        // Nothing to do.
+    fn destroy_outbound(&self, substream: Self::OutboundSubstream) {
+        // If the substream hasn't been successfully opened, nobody will ever use its state.
+        if let OutboundSubstreamState::Done = substream.state {
+            return
+        }
+
+        let id = SubstreamId { num: substream.num, endpoint: Endpoint::Dialer };
+        let mut inner = self.inner.lock();
+        if inner.substreams.remove(&id).is_some() {
+            if let OutboundSubstreamState::Flush = substream.state {
+                // The `Open` frame has already been sent.
+                queue_reset(&mut inner, id);
+            }
+        }
     }
 
     fn read_substream(&self, cx: &mut Context, substream: &mut Self::Substream, buf: &mut [u8]) -> Poll<Result<usize, IoError>> {
+        let id = substream.id();
+
         loop {
             // First, transfer from `current_data`.
             if !substream.current_data.is_empty() {
@@ -479,58 +687,56 @@ where C: AsyncRead + AsyncWrite + Unpin
                 return Poll::Ready(Ok(len));
             }
 
-            // If the remote writing side is closed, return EOF.
-            if !substream.remote_open {
-                return Poll::Ready(Ok(0));
-            }
-
-            // Try to find a packet of data in the buffer.
             let mut inner = self.inner.lock();
-            let next_data_poll = next_match(&mut inner, cx, |elem| {
-                match elem {
-                    codec::Elem::Data { substream_id, endpoint, data, .. }
-                        if *substream_id == substream.num && *endpoint != substream.endpoint => // see note [StreamId]
-                    {
-                        Some(Some(data.clone()))
+            let inner = &mut *inner; // Avoids borrow errors
+
+            // Try to find a packet of data in the buffer of the substream.
+            match inner.substreams.get_mut(&id) {
+                Some(state) => {
+                    if let Some(data) = state.buffer.pop_front() {
+                        // The substream was paused, and frames held back in the overflow area
+                        // may have made room for new ones, so let's notify everything.
+                        if state.buffer.len() + 1 >= inner.config.max_buffer_len {
+                            ArcWake::wake_by_ref(&inner.notifier_read);
+                        }
+                        substream.current_data = data;
+                        continue;
                     }
-                    codec::Elem::Close { substream_id, endpoint }
-                        if *substream_id == substream.num && *endpoint != substream.endpoint => // see note [StreamId]
-                    {
-                        Some(None)
+
+                    // The remote closing its writing side gracefully takes precedence over a
+                    // later reset, as all the data it wanted to send has been received.
+                    if state.remote_closed {
+                        return Poll::Ready(Ok(0));
                     }
-                    _ => None
-                }
-            });
 
-            // We're in a loop, so all we need to do is set `substream.current_data` to the data we
-            // just read and wait for the next iteration.
-            match next_data_poll {
-                Poll::Ready(Ok(Some(data))) => substream.current_data = data,
-                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
-                Poll::Ready(Ok(None)) => {
-                    substream.remote_open = false;
-                    return Poll::Ready(Ok(0));
-                },
-                Poll::Pending => {
-                    // There was no data packet in the buffer about this substream; maybe it's
-                    // because it has been closed.
-                    if inner.opened_substreams.contains(&(substream.num, substream.endpoint)) {
-                        return Poll::Pending
-                    } else {
-                        return Poll::Ready(Ok(0))
+                    if state.reset.is_some() {
+                        return Poll::Ready(Err(reset_error(Some(state))));
                     }
                 },
+                None => return Poll::Ready(Err(reset_error(None))),
+            }
+
+            // The buffer is empty; read the next frame from the connection.
+            match poll_next_frame(inner, cx) {
+                Poll::Ready(Ok(())) => (),
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
             }
         }
     }
 
     fn write_substream(&self, cx: &mut Context, substream: &mut Self::Substream, buf: &[u8]) -> Poll<Result<usize, IoError>> {
         if !substream.local_open {
-            return Poll::Ready(Err(IoErrorKind::BrokenPipe.into()));
+            return Poll::Ready(Err(SubstreamTermination::Closed.into()));
         }
 
         let mut inner = self.inner.lock();
 
+        let state = inner.substreams.get(&substream.id());
+        if state.map(|s| s.reset.is_some()).unwrap_or(true) {
+            return Poll::Ready(Err(reset_error(state)));
+        }
+
         let to_write = cmp::min(buf.len(), inner.config.split_send_size);
 
         let elem = codec::Elem::Data {
@@ -549,12 +755,10 @@ where C: AsyncRead + AsyncWrite + Unpin
     fn flush_substream(&self, cx: &mut Context, _substream: &mut Self::Substream) -> Poll<Result<(), IoError>> {
         let mut inner = self.inner.lock();
         if inner.is_shutdown {
-            return Poll::Ready(Err(IoError::new(IoErrorKind::Other, "connection is shut down")))
+            return Poll::Ready(Err(SubstreamTermination::ConnectionClosed.into()))
         }
 
-        let inner = &mut *inner; // Avoids borrow errors
-        inner.notifier_write.insert(cx.waker());
-        Sink::poll_flush(Pin::new(&mut inner.inner), &mut Context::from_waker(&waker_ref(&inner.notifier_write)))
+        poll_flush(&mut inner, cx)
     }
 
     fn shutdown_substream(&self, cx: &mut Context, sub: &mut Self::Substream) -> Poll<Result<(), IoError>> {
@@ -562,12 +766,19 @@ where C: AsyncRead + AsyncWrite + Unpin
             return Poll::Ready(Ok(()));
         }
 
+        let mut inner = self.inner.lock();
+
+        // There is nothing to close gracefully on a substream that has been reset.
+        if inner.substreams.get(&sub.id()).map(|s| s.reset.is_some()).unwrap_or(true) {
+            sub.local_open = false;
+            return Poll::Ready(Ok(()));
+        }
+
         let elem = codec::Elem::Close {
             substream_id: sub.num,
             endpoint: sub.endpoint,
         };
 
-        let mut inner = self.inner.lock();
         let result = poll_send(&mut inner, cx, elem);
         if let Poll::Ready(Ok(())) = result {
             sub.local_open = false;
@@ -576,9 +787,15 @@ where C: AsyncRead + AsyncWrite + Unpin
     }
 
     fn destroy_substream(&self, sub: Self::Substream) {
-        self.inner.lock().buffer.retain(|elem| {
-            elem.substream_id() != sub.num || elem.endpoint() == Some(sub.endpoint)
-        })
+        let mut inner = self.inner.lock();
+        if let Some(state) = inner.substreams.remove(&sub.id()) {
+            // Dropping a substream whose writing side is still open resets it, so that the
+            // remote doesn't wait for data that will never come.
+            if sub.local_open && state.reset.is_none() {
+                debug!("Resetting substream {} because it has been dropped", sub.num);
+                queue_reset(&mut inner, sub.id());
+            }
+        }
     }
 
     fn is_remote_acknowledged(&self) -> bool {
@@ -586,8 +803,16 @@ where C: AsyncRead + AsyncWrite + Unpin
     }
 
     #[inline]
-    fn close(&self, cx: &mut Context) -> Poll<Result<(), IoError>> {
+    fn poll_close(&self, cx: &mut Context) -> Poll<Result<(), IoError>> {
         let inner = &mut *self.inner.lock();
+
+        // Send the resets that are still pending before closing.
+        match poll_send_pending(inner, cx) {
+            Poll::Ready(Ok(())) => (),
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+            Poll::Pending => return Poll::Pending,
+        }
+
         inner.notifier_write.insert(cx.waker());
         match Sink::poll_close(Pin::new(&mut inner.inner), &mut Context::from_waker(&waker_ref(&inner.notifier_write))) {
             Poll::Ready(Ok(())) => {
@@ -605,8 +830,7 @@ where C: AsyncRead + AsyncWrite + Unpin
         if inner.is_shutdown {
             return Poll::Ready(Ok(()))
         }
-        inner.notifier_write.insert(cx.waker());
-        Sink::poll_flush(Pin::new(&mut inner.inner), &mut Context::from_waker(&waker_ref(&inner.notifier_write)))
+        poll_flush(inner, cx)
     }
 }
 
@@ -635,6 +859,21 @@ pub struct Substream {
     endpoint: Endpoint,
     /// If true, our writing side is still open.
     local_open: bool,
-    /// If true, the remote writing side is still open.
-    remote_open: bool,
+}
+
+impl Substream {
+    fn new(num: u32, endpoint: Endpoint) -> Self {
+        Substream {
+            num,
+            current_data: Bytes::new(),
+            endpoint,
+            local_open: true,
+        }
+    }
+
+    /// Returns the identifier of this substream, as found in `MplexEvent`s.
+    #[inline]
+    pub fn id(&self) -> SubstreamId {
+        SubstreamId { num: self.num, endpoint: self.endpoint }
+    }
 }
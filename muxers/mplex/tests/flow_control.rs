@@ -0,0 +1,340 @@
+// Copyright 2018 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use libp2p_core::{
+    Endpoint, Multiaddr, StreamMuxer, Transport,
+    muxing::{self, SubstreamTermination},
+    upgrade::{self, Negotiated},
+};
+use libp2p_mplex::{MaxBufferBehaviour, MplexConfig, MplexEvent, Multiplex, ResetReason};
+use libp2p_tcp::{TcpConfig, TcpTransStream};
+use futures::{channel::oneshot, future, prelude::*};
+use std::{sync::Arc, time::Duration};
+
+/// Listens on a random port, sends the address through `tx` and returns the first
+/// connection, upgraded with `config`.
+async fn listen(config: MplexConfig, tx: oneshot::Sender<Multiaddr>) -> Arc<Multiplex<Negotiated<TcpTransStream>>> {
+    let transport = TcpConfig::new().and_then(move |c, e|
+        upgrade::apply(c, config, e, upgrade::Version::V1));
+
+    let mut listener = transport
+        .listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+        .unwrap();
+
+    let addr = listener.next().await
+        .expect("some event")
+        .expect("no error")
+        .into_new_address()
+        .expect("listen address");
+
+    tx.send(addr).unwrap();
+
+    let muxer = listener
+        .next().await
+        .unwrap()
+        .unwrap()
+        .into_upgrade().unwrap().0.await.unwrap();
+    Arc::new(muxer)
+}
+
+/// Dials the address received through `rx` and upgrades the connection with `config`.
+async fn dial(config: MplexConfig, rx: oneshot::Receiver<Multiaddr>) -> Arc<Multiplex<Negotiated<TcpTransStream>>> {
+    let transport = TcpConfig::new().and_then(move |c, e|
+        upgrade::apply(c, config, e, upgrade::Version::V1));
+
+    Arc::new(transport.dial(rx.await.unwrap()).unwrap().await.unwrap())
+}
+
+/// Data sent on the substream with the given index.
+fn payload(index: usize) -> Vec<u8> {
+    (0 .. 16 * 1024).map(|n| (n + index) as u8).collect()
+}
+
+#[test]
+fn many_substreams_mixed_read_speeds() {
+    // Many substreams are written to simultaneously, while the receiving side reads some of them
+    // slowly. With a small per-substream buffer and `Block`, every substream must still receive
+    // all of its data.
+
+    const NUM_SUBSTREAMS: usize = 32;
+
+    let mut config = MplexConfig::new();
+    config.max_buffer_len(4)
+        .max_buffer_len_behaviour(MaxBufferBehaviour::Block)
+        .split_send_size(512);
+
+    let (tx, rx) = oneshot::channel();
+
+    let listener_config = config.clone();
+    let bg_thread = async_std::task::spawn(async move {
+        let muxer = listen(listener_config, tx).await;
+
+        let mut readers = Vec::new();
+        for _ in 0 .. NUM_SUBSTREAMS {
+            let mut inbound = muxing::inbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+            readers.push(async_std::task::spawn(async move {
+                let mut index = [0u8; 1];
+                inbound.read_exact(&mut index).await.unwrap();
+                let index = usize::from(index[0]);
+
+                let mut received = Vec::new();
+                let mut buf = [0u8; 256];
+                loop {
+                    let n = inbound.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    received.extend_from_slice(&buf[..n]);
+                    if index % 2 == 0 {
+                        async_std::task::sleep(Duration::from_millis(1)).await;
+                    }
+                }
+
+                assert_eq!(received, payload(index));
+            }));
+        }
+
+        future::join_all(readers).await;
+    });
+
+    async_std::task::block_on(async {
+        let muxer = dial(config, rx).await;
+
+        let writers = (0 .. NUM_SUBSTREAMS).map(|index| {
+            let muxer = muxer.clone();
+            async_std::task::spawn(async move {
+                let mut outbound = muxing::outbound_from_ref_and_wrap(muxer).await.unwrap();
+                outbound.write_all(&[index as u8]).await.unwrap();
+                outbound.write_all(&payload(index)).await.unwrap();
+                outbound.close().await.unwrap();
+                outbound
+            })
+        }).collect::<Vec<_>>();
+
+        // Keep the substreams alive until the remote has read everything.
+        let _outbounds = future::join_all(writers).await;
+        bg_thread.await;
+    });
+}
+
+#[test]
+fn overflow_resets_only_the_backlogged_substream() {
+    // The remote fills the buffer of a substream that is never read from. Only that substream
+    // must be reset, and the other one must keep working.
+
+    let mut config = MplexConfig::new();
+    config.max_buffer_len(4)
+        .max_buffer_len_behaviour(MaxBufferBehaviour::ResetStream)
+        .split_send_size(16);
+
+    let (tx, rx) = oneshot::channel();
+
+    let listener_config = config.clone();
+    let bg_thread = async_std::task::spawn(async move {
+        let muxer = listen(listener_config, tx).await;
+
+        let mut backlogged = muxing::inbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+        let mut other = muxing::inbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+
+        let mut buf = Vec::new();
+        other.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"hello world");
+
+        // The data received before the reset is still readable.
+        let mut buf = Vec::new();
+        let err = backlogged.read_to_end(&mut buf).await.unwrap_err();
+        assert_eq!(SubstreamTermination::from_io_error(&err), Some(SubstreamTermination::LocalReset));
+        assert_eq!(buf, vec![0u8; 4 * 16]);
+
+        match future::poll_fn(|cx| muxer.poll_substream_event(cx)).await {
+            MplexEvent::SubstreamReset { id, reason } => {
+                assert_eq!(id.num(), 0);
+                assert_eq!(id.endpoint(), Endpoint::Listener);
+                assert_eq!(reason, ResetReason::BufferOverflow);
+            }
+        }
+    });
+
+    async_std::task::block_on(async {
+        let muxer = dial(config, rx).await;
+
+        let mut backlogged = muxing::outbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+        backlogged.write_all(&[0u8; 1024]).await.unwrap();
+        backlogged.flush().await.unwrap();
+
+        let mut other = muxing::outbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+        other.write_all(b"hello world").await.unwrap();
+        other.close().await.unwrap();
+
+        bg_thread.await;
+    });
+}
+
+#[test]
+fn block_pauses_only_the_backlogged_substream() {
+    // The remote sends many more frames on a substream than its buffer and the overflow area can
+    // hold, and the substream is only read from once another substream has been read to the end.
+
+    const MAX_BUFFER_LEN: usize = 4;
+    const NUM_FRAMES: usize = 8 * MAX_BUFFER_LEN;
+
+    let mut config = MplexConfig::new();
+    config.max_buffer_len(MAX_BUFFER_LEN)
+        .max_buffer_len_behaviour(MaxBufferBehaviour::Block)
+        .split_send_size(16);
+
+    let data: Vec<u8> = (0 .. NUM_FRAMES * 16).map(|n| n as u8).collect();
+    let expected = data.clone();
+
+    let (tx, rx) = oneshot::channel();
+    let (sent_tx, sent_rx) = oneshot::channel();
+
+    let listener_config = config.clone();
+    let bg_thread = async_std::task::spawn(async move {
+        let muxer = listen(listener_config, tx).await;
+
+        let mut backlogged = muxing::inbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+        let mut other = muxing::inbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+
+        // The other substream completes while the backlogged one is unread.
+        let mut buf = Vec::new();
+        other.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"hello world");
+
+        // The substream opened after all the frames of the backlogged substream can't be received
+        // as long as the connection is paused.
+        sent_rx.await.unwrap();
+        let late = async_std::future::timeout(
+            Duration::from_millis(200),
+            muxing::inbound_from_ref_and_wrap(muxer.clone())
+        ).await;
+        assert!(late.is_err(), "The connection should be paused");
+
+        // Reading the backlogged substream resumes the connection without losing data.
+        let mut buf = Vec::new();
+        backlogged.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, expected);
+
+        let mut late = muxing::inbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+        let mut buf = Vec::new();
+        late.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"late");
+    });
+
+    async_std::task::block_on(async {
+        let muxer = dial(config, rx).await;
+
+        // The frames of the other substream are sent after the backlogged substream has filled
+        // its buffer, but before the overflow area is full.
+        let (first, rest) = data.split_at((MAX_BUFFER_LEN + 2) * 16);
+        let mut backlogged = muxing::outbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+        backlogged.write_all(first).await.unwrap();
+        backlogged.flush().await.unwrap();
+
+        let mut other = muxing::outbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+        other.write_all(b"hello world").await.unwrap();
+        other.close().await.unwrap();
+
+        backlogged.write_all(rest).await.unwrap();
+        backlogged.close().await.unwrap();
+
+        let mut late = muxing::outbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+        late.write_all(b"late").await.unwrap();
+        late.close().await.unwrap();
+        sent_tx.send(()).unwrap();
+
+        bg_thread.await;
+    });
+}
+
+#[test]
+fn dropped_substream_is_reset() {
+    // Dropping a substream without closing it must be reported to the remote as a reset rather
+    // than as a graceful end of the data.
+
+    let (tx, rx) = oneshot::channel();
+
+    let bg_thread = async_std::task::spawn(async move {
+        let muxer = listen(MplexConfig::new(), tx).await;
+
+        let mut inbound = muxing::inbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+        let mut buf = [0u8; 5];
+        inbound.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+
+        let err = inbound.read_to_end(&mut Vec::new()).await.unwrap_err();
+        assert_eq!(SubstreamTermination::from_io_error(&err), Some(SubstreamTermination::RemoteReset));
+
+        let err = inbound.write_all(b"too late").await.unwrap_err();
+        assert_eq!(SubstreamTermination::from_io_error(&err), Some(SubstreamTermination::RemoteReset));
+    });
+
+    async_std::task::block_on(async {
+        let muxer = dial(MplexConfig::new(), rx).await;
+
+        let mut outbound = muxing::outbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+        outbound.write_all(b"hello").await.unwrap();
+        outbound.flush().await.unwrap();
+        drop(outbound);
+
+        // Sends the reset.
+        future::poll_fn(|cx| muxer.flush_all(cx)).await.unwrap();
+
+        bg_thread.await;
+    });
+}
+
+#[test]
+fn closed_connection_is_distinguished_from_graceful_close() {
+    // A substream closed gracefully produces EOF, while a substream whose connection has been
+    // closed produces an error.
+
+    let (tx, rx) = oneshot::channel();
+
+    let bg_thread = async_std::task::spawn(async move {
+        let muxer = listen(MplexConfig::new(), tx).await;
+
+        let mut graceful = muxing::inbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+        let mut abrupt = muxing::inbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+
+        let mut buf = Vec::new();
+        graceful.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"bye");
+
+        let err = abrupt.read_to_end(&mut Vec::new()).await.unwrap_err();
+        assert_eq!(SubstreamTermination::from_io_error(&err), Some(SubstreamTermination::ConnectionClosed));
+    });
+
+    async_std::task::block_on(async {
+        let muxer = dial(MplexConfig::new(), rx).await;
+
+        let mut graceful = muxing::outbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+        let abrupt = muxing::outbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+        graceful.write_all(b"bye").await.unwrap();
+        graceful.close().await.unwrap();
+
+        future::poll_fn(|cx| muxer.poll_close(cx)).await.unwrap();
+
+        // Not all transports shut down their writing side when closed, so drop the connection.
+        drop((graceful, abrupt, muxer));
+
+        bg_thread.await;
+    });
+}
@@ -0,0 +1,193 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Measures the throughput of a single Yamux substream over a `MemoryTransport` connection
+//! with an injected latency, with a fixed and with an automatically tuned receive window.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use futures::{future, prelude::*, ready};
+use libp2p_core::{
+    muxing::{self, StreamMuxer, StreamMuxerEvent},
+    transport::{ListenerEvent, MemoryTransport},
+    upgrade::{InboundUpgrade, OutboundUpgrade},
+    Transport,
+};
+use libp2p_yamux::{Config, WindowTuning};
+use std::{collections::VecDeque, io, pin::Pin, sync::Arc, task::{Context, Poll}, time::Duration};
+use wasm_timer::{Delay, Instant};
+
+/// One-way latency injected in each direction.
+const LATENCY: Duration = Duration::from_millis(25);
+/// Amount of data transferred in each iteration.
+const DATA_LEN: usize = 8 * 1024 * 1024;
+
+/// Wraps a connection and delays the delivery of the data read from it by `LATENCY`.
+struct Latency<C> {
+    inner: C,
+    /// Data read from `inner` and the instant when it can be delivered.
+    queue: VecDeque<(Instant, Vec<u8>)>,
+    /// Timer waiting for the first element of the queue, and the instant it fires at.
+    delay: Option<(Instant, Delay)>,
+    eof: bool,
+}
+
+impl<C> Latency<C> {
+    fn new(inner: C) -> Self {
+        Latency { inner, queue: VecDeque::new(), delay: None, eof: false }
+    }
+}
+
+impl<C: AsyncRead + Unpin> AsyncRead for Latency<C> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+
+        // Move everything that is available from the connection into the queue.
+        while !this.eof {
+            let mut chunk = vec![0; 64 * 1024];
+            match Pin::new(&mut this.inner).poll_read(cx, &mut chunk) {
+                Poll::Ready(Ok(0)) => this.eof = true,
+                Poll::Ready(Ok(n)) => {
+                    chunk.truncate(n);
+                    this.queue.push_back((Instant::now() + LATENCY, chunk));
+                }
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => break,
+            }
+        }
+
+        loop {
+            let deadline = match this.queue.front() {
+                Some((deadline, _)) => *deadline,
+                None if this.eof => return Poll::Ready(Ok(0)),
+                None => return Poll::Pending,
+            };
+
+            if deadline > Instant::now() {
+                if this.delay.as_ref().map(|(at, _)| *at != deadline).unwrap_or(true) {
+                    this.delay = Some((deadline, Delay::new_at(deadline)));
+                }
+                let (_, delay) = this.delay.as_mut().expect("delay was just set");
+                ready!(Pin::new(delay).poll(cx))?;
+                continue;
+            }
+
+            let (_, chunk) = this.queue.front_mut().expect("queue is not empty");
+            let n = std::cmp::min(buf.len(), chunk.len());
+            buf[..n].copy_from_slice(&chunk[..n]);
+            chunk.drain(..n);
+            if chunk.is_empty() {
+                this.queue.pop_front();
+            }
+            return Poll::Ready(Ok(n));
+        }
+    }
+}
+
+impl<C: AsyncWrite + Unpin> AsyncWrite for Latency<C> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+/// Polls the muxer for events until the connection is closed, which drives the connection.
+async fn drive<M: StreamMuxer>(muxer: Arc<M>) {
+    future::poll_fn(|cx| loop {
+        match ready!(muxer.poll_event(cx)) {
+            Ok(StreamMuxerEvent::InboundSubstream(s)) => muxer.destroy_substream(s),
+            Ok(StreamMuxerEvent::AddressChange(_)) => {}
+            Err(_) => return Poll::Ready(()),
+        }
+    }).await
+}
+
+/// Transfers `DATA_LEN` bytes over a new substream of a new connection upgraded with `config`.
+fn transfer(config: Config) {
+    async_std::task::block_on(async move {
+        let mut listener = MemoryTransport.listen_on("/memory/0".parse().unwrap()).unwrap();
+        let addr = listener.next().await.unwrap().unwrap().into_new_address().unwrap();
+
+        let listener_config = config.clone();
+        let receiver = async move {
+            let upgrade = listener
+                .filter_map(|ev| future::ready(ListenerEvent::into_upgrade(ev.unwrap())))
+                .next().await.unwrap();
+            let conn = Latency::new(upgrade.0.await.unwrap());
+            let muxer = Arc::new(listener_config.upgrade_inbound(conn, &b"/yamux/1.0.0"[..]).await.unwrap());
+
+            let mut substream = muxing::inbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+            let read = async move {
+                let mut buf = Vec::with_capacity(DATA_LEN);
+                substream.read_to_end(&mut buf).await.unwrap();
+                assert_eq!(buf.len(), DATA_LEN);
+            };
+            future::select(read.boxed(), drive(muxer).boxed()).await;
+        };
+
+        let sender = async move {
+            let conn = Latency::new(MemoryTransport.dial(addr).unwrap().await.unwrap());
+            let muxer = Arc::new(config.upgrade_outbound(conn, &b"/yamux/1.0.0"[..]).await.unwrap());
+
+            let write = {
+                let muxer = muxer.clone();
+                async move {
+                    let mut substream = muxing::outbound_from_ref_and_wrap(muxer).await.unwrap();
+                    substream.write_all(&vec![0x42; DATA_LEN]).await.unwrap();
+                    substream.close().await.unwrap();
+                    // Keep the connection alive until the receiver is done.
+                    future::pending::<()>().await
+                }
+            };
+            future::select(write.boxed(), drive(muxer).boxed()).await;
+        };
+
+        future::select(receiver.boxed(), sender.boxed()).await;
+    })
+}
+
+fn window(c: &mut Criterion) {
+    let mut group = c.benchmark_group("yamux-50ms-rtt");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(DATA_LEN as u64));
+
+    group.bench_function("fixed-window", |b| {
+        b.iter(|| transfer(Config::default()))
+    });
+
+    // The tuning state is shared by all the iterations, so that the window grown during the
+    // first transfers is used by the next ones.
+    let mut tuned = Config::default();
+    tuned.set_window_tuning(WindowTuning::new(256 * 1024, 16 * 1024 * 1024));
+    group.bench_function("tuned-window", |b| {
+        b.iter(|| transfer(tuned.clone()))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, window);
+criterion_main!(benches);
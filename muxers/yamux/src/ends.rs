@@ -0,0 +1,176 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Observation of the way the remote ends the substreams of a connection.
+//!
+//! A `yamux::Stream` doesn't expose its state: reading from a substream which has been reset by
+//! the remote returns EOF, and writing to it fails like writing to a substream of a closed
+//! connection. We wrap the socket of the connection in order to read the headers of the frames
+//! sent by the remote ourselves, and record which substreams it has finished or reset. The frames
+//! go through the wrapper before the connection processes them, hence the end of a substream is
+//! always recorded by the time it can be observed on the `yamux::Stream`.
+
+use futures::prelude::*;
+use parking_lot::Mutex;
+use std::{cmp, collections::HashMap, io, pin::Pin, sync::Arc, task::{Context, Poll}};
+
+/// Size of the header of a Yamux frame.
+const HEADER_SIZE: usize = 12;
+/// Type of the frames carrying data, whose length is the size of their body.
+const TYPE_DATA: u8 = 0;
+/// Type of the frames updating the window, which have no body.
+const TYPE_WINDOW_UPDATE: u8 = 1;
+/// Flag of the frames half-closing a substream.
+const FLAG_FIN: u16 = 4;
+/// Flag of the frames resetting a substream.
+const FLAG_RST: u16 = 8;
+
+/// How the remote has ended a substream.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum End {
+    /// The remote has closed its writing side.
+    Finished,
+    /// The remote has reset the substream without closing its writing side first.
+    Reset,
+    /// The remote has reset the substream after closing its writing side.
+    FinishedThenReset
+}
+
+/// The ends of the substreams of a connection, by ID of substream.
+///
+/// The entry of a substream is kept until the substream is destroyed.
+#[derive(Debug, Default)]
+pub(crate) struct Ends(Mutex<HashMap<u32, End>>);
+
+impl Ends {
+    /// Returns true if the remote has reset the substream, in which case it may not have sent
+    /// all its data and reaching the end of the substream isn't a graceful EOF.
+    pub(crate) fn is_reset_before_finish(&self, id: u32) -> bool {
+        self.0.lock().get(&id) == Some(&End::Reset)
+    }
+
+    /// Returns true if the remote has reset the substream, whether it had finished it or not.
+    pub(crate) fn is_reset(&self, id: u32) -> bool {
+        match self.0.lock().get(&id) {
+            Some(End::Reset) | Some(End::FinishedThenReset) => true,
+            Some(End::Finished) | None => false
+        }
+    }
+
+    /// Forgets about a substream, once it has been destroyed.
+    pub(crate) fn remove(&self, id: u32) {
+        self.0.lock().remove(&id);
+    }
+
+    fn on_fin(&self, id: u32) {
+        self.0.lock().entry(id).or_insert(End::Finished);
+    }
+
+    fn on_rst(&self, id: u32) {
+        let mut ends = self.0.lock();
+        let end = ends.entry(id).or_insert(End::Reset);
+        if *end == End::Finished {
+            *end = End::FinishedThenReset
+        }
+    }
+}
+
+/// Wraps the socket of a connection and records the ends of its substreams in `Ends`.
+pub(crate) struct Observed<C> {
+    io: C,
+    ends: Arc<Ends>,
+    /// The header of the frame being read.
+    header: [u8; HEADER_SIZE],
+    /// Number of bytes of `header` read so far.
+    header_len: usize,
+    /// Number of bytes of the body of the current frame that are left to read.
+    body_len: usize
+}
+
+impl<C> Observed<C> {
+    pub(crate) fn new(io: C, ends: Arc<Ends>) -> Self {
+        Observed { io, ends, header: [0; HEADER_SIZE], header_len: 0, body_len: 0 }
+    }
+
+    /// Goes through bytes read from the socket.
+    fn observe(&mut self, mut buf: &[u8]) {
+        while !buf.is_empty() {
+            if self.body_len > 0 {
+                let n = cmp::min(self.body_len, buf.len());
+                self.body_len -= n;
+                buf = &buf[n ..];
+                continue
+            }
+            let n = cmp::min(HEADER_SIZE - self.header_len, buf.len());
+            self.header[self.header_len .. self.header_len + n].copy_from_slice(&buf[.. n]);
+            self.header_len += n;
+            buf = &buf[n ..];
+            if self.header_len == HEADER_SIZE {
+                self.header_len = 0;
+                self.on_header()
+            }
+        }
+    }
+
+    /// Processes the header of a frame, stored in `header`.
+    ///
+    /// A header which isn't valid makes the connection fail, so we don't need to care about
+    /// what comes after it.
+    fn on_header(&mut self) {
+        let h = &self.header;
+        let ty = h[1];
+        let flags = u16::from_be_bytes([h[2], h[3]]);
+        let id = u32::from_be_bytes([h[4], h[5], h[6], h[7]]);
+        let len = u32::from_be_bytes([h[8], h[9], h[10], h[11]]);
+        if ty != TYPE_DATA && ty != TYPE_WINDOW_UPDATE {
+            return
+        }
+        if ty == TYPE_DATA {
+            self.body_len = len as usize
+        }
+        if flags & FLAG_RST != 0 {
+            self.ends.on_rst(id)
+        } else if flags & FLAG_FIN != 0 {
+            self.ends.on_fin(id)
+        }
+    }
+}
+
+impl<C: AsyncRead + Unpin> AsyncRead for Observed<C> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let n = futures::ready!(Pin::new(&mut self.io).poll_read(cx, buf))?;
+        self.observe(&buf[.. n]);
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<C: AsyncWrite + Unpin> AsyncWrite for Observed<C> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_close(cx)
+    }
+}
@@ -22,11 +22,18 @@
 //! [specification](https://github.com/hashicorp/yamux/blob/master/spec.md).
 
 use futures::{future, prelude::*, ready, stream::{BoxStream, LocalBoxStream}};
+use libp2p_core::muxing::{StreamMuxerEvent, SubstreamTermination};
 use libp2p_core::upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo};
 use parking_lot::Mutex;
-use std::{fmt, io, iter, pin::Pin, task::Context};
+use std::{fmt, io, iter, pin::Pin, sync::Arc, task::Context};
 use thiserror::Error;
 
+pub use tuning::WindowTuning;
+pub use yamux::WindowUpdateMode;
+
+mod ends;
+mod tuning;
+
 /// A Yamux connection.
 pub struct Yamux<S>(Mutex<Inner<S>>);
 
@@ -42,7 +49,60 @@ struct Inner<S> {
     /// Handle to control the connection.
     control: yamux::Control,
     /// True, once we have received an inbound substream.
-    acknowledged: bool
+    acknowledged: bool,
+    /// If set, the receive window tuning to report the reads of the substreams to.
+    tuning: Option<WindowTuning>,
+    /// The receive window of this connection. Only meaningful if `tuning` is set.
+    receive_window: u32,
+    /// The ends of the substreams, as observed on the socket.
+    ends: Arc<ends::Ends>
+}
+
+/// A substream of a Yamux connection.
+pub struct Substream {
+    inner: yamux::Stream,
+    /// Observes the reads if the receive window is tuned automatically.
+    meter: Option<tuning::Meter>,
+    /// True, once we have closed our writing side.
+    local_closed: bool,
+    /// The ends of the substreams of the connection.
+    ends: Arc<ends::Ends>
+}
+
+impl fmt::Debug for Substream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Substream")
+    }
+}
+
+impl<S> Inner<S> {
+    fn new_substream(&self, inner: yamux::Stream) -> Substream {
+        let meter = self.tuning.clone().map(|t| tuning::Meter::new(t, self.receive_window));
+        Substream { inner, meter, local_closed: false, ends: self.ends.clone() }
+    }
+}
+
+impl<S> Yamux<S> {
+    /// Builds the error of a substream from an error produced by its `yamux::Stream`.
+    ///
+    /// The state of a `yamux::Stream` isn't exposed, and it reports an attempt to write to it
+    /// once it can no longer be written to with `WriteZero`, whatever the reason. We track
+    /// whether we have closed the writing side ourselves and whether the remote has reset the
+    /// substream, so that the remaining cause is the closing of the connection.
+    fn substream_error(&self, s: &Substream, e: io::Error) -> YamuxError {
+        if e.kind() != io::ErrorKind::WriteZero || SubstreamTermination::from_io_error(&e).is_some() {
+            return YamuxError(e.into())
+        }
+        let termination =
+            if s.local_closed {
+                SubstreamTermination::Closed
+            } else if s.ends.is_reset(s.inner.id().val()) {
+                SubstreamTermination::RemoteReset
+            } else {
+                SubstreamTermination::ConnectionClosed
+            };
+        YamuxError(yamux::ConnectionError::Io(termination.into()))
+    }
 }
 
 /// A token to poll for an outbound substream.
@@ -54,9 +114,23 @@ where
     C: AsyncRead + AsyncWrite + Send + Unpin + 'static
 {
     /// Create a new Yamux connection.
-    pub fn new(io: C, mut cfg: yamux::Config, mode: yamux::Mode) -> Self {
+    pub fn new(io: C, cfg: yamux::Config, mode: yamux::Mode) -> Self {
+        Self::with_tuning(io, cfg, mode, None, 0)
+    }
+
+    /// Create a new Yamux connection whose substreams report their reads to `tuning`, if any.
+    ///
+    /// `receive_window` must be the receive window set in `cfg`.
+    fn with_tuning(
+        io: C,
+        mut cfg: yamux::Config,
+        mode: yamux::Mode,
+        tuning: Option<WindowTuning>,
+        receive_window: u32
+    ) -> Self {
         cfg.set_read_after_close(false);
-        let conn = yamux::Connection::new(io, cfg, mode);
+        let ends = Arc::new(ends::Ends::default());
+        let conn = yamux::Connection::new(ends::Observed::new(io, ends.clone()), cfg, mode);
         let ctrl = conn.control();
         let inner = Inner {
             incoming: Incoming {
@@ -64,7 +138,10 @@ where
                 _marker: std::marker::PhantomData
             },
             control: ctrl,
-            acknowledged: false
+            acknowledged: false,
+            tuning,
+            receive_window,
+            ends
         };
         Yamux(Mutex::new(inner))
     }
@@ -75,9 +152,21 @@ where
     C: AsyncRead + AsyncWrite + Unpin + 'static
 {
     /// Create a new Yamux connection (which is ![`Send`]).
-    pub fn local(io: C, mut cfg: yamux::Config, mode: yamux::Mode) -> Self {
+    pub fn local(io: C, cfg: yamux::Config, mode: yamux::Mode) -> Self {
+        Self::local_with_tuning(io, cfg, mode, None, 0)
+    }
+
+    /// Like `with_tuning`, for connections which are ![`Send`].
+    fn local_with_tuning(
+        io: C,
+        mut cfg: yamux::Config,
+        mode: yamux::Mode,
+        tuning: Option<WindowTuning>,
+        receive_window: u32
+    ) -> Self {
         cfg.set_read_after_close(false);
-        let conn = yamux::Connection::new(io, cfg, mode);
+        let ends = Arc::new(ends::Ends::default());
+        let conn = yamux::Connection::new(ends::Observed::new(io, ends.clone()), cfg, mode);
         let ctrl = conn.control();
         let inner = Inner {
             incoming: LocalIncoming {
@@ -85,7 +174,10 @@ where
                 _marker: std::marker::PhantomData
             },
             control: ctrl,
-            acknowledged: false
+            acknowledged: false,
+            tuning,
+            receive_window,
+            ends
         };
         Yamux(Mutex::new(inner))
     }
@@ -97,16 +189,17 @@ impl<S> libp2p_core::StreamMuxer for Yamux<S>
 where
     S: Stream<Item = Result<yamux::Stream, YamuxError>> + Unpin
 {
-    type Substream = yamux::Stream;
+    type Substream = Substream;
     type OutboundSubstream = OpenSubstreamToken;
     type Error = YamuxError;
 
-    fn poll_inbound(&self, c: &mut Context) -> Poll<Self::Substream> {
+    fn poll_event(&self, c: &mut Context) -> Poll<StreamMuxerEvent<Self::Substream>> {
         let mut inner = self.0.lock();
         match ready!(inner.incoming.poll_next_unpin(c)) {
             Some(Ok(s)) => {
                 inner.acknowledged = true;
-                Poll::Ready(Ok(s))
+                let substream = inner.new_substream(s);
+                Poll::Ready(Ok(StreamMuxerEvent::InboundSubstream(substream)))
             }
             Some(Err(e)) => Poll::Ready(Err(e)),
             None => Poll::Ready(Err(yamux::ConnectionError::Closed.into()))
@@ -119,7 +212,10 @@ where
 
     fn poll_outbound(&self, c: &mut Context, _: &mut OpenSubstreamToken) -> Poll<Self::Substream> {
         let mut inner = self.0.lock();
-        Pin::new(&mut inner.control).poll_open_stream(c).map_err(YamuxError)
+        match ready!(Pin::new(&mut inner.control).poll_open_stream(c)) {
+            Ok(stream) => Poll::Ready(Ok(inner.new_substream(stream))),
+            Err(e) => Poll::Ready(Err(YamuxError(e)))
+        }
     }
 
     fn destroy_outbound(&self, _: Self::OutboundSubstream) {
@@ -127,30 +223,54 @@ where
     }
 
     fn read_substream(&self, c: &mut Context, s: &mut Self::Substream, b: &mut [u8]) -> Poll<usize> {
-        Pin::new(s).poll_read(c, b).map_err(|e| YamuxError(e.into()))
+        let result = Pin::new(&mut s.inner).poll_read(c, b);
+        if let Some(meter) = s.meter.as_mut() {
+            match result {
+                std::task::Poll::Ready(Ok(n)) if n > 0 => meter.on_read(n),
+                std::task::Poll::Pending => meter.on_pending(),
+                _ => {}
+            }
+        }
+        // A substream reset by the remote reads as EOF.
+        if let std::task::Poll::Ready(Ok(0)) = result {
+            if !b.is_empty() && s.ends.is_reset_before_finish(s.inner.id().val()) {
+                let e = SubstreamTermination::RemoteReset.into();
+                return Poll::Ready(Err(YamuxError(yamux::ConnectionError::Io(e))))
+            }
+        }
+        result.map_err(|e| self.substream_error(s, e))
     }
 
     fn write_substream(&self, c: &mut Context, s: &mut Self::Substream, b: &[u8]) -> Poll<usize> {
-        Pin::new(s).poll_write(c, b).map_err(|e| YamuxError(e.into()))
+        let result = Pin::new(&mut s.inner).poll_write(c, b);
+        result.map_err(|e| self.substream_error(s, e))
     }
 
     fn flush_substream(&self, c: &mut Context, s: &mut Self::Substream) -> Poll<()> {
-        Pin::new(s).poll_flush(c).map_err(|e| YamuxError(e.into()))
+        let result = Pin::new(&mut s.inner).poll_flush(c);
+        result.map_err(|e| self.substream_error(s, e))
     }
 
     fn shutdown_substream(&self, c: &mut Context, s: &mut Self::Substream) -> Poll<()> {
-        Pin::new(s).poll_close(c).map_err(|e| YamuxError(e.into()))
+        let result = Pin::new(&mut s.inner).poll_close(c);
+        if let std::task::Poll::Ready(Ok(())) = result {
+            s.local_closed = true;
+        }
+        result.map_err(|e| self.substream_error(s, e))
     }
 
-    fn destroy_substream(&self, _: Self::Substream) { }
+    fn destroy_substream(&self, s: Self::Substream) {
+        s.ends.remove(s.inner.id().val())
+    }
 
     fn is_remote_acknowledged(&self) -> bool {
         self.0.lock().acknowledged
     }
 
-    fn close(&self, c: &mut Context) -> Poll<()> {
+    fn poll_close(&self, c: &mut Context) -> Poll<()> {
         let mut inner = self.0.lock();
-        Pin::new(&mut inner.control).poll_close(c).map_err(YamuxError)
+        let result = ready!(Pin::new(&mut inner.control).poll_close(c));
+        std::task::Poll::Ready(result.map_err(YamuxError))
     }
 
     fn flush_all(&self, _: &mut Context) -> Poll<()> {
@@ -158,9 +278,17 @@ where
     }
 }
 
+/// Default maximum size of the receive buffer of a substream in `yamux::Config`.
+const DEFAULT_MAX_BUFFER_SIZE: usize = 1024 * 1024;
+
 /// The yamux configuration.
 #[derive(Clone)]
-pub struct Config(yamux::Config);
+pub struct Config {
+    inner: yamux::Config,
+    /// The value passed to `set_max_buffer_size`, if any.
+    max_buffer_size: Option<usize>,
+    tuning: Option<WindowTuning>
+}
 
 /// The yamux configuration for upgrading I/O resources which are ![`Send`].
 #[derive(Clone)]
@@ -168,18 +296,90 @@ pub struct LocalConfig(Config);
 
 impl Config {
     pub fn new(cfg: yamux::Config) -> Self {
-        Config(cfg)
+        Config { inner: cfg, max_buffer_size: None, tuning: None }
+    }
+
+    /// Sets the size (in bytes) of the receive window of each substream, which is the amount
+    /// of data the remote can send before waiting for us to acknowledge it.
+    ///
+    /// On links with a high latency, the throughput of a substream is capped at roughly one
+    /// receive window per round-trip.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_bytes` is smaller than 256 KiB, which Yamux doesn't accept.
+    pub fn set_receive_window(&mut self, num_bytes: u32) -> &mut Self {
+        self.inner.set_receive_window(num_bytes);
+        self
+    }
+
+    /// Sets the maximum size (in bytes) of the receive buffer of each substream.
+    ///
+    /// Must be at least as large as the receive window.
+    pub fn set_max_buffer_size(&mut self, num_bytes: usize) -> &mut Self {
+        self.inner.set_max_buffer_size(num_bytes);
+        self.max_buffer_size = Some(num_bytes);
+        self
+    }
+
+    /// Sets the maximum number of simultaneously open substreams.
+    pub fn set_max_num_streams(&mut self, num_streams: usize) -> &mut Self {
+        self.inner.set_max_num_streams(num_streams);
+        self
+    }
+
+    /// Sets when window updates are sent to the remote.
+    ///
+    /// See the documentation of `WindowUpdateMode`.
+    pub fn set_window_update_mode(&mut self, mode: WindowUpdateMode) -> &mut Self {
+        self.inner.set_window_update_mode(mode);
+        self
+    }
+
+    /// Enables the automatic tuning of the receive window.
+    ///
+    /// The reads on the substreams of the connections upgraded with this configuration are
+    /// observed in order to estimate the round-trip time and the throughput of the links, and
+    /// the receive window of new connections is grown accordingly, up to the maximum of the
+    /// `WindowTuning`. The receive buffer is grown along with the window. This overrides the
+    /// value passed to `set_receive_window`.
+    pub fn set_window_tuning(&mut self, tuning: WindowTuning) -> &mut Self {
+        self.tuning = Some(tuning);
+        self
+    }
+
+    /// Returns the receive window tuning, if enabled.
+    pub fn window_tuning(&self) -> Option<&WindowTuning> {
+        self.tuning.as_ref()
     }
 
     /// Turn this into a `LocalConfig` for use with upgrades of !Send resources.
     pub fn local(self) -> LocalConfig {
         LocalConfig(self)
     }
+
+    /// Returns the `yamux::Config` to use for a new connection, along with its receive window
+    /// if the receive window is tuned automatically (and 0 otherwise).
+    ///
+    /// The tuned receive window can change at any time, so it is read only once here and
+    /// both the window and the receive buffer of the connection are derived from that value.
+    fn connection_config(&self) -> (yamux::Config, u32) {
+        let mut cfg = self.inner.clone();
+        let mut receive_window = 0;
+        if let Some(ref tuning) = self.tuning {
+            receive_window = tuning.receive_window();
+            cfg.set_receive_window(receive_window);
+            if receive_window as usize > self.max_buffer_size.unwrap_or(DEFAULT_MAX_BUFFER_SIZE) {
+                cfg.set_max_buffer_size(receive_window as usize);
+            }
+        }
+        (cfg, receive_window)
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
-        Config(yamux::Config::default())
+        Config::new(yamux::Config::default())
     }
 }
 
@@ -210,7 +410,8 @@ where
     type Future = future::Ready<Result<Self::Output, Self::Error>>;
 
     fn upgrade_inbound(self, io: C, _: Self::Info) -> Self::Future {
-        future::ready(Ok(Yamux::new(io, self.0, yamux::Mode::Server)))
+        let (cfg, receive_window) = self.connection_config();
+        future::ready(Ok(Yamux::with_tuning(io, cfg, yamux::Mode::Server, self.tuning, receive_window)))
     }
 }
 
@@ -223,7 +424,8 @@ where
     type Future = future::Ready<Result<Self::Output, Self::Error>>;
 
     fn upgrade_inbound(self, io: C, _: Self::Info) -> Self::Future {
-        future::ready(Ok(Yamux::local(io, (self.0).0, yamux::Mode::Server)))
+        let (cfg, receive_window) = self.0.connection_config();
+        future::ready(Ok(Yamux::local_with_tuning(io, cfg, yamux::Mode::Server, self.0.tuning, receive_window)))
     }
 }
 
@@ -236,7 +438,8 @@ where
     type Future = future::Ready<Result<Self::Output, Self::Error>>;
 
     fn upgrade_outbound(self, io: C, _: Self::Info) -> Self::Future {
-        future::ready(Ok(Yamux::new(io, self.0, yamux::Mode::Client)))
+        let (cfg, receive_window) = self.connection_config();
+        future::ready(Ok(Yamux::with_tuning(io, cfg, yamux::Mode::Client, self.tuning, receive_window)))
     }
 }
 
@@ -249,7 +452,8 @@ where
     type Future = future::Ready<Result<Self::Output, Self::Error>>;
 
     fn upgrade_outbound(self, io: C, _: Self::Info) -> Self::Future {
-        future::ready(Ok(Yamux::local(io, (self.0).0, yamux::Mode::Client)))
+        let (cfg, receive_window) = self.0.connection_config();
+        future::ready(Ok(Yamux::local_with_tuning(io, cfg, yamux::Mode::Client, self.0.tuning, receive_window)))
     }
 }
 
@@ -260,7 +464,11 @@ pub struct YamuxError(#[from] pub yamux::ConnectionError);
 
 impl Into<io::Error> for YamuxError {
     fn into(self: YamuxError) -> io::Error {
-        io::Error::new(io::ErrorKind::Other, self.to_string())
+        match self.0 {
+            yamux::ConnectionError::Io(e) => e,
+            yamux::ConnectionError::Closed => SubstreamTermination::ConnectionClosed.into(),
+            e => io::Error::new(io::ErrorKind::Other, YamuxError(e).to_string())
+        }
     }
 }
 
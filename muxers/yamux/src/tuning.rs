@@ -0,0 +1,243 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Automatic tuning of the Yamux receive window.
+//!
+//! A Yamux sender can only have one receive window worth of data in flight per substream. On
+//! links whose bandwidth-delay product exceeds the window, the sender exhausts its credit, then
+//! stalls for about one round-trip until our window update arrives. We detect these stalls on
+//! the receiving side, estimate the round-trip time and the throughput of the link from them, and
+//! grow the receive window accordingly.
+
+use parking_lot::Mutex;
+use std::{cmp, fmt, sync::Arc, time::Duration};
+use wasm_timer::Instant;
+
+/// Stalls shorter than this are considered to be scheduling noise.
+const MIN_STALL: Duration = Duration::from_millis(1);
+
+/// Smallest receive window accepted by `yamux::Config::set_receive_window`.
+pub(crate) const MIN_WINDOW: u32 = 256 * 1024;
+
+/// Shared state of the receive window tuning.
+///
+/// Cloning a `WindowTuning` produces a handle to the same state, so that all the connections
+/// upgraded with the same `Config` contribute to, and benefit from, the same estimates.
+///
+/// > **Note**: The receive window of a Yamux connection is fixed when the connection is
+/// >           established. A larger window is therefore used by the connections established
+/// >           after the window has been grown.
+#[derive(Clone)]
+pub struct WindowTuning {
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    /// Largest receive window we are allowed to grow to.
+    max_window: u32,
+    /// Receive window to use for new connections.
+    window: u32,
+    /// Smoothed round-trip time estimate.
+    rtt: Option<Duration>,
+    /// Smoothed throughput estimate, in bytes per second.
+    throughput: Option<f64>,
+}
+
+impl WindowTuning {
+    /// Creates a new tuning state that starts with a receive window of `initial_window` bytes
+    /// and can grow it up to `max_window` bytes.
+    ///
+    /// Yamux doesn't accept receive windows smaller than 256 KiB, so both values are raised to
+    /// at least 256 KiB.
+    pub fn new(initial_window: u32, max_window: u32) -> Self {
+        let initial_window = cmp::max(initial_window, MIN_WINDOW);
+        WindowTuning {
+            state: Arc::new(Mutex::new(State {
+                max_window: cmp::max(initial_window, max_window),
+                window: initial_window,
+                rtt: None,
+                throughput: None,
+            })),
+        }
+    }
+
+    /// Returns the receive window, in bytes, to use for new connections.
+    pub fn receive_window(&self) -> u32 {
+        self.state.lock().window
+    }
+
+    /// Returns the current round-trip time estimate, if any.
+    pub fn rtt(&self) -> Option<Duration> {
+        self.state.lock().rtt
+    }
+
+    /// Returns the current throughput estimate in bytes per second, if any.
+    pub fn throughput(&self) -> Option<f64> {
+        self.state.lock().throughput
+    }
+
+    /// Records the observation of `bytes` bytes having been received during `burst`, followed by
+    /// a stall of `stall` on a substream whose receive window is `window`.
+    fn record(&self, bytes: usize, burst: Duration, stall: Duration, window: u32) {
+        let mut state = self.state.lock();
+
+        // If less than half of the window has been received, the sender was most likely
+        // idle rather than waiting for credit.
+        if bytes < window as usize / 2 {
+            return
+        }
+
+        // The sender has sent a window worth of data, then waited for our window update.
+        let rtt = burst + stall;
+        let rtt = match state.rtt {
+            Some(srtt) => (srtt * 7 + rtt) / 8,
+            None => rtt,
+        };
+        state.rtt = Some(rtt);
+
+        // The rate at which data arrived while the sender had credit approximates the
+        // throughput of the link.
+        let burst_secs = cmp::max(burst, MIN_STALL).as_secs_f64();
+        let rate = bytes as f64 / burst_secs;
+        let throughput = match state.throughput {
+            Some(t) => (t * 7.0 + rate) / 8.0,
+            None => rate,
+        };
+        state.throughput = Some(throughput);
+
+        // Leave room for twice the bandwidth-delay product, so that window updates have time to
+        // reach the sender before it runs out of credit.
+        let target = (2.0 * throughput * rtt.as_secs_f64()).min(f64::from(state.max_window)) as u32;
+        if target > state.window {
+            log::debug!("Growing yamux receive window from {} to {} bytes (rtt = {:?})",
+                state.window, target, rtt);
+            state.window = target;
+        }
+    }
+}
+
+impl fmt::Debug for WindowTuning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.state.lock();
+        f.debug_struct("WindowTuning")
+            .field("window", &state.window)
+            .field("max_window", &state.max_window)
+            .field("rtt", &state.rtt)
+            .field("throughput", &state.throughput)
+            .finish()
+    }
+}
+
+/// Observes the reads on a substream and reports stalls to a `WindowTuning`.
+pub(crate) struct Meter {
+    tuning: WindowTuning,
+    /// Receive window of the connection of the substream.
+    window: u32,
+    /// When the current burst of data started arriving.
+    burst_start: Option<Instant>,
+    /// Number of bytes received during the current burst.
+    burst_bytes: usize,
+    /// If `Some`, the last read returned `Pending` at this instant.
+    stalled_since: Option<Instant>,
+}
+
+impl Meter {
+    pub(crate) fn new(tuning: WindowTuning, window: u32) -> Self {
+        Meter {
+            tuning,
+            window,
+            burst_start: None,
+            burst_bytes: 0,
+            stalled_since: None,
+        }
+    }
+
+    /// Must be called when a read on the substream returned `num_bytes` bytes.
+    pub(crate) fn on_read(&mut self, num_bytes: usize) {
+        let now = Instant::now();
+
+        if let Some(stalled_since) = self.stalled_since.take() {
+            let stall = now.duration_since(stalled_since);
+            if stall >= MIN_STALL {
+                if let Some(burst_start) = self.burst_start.take() {
+                    let burst = stalled_since.duration_since(burst_start);
+                    self.tuning.record(self.burst_bytes, burst, stall, self.window);
+                }
+                self.burst_bytes = 0;
+            }
+        }
+
+        if self.burst_start.is_none() {
+            self.burst_start = Some(now);
+        }
+        self.burst_bytes += num_bytes;
+    }
+
+    /// Must be called when a read on the substream returned `Pending`.
+    pub(crate) fn on_pending(&mut self) {
+        if self.stalled_since.is_none() {
+            self.stalled_since = Some(Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_when_window_limited() {
+        let tuning = WindowTuning::new(256 * 1024, 16 * 1024 * 1024);
+        // A full window arrives in 10ms, then the sender waits 90ms for a window update.
+        tuning.record(256 * 1024, Duration::from_millis(10), Duration::from_millis(90), 256 * 1024);
+        assert_eq!(tuning.rtt(), Some(Duration::from_millis(100)));
+        assert!(tuning.receive_window() > 256 * 1024);
+        assert!(tuning.receive_window() <= 16 * 1024 * 1024);
+    }
+
+    #[test]
+    fn ignores_idle_sender() {
+        let tuning = WindowTuning::new(256 * 1024, 16 * 1024 * 1024);
+        tuning.record(1024, Duration::from_millis(1), Duration::from_secs(1), 256 * 1024);
+        assert_eq!(tuning.rtt(), None);
+        assert_eq!(tuning.receive_window(), 256 * 1024);
+    }
+
+    #[test]
+    fn never_exceeds_maximum() {
+        let tuning = WindowTuning::new(256 * 1024, 1024 * 1024);
+        for _ in 0 .. 10 {
+            tuning.record(256 * 1024, Duration::from_millis(1), Duration::from_millis(500), 256 * 1024);
+        }
+        assert_eq!(tuning.receive_window(), 1024 * 1024);
+    }
+
+    #[test]
+    fn small_windows_are_raised_to_minimum() {
+        let tuning = WindowTuning::new(1024, 64 * 1024);
+        assert_eq!(tuning.receive_window(), MIN_WINDOW);
+        for _ in 0 .. 10 {
+            tuning.record(256 * 1024, Duration::from_millis(1), Duration::from_millis(500), 256 * 1024);
+        }
+        assert_eq!(tuning.receive_window(), MIN_WINDOW);
+        // Would panic if the window were below what yamux accepts.
+        yamux::Config::default().set_receive_window(tuning.receive_window());
+    }
+}
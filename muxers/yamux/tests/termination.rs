@@ -0,0 +1,104 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use futures::{channel::oneshot, future, prelude::*};
+use libp2p_core::{
+    StreamMuxer, Transport,
+    multiaddr::Protocol,
+    muxing::{self, StreamMuxerEvent, SubstreamTermination},
+    transport::MemoryTransport,
+    upgrade,
+};
+use std::{io, sync::Arc, task::Poll, time::Duration};
+
+/// Keeps polling the connection of `muxer` in the background, which drives its substreams,
+/// until the connection is closed. The inbound substreams are kept open.
+fn drive<M: StreamMuxer + Send + Sync + 'static>(muxer: Arc<M>)
+where
+    M::Substream: Send
+{
+    let mut inbound = Vec::new();
+    async_std::task::spawn(future::poll_fn(move |cx| loop {
+        match muxer.poll_event(cx) {
+            Poll::Ready(Ok(StreamMuxerEvent::InboundSubstream(s))) => inbound.push(s),
+            Poll::Ready(Ok(_)) => {}
+            Poll::Ready(Err(_)) => return Poll::Ready(()),
+            Poll::Pending => return Poll::Pending,
+        }
+    }));
+}
+
+/// Writes to `substream` until it produces an error.
+async fn write_until_error<S: AsyncWrite + Unpin>(substream: &mut S) -> io::Error {
+    loop {
+        if let Err(err) = substream.write_all(b"ping").await {
+            return err
+        }
+        async_std::task::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[test]
+fn remote_reset_is_distinguished_from_close() {
+    let addr = Protocol::Memory(0).into();
+    let transport = MemoryTransport.and_then(|c, e|
+        upgrade::apply(c, libp2p_yamux::Config::default(), e, upgrade::Version::V1));
+
+    let (reset_tx, reset_rx) = oneshot::channel();
+
+    let mut listener = transport.clone().listen_on(addr).unwrap();
+    let addr = async_std::task::block_on(listener.next()).unwrap().unwrap().into_new_address().unwrap();
+
+    let bg_thread = async_std::task::spawn(async move {
+        let muxer = listener.next().await.unwrap().unwrap().into_upgrade().unwrap().0.await.unwrap();
+        let muxer = Arc::new(muxer);
+
+        // Dropping a substream that is still open resets it.
+        let reset = muxing::inbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+        drop(reset);
+        let mut finished = muxing::inbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+        finished.close().await.unwrap();
+        drive(muxer);
+        reset_tx.send(()).unwrap();
+    });
+
+    async_std::task::block_on(async move {
+        let muxer = Arc::new(transport.dial(addr).unwrap().await.unwrap());
+
+        drive(muxer.clone());
+        let mut reset = muxing::outbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+        let mut finished = muxing::outbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+        let mut closed = muxing::outbound_from_ref_and_wrap(muxer.clone()).await.unwrap();
+
+        reset_rx.await.unwrap();
+        let err = reset.read(&mut [0; 4]).await.unwrap_err();
+        assert_eq!(SubstreamTermination::from_io_error(&err), Some(SubstreamTermination::RemoteReset));
+        assert_eq!(finished.read(&mut [0; 4]).await.unwrap(), 0);
+
+        let err = write_until_error(&mut reset).await;
+        assert_eq!(SubstreamTermination::from_io_error(&err), Some(SubstreamTermination::RemoteReset));
+
+        closed.close().await.unwrap();
+        let err = closed.write_all(b"hello").await.unwrap_err();
+        assert_eq!(SubstreamTermination::from_io_error(&err), Some(SubstreamTermination::Closed));
+
+        bg_thread.await;
+    });
+}
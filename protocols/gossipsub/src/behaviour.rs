@@ -23,11 +23,11 @@ use crate::handler::GossipsubHandler;
 use crate::mcache::MessageCache;
 use crate::protocol::{
     GossipsubControlAction, GossipsubMessage, GossipsubSubscription, GossipsubSubscriptionAction,
-    MessageId,
+    MessageId, PeerInfo,
 };
 use crate::topic::{Topic, TopicHash};
 use futures::prelude::*;
-use libp2p_core::{ConnectedPoint, Multiaddr, PeerId};
+use libp2p_core::{ConnectedPoint, Multiaddr, PeerId, PeerRecord};
 use libp2p_swarm::{NetworkBehaviour, NetworkBehaviourAction, PollParameters, ProtocolsHandler};
 use log::{debug, error, info, trace, warn};
 use lru::LruCache;
@@ -85,6 +85,13 @@ pub struct Gossipsub<TSubstream> {
     /// Heartbeat interval stream.
     heartbeat: Interval,
 
+    /// Signed records of peers, used to dial them and passed on to other peers with peer
+    /// exchange. Records are forgotten when the peer disconnects or can't be dialed.
+    peer_records: HashMap<PeerId, PeerRecord>,
+
+    /// Peers whose PRUNE messages we accept exchanged peers from.
+    px_peers: HashSet<PeerId>,
+
     /// Marker to pin the generics.
     marker: PhantomData<TSubstream>,
 }
@@ -118,10 +125,35 @@ impl<TSubstream> Gossipsub<TSubstream> {
                 Instant::now() + gs_config.heartbeat_initial_delay,
                 gs_config.heartbeat_interval,
             ),
+            peer_records: HashMap::new(),
+            px_peers: HashSet::new(),
             marker: PhantomData,
         }
     }
 
+    /// Adds the signed record of a peer, for example one received through identify.
+    ///
+    /// The addresses of the record are used to dial the peer, and the record is passed on to
+    /// other peers with peer exchange, until the peer disconnects or can't be dialed. Returns
+    /// false if a record with the same or a higher sequence number is already known.
+    pub fn add_peer_record(&mut self, record: PeerRecord) -> bool {
+        if let Some(known) = self.peer_records.get(record.peer_id()) {
+            if known.seq() >= record.seq() {
+                return false;
+            }
+        }
+        self.peer_records.insert(record.peer_id().clone(), record);
+        true
+    }
+
+    /// Accepts the peers exchanged in the PRUNE messages of `peer_id`, if peer exchange is
+    /// enabled.
+    ///
+    /// As the exchanged peers are dialed, peer exchange is only accepted from trusted peers.
+    pub fn accept_px_from(&mut self, peer_id: PeerId) {
+        self.px_peers.insert(peer_id);
+    }
+
     /// Subscribe to a topic.
     ///
     /// Returns true if the subscription worked. Returns false if we were already subscribed.
@@ -397,13 +429,8 @@ impl<TSubstream> Gossipsub<TSubstream> {
             for peer in peers {
                 // Send a PRUNE control message
                 info!("LEAVE: Sending PRUNE to peer: {:?}", peer);
-                Self::control_pool_add(
-                    &mut self.control_pool,
-                    peer.clone(),
-                    GossipsubControlAction::Prune {
-                        topic_hash: topic_hash.clone(),
-                    },
-                );
+                let prune = self.make_prune(topic_hash, &peer);
+                Self::control_pool_add(&mut self.control_pool, peer.clone(), prune);
             }
         }
         debug!("Completed LEAVE for topic: {:?}", topic_hash);
@@ -504,9 +531,7 @@ impl<TSubstream> Gossipsub<TSubstream> {
             // build the prune messages to send
             let prune_messages = to_prune_topics
                 .iter()
-                .map(|t| GossipsubControlAction::Prune {
-                    topic_hash: t.clone(),
-                })
+                .map(|t| self.make_prune(t, peer_id))
                 .collect();
             // Send the prune messages to the peer
             info!(
@@ -525,10 +550,11 @@ impl<TSubstream> Gossipsub<TSubstream> {
         debug!("Completed GRAFT handling for peer: {:?}", peer_id);
     }
 
-    /// Handles PRUNE control messages. Removes peer from the mesh.
-    fn handle_prune(&mut self, peer_id: &PeerId, topics: Vec<TopicHash>) {
+    /// Handles PRUNE control messages. Removes peer from the mesh, and connects to the exchanged
+    /// peers if peer exchange is enabled and accepted from the peer.
+    fn handle_prune(&mut self, peer_id: &PeerId, topics: Vec<(TopicHash, Vec<PeerInfo>)>) {
         debug!("Handling PRUNE message for peer: {:?}", peer_id);
-        for topic_hash in topics {
+        for (topic_hash, px) in topics {
             if let Some(peers) = self.mesh.get_mut(&topic_hash) {
                 // remove the peer if it exists in the mesh
                 info!(
@@ -537,10 +563,58 @@ impl<TSubstream> Gossipsub<TSubstream> {
                 );
                 peers.retain(|p| p != peer_id);
             }
+            if self.config.do_px && !px.is_empty() {
+                if self.px_peers.contains(peer_id) {
+                    self.px_connect(px);
+                } else {
+                    debug!("PRUNE: Ignoring exchanged peers of untrusted peer: {:?}", peer_id);
+                }
+            }
         }
         debug!("Completed PRUNE handling for peer: {:?}", peer_id);
     }
 
+    /// Connects to the peers received in a PRUNE message we aren't connected to yet.
+    fn px_connect(&mut self, px: Vec<PeerInfo>) {
+        for info in px.into_iter().take(self.config.prune_peers) {
+            if info.peer_id == self.local_peer_id || self.peer_topics.contains_key(&info.peer_id) {
+                continue;
+            }
+            if let Some(record) = info.signed_peer_record {
+                self.add_peer_record(record);
+            }
+            debug!("PRUNE: Connecting to exchanged peer: {:?}", info.peer_id);
+            self.events.push_back(NetworkBehaviourAction::DialPeer {
+                peer_id: info.peer_id,
+            });
+        }
+    }
+
+    /// Builds a PRUNE message for `peer` in `topic_hash`. With peer exchange enabled, the
+    /// message carries other peers of the topic.
+    fn make_prune(&self, topic_hash: &TopicHash, peer: &PeerId) -> GossipsubControlAction {
+        let mut peers = Vec::new();
+        if self.config.do_px {
+            if let Some(topic_peers) = self.topic_peers.get(topic_hash) {
+                let mut candidates: Vec<&PeerId> =
+                    topic_peers.iter().filter(|p| *p != peer).collect();
+                candidates.shuffle(&mut thread_rng());
+                peers = candidates
+                    .into_iter()
+                    .take(self.config.prune_peers)
+                    .map(|p| PeerInfo {
+                        peer_id: p.clone(),
+                        signed_peer_record: self.peer_records.get(p).cloned(),
+                    })
+                    .collect();
+            }
+        }
+        GossipsubControlAction::Prune {
+            topic_hash: topic_hash.clone(),
+            peers,
+        }
+    }
+
     /// Handles a newly received GossipsubMessage.
     /// Forwards the message to all peers in the mesh.
     fn handle_received_message(&mut self, msg: GossipsubMessage, propagation_source: &PeerId) {
@@ -849,9 +923,7 @@ impl<TSubstream> Gossipsub<TSubstream> {
                 .remove(&peer)
                 .unwrap_or_else(|| vec![])
                 .iter()
-                .map(|topic_hash| GossipsubControlAction::Prune {
-                    topic_hash: topic_hash.clone(),
-                })
+                .map(|topic_hash| self.make_prune(topic_hash, peer))
                 .collect();
             grafts.append(&mut prunes);
 
@@ -870,9 +942,7 @@ impl<TSubstream> Gossipsub<TSubstream> {
         for (peer, topics) in to_prune.iter() {
             let remaining_prunes = topics
                 .iter()
-                .map(|topic_hash| GossipsubControlAction::Prune {
-                    topic_hash: topic_hash.clone(),
-                })
+                .map(|topic_hash| self.make_prune(topic_hash, peer))
                 .collect();
             self.events.push_back(NetworkBehaviourAction::SendEvent {
                 peer_id: peer.clone(),
@@ -1001,8 +1071,11 @@ where
         )
     }
 
-    fn addresses_of_peer(&mut self, _: &PeerId) -> Vec<Multiaddr> {
-        Vec::new()
+    fn addresses_of_peer(&mut self, peer_id: &PeerId) -> Vec<Multiaddr> {
+        self.peer_records
+            .get(peer_id)
+            .map(|record| record.addresses().to_vec())
+            .unwrap_or_default()
     }
 
     fn inject_connected(&mut self, id: PeerId, _: ConnectedPoint) {
@@ -1035,6 +1108,7 @@ where
     fn inject_disconnected(&mut self, id: &PeerId, _: ConnectedPoint) {
         // remove from mesh, topic_peers, peer_topic and fanout
         debug!("Peer disconnected: {:?}", id);
+        self.peer_records.remove(id);
         {
             let topics = match self.peer_topics.get(&id) {
                 Some(topics) => (topics),
@@ -1082,6 +1156,10 @@ where
         debug_assert!(was_in.is_some());
     }
 
+    fn inject_dial_failure(&mut self, id: &PeerId) {
+        self.peer_records.remove(id);
+    }
+
     fn inject_node_event(&mut self, propagation_source: PeerId, event: GossipsubRpc) {
         // Handle subscriptions
         // Update connected peers topics
@@ -1109,7 +1187,9 @@ where
                     self.handle_iwant(&propagation_source, message_ids)
                 }
                 GossipsubControlAction::Graft { topic_hash } => graft_msgs.push(topic_hash),
-                GossipsubControlAction::Prune { topic_hash } => prune_msgs.push(topic_hash),
+                GossipsubControlAction::Prune { topic_hash, peers } => {
+                    prune_msgs.push((topic_hash, peers))
+                }
             }
         }
         if !ihave_msgs.is_empty() {
@@ -24,6 +24,7 @@
 #[cfg(test)]
 mod tests {
     use super::super::*;
+    use crate::GossipsubConfigBuilder;
     use async_std::net::TcpStream;
 
     // helper functions for testing
@@ -855,10 +856,134 @@ mod tests {
             "Expected peer to be in mesh"
         );
 
-        gs.handle_prune(&peers[7], topic_hashes.clone());
+        gs.handle_prune(
+            &peers[7],
+            topic_hashes.iter().map(|t| (t.clone(), vec![])).collect(),
+        );
         assert!(
             !gs.mesh.get(&topic_hashes[0]).unwrap().contains(&peers[7]),
             "Expected peer to be removed from mesh"
         );
     }
+
+    #[test]
+    // tests that PRUNE messages carry other peers of the topic when peer exchange is enabled
+    fn test_prune_with_px() {
+        let gs_config = GossipsubConfigBuilder::new().do_px().prune_peers(5).build();
+        let mut gs: Gossipsub<TcpStream> = Gossipsub::new(PeerId::random(), gs_config);
+
+        let topic_hash = Topic::new(String::from("topic1")).no_hash();
+        let peers: Vec<_> = (0..10).map(|_| PeerId::random()).collect();
+        gs.topic_peers.insert(topic_hash.clone(), peers.clone());
+
+        let key = libp2p_core::identity::Keypair::generate_ed25519();
+        let record =
+            PeerRecord::new(&key, vec!["/ip4/1.2.3.4/tcp/5".parse().unwrap()]).unwrap();
+        gs.topic_peers
+            .get_mut(&topic_hash)
+            .unwrap()
+            .push(record.peer_id().clone());
+        assert!(gs.add_peer_record(record.clone()));
+
+        // we are not subscribed, so a GRAFT is answered with a PRUNE
+        gs.handle_graft(&peers[0], vec![topic_hash.clone()]);
+
+        let exchanged = gs
+            .events
+            .iter()
+            .filter_map(|e| match e {
+                NetworkBehaviourAction::SendEvent { peer_id, event } if peer_id == &peers[0] => {
+                    Some(event.control_msgs.clone())
+                }
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|c| match c {
+                GossipsubControlAction::Prune { peers, .. } => Some(peers),
+                _ => None,
+            })
+            .next()
+            .expect("Expected a PRUNE message");
+
+        assert_eq!(exchanged.len(), 5, "Expected prune_peers peers in the PRUNE");
+        assert!(exchanged.iter().all(|info| info.peer_id != peers[0]));
+        for info in &exchanged {
+            if &info.peer_id == record.peer_id() {
+                assert_eq!(info.signed_peer_record.as_ref(), Some(&record));
+            } else {
+                assert!(info.signed_peer_record.is_none());
+            }
+        }
+    }
+
+    #[test]
+    // tests that exchanged peers are dialed with the addresses of their records
+    fn test_handle_prune_with_px() {
+        let gs_config = GossipsubConfigBuilder::new().do_px().build();
+        let mut gs: Gossipsub<TcpStream> = Gossipsub::new(PeerId::random(), gs_config);
+        let topic_hash = Topic::new(String::from("topic1")).no_hash();
+
+        let key = libp2p_core::identity::Keypair::generate_ed25519();
+        let addr: Multiaddr = "/ip4/1.2.3.4/tcp/5".parse().unwrap();
+        let record = PeerRecord::new(&key, vec![addr.clone()]).unwrap();
+        let exchanged = record.peer_id().clone();
+        let prune = vec![(
+            topic_hash,
+            vec![PeerInfo {
+                peer_id: exchanged.clone(),
+                signed_peer_record: Some(record),
+            }],
+        )];
+
+        // peer exchange is ignored unless the sender is trusted
+        let sender = PeerId::random();
+        gs.handle_prune(&sender, prune.clone());
+        assert!(gs.events.is_empty(), "Expected untrusted peer exchange to be ignored");
+        assert!(gs.peer_records.is_empty());
+
+        gs.accept_px_from(sender.clone());
+        gs.handle_prune(&sender, prune);
+
+        assert!(
+            gs.events.iter().any(|e| match e {
+                NetworkBehaviourAction::DialPeer { peer_id } => peer_id == &exchanged,
+                _ => false,
+            }),
+            "Expected the exchanged peer to be dialed"
+        );
+        assert_eq!(
+            <Gossipsub<TcpStream> as NetworkBehaviour>::addresses_of_peer(&mut gs, &exchanged),
+            vec![addr]
+        );
+
+        // the record is forgotten if the peer can't be dialed
+        <Gossipsub<TcpStream> as NetworkBehaviour>::inject_dial_failure(&mut gs, &exchanged);
+        assert!(gs.peer_records.is_empty());
+    }
+
+    #[test]
+    // tests that the records of peers are forgotten when they disconnect
+    fn test_peer_records_removed_on_disconnect() {
+        let mut gs: Gossipsub<TcpStream> = Gossipsub::new(PeerId::random(), GossipsubConfig::default());
+
+        let key = libp2p_core::identity::Keypair::generate_ed25519();
+        let record = PeerRecord::new(&key, vec!["/ip4/1.2.3.4/tcp/5".parse().unwrap()]).unwrap();
+        let peer = record.peer_id().clone();
+        assert!(gs.add_peer_record(record));
+
+        let dummy_connected_point = ConnectedPoint::Dialer {
+            address: "/ip4/0.0.0.0/tcp/0".parse().unwrap(),
+        };
+        <Gossipsub<TcpStream> as NetworkBehaviour>::inject_connected(
+            &mut gs,
+            peer.clone(),
+            dummy_connected_point.clone(),
+        );
+        <Gossipsub<TcpStream> as NetworkBehaviour>::inject_disconnected(
+            &mut gs,
+            &peer,
+            dummy_connected_point,
+        );
+        assert!(gs.peer_records.is_empty());
+    }
 }
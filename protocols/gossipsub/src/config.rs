@@ -85,6 +85,14 @@ pub struct GossipsubConfig {
     /// The function takes a `GossipsubMessage` as input and outputs a String to be interpreted as
     /// the message id.
     pub message_id_fn: fn(&GossipsubMessage) -> MessageId,
+
+    /// When set, PRUNE messages carry other peers of the topic, and the peers received in PRUNE
+    /// messages of the peers passed to `Gossipsub::accept_px_from` are dialed (peer exchange,
+    /// default is false).
+    pub do_px: bool,
+
+    /// Maximum number of peers to exchange in a PRUNE message (default is 16).
+    pub prune_peers: usize,
 }
 
 impl Default for GossipsubConfig {
@@ -110,6 +118,8 @@ impl Default for GossipsubConfig {
                 source_string.push_str(&message.sequence_number.to_string());
                 MessageId(source_string)
             },
+            do_px: false,
+            prune_peers: 16,
         }
     }
 }
@@ -224,6 +234,16 @@ impl GossipsubConfigBuilder {
         self
     }
 
+    pub fn do_px(&mut self) -> &mut Self {
+        self.config.do_px = true;
+        self
+    }
+
+    pub fn prune_peers(&mut self, prune_peers: usize) -> &mut Self {
+        self.config.prune_peers = prune_peers;
+        self
+    }
+
     pub fn build(&self) -> GossipsubConfig {
         self.config.clone()
     }
@@ -246,6 +266,8 @@ impl std::fmt::Debug for GossipsubConfig {
         let _ = builder.field("hash_topics", &self.hash_topics);
         let _ = builder.field("no_source_id", &self.no_source_id);
         let _ = builder.field("manual_propagation", &self.manual_propagation);
+        let _ = builder.field("do_px", &self.do_px);
+        let _ = builder.field("prune_peers", &self.prune_peers);
         builder.finish()
     }
 }
@@ -27,9 +27,10 @@ use bytes::BytesMut;
 use futures::future;
 use futures::prelude::*;
 use futures_codec::{Decoder, Encoder, Framed};
-use libp2p_core::{InboundUpgrade, OutboundUpgrade, PeerId, UpgradeInfo};
+use libp2p_core::{InboundUpgrade, OutboundUpgrade, PeerId, PeerRecord, SignedEnvelope, UpgradeInfo};
+use log::debug;
 use prost::Message as ProtobufMessage;
-use std::{borrow::Cow, io, iter, pin::Pin};
+use std::{borrow::Cow, hash::{Hash, Hasher}, io, iter, pin::Pin};
 use unsigned_varint::codec;
 
 /// Implementation of the `ConnectionUpgrade` for the Gossipsub protocol.
@@ -180,9 +181,18 @@ impl Encoder for GossipsubCodec {
                     };
                     control.graft.push(rpc_graft);
                 }
-                GossipsubControlAction::Prune { topic_hash } => {
+                GossipsubControlAction::Prune { topic_hash, peers } => {
                     let rpc_prune = rpc_proto::ControlPrune {
                         topic_id: Some(topic_hash.into_string()),
+                        peers: peers
+                            .into_iter()
+                            .map(|info| rpc_proto::PeerInfo {
+                                peer_id: Some(info.peer_id.into_bytes()),
+                                signed_peer_record: info.signed_peer_record.map(|record| {
+                                    record.into_signed_envelope().into_protobuf_encoding()
+                                }),
+                            })
+                            .collect(),
                     };
                     control.prune.push(rpc_prune);
                 }
@@ -291,6 +301,11 @@ impl Decoder for GossipsubCodec {
                 .into_iter()
                 .map(|prune| GossipsubControlAction::Prune {
                     topic_hash: TopicHash::from_raw(prune.topic_id.unwrap_or_default()),
+                    peers: prune
+                        .peers
+                        .into_iter()
+                        .filter_map(PeerInfo::from_rpc)
+                        .collect(),
                 })
                 .collect();
 
@@ -395,5 +410,65 @@ pub enum GossipsubControlAction {
     Prune {
         /// The mesh topic the peer should be removed from.
         topic_hash: TopicHash,
+        /// Other peers of the topic the pruned peer can connect to (peer exchange).
+        peers: Vec<PeerInfo>,
     },
 }
+
+/// A peer of a topic, sent in a PRUNE message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerInfo {
+    /// The id of the peer.
+    pub peer_id: PeerId,
+    /// The addresses of the peer, signed by the peer. Only contains records that have been
+    /// verified to be signed by `peer_id`.
+    pub signed_peer_record: Option<PeerRecord>,
+}
+
+impl PeerInfo {
+    /// Decodes a `PeerInfo`, discarding invalid peer ids and records.
+    fn from_rpc(info: rpc_proto::PeerInfo) -> Option<PeerInfo> {
+        let peer_id = match PeerId::from_bytes(info.peer_id.unwrap_or_default()) {
+            Ok(peer_id) => peer_id,
+            Err(_) => {
+                debug!("PRUNE: Ignoring peer with invalid peer id");
+                return None;
+            }
+        };
+
+        let signed_peer_record = info.signed_peer_record.and_then(|bytes| {
+            let record = SignedEnvelope::from_protobuf_encoding(&bytes)
+                .map_err(|err| err.to_string())
+                .and_then(|envelope| {
+                    PeerRecord::from_signed_envelope(envelope).map_err(|err| err.to_string())
+                });
+            match record {
+                Ok(record) if record.peer_id() == &peer_id => Some(record),
+                Ok(_) => {
+                    debug!("PRUNE: Ignoring record not matching peer: {:?}", peer_id);
+                    None
+                }
+                Err(err) => {
+                    debug!("PRUNE: Ignoring invalid record of peer: {:?}: {}", peer_id, err);
+                    None
+                }
+            }
+        });
+
+        Some(PeerInfo {
+            peer_id,
+            signed_peer_record,
+        })
+    }
+}
+
+// Records don't implement `Hash`; the sequence number identifies them for a given peer.
+impl Hash for PeerInfo {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.peer_id.hash(state);
+        self.signed_peer_record
+            .as_ref()
+            .map(|record| record.seq())
+            .hash(state);
+    }
+}
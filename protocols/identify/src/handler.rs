@@ -18,13 +18,24 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::protocol::{RemoteInfo, IdentifyProtocolConfig, ReplySubstream};
+use crate::protocol::{
+    IdentifyProtocolConfig,
+    IdentifyPush,
+    IdentifyPushProtocolConfig,
+    RemoteInfo,
+    ReplySubstream
+};
 use futures::prelude::*;
-use libp2p_core::upgrade::{
-    InboundUpgrade,
-    OutboundUpgrade,
-    ReadOneError,
-    Negotiated
+use libp2p_core::{
+    either::{EitherError, EitherOutput},
+    upgrade::{
+        EitherUpgrade,
+        InboundUpgrade,
+        OutboundUpgrade,
+        ReadOneError,
+        Negotiated,
+        SelectUpgrade
+    }
 };
 use libp2p_swarm::{
     KeepAlive,
@@ -34,7 +45,7 @@ use libp2p_swarm::{
     ProtocolsHandlerUpgrErr
 };
 use smallvec::SmallVec;
-use std::{marker::PhantomData, pin::Pin, task::Context, task::Poll, time::Duration};
+use std::{collections::VecDeque, marker::PhantomData, pin::Pin, task::Context, task::Poll, time::Duration};
 use wasm_timer::Delay;
 
 /// Delay between the moment we connect and the first time we identify.
@@ -49,6 +60,10 @@ const TRY_AGAIN_ON_ERR: Duration = Duration::from_secs(60 * 60);
 /// Outbound requests are sent periodically. The handler performs expects
 /// at least one identification request to be answered by the remote before
 /// permitting the underlying connection to be closed.
+///
+/// The information of the local node is pushed to the remote whenever the
+/// handler receives an `IdentifyPush`, and the remote is allowed to push its
+/// own information at any time.
 pub struct IdentifyHandler<TSubstream> {
     /// Configuration for the protocol.
     config: IdentifyProtocolConfig,
@@ -62,6 +77,13 @@ pub struct IdentifyHandler<TSubstream> {
     /// Whether the handler should keep the connection alive.
     keep_alive: KeepAlive,
 
+    /// Pushes for which an outbound substream has yet to be requested.
+    pending_pushes: VecDeque<IdentifyPush>,
+
+    /// Number of pushes being sent. The connection is kept alive until all
+    /// the pushes have been sent.
+    pushes_in_flight: usize,
+
     /// Marker for strong typing.
     marker: PhantomData<TSubstream>,
 }
@@ -69,14 +91,26 @@ pub struct IdentifyHandler<TSubstream> {
 /// Event produced by the `IdentifyHandler`.
 #[derive(Debug)]
 pub enum IdentifyHandlerEvent<TSubstream> {
-    /// We obtained identification information from the remote
+    /// We obtained identification information from the remote, either as an
+    /// answer to a request or as a push.
     Identified(RemoteInfo),
+    /// We pushed our identification information to the remote.
+    Pushed,
     /// We received a request for identification.
     Identify(ReplySubstream<Negotiated<TSubstream>>),
-    /// Failed to identify the remote.
+    /// Failed to identify the remote or to push our information to it.
     IdentificationError(ProtocolsHandlerUpgrErr<ReadOneError>),
 }
 
+/// Kind of outbound substream opened by the `IdentifyHandler`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdentifyOutbound {
+    /// Request for the information of the remote.
+    Request,
+    /// Push of the information of the local node.
+    Push,
+}
+
 impl<TSubstream> IdentifyHandler<TSubstream> {
     /// Creates a new `IdentifyHandler`.
     pub fn new() -> Self {
@@ -85,6 +119,8 @@ impl<TSubstream> IdentifyHandler<TSubstream> {
             events: SmallVec::new(),
             next_id: Delay::new(DELAY_TO_FIRST_ID),
             keep_alive: KeepAlive::Yes,
+            pending_pushes: VecDeque::new(),
+            pushes_in_flight: 0,
             marker: PhantomData,
         }
     }
@@ -94,23 +130,28 @@ impl<TSubstream> ProtocolsHandler for IdentifyHandler<TSubstream>
 where
     TSubstream: AsyncRead + AsyncWrite + Unpin + Send + 'static,
 {
-    type InEvent = ();
+    type InEvent = IdentifyPush;
     type OutEvent = IdentifyHandlerEvent<TSubstream>;
     type Error = ReadOneError;
     type Substream = TSubstream;
-    type InboundProtocol = IdentifyProtocolConfig;
-    type OutboundProtocol = IdentifyProtocolConfig;
-    type OutboundOpenInfo = ();
+    type InboundProtocol = SelectUpgrade<IdentifyProtocolConfig, IdentifyPushProtocolConfig>;
+    type OutboundProtocol = EitherUpgrade<IdentifyProtocolConfig, IdentifyPush>;
+    type OutboundOpenInfo = IdentifyOutbound;
 
     fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol> {
-        SubstreamProtocol::new(self.config.clone())
+        SubstreamProtocol::new(SelectUpgrade::new(self.config.clone(), IdentifyPushProtocolConfig))
     }
 
     fn inject_fully_negotiated_inbound(
         &mut self,
         protocol: <Self::InboundProtocol as InboundUpgrade<Negotiated<TSubstream>>>::Output
     ) {
-        self.events.push(IdentifyHandlerEvent::Identify(protocol))
+        match protocol {
+            EitherOutput::First(sender) =>
+                self.events.push(IdentifyHandlerEvent::Identify(sender)),
+            EitherOutput::Second(remote) =>
+                self.events.push(IdentifyHandlerEvent::Identified(remote)),
+        }
     }
 
     fn inject_fully_negotiated_outbound(
@@ -118,26 +159,54 @@ where
         protocol: <Self::OutboundProtocol as OutboundUpgrade<Negotiated<TSubstream>>>::Output,
         _info: Self::OutboundOpenInfo,
     ) {
-        self.events.push(IdentifyHandlerEvent::Identified(protocol));
-        self.keep_alive = KeepAlive::No;
+        match protocol {
+            EitherOutput::First(remote) => {
+                self.events.push(IdentifyHandlerEvent::Identified(remote));
+                self.keep_alive = KeepAlive::No;
+            }
+            EitherOutput::Second(()) => {
+                self.events.push(IdentifyHandlerEvent::Pushed);
+                self.pushes_in_flight -= 1;
+            }
+        }
     }
 
-    fn inject_event(&mut self, _: Self::InEvent) {}
+    fn inject_event(&mut self, push: Self::InEvent) {
+        self.pending_pushes.push_back(push);
+    }
 
     fn inject_dial_upgrade_error(
         &mut self,
-        _info: Self::OutboundOpenInfo,
+        info: Self::OutboundOpenInfo,
         err: ProtocolsHandlerUpgrErr<
             <Self::OutboundProtocol as OutboundUpgrade<Self::Substream>>::Error
         >
     ) {
+        let err = match err {
+            ProtocolsHandlerUpgrErr::Timeout => ProtocolsHandlerUpgrErr::Timeout,
+            ProtocolsHandlerUpgrErr::Timer => ProtocolsHandlerUpgrErr::Timer,
+            ProtocolsHandlerUpgrErr::Upgrade(err) =>
+                ProtocolsHandlerUpgrErr::Upgrade(err.map_err(|err| match err {
+                    EitherError::A(err) | EitherError::B(err) => err,
+                })),
+        };
         self.events.push(IdentifyHandlerEvent::IdentificationError(err));
-        self.keep_alive = KeepAlive::No;
-        self.next_id.reset(TRY_AGAIN_ON_ERR);
+
+        match info {
+            IdentifyOutbound::Request => {
+                self.keep_alive = KeepAlive::No;
+                self.next_id.reset(TRY_AGAIN_ON_ERR);
+            }
+            IdentifyOutbound::Push => self.pushes_in_flight -= 1,
+        }
     }
 
     fn connection_keep_alive(&self) -> KeepAlive {
-        self.keep_alive
+        if !self.pending_pushes.is_empty() || self.pushes_in_flight > 0 {
+            KeepAlive::Yes
+        } else {
+            self.keep_alive
+        }
     }
 
     fn poll(&mut self, cx: &mut Context) -> Poll<
@@ -154,14 +223,22 @@ where
             ));
         }
 
+        if let Some(push) = self.pending_pushes.pop_front() {
+            self.pushes_in_flight += 1;
+            return Poll::Ready(ProtocolsHandlerEvent::OutboundSubstreamRequest {
+                protocol: SubstreamProtocol::new(EitherUpgrade::B(push)),
+                info: IdentifyOutbound::Push,
+            });
+        }
+
         // Poll the future that fires when we need to identify the node again.
         match Future::poll(Pin::new(&mut self.next_id), cx) {
             Poll::Pending => Poll::Pending,
             Poll::Ready(Ok(())) => {
                 self.next_id.reset(DELAY_TO_NEXT_ID);
                 let ev = ProtocolsHandlerEvent::OutboundSubstreamRequest {
-                    protocol: SubstreamProtocol::new(self.config.clone()),
-                    info: (),
+                    protocol: SubstreamProtocol::new(EitherUpgrade::A(self.config.clone())),
+                    info: IdentifyOutbound::Request,
                 };
                 Poll::Ready(ev)
             }
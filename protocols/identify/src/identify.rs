@@ -19,13 +19,15 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::handler::{IdentifyHandler, IdentifyHandlerEvent};
-use crate::protocol::{IdentifyInfo, ReplySubstream};
-use futures::prelude::*;
+use crate::protocol::{IdentifyInfo, IdentifyPush, ReplySubstream};
+use futures::{future::BoxFuture, prelude::*};
 use libp2p_core::{
     ConnectedPoint,
     Multiaddr,
     PeerId,
+    PeerRecord,
     PublicKey,
+    identity::{self, error::SigningError},
     upgrade::{Negotiated, ReadOneError, UpgradeError}
 };
 use libp2p_swarm::{
@@ -35,7 +37,8 @@ use libp2p_swarm::{
     ProtocolsHandler,
     ProtocolsHandlerUpgrErr
 };
-use std::{collections::HashMap, collections::VecDeque, io, pin::Pin, task::Context, task::Poll};
+use log::warn;
+use std::{collections::HashMap, collections::VecDeque, error, fmt, io, pin::Pin, task::Context, task::Poll};
 
 /// Network behaviour that automatically identifies nodes periodically, returns information
 /// about them, and answers identify queries from other nodes.
@@ -46,14 +49,45 @@ pub struct Identify<TSubstream> {
     agent_version: String,
     /// The public key of the local node. To report on the wire.
     local_public_key: PublicKey,
+    /// The keypair of the local node, if signed peer records are enabled.
+    local_keypair: Option<identity::Keypair>,
+    /// The last peer record of the local node, which is reused as long as the addresses
+    /// don't change.
+    local_peer_record: Option<PeerRecord>,
+    /// The addresses of the last peer record of the local node we tried to sign.
+    signed_addrs: Option<Vec<Multiaddr>>,
+    /// The peer record of the local node being signed.
+    pending_peer_record: Option<BoxFuture<'static, Result<PeerRecord, SigningError>>>,
     /// For each peer we're connected to, the observed address to send back to it.
     observed_addresses: HashMap<PeerId, Multiaddr>,
     /// Pending replies to send.
     pending_replies: VecDeque<Reply<TSubstream>>,
     /// Pending events to be emitted when polled.
-    events: VecDeque<NetworkBehaviourAction<(), IdentifyEvent>>,
+    events: VecDeque<NetworkBehaviourAction<IdentifyPush, IdentifyEvent>>,
+    /// Peers to push the information of the local node to.
+    pending_pushes: VecDeque<PeerId>,
+    /// Listen addresses, protocols and sequence number of the peer record of the local node,
+    /// as last seen while connected to peers. The information is pushed to all connected
+    /// peers when they change.
+    advertised: Option<(Vec<Multiaddr>, Vec<String>, Option<u64>)>,
+    /// Whether the information of the local node may have changed since it was last compared
+    /// with `advertised`.
+    check_advertised: bool,
 }
 
+/// Error returned by `Identify::with_signed_peer_records` when the keypair is not the one of
+/// the local node.
+#[derive(Debug, Clone)]
+pub struct MismatchedKeypairError;
+
+impl fmt::Display for MismatchedKeypairError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The keypair is not the one of the local node")
+    }
+}
+
+impl error::Error for MismatchedKeypairError {}
+
 /// A pending reply to an inbound identification request.
 enum Reply<TSubstream> {
     /// The reply is queued for sending.
@@ -76,13 +110,110 @@ impl<TSubstream> Identify<TSubstream> {
             protocol_version,
             agent_version,
             local_public_key,
+            local_keypair: None,
+            local_peer_record: None,
+            signed_addrs: None,
+            pending_peer_record: None,
             observed_addresses: HashMap::new(),
             pending_replies: VecDeque::new(),
             events: VecDeque::new(),
+            pending_pushes: VecDeque::new(),
+            advertised: None,
+            check_advertised: false,
+        }
+    }
+
+    /// Sends the addresses of the local node in a peer record signed with `keypair`, so that
+    /// remotes can pass them on to other nodes.
+    ///
+    /// Returns an error if the keypair is not the one of the local node.
+    pub fn with_signed_peer_records(mut self, keypair: identity::Keypair)
+        -> Result<Self, MismatchedKeypairError>
+    {
+        if keypair.public() != self.local_public_key {
+            return Err(MismatchedKeypairError)
+        }
+        self.local_keypair = Some(keypair);
+        Ok(self)
+    }
+
+    /// Pushes the information of the local node to the given peers, if connected.
+    ///
+    /// The information is pushed to all connected peers automatically when the listen
+    /// addresses or the supported protocols of the local node change.
+    pub fn push<I>(&mut self, peers: I)
+    where
+        I: IntoIterator<Item = PeerId>
+    {
+        self.pending_pushes.extend(peers);
+    }
+
+    /// Returns the information of the local node to send to remotes.
+    ///
+    /// The peer record of the local node is only sent once it has been signed for the current
+    /// addresses.
+    fn local_info(&self, params: &impl PollParameters) -> IdentifyInfo {
+        // The protocol names can be bytes, but the identify protocol except UTF-8 strings.
+        // There's not much we can do to solve this conflict except strip non-UTF-8 characters.
+        let protocols: Vec<_> = params
+            .supported_protocols()
+            .map(|p| String::from_utf8_lossy(&p).to_string())
+            .collect();
+
+        let listen_addrs = local_addrs(params);
+        let signed_peer_record = self.local_peer_record.as_ref()
+            .filter(|record| record.addresses() == &listen_addrs[..])
+            .cloned();
+
+        IdentifyInfo {
+            public_key: self.local_public_key.clone(),
+            protocol_version: self.protocol_version.clone(),
+            agent_version: self.agent_version.clone(),
+            listen_addrs,
+            protocols,
+            signed_peer_record,
+        }
+    }
+
+    /// Starts signing a peer record of the local node when its addresses change, and polls the
+    /// signature in progress.
+    ///
+    /// The signature is awaited rather than produced synchronously, as the keypair may be the
+    /// one of an external signer.
+    fn poll_peer_record(&mut self, cx: &mut Context, params: &impl PollParameters) {
+        let keypair = match &self.local_keypair {
+            Some(keypair) => keypair,
+            None => return,
+        };
+
+        let listen_addrs = local_addrs(params);
+        if self.signed_addrs.as_ref() != Some(&listen_addrs) {
+            self.pending_peer_record = Some(PeerRecord::new_async(keypair, listen_addrs.clone()));
+            self.signed_addrs = Some(listen_addrs);
+        }
+
+        if let Some(signing) = &mut self.pending_peer_record {
+            if let Poll::Ready(result) = signing.as_mut().poll(cx) {
+                self.pending_peer_record = None;
+                match result {
+                    Ok(record) => {
+                        self.local_peer_record = Some(record);
+                        self.check_advertised = true;
+                    }
+                    Err(err) => warn!("Failed to sign the peer record of the local node: {}", err),
+                }
+            }
         }
     }
 }
 
+/// Returns the external and listen addresses of the local node.
+fn local_addrs(params: &impl PollParameters) -> Vec<Multiaddr> {
+    let mut listen_addrs: Vec<_> = params.external_addresses().collect();
+    listen_addrs.extend(params.listened_addresses());
+    listen_addrs
+}
+
 impl<TSubstream> NetworkBehaviour for Identify<TSubstream>
 where
     TSubstream: AsyncRead + AsyncWrite + Unpin + Send + 'static,
@@ -105,10 +236,28 @@ where
         };
 
         self.observed_addresses.insert(peer_id, observed);
+        if self.advertised.is_none() {
+            self.check_advertised = true;
+        }
     }
 
     fn inject_disconnected(&mut self, peer_id: &PeerId, _: ConnectedPoint) {
         self.observed_addresses.remove(peer_id);
+        if self.observed_addresses.is_empty() {
+            self.advertised = None;
+        }
+    }
+
+    fn inject_new_listen_addr(&mut self, _: &Multiaddr) {
+        self.check_advertised = true;
+    }
+
+    fn inject_expired_listen_addr(&mut self, _: &Multiaddr) {
+        self.check_advertised = true;
+    }
+
+    fn inject_new_external_addr(&mut self, _: &Multiaddr) {
+        self.check_advertised = true;
     }
 
     fn inject_node_event(
@@ -118,6 +267,15 @@ where
     ) {
         match event {
             IdentifyHandlerEvent::Identified(remote) => {
+                if remote.info.public_key.clone().into_peer_id() != peer_id {
+                    warn!("Ignoring identify information of {:?} with the public key of another peer", peer_id);
+                    let err = io::Error::new(io::ErrorKind::InvalidData, "public key does not match the peer");
+                    let error = ProtocolsHandlerUpgrErr::Upgrade(UpgradeError::Apply(err.into()));
+                    self.events.push_back(
+                        NetworkBehaviourAction::GenerateEvent(
+                            IdentifyEvent::Error { peer_id, error }));
+                    return
+                }
                 self.events.push_back(
                     NetworkBehaviourAction::GenerateEvent(
                         IdentifyEvent::Received {
@@ -125,10 +283,18 @@ where
                             info: remote.info,
                             observed_addr: remote.observed_addr.clone(),
                         }));
+                // Pushes from other implementations may not contain the observed address.
+                if remote.observed_addr != Multiaddr::empty() {
+                    self.events.push_back(
+                        NetworkBehaviourAction::ReportObservedAddr {
+                            address: remote.observed_addr,
+                        });
+                }
+            }
+            IdentifyHandlerEvent::Pushed => {
                 self.events.push_back(
-                    NetworkBehaviourAction::ReportObservedAddr {
-                        address: remote.observed_addr,
-                    });
+                    NetworkBehaviourAction::GenerateEvent(
+                        IdentifyEvent::Pushed { peer_id }));
             }
             IdentifyHandlerEvent::Identify(sender) => {
                 let observed = self.observed_addresses.get(&peer_id)
@@ -164,16 +330,44 @@ where
             return Poll::Ready(event);
         }
 
-        if let Some(r) = self.pending_replies.pop_front() {
-            // The protocol names can be bytes, but the identify protocol except UTF-8 strings.
-            // There's not much we can do to solve this conflict except strip non-UTF-8 characters.
-            let protocols: Vec<_> = params
-                .supported_protocols()
-                .map(|p| String::from_utf8_lossy(&p).to_string())
-                .collect();
+        self.poll_peer_record(cx, params);
+
+        if self.observed_addresses.is_empty() {
+            self.pending_pushes.clear();
+        } else if self.check_advertised || !self.pending_pushes.is_empty() {
+            let info = self.local_info(params);
+
+            if self.check_advertised {
+                self.check_advertised = false;
+                let current = (
+                    info.listen_addrs.clone(),
+                    info.protocols.clone(),
+                    info.signed_peer_record.as_ref().map(PeerRecord::seq),
+                );
+                if let Some(advertised) = self.advertised.replace(current.clone()) {
+                    if advertised != current {
+                        self.pending_pushes.extend(self.observed_addresses.keys().cloned());
+                    }
+                }
+            }
+
+            while let Some(peer_id) = self.pending_pushes.pop_front() {
+                if let Some(observed) = self.observed_addresses.get(&peer_id) {
+                    let push = IdentifyPush {
+                        info: info.clone(),
+                        observed_addr: observed.clone(),
+                    };
+                    self.events.push_back(NetworkBehaviourAction::SendEvent { peer_id, event: push });
+                }
+            }
 
-            let mut listen_addrs: Vec<_> = params.external_addresses().collect();
-            listen_addrs.extend(params.listened_addresses());
+            if let Some(event) = self.events.pop_front() {
+                return Poll::Ready(event);
+            }
+        }
+
+        if let Some(r) = self.pending_replies.pop_front() {
+            let info = self.local_info(params);
 
             let mut sending = 0;
             let to_send = self.pending_replies.len() + 1;
@@ -181,14 +375,7 @@ where
             loop {
                 match reply {
                     Some(Reply::Queued { peer, io, observed }) => {
-                        let info = IdentifyInfo {
-                            public_key: self.local_public_key.clone(),
-                            protocol_version: self.protocol_version.clone(),
-                            agent_version: self.agent_version.clone(),
-                            listen_addrs: listen_addrs.clone(),
-                            protocols: protocols.clone(),
-                        };
-                        let io = Box::pin(io.send(info, &observed));
+                        let io = Box::pin(io.send(info.clone(), &observed));
                         reply = Some(Reply::Sending { peer, io });
                     }
                     Some(Reply::Sending { peer, mut io }) => {
@@ -228,7 +415,8 @@ where
 /// Event emitted  by the `Identify` behaviour.
 #[derive(Debug)]
 pub enum IdentifyEvent {
-    /// Identifying information has been received from a peer.
+    /// Identifying information has been received from a peer, either as an answer to a request
+    /// or pushed by the peer.
     Received {
         /// The peer that has been identified.
         peer_id: PeerId,
@@ -242,6 +430,11 @@ pub enum IdentifyEvent {
         /// The peer that the information has been sent to.
         peer_id: PeerId,
     },
+    /// Identifying information of the local node has been pushed to a peer.
+    Pushed {
+        /// The peer that the information has been pushed to.
+        peer_id: PeerId,
+    },
     /// Error while attempting to identify the remote.
     Error {
         /// The peer with whom the error originated.
@@ -266,6 +459,7 @@ mod tests {
     use libp2p_secio::SecioConfig;
     use libp2p_swarm::{Swarm, SwarmEvent};
     use libp2p_mplex::MplexConfig;
+    use libp2p_swarm_test::SwarmTest;
     use std::{fmt, io};
 
     fn transport() -> (identity::PublicKey, impl Transport<
@@ -348,4 +542,83 @@ mod tests {
             }
         })
     }
+
+    #[test]
+    fn listen_addr_changes_are_pushed() {
+        let mut test = SwarmTest::new(2, |keypair| {
+            Identify::new("a".to_string(), "b".to_string(), keypair.public())
+        });
+        test.connect(1, 0);
+        // Let the behaviours see the connection before the listen addresses change, and
+        // before the nodes identify each other and let the connection close.
+        test.run_until_idle();
+
+        let new_addr = "/memory/0".parse().unwrap();
+        Swarm::listen_on(&mut test[0], new_addr).unwrap();
+
+        let (peer0, peer1) = (test.peer_id(0).clone(), test.peer_id(1).clone());
+        let (mut pushed, mut received) = (false, false);
+        while !pushed || !received {
+            test.wait_for_event(|index, event| match event {
+                SwarmEvent::Behaviour(IdentifyEvent::Pushed { peer_id }) => {
+                    assert_eq!((index, &peer_id), (0, &peer1));
+                    pushed = true;
+                    Some(())
+                }
+                SwarmEvent::Behaviour(IdentifyEvent::Received { peer_id, info, .. })
+                    if index == 1 && info.listen_addrs.len() == 2 =>
+                {
+                    assert_eq!(peer_id, peer0);
+                    received = true;
+                    Some(())
+                }
+                _ => None,
+            });
+        }
+    }
+
+    #[test]
+    fn signed_peer_records_are_sent() {
+        let mut test = SwarmTest::new(2, |keypair| {
+            Identify::new("a".to_string(), "b".to_string(), keypair.public())
+                .with_signed_peer_records(keypair.clone())
+                .unwrap()
+        });
+        test.connect(1, 0);
+
+        let peer0 = test.peer_id(0).clone();
+        let addr0 = test.addr(0).clone();
+        test.wait_for_event(|index, event| match event {
+            SwarmEvent::Behaviour(IdentifyEvent::Received { info, .. }) if index == 1 => {
+                let record = info.signed_peer_record.expect("a signed peer record");
+                assert_eq!(record.peer_id(), &peer0);
+                assert_eq!(record.addresses(), &[addr0.clone()]);
+                Some(())
+            }
+            _ => None,
+        });
+    }
+
+    #[test]
+    fn signed_peer_records_of_external_signers_are_sent() {
+        let mut test = SwarmTest::new(2, |keypair| {
+            Identify::new("a".to_string(), "b".to_string(), keypair.public())
+                .with_signed_peer_records(identity::Keypair::from_signer(
+                    identity::signer::LocalSigner::new(keypair.clone())))
+                .unwrap()
+        });
+        test.connect(1, 0);
+
+        let peer0 = test.peer_id(0).clone();
+        let addr0 = test.addr(0).clone();
+        test.wait_for_event(|index, event| match event {
+            SwarmEvent::Behaviour(IdentifyEvent::Received { info, .. }) if index == 1 => {
+                let record = info.signed_peer_record.expect("a signed peer record");
+                assert_eq!(record.peer_id(), &peer0);
+                assert_eq!(record.addresses(), &[addr0.clone()]);
+                Some(())
+            }
+            _ => None,
+        });
+    }
 }
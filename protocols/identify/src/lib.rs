@@ -26,6 +26,11 @@
 //! At least one identification request is sent on a newly established
 //! connection, beyond which the behaviour does not keep connections alive.
 //!
+//! When the listen addresses or the supported protocols of the local node
+//! change, the updated [`IdentifyInfo`] is pushed to all connected peers with
+//! the `/ipfs/id/push/1.0.0` protocol. Information pushed by remotes is
+//! reported like the answers to identification requests.
+//!
 //! # Usage
 //!
 //! The [`Identify`] struct implements a `NetworkBehaviour` that negotiates
@@ -37,7 +42,7 @@
 //! [`IdentifyEvent`]: self::IdentifyEvent
 //! [`IdentifyInfo`]: self::IdentifyEvent
 
-pub use self::identify::{Identify, IdentifyEvent};
+pub use self::identify::{Identify, IdentifyEvent, MismatchedKeypairError};
 pub use self::protocol::IdentifyInfo;
 
 mod handler;
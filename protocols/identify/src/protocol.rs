@@ -22,23 +22,46 @@ use crate::structs_proto;
 use futures::prelude::*;
 use libp2p_core::{
     Multiaddr,
+    PeerRecord,
     PublicKey,
+    SignedEnvelope,
     upgrade::{self, InboundUpgrade, OutboundUpgrade, UpgradeInfo}
 };
 use log::{debug, trace};
 use prost::Message;
-use std::convert::TryFrom;
 use std::{fmt, io, iter, pin::Pin};
 
+/// Name of the identify push protocol.
+const PUSH_PROTOCOL_NAME: &[u8] = b"/ipfs/id/push/1.0.0";
+
 /// Configuration for an upgrade to the `Identify` protocol.
 #[derive(Debug, Clone)]
 pub struct IdentifyProtocolConfig;
 
+/// Configuration for an inbound upgrade to the identify push protocol, receiving the
+/// information that a remote sends without being asked.
+#[derive(Debug, Clone)]
+pub struct IdentifyPushProtocolConfig;
+
+/// Outbound upgrade to the identify push protocol, sending the information of the local node
+/// to a remote without being asked.
+#[derive(Debug, Clone)]
+pub struct IdentifyPush {
+    /// Information about the local node.
+    pub info: IdentifyInfo,
+    /// Address the local node sees for the remote.
+    pub observed_addr: Multiaddr,
+}
+
+/// Information received from a remote, as an answer to a request or as a push.
 #[derive(Debug, Clone)]
 pub struct RemoteInfo {
     /// Information about the remote.
     pub info: IdentifyInfo,
     /// Address the remote sees for us.
+    ///
+    /// Like the received listen addresses, it can end with a `Protocol::Unknown`, after which
+    /// components can only be appended with the fallible `Multiaddr::try_push`.
     pub observed_addr: Multiaddr,
 
     _priv: ()
@@ -69,25 +92,8 @@ where
         debug!("Sending identify info to client");
         trace!("Sending: {:?}", info);
 
-        let listen_addrs = info.listen_addrs
-            .into_iter()
-            .map(|addr| addr.to_vec())
-            .collect();
-
-        let pubkey_bytes = info.public_key.into_protobuf_encoding();
-
-        let message = structs_proto::Identify {
-            agent_version: Some(info.agent_version),
-            protocol_version: Some(info.protocol_version),
-            public_key: Some(pubkey_bytes),
-            listen_addrs: listen_addrs,
-            observed_addr: Some(observed_addr.to_vec()),
-            protocols: info.protocols
-        };
-
+        let bytes = encode_proto_msg(info, observed_addr);
         async move {
-            let mut bytes = Vec::with_capacity(message.encoded_len());
-            message.encode(&mut bytes).expect("Vec<u8> provides capacity as needed");
             upgrade::write_one(&mut self.inner, &bytes).await
         }
     }
@@ -105,9 +111,17 @@ pub struct IdentifyInfo {
     /// the HTTP protocol.
     pub agent_version: String,
     /// The addresses that the peer is listening on.
+    ///
+    /// When received from a remote, the components of protocols unknown to this implementation
+    /// are preserved as a trailing `Protocol::Unknown`. Such an address can't be extended with
+    /// `Multiaddr::push` or `Multiaddr::with`, which panic, but only with
+    /// `Multiaddr::try_push` or `Multiaddr::try_with`.
     pub listen_addrs: Vec<Multiaddr>,
     /// The list of protocols supported by the peer, e.g. `/ipfs/ping/1.0.0`.
     pub protocols: Vec<String>,
+    /// The addresses of the peer, signed by the peer. Only contains records that have been
+    /// verified to be signed by the peer.
+    pub signed_peer_record: Option<PeerRecord>,
 }
 
 impl UpgradeInfo for IdentifyProtocolConfig {
@@ -144,36 +158,126 @@ where
     fn upgrade_outbound(self, mut socket: C, _: Self::Info) -> Self::Future {
         Box::pin(async move {
             socket.close().await?;
-            let msg = upgrade::read_one(&mut socket, 4096).await?;
-            let (info, observed_addr) = match parse_proto_msg(msg) {
-                Ok(v) => v,
-                Err(err) => {
-                    debug!("Failed to parse protobuf message; error = {:?}", err);
-                    return Err(err.into())
-                }
-            };
+            read_remote_info(&mut socket).await
+        })
+    }
+}
+
+impl UpgradeInfo for IdentifyPushProtocolConfig {
+    type Info = &'static [u8];
+    type InfoIter = iter::Once<Self::Info>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        iter::once(PUSH_PROTOCOL_NAME)
+    }
+}
+
+impl<C> InboundUpgrade<C> for IdentifyPushProtocolConfig
+where
+    C: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    type Output = RemoteInfo;
+    type Error = upgrade::ReadOneError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Output, Self::Error>> + Send>>;
+
+    fn upgrade_inbound(self, mut socket: C, _: Self::Info) -> Self::Future {
+        trace!("Upgrading inbound identify push");
+        Box::pin(async move {
+            let remote = read_remote_info(&mut socket).await?;
+            socket.close().await?;
+            Ok(remote)
+        })
+    }
+}
+
+impl UpgradeInfo for IdentifyPush {
+    type Info = &'static [u8];
+    type InfoIter = iter::Once<Self::Info>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        iter::once(PUSH_PROTOCOL_NAME)
+    }
+}
+
+impl<C> OutboundUpgrade<C> for IdentifyPush
+where
+    C: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    type Output = ();
+    type Error = upgrade::ReadOneError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Output, Self::Error>> + Send>>;
 
-            trace!("Remote observes us as {:?}", observed_addr);
-            trace!("Information received: {:?}", info);
+    fn upgrade_outbound(self, mut socket: C, _: Self::Info) -> Self::Future {
+        debug!("Pushing identify info to remote");
+        trace!("Pushing: {:?}", self.info);
 
-            Ok(RemoteInfo {
-                info,
-                observed_addr: observed_addr.clone(),
-                _priv: ()
-            })
+        let bytes = encode_proto_msg(self.info, &self.observed_addr);
+        Box::pin(async move {
+            upgrade::write_one(&mut socket, &bytes).await?;
+            socket.close().await?;
+            Ok(())
         })
     }
 }
 
+// Reads the identify message sent by the remote, as an answer to a request or as a push.
+async fn read_remote_info<C>(socket: &mut C) -> Result<RemoteInfo, upgrade::ReadOneError>
+where
+    C: AsyncRead + Unpin,
+{
+    let msg = upgrade::read_one(socket, 4096).await?;
+    let (info, observed_addr) = match parse_proto_msg(msg) {
+        Ok(v) => v,
+        Err(err) => {
+            debug!("Failed to parse protobuf message; error = {:?}", err);
+            return Err(err.into())
+        }
+    };
+
+    trace!("Remote observes us as {:?}", observed_addr);
+    trace!("Information received: {:?}", info);
+
+    Ok(RemoteInfo {
+        info,
+        observed_addr,
+        _priv: ()
+    })
+}
+
+// Turns an `IdentifyInfo` and the address observed for the remote into a protobuf message.
+fn encode_proto_msg(info: IdentifyInfo, observed_addr: &Multiaddr) -> Vec<u8> {
+    let listen_addrs = info.listen_addrs
+        .into_iter()
+        .map(|addr| addr.to_vec())
+        .collect();
+
+    let pubkey_bytes = info.public_key.into_protobuf_encoding();
+
+    let message = structs_proto::Identify {
+        agent_version: Some(info.agent_version),
+        protocol_version: Some(info.protocol_version),
+        public_key: Some(pubkey_bytes),
+        listen_addrs: listen_addrs,
+        observed_addr: Some(observed_addr.to_vec()),
+        protocols: info.protocols,
+        signed_peer_record: info.signed_peer_record
+            .map(|record| record.into_signed_envelope().into_protobuf_encoding()),
+    };
+
+    let mut bytes = Vec::with_capacity(message.encoded_len());
+    message.encode(&mut bytes).expect("Vec<u8> provides capacity as needed");
+    bytes
+}
+
 // Turns a protobuf message into an `IdentifyInfo` and an observed address. If something bad
 // happens, turn it into an `io::Error`.
 fn parse_proto_msg(msg: impl AsRef<[u8]>) -> Result<(IdentifyInfo, Multiaddr), io::Error> {
     match structs_proto::Identify::decode(msg.as_ref()) {
         Ok(msg) => {
-            // Turn a `Vec<u8>` into a `Multiaddr`. If something bad happens, turn it into
-            // an `io::Error`.
+            // Turn a `Vec<u8>` into a `Multiaddr`, preserving the protocols we don't know
+            // about. If something bad happens, turn it into an `io::Error`.
             fn bytes_to_multiaddr(bytes: Vec<u8>) -> Result<Multiaddr, io::Error> {
-                Multiaddr::try_from(bytes)
+                Multiaddr::from_bytes_passthrough(bytes)
                     .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
             }
 
@@ -188,13 +292,34 @@ fn parse_proto_msg(msg: impl AsRef<[u8]>) -> Result<(IdentifyInfo, Multiaddr), i
             let public_key = PublicKey::from_protobuf_encoding(&msg.public_key.unwrap_or_default())
                 .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
+            // An invalid record is ignored, as the rest of the information is still usable.
+            let signed_peer_record = msg.signed_peer_record.and_then(|bytes| {
+                let record = SignedEnvelope::from_protobuf_encoding(&bytes)
+                    .map_err(|err| err.to_string())
+                    .and_then(|envelope| PeerRecord::from_signed_envelope(envelope)
+                        .map_err(|err| err.to_string()));
+                match record {
+                    Ok(record) if record.peer_id() == &public_key.clone().into_peer_id() =>
+                        Some(record),
+                    Ok(record) => {
+                        debug!("Ignoring peer record of {:?} sent by another peer", record.peer_id());
+                        None
+                    }
+                    Err(err) => {
+                        debug!("Ignoring invalid signed peer record; error = {}", err);
+                        None
+                    }
+                }
+            });
+
             let observed_addr = bytes_to_multiaddr(msg.observed_addr.unwrap_or_default())?;
             let info = IdentifyInfo {
                 public_key,
                 protocol_version: msg.protocol_version.unwrap_or_default(),
                 agent_version: msg.agent_version.unwrap_or_default(),
                 listen_addrs,
-                protocols: msg.protocols
+                protocols: msg.protocols,
+                signed_peer_record,
             };
 
             Ok((info, observed_addr))
@@ -250,6 +375,7 @@ mod tests {
                         "/ip6/::1/udp/1000".parse().unwrap(),
                     ],
                     protocols: vec!["proto1".to_string(), "proto2".to_string()],
+                    signed_peer_record: None,
                 },
                 &"/ip4/100.101.102.103/tcp/5000".parse().unwrap(),
             ).await.unwrap();
@@ -333,8 +333,9 @@ where
 
     /// Performs a lookup for the closest peers to the given key.
     ///
-    /// The result of this operation is delivered in [`KademliaEvent::GetClosestPeersResult`].
-    pub fn get_closest_peers<K>(&mut self, key: K)
+    /// The result of this operation is delivered in [`KademliaEvent::GetClosestPeersResult`],
+    /// and its progress in [`KademliaEvent::QueryProgressed`] events with the returned ID.
+    pub fn get_closest_peers<K>(&mut self, key: K) -> QueryId
     where
         K: AsRef<[u8]> + Clone
     {
@@ -342,27 +343,41 @@ where
         let info = QueryInfo::GetClosestPeers { key: key.clone() };
         let target = kbucket::Key::new(key);
         let peers = self.kbuckets.closest_keys(&target);
-        let inner = QueryInner::new(info);
-        self.queries.add_iter_closest(target.clone(), peers, inner);
+        let inner = QueryInner::with_progress(info, 0);
+        self.queries.add_iter_closest(target.clone(), peers, inner)
     }
 
     /// Performs a lookup for a record in the DHT.
     ///
-    /// The result of this operation is delivered in [`KademliaEvent::GetRecordResult`].
-    pub fn get_record(&mut self, key: &record::Key, quorum: Quorum) {
+    /// The result of this operation is delivered in [`KademliaEvent::GetRecordResult`],
+    /// and its progress in [`KademliaEvent::QueryProgressed`] events with the returned ID,
+    /// with one event per record received.
+    pub fn get_record(&mut self, key: &record::Key, quorum: Quorum) -> QueryId {
         let quorum = quorum.eval(self.queries.config().replication_factor);
         let mut records = Vec::with_capacity(quorum.get());
+        let id = self.queries.next_query_id();
+        let mut step = 0;
 
         if let Some(record) = self.store.get(key) {
             if record.is_expired(Instant::now()) {
                 self.store.remove(key)
             } else {
-                records.push(record.into_owned());
+                let record = record.into_owned();
+                step += 1;
+                self.queued_events.push_back(NetworkBehaviourAction::GenerateEvent(
+                    KademliaEvent::QueryProgressed {
+                        id,
+                        result: QueryProgress::RecordReceived { record: record.clone() },
+                        step,
+                    }
+                ));
+                records.push(record);
                 if quorum.get() == 1 {
                     self.queued_events.push_back(NetworkBehaviourAction::GenerateEvent(
                         KademliaEvent::GetRecordResult(Ok(GetRecordOk { records }))
                     ));
-                    return;
+                    self.query_ended(id, Some(step));
+                    return id;
                 }
             }
         }
@@ -370,13 +385,15 @@ where
         let target = kbucket::Key::new(key.clone());
         let info = QueryInfo::GetRecord { key: key.clone(), records, quorum, cache_at: None };
         let peers = self.kbuckets.closest_keys(&target);
-        let inner = QueryInner::new(info);
-        self.queries.add_iter_closest(target.clone(), peers, inner);
+        let inner = QueryInner::with_progress(info, step);
+        self.queries.continue_iter_closest(id, target.clone(), peers, inner);
+        id
     }
 
     /// Stores a record in the DHT.
     ///
-    /// The result of this operation is delivered in [`KademliaEvent::PutRecordResult`].
+    /// The result of this operation is delivered in [`KademliaEvent::PutRecordResult`],
+    /// and its progress in [`KademliaEvent::QueryProgressed`] events with the returned ID.
     ///
     /// The record is always stored locally with the given expiration. If the record's
     /// expiration is `None`, the common case, it does not expire in local storage
@@ -388,9 +405,10 @@ where
     /// does not update the record's expiration in local storage, thus a given record
     /// with an explicit expiration will always expire at that instant and until then
     /// is subject to regular (re-)replication and (re-)publication.
-    pub fn put_record(&mut self, mut record: Record, quorum: Quorum) {
+    pub fn put_record(&mut self, mut record: Record, quorum: Quorum) -> QueryId {
         record.publisher = Some(self.kbuckets.local_key().preimage().clone());
         if let Err(err) = self.store.put(record.clone()) {
+            let id = self.queries.next_query_id();
             self.queued_events.push_back(NetworkBehaviourAction::GenerateEvent(
                 KademliaEvent::PutRecordResult(Err(
                     PutRecordError::LocalStorageError {
@@ -399,6 +417,8 @@ where
                     }
                 ))
             ));
+            self.query_ended(id, Some(0));
+            id
         } else {
             record.expires = record.expires.or_else(||
                 self.record_ttl.map(|ttl| Instant::now() + ttl));
@@ -407,8 +427,8 @@ where
             let peers = self.kbuckets.closest_keys(&target);
             let context = PutRecordContext::Publish;
             let info = QueryInfo::PreparePutRecord { record, quorum, context };
-            let inner = QueryInner::new(info);
-            self.queries.add_iter_closest(target.clone(), peers, inner);
+            let inner = QueryInner::with_progress(info, 0);
+            self.queries.add_iter_closest(target.clone(), peers, inner)
         }
     }
 
@@ -445,17 +465,18 @@ where
     /// bucket with random keys.
     ///
     /// The result(s) of this operation are delivered in [`KademliaEvent::BootstrapResult`],
-    /// with one event per bootstrapping query.
+    /// with one event per bootstrapping query. The progress of the lookup of the local node's
+    /// own ID is delivered in [`KademliaEvent::QueryProgressed`] events with the returned ID.
     ///
     /// > **Note**: Bootstrapping requires at least one node of the DHT to be known.
     /// > See [`Kademlia::add_address`].
-    pub fn bootstrap(&mut self) {
+    pub fn bootstrap(&mut self) -> QueryId {
         let local_key = self.kbuckets.local_key().clone();
         let info = QueryInfo::Bootstrap { peer: local_key.preimage().clone() };
         let peers = self.kbuckets.closest_keys(&local_key).collect::<Vec<_>>();
         // TODO: Emit error if `peers` is empty? BootstrapError::NoPeers?
-        let inner = QueryInner::new(info);
-        self.queries.add_iter_closest(local_key, peers, inner);
+        let inner = QueryInner::with_progress(info, 0);
+        self.queries.add_iter_closest(local_key, peers, inner)
     }
 
     /// Establishes the local node as a provider of a value for the given key.
@@ -476,22 +497,27 @@ where
     /// of the libp2p Kademlia provider API.
     ///
     /// The results of the (repeated) provider announcements sent by this node are
-    /// delivered in [`KademliaEvent::AddProviderResult`].
-    pub fn start_providing(&mut self, key: record::Key) {
+    /// delivered in [`KademliaEvent::AddProviderResult`]. The progress of the first
+    /// announcement is delivered in [`KademliaEvent::QueryProgressed`] events with the
+    /// returned ID.
+    pub fn start_providing(&mut self, key: record::Key) -> QueryId {
         let record = ProviderRecord::new(key.clone(), self.kbuckets.local_key().preimage().clone());
         if let Err(err) = self.store.add_provider(record) {
+            let id = self.queries.next_query_id();
             self.queued_events.push_back(NetworkBehaviourAction::GenerateEvent(
                 KademliaEvent::StartProvidingResult(Err(
                     AddProviderError::LocalStorageError { key, cause: err }
                 ))
             ));
+            self.query_ended(id, Some(0));
+            id
         } else {
             let target = kbucket::Key::new(key.clone());
             let peers = self.kbuckets.closest_keys(&target);
             let context = AddProviderContext::Publish;
             let info = QueryInfo::PrepareAddProvider { key, context };
-            let inner = QueryInner::new(info);
-            self.queries.add_iter_closest(target.clone(), peers, inner);
+            let inner = QueryInner::with_progress(info, 0);
+            self.queries.add_iter_closest(target.clone(), peers, inner)
         }
     }
 
@@ -505,16 +531,66 @@ where
 
     /// Performs a lookup for providers of a value to the given key.
     ///
-    /// The result of this operation is delivered in [`KademliaEvent::GetProvidersResult`].
-    pub fn get_providers(&mut self, key: record::Key) {
+    /// The result of this operation is delivered in [`KademliaEvent::GetProvidersResult`],
+    /// and its progress in [`KademliaEvent::QueryProgressed`] events with the returned ID,
+    /// with one event per provider found.
+    pub fn get_providers(&mut self, key: record::Key) -> QueryId {
         let info = QueryInfo::GetProviders {
             key: key.clone(),
             providers: Vec::new(),
         };
         let target = kbucket::Key::new(key);
         let peers = self.kbuckets.closest_keys(&target);
-        let inner = QueryInner::new(info);
-        self.queries.add_iter_closest(target.clone(), peers, inner);
+        let inner = QueryInner::with_progress(info, 0);
+        self.queries.add_iter_closest(target.clone(), peers, inner)
+    }
+
+    /// Reports the progress of a query, if the query reports its progress and has not
+    /// finished yet.
+    ///
+    /// A query finished prematurely, e.g. once the quorum of records is reached, remains in
+    /// the pool until it is polled and can still receive responses to pending requests,
+    /// which are not reported after its [`QueryProgress::Finished`] event.
+    fn query_progressed(&mut self, id: QueryId, result: QueryProgress) {
+        if let Some(query) = self.queries.get_mut(&id) {
+            if query.is_finished() {
+                return
+            }
+            if let Some(step) = query.inner.step.as_mut() {
+                *step += 1;
+                self.queued_events.push_back(NetworkBehaviourAction::GenerateEvent(
+                    KademliaEvent::QueryProgressed { id, result, step: *step }
+                ));
+            }
+        }
+    }
+
+    /// Reports the peers closer to the target of a query received in a response.
+    fn closer_peers_received(&mut self, id: QueryId, peers: &[KadPeer]) {
+        let local_id = self.kbuckets.local_key().preimage();
+        let peers = peers.iter()
+            .filter(|p| &p.node_id != local_id)
+            .map(|p| p.node_id.clone())
+            .collect::<Vec<_>>();
+        if !peers.is_empty() {
+            self.query_progressed(id, QueryProgress::CloserPeers { peers })
+        }
+    }
+
+    /// Reports the end of a query that reports its progress, after `step` progress events,
+    /// unless the query continues with the same ID in another phase.
+    fn query_ended(&mut self, id: QueryId, step: Option<usize>) {
+        if let Some(step) = step {
+            if self.queries.get(&id).is_none() {
+                self.queued_events.push_back(NetworkBehaviourAction::GenerateEvent(
+                    KademliaEvent::QueryProgressed {
+                        id,
+                        result: QueryProgress::Finished,
+                        step: step + 1,
+                    }
+                ));
+            }
+        }
     }
 
     /// Processes discovered peers from a successful request in an iterative `Query`.
@@ -665,7 +741,9 @@ where
     fn query_finished(&mut self, q: Query<QueryInner>, params: &mut impl PollParameters)
         -> Option<KademliaEvent>
     {
+        let query_id = q.id();
         let result = q.into_result();
+        let step = result.inner.step;
         match result.inner.info {
             QueryInfo::Bootstrap { peer } => {
                 let local_key = self.kbuckets.local_key().clone();
@@ -732,13 +810,16 @@ where
                 let closest_peers = result.peers.map(kbucket::Key::from);
                 let provider_id = params.local_peer_id().clone();
                 let external_addresses = params.external_addresses().collect();
-                let inner = QueryInner::new(QueryInfo::AddProvider {
-                    key,
-                    provider_id,
-                    external_addresses,
-                    context,
-                });
-                self.queries.add_fixed(closest_peers, inner);
+                let inner = QueryInner {
+                    step,
+                    .. QueryInner::new(QueryInfo::AddProvider {
+                        key,
+                        provider_id,
+                        external_addresses,
+                        context,
+                    })
+                };
+                self.queries.continue_fixed(query_id, closest_peers, inner);
                 None
             }
 
@@ -784,8 +865,8 @@ where
             QueryInfo::PreparePutRecord { record, quorum, context } => {
                 let closest_peers = result.peers.map(kbucket::Key::from);
                 let info = QueryInfo::PutRecord { record, quorum, context, num_results: 0 };
-                let inner = QueryInner::new(info);
-                self.queries.add_fixed(closest_peers, inner);
+                let inner = QueryInner { step, .. QueryInner::new(info) };
+                self.queries.continue_fixed(query_id, closest_peers, inner);
                 None
             }
 
@@ -1161,6 +1242,7 @@ where
                 closer_peers,
                 user_data,
             } => {
+                self.closer_peers_received(user_data, &closer_peers);
                 self.discovered(&user_data, &source, closer_peers.iter());
             }
 
@@ -1182,17 +1264,26 @@ where
                 provider_peers,
                 user_data,
             } => {
+                self.closer_peers_received(user_data, &closer_peers);
                 let peers = closer_peers.iter().chain(provider_peers.iter());
                 self.discovered(&user_data, &source, peers);
+                let mut found = Vec::new();
                 if let Some(query) = self.queries.get_mut(&user_data) {
                     if let QueryInfo::GetProviders {
-                        providers, ..
+                        key, providers,
                     } = &mut query.inner.info {
                         for peer in provider_peers {
-                            providers.push(peer.node_id);
+                            if !providers.contains(&peer.node_id) {
+                                providers.push(peer.node_id.clone());
+                                found.push((key.clone(), peer.node_id, peer.multiaddrs));
+                            }
                         }
                     }
                 }
+                for (key, provider, addresses) in found {
+                    let result = QueryProgress::ProviderFound { key, provider, addresses };
+                    self.query_progressed(user_data, result);
+                }
             }
 
             KademliaHandlerEvent::QueryError { user_data, .. } => {
@@ -1249,6 +1340,11 @@ where
                 closer_peers,
                 user_data,
             } => {
+                if let Some(record) = &record {
+                    let result = QueryProgress::RecordReceived { record: record.clone() };
+                    self.query_progressed(user_data, result);
+                }
+                self.closer_peers_received(user_data, &closer_peers);
                 if let Some(query) = self.queries.get_mut(&user_data) {
                     if let QueryInfo::GetRecord {
                         key, records, quorum, cache_at
@@ -1368,12 +1464,18 @@ where
             loop {
                 match self.queries.poll(now) {
                     QueryPoolState::Finished(q) => {
-                        if let Some(event) = self.query_finished(q, parameters) {
+                        let (id, step) = (q.id(), q.inner.step);
+                        let event = self.query_finished(q, parameters);
+                        self.query_ended(id, step);
+                        if let Some(event) = event {
                             return Poll::Ready(NetworkBehaviourAction::GenerateEvent(event))
                         }
                     }
                     QueryPoolState::Timeout(q) => {
-                        if let Some(event) = self.query_timeout(q) {
+                        let (id, step) = (q.id(), q.inner.step);
+                        let event = self.query_timeout(q);
+                        self.query_ended(id, step);
+                        if let Some(event) = event {
                             return Poll::Ready(NetworkBehaviourAction::GenerateEvent(event))
                         }
                     }
@@ -1467,6 +1569,21 @@ pub enum KademliaEvent {
     /// The result of a (automatic) republishing of a (value-)record.
     RepublishRecordResult(PutRecordResult),
 
+    /// A query started by the local node made progress.
+    ///
+    /// These events are emitted as the query proceeds, before the final result of the
+    /// query is known, which is delivered in the event specific to the query.
+    QueryProgressed {
+        /// The ID of the query, as returned by the method that started it.
+        id: QueryId,
+        /// The progress made by the query.
+        result: QueryProgress,
+        /// The number of progress events emitted for the query so far, including this one.
+        ///
+        /// The last event of a query is [`QueryProgress::Finished`].
+        step: usize,
+    },
+
     /// A peer has been discovered during a query.
     Discovered {
         /// The ID of the discovered peer.
@@ -1498,6 +1615,33 @@ pub enum KademliaEvent {
     }
 }
 
+/// The progress made by a query, reported in [`KademliaEvent::QueryProgressed`].
+#[derive(Debug, Clone)]
+pub enum QueryProgress {
+    /// A peer responded with peers closer to the target of the query.
+    CloserPeers {
+        /// The closer peers.
+        peers: Vec<PeerId>,
+    },
+    /// A provider has been found by [`Kademlia::get_providers`].
+    ProviderFound {
+        /// The key of the query.
+        key: record::Key,
+        /// The provider.
+        provider: PeerId,
+        /// The addresses of the provider, as known to the peer that reported it.
+        addresses: Vec<Multiaddr>,
+    },
+    /// A record has been received by [`Kademlia::get_record`].
+    RecordReceived {
+        /// The record.
+        record: Record,
+    },
+    /// The query finished and its result has been delivered. No more progress is reported
+    /// for the query.
+    Finished,
+}
+
 /// The result of [`Kademlia::get_record`].
 pub type GetRecordResult = Result<GetRecordOk, GetRecordError>;
 
@@ -1752,7 +1896,10 @@ struct QueryInner {
     ///
     /// A request is pending if the targeted peer is not currently connected
     /// and these requests are sent as soon as a connection to the peer is established.
-    pending_rpcs: SmallVec<[(PeerId, KademliaHandlerIn<QueryId>); K_VALUE.get()]>
+    pending_rpcs: SmallVec<[(PeerId, KademliaHandlerIn<QueryId>); K_VALUE.get()]>,
+    /// The number of `QueryProgressed` events emitted for the query, or `None` if the
+    /// query was not started by the user and doesn't report its progress.
+    step: Option<usize>,
 }
 
 impl QueryInner {
@@ -1760,9 +1907,16 @@ impl QueryInner {
         QueryInner {
             info,
             addresses: Default::default(),
-            pending_rpcs: SmallVec::default()
+            pending_rpcs: SmallVec::default(),
+            step: None,
         }
     }
+
+    /// Creates the state of a query that reports its progress, for which `step`
+    /// progress events have already been emitted.
+    fn with_progress(info: QueryInfo, step: usize) -> Self {
+        QueryInner { step: Some(step), .. QueryInner::new(info) }
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
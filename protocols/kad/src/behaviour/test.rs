@@ -492,6 +492,176 @@ fn get_value() {
     )
 }
 
+#[test]
+fn get_value_progress() {
+    let (port_base, mut swarms) = build_nodes(3);
+
+    let swarm_ids: Vec<_> = swarms.iter().map(Swarm::local_peer_id).cloned().collect();
+
+    swarms[0].add_address(&swarm_ids[1], Protocol::Memory(port_base + 1).into());
+    swarms[1].add_address(&swarm_ids[2], Protocol::Memory(port_base + 2).into());
+
+    let record = Record::new(Multihash::random(SHA2256), vec![4,5,6]);
+
+    swarms[1].store.put(record.clone()).unwrap();
+    let query_id = swarms[0].get_record(&record.key, Quorum::One);
+
+    let mut expected_step = 1;
+    let mut received = false;
+    let mut result = false;
+
+    block_on(
+        poll_fn(move |ctx| {
+            loop {
+                match swarms[0].poll_next_unpin(ctx) {
+                    Poll::Ready(Some(KademliaEvent::QueryProgressed { id, result: progress, step })) => {
+                        assert_eq!(id, query_id);
+                        assert_eq!(step, expected_step);
+                        expected_step += 1;
+                        match progress {
+                            QueryProgress::RecordReceived { record: r } => {
+                                assert_eq!(r, record);
+                                received = true;
+                            }
+                            QueryProgress::Finished => {
+                                assert!(received && result);
+                                return Poll::Ready(());
+                            }
+                            _ => (),
+                        }
+                    }
+                    Poll::Ready(Some(KademliaEvent::GetRecordResult(Ok(ok)))) => {
+                        assert_eq!(ok.records, vec![record.clone()]);
+                        result = true;
+                    }
+                    // Ignore any other event.
+                    Poll::Ready(Some(_)) => (),
+                    e @ Poll::Ready(_) => panic!("Unexpected return value: {:?}", e),
+                    Poll::Pending => break,
+                }
+            }
+            for swarm in &mut swarms[1..] {
+                loop {
+                    match swarm.poll_next_unpin(ctx) {
+                        Poll::Ready(Some(_)) => (),
+                        e @ Poll::Ready(_) => panic!("Unexpected return value: {:?}", e),
+                        Poll::Pending => break,
+                    }
+                }
+            }
+            Poll::Pending
+        })
+    )
+}
+
+#[test]
+fn no_progress_after_query_finished() {
+    let local_id = PeerId::random();
+    let mut kad: Kademlia<Substream<StreamMuxerBox>, _> =
+        Kademlia::new(local_id.clone(), MemoryStore::new(local_id));
+    let peers = (0 .. 2).map(|_| PeerId::random()).collect::<Vec<_>>();
+    for peer in &peers {
+        kad.add_address(peer, Protocol::Memory(0).into());
+    }
+
+    let record = Record::new(Multihash::random(SHA2256), vec![4,5,6]);
+    let query_id = kad.get_record(&record.key, Quorum::One);
+
+    // Both peers respond with the record, but the query finishes with the first response
+    // and remains in the pool until it is polled.
+    for peer in &peers {
+        kad.inject_node_event(peer.clone(), KademliaHandlerEvent::GetRecordRes {
+            record: Some(record.clone()),
+            closer_peers: Vec::new(),
+            user_data: query_id,
+        });
+    }
+
+    let received = kad.queued_events.iter()
+        .filter(|event| match event {
+            NetworkBehaviourAction::GenerateEvent(KademliaEvent::QueryProgressed {
+                result: QueryProgress::RecordReceived { .. }, ..
+            }) => true,
+            _ => false
+        })
+        .count();
+    assert_eq!(received, 1);
+}
+
+#[test]
+fn get_providers_progress() {
+    // The nodes form a chain, and only the third node knows the provider, the fourth node.
+    let (port_base, mut swarms) = build_nodes(4);
+
+    let swarm_ids: Vec<_> = swarms.iter().map(Swarm::local_peer_id).cloned().collect();
+
+    for i in 0 .. 3 {
+        swarms[i].add_address(&swarm_ids[i + 1], Protocol::Memory(port_base + i as u64 + 1).into());
+    }
+
+    let key = record::Key::from(Multihash::random(SHA2256));
+    swarms[2].store.add_provider(ProviderRecord::new(key.clone(), swarm_ids[3].clone())).unwrap();
+    let query_id = swarms[0].get_providers(key.clone());
+
+    let mut expected_step = 1;
+    let mut closer_peers = HashSet::new();
+    let mut found = false;
+    let mut result = false;
+
+    block_on(
+        poll_fn(move |ctx| {
+            loop {
+                match swarms[0].poll_next_unpin(ctx) {
+                    Poll::Ready(Some(KademliaEvent::QueryProgressed { id, result: progress, step })) => {
+                        assert_eq!(id, query_id);
+                        assert_eq!(step, expected_step);
+                        expected_step += 1;
+                        match progress {
+                            QueryProgress::CloserPeers { peers } => {
+                                closer_peers.extend(peers);
+                            }
+                            QueryProgress::ProviderFound { key: k, provider, addresses } => {
+                                assert!(!result, "Provider reported after the result of the query");
+                                assert_eq!(k, key);
+                                assert_eq!(provider, swarm_ids[3]);
+                                assert_eq!(addresses, vec![Protocol::Memory(port_base + 3).into()]);
+                                found = true;
+                            }
+                            QueryProgress::Finished => {
+                                assert!(found && result);
+                                // The peers were reached hop by hop.
+                                assert!(closer_peers.contains(&swarm_ids[2]));
+                                assert!(closer_peers.contains(&swarm_ids[3]));
+                                return Poll::Ready(());
+                            }
+                            QueryProgress::RecordReceived { .. } => panic!("Unexpected record"),
+                        }
+                    }
+                    Poll::Ready(Some(KademliaEvent::GetProvidersResult(Ok(ok)))) => {
+                        assert!(found);
+                        assert_eq!(ok.providers, vec![swarm_ids[3].clone()]);
+                        result = true;
+                    }
+                    // Ignore any other event.
+                    Poll::Ready(Some(_)) => (),
+                    e @ Poll::Ready(_) => panic!("Unexpected return value: {:?}", e),
+                    Poll::Pending => break,
+                }
+            }
+            for swarm in &mut swarms[1..] {
+                loop {
+                    match swarm.poll_next_unpin(ctx) {
+                        Poll::Ready(Some(_)) => (),
+                        e @ Poll::Ready(_) => panic!("Unexpected return value: {:?}", e),
+                        Poll::Pending => break,
+                    }
+                }
+            }
+            Poll::Pending
+        })
+    )
+}
+
 #[test]
 fn get_value_many() {
     // TODO: Randomise
@@ -666,16 +836,17 @@ fn exceed_jobs_max_queries() {
 
     block_on(
         poll_fn(move |ctx| {
-            for _ in 0 .. num {
+            let mut results = 0;
+            while results < num {
                 // There are no other nodes, so the queries finish instantly.
-                if let Poll::Ready(Some(e)) = swarms[0].poll_next_unpin(ctx) {
-                    if let KademliaEvent::BootstrapResult(r) = e {
-                        assert!(r.is_ok(), "Unexpected error")
-                    } else {
-                        panic!("Unexpected event: {:?}", e)
+                match swarms[0].poll_next_unpin(ctx) {
+                    Poll::Ready(Some(KademliaEvent::BootstrapResult(r))) => {
+                        assert!(r.is_ok(), "Unexpected error");
+                        results += 1;
                     }
-                } else {
-                    panic!("Expected event")
+                    Poll::Ready(Some(KademliaEvent::QueryProgressed { .. })) => (),
+                    Poll::Ready(Some(e)) => panic!("Unexpected event: {:?}", e),
+                    _ => panic!("Expected event")
                 }
             }
             Poll::Ready(())
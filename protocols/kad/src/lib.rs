@@ -39,7 +39,7 @@ mod dht_proto {
 }
 
 pub use addresses::Addresses;
-pub use behaviour::{Kademlia, KademliaConfig, KademliaEvent, Quorum};
+pub use behaviour::{Kademlia, KademliaConfig, KademliaEvent, QueryProgress, Quorum};
 pub use behaviour::{
     BootstrapResult,
     BootstrapOk,
@@ -66,6 +66,7 @@ pub use behaviour::{
     GetProvidersError,
 };
 pub use protocol::KadConnectionType;
+pub use query::QueryId;
 pub use record::{store, Record, ProviderRecord};
 
 use std::num::NonZeroUsize;
@@ -86,15 +86,40 @@ impl<TInner> QueryPool<TInner> {
         self.queries.values_mut()
     }
 
+    /// Allocates the ID of a new query, without adding a query to the pool.
+    ///
+    /// The ID can later be given to [`QueryPool::continue_fixed`] or
+    /// [`QueryPool::continue_iter_closest`].
+    pub fn next_query_id(&mut self) -> QueryId {
+        let id = QueryId(self.next_id);
+        self.next_id = self.next_id.wrapping_add(1);
+        id
+    }
+
     /// Adds a query to the pool that contacts a fixed set of peers.
     pub fn add_fixed<I>(&mut self, peers: I, inner: TInner) -> QueryId
     where
         I: IntoIterator<Item = Key<PeerId>>
     {
+        let id = self.next_query_id();
+        self.continue_fixed(id, peers, inner);
+        id
+    }
+
+    /// Adds a query to the pool that contacts a fixed set of peers, with the ID of a query
+    /// that is no longer in the pool (or was allocated with [`QueryPool::next_query_id`]).
+    ///
+    /// This allows a query to continue in another phase with the same ID.
+    pub fn continue_fixed<I>(&mut self, id: QueryId, peers: I, inner: TInner)
+    where
+        I: IntoIterator<Item = Key<PeerId>>
+    {
+        assert!(!self.queries.contains_key(&id));
         let peers = peers.into_iter().map(|k| k.into_preimage()).collect::<Vec<_>>();
         let parallelism = self.config.replication_factor.get();
         let peer_iter = QueryPeerIter::Fixed(FixedPeersIter::new(peers, parallelism));
-        self.add(peer_iter, inner)
+        let query = Query::new(id, peer_iter, inner);
+        self.queries.insert(id, query);
     }
 
     /// Adds a query to the pool that iterates towards the closest peers to the target.
@@ -103,20 +128,27 @@ impl<TInner> QueryPool<TInner> {
         T: Into<KeyBytes>,
         I: IntoIterator<Item = Key<PeerId>>
     {
+        let id = self.next_query_id();
+        self.continue_iter_closest(id, target, peers, inner);
+        id
+    }
+
+    /// Adds a query to the pool that iterates towards the closest peers to the target, with the
+    /// ID of a query that is no longer in the pool (or was allocated with
+    /// [`QueryPool::next_query_id`]).
+    pub fn continue_iter_closest<T, I>(&mut self, id: QueryId, target: T, peers: I, inner: TInner)
+    where
+        T: Into<KeyBytes>,
+        I: IntoIterator<Item = Key<PeerId>>
+    {
+        assert!(!self.queries.contains_key(&id));
         let cfg = ClosestPeersIterConfig {
             num_results: self.config.replication_factor.get(),
             .. ClosestPeersIterConfig::default()
         };
         let peer_iter = QueryPeerIter::Closest(ClosestPeersIter::with_config(cfg, target, peers));
-        self.add(peer_iter, inner)
-    }
-
-    fn add(&mut self, peer_iter: QueryPeerIter, inner: TInner) -> QueryId {
-        let id = QueryId(self.next_id);
-        self.next_id = self.next_id.wrapping_add(1);
         let query = Query::new(id, peer_iter, inner);
         self.queries.insert(id, query);
-        id
     }
 
     /// Returns a reference to a query with the given ID, if it is in the pool.
@@ -181,6 +213,9 @@ impl<TInner> QueryPool<TInner> {
 }
 
 /// Unique identifier for an active query.
+///
+/// Returned by the methods of `Kademlia` that start a query, and reported in the
+/// `KademliaEvent::QueryProgressed` events of that query.
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
 pub struct QueryId(usize);
 
@@ -278,6 +313,15 @@ impl<TInner> Query<TInner> {
         }
     }
 
+    /// Checks whether the query has finished, either prematurely through [`Query::finish`] or
+    /// because its peer iterator is exhausted, without having been reported yet.
+    pub fn is_finished(&self) -> bool {
+        match &self.peer_iter {
+            QueryPeerIter::Closest(iter) => iter.finished(),
+            QueryPeerIter::Fixed(iter) => iter.finished()
+        }
+    }
+
     /// Consumes the query, producing the final `QueryResult`.
     pub fn into_result(self) -> QueryResult<TInner, impl Iterator<Item = PeerId>> {
         let peers = match self.peer_iter {
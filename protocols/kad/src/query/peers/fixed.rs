@@ -93,6 +93,10 @@ impl FixedPeersIter {
         }
     }
 
+    pub fn finished(&self) -> bool {
+        if let State::Finished = self.state { true } else { false }
+    }
+
     pub fn next(&mut self) -> PeersIterState {
         match &mut self.state {
             State::Finished => return PeersIterState::Finished,
@@ -23,7 +23,7 @@
 pub mod store;
 
 use bytes::Bytes;
-use libp2p_core::PeerId;
+use libp2p_core::{PeerId, cid::Cid};
 use multihash::Multihash;
 use std::hash::{Hash, Hasher};
 use wasm_timer::Instant;
@@ -62,6 +62,20 @@ impl From<Multihash> for Key {
     }
 }
 
+/// The key of a CID is the key of its multihash, as for other IPFS implementations, so that
+/// all versions and codecs of a CID refer to the same provider records.
+impl From<&Cid> for Key {
+    fn from(cid: &Cid) -> Key {
+        Key::from(cid.hash().clone())
+    }
+}
+
+impl From<Cid> for Key {
+    fn from(cid: Cid) -> Key {
+        Key::from(Multihash::from(cid))
+    }
+}
+
 /// A record stored in the DHT.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Record {
@@ -173,5 +187,13 @@ mod tests {
             }
         }
     }
-}
 
+    #[test]
+    fn cid_key_is_multihash_key() {
+        let hash = multihash::encode(SHA2256, b"hello world").unwrap();
+        let v0 = Cid::new_v0(hash.clone()).unwrap();
+        let v1 = Cid::new_v1(libp2p_core::cid::Codec::Raw, hash.clone());
+        assert_eq!(Key::from(&v0), Key::from(hash));
+        assert_eq!(Key::from(&v0), Key::from(v1));
+    }
+}
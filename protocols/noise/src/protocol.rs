@@ -162,6 +162,23 @@ impl<T: Zeroize> Keypair<T> {
 
         Ok(AuthenticKeypair { keypair: self, identity })
     }
+
+    /// Same as [`Keypair::into_authentic`], but signs with [`identity::Keypair::sign_async`],
+    /// which supports the identity keypairs of external signers.
+    pub async fn into_authentic_async(self, id_keys: &identity::Keypair)
+        -> Result<AuthenticKeypair<T>, NoiseError>
+    where
+        T: AsRef<[u8]>
+    {
+        let sig = id_keys.sign_async(self.public.as_ref()).await?;
+
+        let identity = KeypairIdentity {
+            public: id_keys.public(),
+            signature: Some(sig)
+        };
+
+        Ok(AuthenticKeypair { keypair: self, identity })
+    }
 }
 
 /// DH secret key.
@@ -67,6 +67,61 @@ fn xx() {
     QuickCheck::new().max_tests(30).quickcheck(prop as fn(Vec<u8>) -> bool)
 }
 
+#[test]
+fn xx_ecdsa() {
+    let _ = env_logger::try_init();
+    let server_id = identity::Keypair::generate_ecdsa();
+    let client_id = identity::Keypair::generate_ecdsa();
+
+    let server_id_public = server_id.public();
+    let client_id_public = client_id.public();
+
+    let server_dh = Keypair::<X25519>::new().into_authentic(&server_id).unwrap();
+    let server_transport = TcpConfig::new()
+        .and_then(move |output, endpoint| {
+            upgrade::apply(output, NoiseConfig::xx(server_dh), endpoint, upgrade::Version::V1)
+        })
+        .and_then(move |out, _| expect_identity(out, &client_id_public));
+
+    let client_dh = Keypair::<X25519>::new().into_authentic(&client_id).unwrap();
+    let client_transport = TcpConfig::new()
+        .and_then(move |output, endpoint| {
+            upgrade::apply(output, NoiseConfig::xx(client_dh), endpoint, upgrade::Version::V1)
+        })
+        .and_then(move |out, _| expect_identity(out, &server_id_public));
+
+    run(server_transport, client_transport, b"hello ecdsa".to_vec());
+}
+
+#[test]
+fn xx_external_signer() {
+    let _ = env_logger::try_init();
+    let server_id = identity::Keypair::from_signer(
+        identity::signer::LocalSigner::new(identity::Keypair::generate_ed25519()));
+    let client_id = identity::Keypair::generate_ed25519();
+
+    let server_id_public = server_id.public();
+    let client_id_public = client_id.public();
+
+    assert!(Keypair::<X25519>::new().into_authentic(&server_id).is_err());
+    let server_dh = futures::executor::block_on(Keypair::<X25519>::new().into_authentic_async(&server_id))
+        .unwrap();
+    let server_transport = TcpConfig::new()
+        .and_then(move |output, endpoint| {
+            upgrade::apply(output, NoiseConfig::xx(server_dh), endpoint, upgrade::Version::V1)
+        })
+        .and_then(move |out, _| expect_identity(out, &client_id_public));
+
+    let client_dh = Keypair::<X25519>::new().into_authentic(&client_id).unwrap();
+    let client_transport = TcpConfig::new()
+        .and_then(move |output, endpoint| {
+            upgrade::apply(output, NoiseConfig::xx(client_dh), endpoint, upgrade::Version::V1)
+        })
+        .and_then(move |out, _| expect_identity(out, &server_id_public));
+
+    run(server_transport, client_transport, b"hello signer".to_vec());
+}
+
 #[test]
 fn ix() {
     let _ = env_logger::try_init();
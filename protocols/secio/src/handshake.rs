@@ -208,7 +208,7 @@ where
 
         Exchange {
             epubkey: Some(tmp_pub_key.clone()),
-            signature: match config.key.sign(&data_to_sign) {
+            signature: match config.key.sign_async(&data_to_sign).await {
                 Ok(sig) => Some(sig),
                 Err(_) => return Err(SecioError::SigningFailure)
             }
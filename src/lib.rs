@@ -156,6 +156,8 @@ use libp2p_pnet::{PnetConfig, PreSharedKey};
 pub use bytes;
 pub use futures;
 #[doc(inline)]
+pub use cid;
+#[doc(inline)]
 pub use multiaddr;
 #[doc(inline)]
 pub use multihash;
@@ -190,6 +192,9 @@ pub use libp2p_ping as ping;
 pub use libp2p_plaintext as plaintext;
 #[doc(inline)]
 pub use libp2p_secio as secio;
+#[cfg(not(any(target_os = "emscripten", target_os = "unknown")))]
+#[doc(inline)]
+pub use libp2p_socks5 as socks5;
 #[doc(inline)]
 pub use libp2p_swarm as swarm;
 #[cfg(not(any(target_os = "emscripten", target_os = "unknown")))]
@@ -0,0 +1,225 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Resolver querying nameservers without blocking a thread.
+
+use crate::wire::{self, Answer, Record, RecordType, Response};
+use crate::resolver::{Resolver, txt_records};
+use async_std::net::{TcpStream, UdpSocket};
+use futures::{prelude::*, future::{BoxFuture, Either}};
+use futures_timer::Delay;
+use log::{debug, trace};
+use std::{
+    collections::HashMap,
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant}
+};
+
+/// Cached records, with the instant they expire at.
+type CacheEntry = (Instant, Vec<Record>);
+
+/// Configuration of an `AsyncResolver`.
+#[derive(Debug, Clone)]
+pub struct ResolverConfig {
+    /// Nameservers to query, in order.
+    nameservers: Vec<SocketAddr>,
+    /// How long to wait for the answer of a nameserver.
+    timeout: Duration,
+    /// How many times each nameserver is queried before giving up.
+    attempts: usize,
+    /// Maximum number of answers to cache.
+    max_cache_entries: usize,
+}
+
+impl ResolverConfig {
+    /// Creates a configuration using the nameservers of `/etc/resolv.conf` on Unix platforms.
+    pub fn new() -> Self {
+        ResolverConfig {
+            nameservers: wire::system_nameservers(),
+            timeout: Duration::from_secs(5),
+            attempts: 2,
+            max_cache_entries: 1024,
+        }
+    }
+
+    /// Sets the nameservers to query, in order.
+    pub fn nameservers(&mut self, nameservers: Vec<SocketAddr>) -> &mut Self {
+        self.nameservers = nameservers;
+        self
+    }
+
+    /// Sets how long to wait for the answer of a nameserver.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how many times each nameserver is queried before giving up.
+    pub fn attempts(&mut self, attempts: usize) -> &mut Self {
+        self.attempts = attempts;
+        self
+    }
+
+    /// Sets the maximum number of answers to cache. A value of `0` disables caching.
+    pub fn max_cache_entries(&mut self, max: usize) -> &mut Self {
+        self.max_cache_entries = max;
+        self
+    }
+}
+
+impl Default for ResolverConfig {
+    fn default() -> Self {
+        ResolverConfig::new()
+    }
+}
+
+/// Resolver sending queries to the configured nameservers over UDP, without blocking a thread.
+/// Answers that don't fit in a UDP message are queried again over TCP.
+///
+/// Answers are cached for as long as their TTL allows. Unlike the system resolver, the
+/// `hosts` file is not taken into account.
+#[derive(Clone)]
+pub struct AsyncResolver {
+    config: Arc<ResolverConfig>,
+    /// Cached answers.
+    cache: Arc<Mutex<HashMap<(String, RecordType), CacheEntry>>>,
+}
+
+impl AsyncResolver {
+    /// Creates a resolver with the given configuration.
+    pub fn new(config: ResolverConfig) -> Self {
+        AsyncResolver {
+            config: Arc::new(config),
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Looks up the records of type `ty` of `name`, from the cache if possible.
+    fn lookup(&self, name: &str, ty: RecordType) -> BoxFuture<'static, Result<Vec<Record>, io::Error>> {
+        let key = (name.to_string(), ty);
+        {
+            let mut cache = self.cache.lock().expect("lock is never poisoned");
+            match cache.get(&key) {
+                Some((expires, records)) if *expires > Instant::now() => {
+                    trace!("Using cached {:?} records of {}", ty, name);
+                    return future::ok(records.clone()).boxed()
+                }
+                Some(_) => { cache.remove(&key); }
+                None => {}
+            }
+        }
+
+        let config = self.config.clone();
+        let cache = self.cache.clone();
+        async move {
+            let answer = query(&config, &key.0, ty).await?;
+            if let Some(ttl) = answer.ttl {
+                let mut cache = cache.lock().expect("lock is never poisoned");
+                let now = Instant::now();
+                if cache.len() >= config.max_cache_entries {
+                    cache.retain(|_, (expires, _)| *expires > now);
+                }
+                if cache.len() < config.max_cache_entries {
+                    cache.insert(key, (now + ttl, answer.records.clone()));
+                }
+            }
+            Ok(answer.records)
+        }.boxed()
+    }
+}
+
+impl Resolver for AsyncResolver {
+    fn lookup_ip(&self, name: &str) -> BoxFuture<'static, Result<Vec<IpAddr>, io::Error>> {
+        let lookups = future::join(self.lookup(name, RecordType::A), self.lookup(name, RecordType::Aaaa));
+        lookups.map(|results| {
+            let (v4, v6) = match results {
+                (Err(err), Err(_)) => return Err(err),
+                (v4, v6) => (v4.unwrap_or_default(), v6.unwrap_or_default())
+            };
+            Ok(v4.into_iter().chain(v6)
+                .filter_map(|record| match record {
+                    Record::Ip(ip) => Some(ip),
+                    Record::Txt(_) => None
+                })
+                .collect())
+        }).boxed()
+    }
+
+    fn lookup_txt(&self, name: &str) -> BoxFuture<'static, Result<Vec<String>, io::Error>> {
+        self.lookup(name, RecordType::Txt)
+            .map_ok(|records| txt_records(Answer { records, ttl: None }))
+            .boxed()
+    }
+}
+
+/// Queries the configured nameservers in order until one answers.
+async fn query(config: &ResolverConfig, name: &str, ty: RecordType) -> Result<Answer, io::Error> {
+    let mut last_err = io::Error::new(io::ErrorKind::Other, "no nameserver configured");
+    for _ in 0 .. config.attempts {
+        for nameserver in &config.nameservers {
+            let query = query_nameserver(*nameserver, name, ty);
+            futures::pin_mut!(query);
+            match future::select(query, Delay::new(config.timeout)).await {
+                Either::Left((Ok(answer), _)) => return Ok(answer),
+                Either::Left((Err(err), _)) => {
+                    debug!("Failed to query {} for {}: {}", nameserver, name, err);
+                    last_err = err
+                }
+                Either::Right(_) => {
+                    debug!("Timeout querying {} for {}", nameserver, name);
+                    last_err = io::ErrorKind::TimedOut.into()
+                }
+            }
+        }
+    }
+    Err(last_err)
+}
+
+/// Sends a query to `nameserver` over UDP and waits for the answer, querying again over TCP if
+/// the answer is truncated.
+async fn query_nameserver(nameserver: SocketAddr, name: &str, ty: RecordType) -> Result<Answer, io::Error> {
+    let socket = UdpSocket::bind(wire::unspecified(&nameserver)).await?;
+    socket.connect(nameserver).await?;
+
+    let id = rand::random();
+    socket.send(&wire::encode_query(id, name, ty)?).await?;
+
+    let mut buf = [0u8; wire::MAX_MESSAGE_SIZE];
+    loop {
+        let n = socket.recv(&mut buf).await?;
+        match wire::decode_response(id, ty, &buf[.. n])? {
+            Some(Response::Answer(answer)) => return Ok(answer),
+            Some(Response::Truncated) => break,
+            None => {}
+        }
+    }
+
+    debug!("Answer of {} for {} is truncated; querying over TCP", nameserver, name);
+    let mut stream = TcpStream::connect(nameserver).await?;
+    let id = rand::random();
+    stream.write_all(&wire::encode_tcp(wire::encode_query(id, name, ty)?)).await?;
+    let mut len = [0u8; 2];
+    stream.read_exact(&mut len).await?;
+    let mut msg = vec![0u8; usize::from(u16::from_be_bytes(len))];
+    stream.read_exact(&mut msg).await?;
+    wire::decode_tcp_response(id, ty, &msg)
+}
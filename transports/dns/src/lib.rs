@@ -20,8 +20,8 @@
 
 //! # libp2p-dns
 //!
-//! This crate provides the type `DnsConfig` that allows one to resolve the `/dns4/` and `/dns6/`
-//! components of multiaddresses.
+//! This crate provides the type `DnsConfig` that allows one to resolve the `/dns/`, `/dns4/`,
+//! `/dns6/` and `/dnsaddr/` components of multiaddresses.
 //!
 //! ## Usage
 //!
@@ -29,32 +29,67 @@
 //! implementation of the `Transport` trait.
 //!
 //! Whenever we want to dial an address through the `DnsConfig` and that address contains a
-//! `/dns4/` or `/dns6/` component, a DNS resolve will be performed and the component will be
-//! replaced with respectively an `/ip4/` or an `/ip6/` component.
+//! `/dns/`, `/dns4/` or `/dns6/` component, a DNS resolve will be performed and the component
+//! will be replaced with an `/ip4/` or an `/ip6/` component. `/dns4/` and `/dns6/` only resolve
+//! to addresses of the respective IP version, while `/dns/` resolves to both.
 //!
+//! A `/dnsaddr/<name>` component is resolved by looking up the TXT records of
+//! `_dnsaddr.<name>`. Every record of the form `dnsaddr=<multiaddr>` whose multiaddress ends
+//! with the components following the `/dnsaddr/` component (typically `/p2p/<peer-id>`) replaces
+//! the address, and is itself resolved recursively.
+//!
+//! The resulting addresses are dialed one after the other until a connection succeeds.
+//!
+//! ## Resolvers
+//!
+//! The lookups are performed by an implementation of the `Resolver` trait. By default, the
+//! `ThreadPoolResolver` uses the resolver of the operating system on a pool of threads. The
+//! `AsyncResolver`, available with the `async-std` feature enabled by default, instead queries
+//! nameservers directly, without blocking any thread, and caches the answers according to their
+//! TTL.
+//!
+
+#[cfg(feature = "async-std")]
+mod async_resolver;
+mod resolver;
+mod wire;
 
-use futures::{prelude::*, channel::oneshot, future::BoxFuture};
+#[cfg(feature = "async-std")]
+pub use async_resolver::{AsyncResolver, ResolverConfig};
+pub use resolver::{Resolver, ThreadPoolResolver};
+
+use futures::{prelude::*, future::BoxFuture};
 use libp2p_core::{
     Transport,
     multiaddr::{Protocol, Multiaddr},
     transport::{TransportError, ListenerEvent}
 };
-use log::{error, debug, trace};
-use std::{error, fmt, io, net::ToSocketAddrs};
+use log::{debug, trace};
+use std::{collections::VecDeque, error, fmt, io, net::SocketAddr, sync::Arc};
+
+/// Maximum number of nested `/dnsaddr/` lookups performed for a single address.
+const MAX_DNSADDR_RECURSION: usize = 8;
+/// Maximum number of addresses an address is resolved to, including the ones that are yet to be
+/// resolved.
+const MAX_RESOLVED_ADDRS: usize = 32;
+/// Maximum number of lookups performed to resolve a single address.
+const MAX_LOOKUPS: usize = 32;
 
 /// Represents the configuration for a DNS transport capability of libp2p.
 ///
 /// This struct implements the `Transport` trait and holds an underlying transport. Any call to
-/// `dial` with a multiaddr that contains `/dns4/` or `/dns6/` will be first be resolved, then
-/// passed to the underlying transport.
+/// `dial` with a multiaddr that contains `/dns/`, `/dns4/`, `/dns6/` or `/dnsaddr/` will be first
+/// be resolved, then passed to the underlying transport.
 ///
 /// Listening is unaffected.
 #[derive(Clone)]
 pub struct DnsConfig<T> {
     /// Underlying transport to use once the DNS addresses have been resolved.
     inner: T,
-    /// Pool of threads to use when resolving DNS addresses.
-    thread_pool: futures::executor::ThreadPool,
+    /// Resolver to use when resolving DNS addresses.
+    resolver: Arc<dyn Resolver + Send + Sync>,
+    /// The resolver created by `new` or `with_resolve_threads`, if `resolver` is that one.
+    thread_pool: Option<ThreadPoolResolver>,
 }
 
 impl<T> DnsConfig<T> {
@@ -65,18 +100,38 @@ impl<T> DnsConfig<T> {
 
     /// Same as `new`, but allows specifying a number of threads for the resolving.
     pub fn with_resolve_threads(inner: T, num_threads: usize) -> Result<DnsConfig<T>, io::Error> {
-        let thread_pool = futures::executor::ThreadPool::builder()
-            .pool_size(num_threads)
-            .name_prefix("libp2p-dns-")
-            .create()?;
-
-        trace!("Created a DNS thread pool");
-
+        let thread_pool = ThreadPoolResolver::new(num_threads)?;
         Ok(DnsConfig {
             inner,
-            thread_pool,
+            resolver: Arc::new(thread_pool.clone()),
+            thread_pool: Some(thread_pool),
         })
     }
+
+    /// Creates a new configuration object for DNS that uses the given resolver.
+    pub fn with_resolver<R>(inner: T, resolver: R) -> DnsConfig<T>
+    where
+        R: Resolver + Send + Sync + 'static
+    {
+        DnsConfig {
+            inner,
+            resolver: Arc::new(resolver),
+            thread_pool: None,
+        }
+    }
+
+    /// Sets the nameserver used to look up the TXT records of `/dnsaddr/` components.
+    ///
+    /// Defaults to the first nameserver of `/etc/resolv.conf` on Unix platforms. Has no effect
+    /// on a resolver passed to `with_resolver`, which is configured on its own.
+    pub fn with_nameserver(mut self, nameserver: SocketAddr) -> Self {
+        if let Some(thread_pool) = self.thread_pool.take() {
+            let thread_pool = thread_pool.with_nameserver(nameserver);
+            self.resolver = Arc::new(thread_pool.clone());
+            self.thread_pool = Some(thread_pool);
+        }
+        self
+    }
 }
 
 impl<T> fmt::Debug for DnsConfig<T>
@@ -90,7 +145,7 @@ where
 
 impl<T> Transport for DnsConfig<T>
 where
-    T: Transport + Send + 'static,
+    T: Transport + Clone + Send + 'static,
     T::Error: Send,
     T::Dial: Send
 {
@@ -120,8 +175,10 @@ where
         // As an optimization, we immediately pass through if no component of the address contain
         // a DNS protocol.
         let contains_dns = addr.iter().any(|cmp| match cmp {
+            Protocol::Dns(_) => true,
             Protocol::Dns4(_) => true,
             Protocol::Dns6(_) => true,
+            Protocol::Dnsaddr(_) => true,
             _ => false,
         });
 
@@ -133,67 +190,154 @@ where
         }
 
         trace!("Dialing address with DNS: {}", addr);
-        let resolve_futs = addr.iter()
-            .map(|cmp| match cmp {
-                Protocol::Dns4(ref name) | Protocol::Dns6(ref name) => {
-                    let name = name.to_string();
-                    let to_resolve = format!("{}:0", name);
-                    let (tx, rx) = oneshot::channel();
-                    self.thread_pool.spawn_ok(async {
-                        let to_resolve = to_resolve;
-                        let _ = tx.send(match to_resolve[..].to_socket_addrs() {
-                            Ok(list) => Ok(list.map(|s| s.ip()).collect::<Vec<_>>()),
-                            Err(e) => Err(e),
-                        });
-                    });
-
-                    let is_dns4 = if let Protocol::Dns4(_) = cmp { true } else { false };
-
-                    async move {
-                        let list = rx.await
-                            .map_err(|_| {
-                                error!("DNS resolver crashed");
-                                DnsErr::ResolveFail(name.clone())
-                            })?
-                            .map_err(|err| DnsErr::ResolveError {
-                                domain_name: name.clone(),
-                                error: err,
-                            })?;
-
-                        list.into_iter()
-                            .filter_map(|addr| {
-                                if (is_dns4 && addr.is_ipv4()) || (!is_dns4 && addr.is_ipv6()) {
-                                    Some(Protocol::from(addr))
-                                } else {
-                                    None
-                                }
-                            })
-                            .next()
-                            .ok_or_else(|| DnsErr::ResolveFail(name))
-                    }.left_future()
-                },
-                cmp => future::ready(Ok(cmp.acquire())).right_future()
-            })
-            .collect::<stream::FuturesOrdered<_>>();
-
-        let future = resolve_futs.collect::<Vec<_>>()
-            .then(move |outcome| async move {
-                let outcome = outcome.into_iter().collect::<Result<Vec<_>, _>>()?;
-                let outcome = outcome.into_iter().collect::<Multiaddr>();
-                debug!("DNS resolution outcome: {} => {}", addr, outcome);
-
-                match self.inner.dial(outcome) {
-                    Ok(d) => d.await.map_err(DnsErr::Underlying),
-                    Err(TransportError::MultiaddrNotSupported(_addr)) =>
-                        Err(DnsErr::MultiaddrNotSupported),
-                    Err(TransportError::Other(err)) => Err(DnsErr::Underlying(err))
+        let future = async move {
+            let addrs = resolve(&*self.resolver, addr.clone()).await?;
+            debug!("DNS resolution outcome: {} => {:?}", addr, addrs);
+
+            let mut last_err = None;
+            for addr in addrs {
+                match self.inner.clone().dial(addr) {
+                    Ok(d) => match d.await {
+                        Ok(output) => return Ok(output),
+                        Err(err) => last_err = Some(DnsErr::Underlying(err))
+                    },
+                    Err(TransportError::MultiaddrNotSupported(addr)) => {
+                        debug!("Resolved address not supported: {}", addr);
+                        last_err = Some(DnsErr::MultiaddrNotSupported)
+                    }
+                    Err(TransportError::Other(err)) => last_err = Some(DnsErr::Underlying(err))
                 }
-            });
+            }
+
+            Err(last_err.expect("resolve returns at least one address"))
+        };
 
         Ok(future.boxed().right_future())
     }
 }
 
+/// Resolves the DNS components of `addr`, returning at least one address without DNS
+/// components on success.
+async fn resolve<TErr>(resolver: &(dyn Resolver + Send + Sync), addr: Multiaddr)
+    -> Result<Vec<Multiaddr>, DnsErr<TErr>>
+{
+    let mut resolved = Vec::new();
+    let mut last_err = None;
+    // Addresses yet to be resolved, with the number of `/dnsaddr/` lookups that lead to them.
+    let mut unresolved = VecDeque::new();
+    unresolved.push_back((addr.clone(), 0));
+    let mut lookups = 0;
+
+    while let Some((addr, depth)) = unresolved.pop_front() {
+        let (index, name) = match addr.iter().enumerate().find_map(|(i, p)| dns_name(&p).map(|n| (i, n))) {
+            Some(found) => found,
+            None => {
+                resolved.push(addr);
+                continue
+            }
+        };
+
+        if lookups >= MAX_LOOKUPS {
+            debug!("Too many DNS lookups; ignoring {}", addr);
+            continue
+        }
+        lookups += 1;
+
+        match addr.iter().nth(index).expect("index was found above") {
+            Protocol::Dnsaddr(_) => {
+                if depth >= MAX_DNSADDR_RECURSION {
+                    debug!("Too many nested /dnsaddr/ lookups; ignoring {}", addr);
+                    continue
+                }
+                let txt_name = format!("_dnsaddr.{}", name);
+                let records = match resolver.lookup_txt(&txt_name).await {
+                    Ok(records) => records,
+                    Err(error) => {
+                        last_err = Some(DnsErr::ResolveError { domain_name: txt_name, error });
+                        continue
+                    }
+                };
+                let prefix = addr.iter().take(index).collect::<Vec<_>>();
+                let suffix = addr.iter().skip(index + 1).collect::<Vec<_>>();
+                for record in records {
+                    if !record.starts_with("dnsaddr=") {
+                        continue
+                    }
+                    let value = &record["dnsaddr=".len() ..];
+                    let found = match value.parse::<Multiaddr>() {
+                        Ok(found) => found,
+                        Err(err) => {
+                            debug!("Invalid multiaddr in TXT record of {}: {:?}: {}", name, value, err);
+                            continue
+                        }
+                    };
+                    if !ends_with(&found, &suffix) {
+                        trace!("Ignoring {} as it does not end with {:?}", found, suffix);
+                        continue
+                    }
+                    if resolved.len() + unresolved.len() >= MAX_RESOLVED_ADDRS {
+                        debug!("Too many resolved addresses; ignoring {}", found);
+                        break
+                    }
+                    let new_addr = prefix.iter().cloned().chain(found.iter()).collect();
+                    unresolved.push_back((new_addr, depth + 1));
+                }
+            }
+            cmp => {
+                let ips = match resolver.lookup_ip(&name).await {
+                    Ok(ips) => ips,
+                    Err(error) => {
+                        last_err = Some(DnsErr::ResolveError { domain_name: name, error });
+                        continue
+                    }
+                };
+                for ip in ips {
+                    let matches = match cmp {
+                        Protocol::Dns4(_) => ip.is_ipv4(),
+                        Protocol::Dns6(_) => ip.is_ipv6(),
+                        _ => true
+                    };
+                    if !matches {
+                        continue
+                    }
+                    if resolved.len() + unresolved.len() >= MAX_RESOLVED_ADDRS {
+                        debug!("Too many resolved addresses; ignoring {}", ip);
+                        break
+                    }
+                    let new_addr = addr.iter()
+                        .enumerate()
+                        .map(|(i, p)| if i == index { Protocol::from(ip) } else { p })
+                        .collect();
+                    unresolved.push_back((new_addr, depth));
+                }
+            }
+        }
+    }
+
+    if resolved.is_empty() {
+        return Err(last_err.unwrap_or_else(|| DnsErr::ResolveFail(addr.to_string())))
+    }
+
+    Ok(resolved)
+}
+
+/// Returns the name to resolve if `p` is a DNS component.
+fn dns_name(p: &Protocol) -> Option<String> {
+    match p {
+        Protocol::Dns(name)
+        | Protocol::Dns4(name)
+        | Protocol::Dns6(name)
+        | Protocol::Dnsaddr(name) => Some(name.to_string()),
+        _ => None
+    }
+}
+
+/// Returns true if the last components of `addr` are `suffix`.
+fn ends_with(addr: &Multiaddr, suffix: &[Protocol]) -> bool {
+    let len = addr.iter().count();
+    len >= suffix.len() && addr.iter().skip(len - suffix.len()).eq(suffix.iter().cloned())
+}
+
 /// Error that can be generated by the DNS layer.
 #[derive(Debug)]
 pub enum DnsErr<TErr> {
@@ -240,7 +384,9 @@ where TErr: error::Error + 'static
 
 #[cfg(test)]
 mod tests {
-    use super::DnsConfig;
+    #[cfg(feature = "async-std")]
+    use super::{AsyncResolver, ResolverConfig};
+    use super::{DnsConfig, Resolver, ThreadPoolResolver};
     use futures::{future::BoxFuture, prelude::*, stream::BoxStream};
     use libp2p_core::{
         Transport,
@@ -248,6 +394,112 @@ mod tests {
         transport::ListenerEvent,
         transport::TransportError,
     };
+    use std::{
+        collections::HashMap,
+        io,
+        io::{Read, Write},
+        net::{IpAddr, SocketAddr, TcpListener, UdpSocket},
+        sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}},
+        thread
+    };
+
+    /// Transport that records the dialed addresses.
+    #[derive(Clone, Default)]
+    struct RecordingTransport(Arc<Mutex<Vec<Multiaddr>>>);
+
+    impl Transport for RecordingTransport {
+        type Output = ();
+        type Error = std::io::Error;
+        type Listener = BoxStream<'static, Result<ListenerEvent<Self::ListenerUpgrade>, Self::Error>>;
+        type ListenerUpgrade = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+        type Dial = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+        fn listen_on(self, _: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
+            unreachable!()
+        }
+
+        fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+            self.0.lock().unwrap().push(addr);
+            Ok(Box::pin(future::ready(Ok(()))))
+        }
+    }
+
+    /// Spawns a DNS server answering TXT queries with the given records, returning its address
+    /// and the number of queries it received.
+    fn stub_resolver(records: Vec<(&'static str, Vec<&'static str>)>) -> (SocketAddr, Arc<AtomicUsize>) {
+        stub_resolver_with_udp_limit(records, 4096)
+    }
+
+    /// Same as `stub_resolver`, but answers larger than `udp_limit` bytes are truncated over
+    /// UDP and must be queried again over TCP.
+    fn stub_resolver_with_udp_limit(records: Vec<(&'static str, Vec<&'static str>)>, udp_limit: usize)
+        -> (SocketAddr, Arc<AtomicUsize>)
+    {
+        let records = Arc::new(records.into_iter().collect::<HashMap<_, _>>());
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let listener = TcpListener::bind(addr).unwrap();
+        let queries = Arc::new(AtomicUsize::new(0));
+
+        let (records2, queries2) = (records.clone(), queries.clone());
+        thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            loop {
+                let (n, from) = socket.recv_from(&mut buf).unwrap();
+                queries2.fetch_add(1, Ordering::SeqCst);
+                let mut response = stub_answer(&buf[.. n], &records2);
+                if response.len() > udp_limit {
+                    // Only keep the header and the question, and set the TC flag.
+                    response.truncate(n - 11);
+                    response[2] |= 0x02;
+                    response[7] = 0;
+                }
+                socket.send_to(&response, from).unwrap();
+            }
+        });
+
+        let queries2 = queries.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                queries2.fetch_add(1, Ordering::SeqCst);
+                let mut len = [0u8; 2];
+                stream.read_exact(&mut len).unwrap();
+                let mut query = vec![0u8; usize::from(u16::from_be_bytes(len))];
+                stream.read_exact(&mut query).unwrap();
+                let response = stub_answer(&query, &records);
+                stream.write_all(&(response.len() as u16).to_be_bytes()).unwrap();
+                stream.write_all(&response).unwrap();
+            }
+        });
+
+        (addr, queries)
+    }
+
+    /// Builds the answer to a TXT `query` from `records`.
+    fn stub_answer(query: &[u8], records: &HashMap<&'static str, Vec<&'static str>>) -> Vec<u8> {
+        let mut labels = Vec::new();
+        let mut pos = 12;
+        while query[pos] != 0 {
+            let len = query[pos] as usize;
+            labels.push(std::str::from_utf8(&query[pos + 1 .. pos + 1 + len]).unwrap());
+            pos += 1 + len;
+        }
+        let texts = records.get(labels.join(".").as_str()).cloned().unwrap_or_default();
+
+        // Keep the question, without the `OPT` record of the query.
+        let mut response = query[.. pos + 5].to_vec();
+        response[2] |= 0x80;
+        response[7] = texts.len() as u8;
+        response[11] = 0;
+        for text in texts {
+            response.extend_from_slice(&[0xc0, 12, 0, 16, 0, 1, 0, 0, 0, 60]);
+            response.extend_from_slice(&(text.len() as u16 + 1).to_be_bytes());
+            response.push(text.len() as u8);
+            response.extend_from_slice(text.as_bytes());
+        }
+        response
+    }
 
     #[test]
     fn basic_resolve() {
@@ -305,4 +557,195 @@ mod tests {
                 .unwrap();
         });
     }
+
+    #[test]
+    fn dnsaddr_resolve() {
+        let (nameserver, _) = stub_resolver(vec![
+            ("_dnsaddr.bootstrap.example.com", vec![
+                "dnsaddr=/dnsaddr/a.example.com/p2p/QmNnooDu7bfjPFoTZYxMNLWUQJyrVwtbZg5gBMjTezGAJN",
+                "dnsaddr=/dnsaddr/b.example.com/p2p/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC",
+            ]),
+            ("_dnsaddr.a.example.com", vec![
+                "dnsaddr=/ip4/1.2.3.4/tcp/4001/p2p/QmNnooDu7bfjPFoTZYxMNLWUQJyrVwtbZg5gBMjTezGAJN",
+            ]),
+            ("_dnsaddr.b.example.com", vec![
+                "unrelated",
+                "dnsaddr=/ip6/::1/tcp/4001/p2p/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC",
+            ]),
+            ("_dnsaddr.loop.example.com", vec!["dnsaddr=/dnsaddr/loop.example.com"]),
+        ]);
+
+        futures::executor::block_on(async move {
+            let inner = RecordingTransport::default();
+            let transport = DnsConfig::new(inner.clone()).unwrap().with_nameserver(nameserver);
+
+            // Only the addresses with a matching `/p2p/` suffix are dialed.
+            transport.clone()
+                .dial("/dnsaddr/bootstrap.example.com/p2p/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC".parse().unwrap())
+                .unwrap()
+                .await
+                .unwrap();
+            assert_eq!(
+                inner.0.lock().unwrap().drain(..).collect::<Vec<_>>(),
+                vec!["/ip6/::1/tcp/4001/p2p/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC".parse().unwrap()]
+            );
+
+            // Without a suffix, the first address is dialed.
+            transport.clone()
+                .dial("/dnsaddr/bootstrap.example.com".parse().unwrap())
+                .unwrap()
+                .await
+                .unwrap();
+            assert_eq!(
+                inner.0.lock().unwrap().drain(..).collect::<Vec<_>>(),
+                vec!["/ip4/1.2.3.4/tcp/4001/p2p/QmNnooDu7bfjPFoTZYxMNLWUQJyrVwtbZg5gBMjTezGAJN".parse().unwrap()]
+            );
+
+            // Recursion is bounded.
+            assert!(transport.clone().dial("/dnsaddr/loop.example.com".parse().unwrap()).unwrap().await.is_err());
+            assert!(transport.dial("/dnsaddr/missing.example.com".parse().unwrap()).unwrap().await.is_err());
+            assert!(inner.0.lock().unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn truncated_answers_are_queried_over_tcp() {
+        let (nameserver, queries) = stub_resolver_with_udp_limit(vec![
+            ("_dnsaddr.example.com", vec![
+                "dnsaddr=/ip4/1.2.3.4/tcp/4001/p2p/QmNnooDu7bfjPFoTZYxMNLWUQJyrVwtbZg5gBMjTezGAJN",
+                "dnsaddr=/ip4/1.2.3.5/tcp/4001/p2p/QmNnooDu7bfjPFoTZYxMNLWUQJyrVwtbZg5gBMjTezGAJN",
+            ]),
+        ], 100);
+
+        futures::executor::block_on(async move {
+            let inner = RecordingTransport::default();
+            let resolver = ThreadPoolResolver::new(1).unwrap().with_nameserver(nameserver);
+            let transport = DnsConfig::with_resolver(inner.clone(), resolver);
+            transport.dial("/dnsaddr/example.com".parse().unwrap()).unwrap().await.unwrap();
+            assert_eq!(queries.load(Ordering::SeqCst), 2);
+            assert_eq!(
+                inner.0.lock().unwrap().drain(..).collect::<Vec<_>>(),
+                vec!["/ip4/1.2.3.4/tcp/4001/p2p/QmNnooDu7bfjPFoTZYxMNLWUQJyrVwtbZg5gBMjTezGAJN".parse().unwrap()]
+            );
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "async-std")]
+    fn async_resolver_queries_truncated_answers_over_tcp() {
+        let (nameserver, queries) = stub_resolver_with_udp_limit(vec![
+            ("_dnsaddr.example.com", vec![
+                "dnsaddr=/ip4/1.2.3.4/tcp/4001/p2p/QmNnooDu7bfjPFoTZYxMNLWUQJyrVwtbZg5gBMjTezGAJN",
+                "dnsaddr=/ip4/1.2.3.5/tcp/4001/p2p/QmNnooDu7bfjPFoTZYxMNLWUQJyrVwtbZg5gBMjTezGAJN",
+            ]),
+        ], 100);
+
+        futures::executor::block_on(async move {
+            let inner = RecordingTransport::default();
+            let resolver = AsyncResolver::new(ResolverConfig::new().nameservers(vec![nameserver]).clone());
+            let transport = DnsConfig::with_resolver(inner.clone(), resolver);
+            transport.dial("/dnsaddr/example.com".parse().unwrap()).unwrap().await.unwrap();
+            assert_eq!(queries.load(Ordering::SeqCst), 2);
+            assert_eq!(
+                inner.0.lock().unwrap().drain(..).collect::<Vec<_>>(),
+                vec!["/ip4/1.2.3.4/tcp/4001/p2p/QmNnooDu7bfjPFoTZYxMNLWUQJyrVwtbZg5gBMjTezGAJN".parse().unwrap()]
+            );
+        });
+    }
+
+    #[test]
+    fn resolution_is_bounded() {
+        let wide = (0 .. 100)
+            .map(|i| &*Box::leak(format!("dnsaddr=/ip4/10.0.0.{}/tcp/4001", i).into_boxed_str()))
+            .collect();
+        let (nameserver, _) = stub_resolver(vec![("_dnsaddr.wide.example.com", wide)]);
+
+        futures::executor::block_on(async move {
+            let resolver = ThreadPoolResolver::new(1).unwrap().with_nameserver(nameserver);
+            let addrs = super::resolve::<io::Error>(&resolver, "/dnsaddr/wide.example.com".parse().unwrap())
+                .await
+                .unwrap();
+            assert_eq!(addrs.len(), super::MAX_RESOLVED_ADDRS);
+        });
+    }
+
+    #[test]
+    fn dns_resolves_both_families() {
+        futures::executor::block_on(async move {
+            let inner = RecordingTransport::default();
+            let transport = DnsConfig::new(inner.clone()).unwrap();
+
+            transport.dial("/dns/localhost/tcp/20000".parse().unwrap()).unwrap().await.unwrap();
+            let dialed = inner.0.lock().unwrap().clone();
+            assert_eq!(dialed.len(), 1);
+            match dialed[0].iter().next() {
+                Some(Protocol::Ip4(ip)) => assert!(ip.is_loopback()),
+                Some(Protocol::Ip6(ip)) => assert!(ip.is_loopback()),
+                other => panic!("Unexpected protocol: {:?}", other)
+            }
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "async-std")]
+    fn async_resolver_caches_answers() {
+        let (nameserver, queries) = stub_resolver(vec![
+            ("_dnsaddr.example.com", vec!["dnsaddr=/ip4/1.2.3.4/tcp/4001"]),
+        ]);
+
+        futures::executor::block_on(async move {
+            let resolver = AsyncResolver::new(ResolverConfig::new().nameservers(vec![nameserver]).clone());
+            let inner = RecordingTransport::default();
+            let transport = DnsConfig::with_resolver(inner.clone(), resolver);
+
+            for _ in 0 .. 3 {
+                transport.clone().dial("/dnsaddr/example.com".parse().unwrap()).unwrap().await.unwrap();
+            }
+            assert_eq!(inner.0.lock().unwrap().len(), 3);
+            assert_eq!(queries.load(Ordering::SeqCst), 1);
+
+            let resolver = AsyncResolver::new(ResolverConfig::new().nameservers(vec![nameserver]).max_cache_entries(0).clone());
+            let transport = DnsConfig::with_resolver(inner.clone(), resolver);
+            for _ in 0 .. 3 {
+                transport.clone().dial("/dnsaddr/example.com".parse().unwrap()).unwrap().await.unwrap();
+            }
+            assert_eq!(queries.load(Ordering::SeqCst), 4);
+        });
+    }
+
+    #[test]
+    fn custom_resolver() {
+        struct FakeResolver;
+
+        impl Resolver for FakeResolver {
+            fn lookup_ip(&self, name: &str) -> BoxFuture<'static, Result<Vec<IpAddr>, io::Error>> {
+                let ips = match name {
+                    "example.com" => vec!["::1".parse().unwrap(), "10.0.0.1".parse().unwrap()],
+                    _ => Vec::new()
+                };
+                future::ok(ips).boxed()
+            }
+
+            fn lookup_txt(&self, _: &str) -> BoxFuture<'static, Result<Vec<String>, io::Error>> {
+                future::err(io::ErrorKind::Other.into()).boxed()
+            }
+        }
+
+        futures::executor::block_on(async move {
+            let inner = RecordingTransport::default();
+            let transport = DnsConfig::with_resolver(inner.clone(), FakeResolver);
+
+            transport.clone().dial("/dns4/example.com/tcp/20000".parse().unwrap()).unwrap().await.unwrap();
+            transport.clone().dial("/dns6/example.com/tcp/20000".parse().unwrap()).unwrap().await.unwrap();
+            assert!(transport.clone().dial("/dns/unknown.com/tcp/20000".parse().unwrap()).unwrap().await.is_err());
+            assert!(transport.dial("/dnsaddr/example.com".parse().unwrap()).unwrap().await.is_err());
+            assert_eq!(
+                inner.0.lock().unwrap().clone(),
+                vec![
+                    "/ip4/10.0.0.1/tcp/20000".parse().unwrap(),
+                    "/ip6/::1/tcp/20000".parse().unwrap(),
+                ]
+            );
+        });
+    }
 }
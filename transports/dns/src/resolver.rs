@@ -0,0 +1,130 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Resolvers used by `DnsConfig` to look up names.
+
+use crate::wire::{self, Answer, Record, RecordType};
+use futures::{prelude::*, channel::oneshot, executor::ThreadPool, future::BoxFuture};
+use log::{error, trace};
+use std::{io, net::{IpAddr, SocketAddr, ToSocketAddrs}, time::Duration};
+
+/// Looks up the records of names.
+pub trait Resolver {
+    /// Resolves `name` to IP addresses of any version.
+    fn lookup_ip(&self, name: &str) -> BoxFuture<'static, Result<Vec<IpAddr>, io::Error>>;
+
+    /// Looks up the TXT records of `name`, each being the concatenation of its strings.
+    ///
+    /// A name without TXT records, or that does not exist, has an empty list of records.
+    fn lookup_txt(&self, name: &str) -> BoxFuture<'static, Result<Vec<String>, io::Error>>;
+}
+
+/// Resolver using the resolver of the operating system on a pool of threads.
+///
+/// As the system resolver cannot look up TXT records, those are queried directly from a
+/// nameserver.
+#[derive(Clone)]
+pub struct ThreadPoolResolver {
+    /// Pool of threads to use when resolving DNS addresses.
+    thread_pool: ThreadPool,
+    /// Nameserver to send TXT queries to.
+    nameserver: Option<SocketAddr>,
+    /// Timeout of TXT queries.
+    timeout: Duration,
+}
+
+impl ThreadPoolResolver {
+    /// Creates a resolver running on `num_threads` threads.
+    pub fn new(num_threads: usize) -> Result<Self, io::Error> {
+        let thread_pool = ThreadPool::builder()
+            .pool_size(num_threads)
+            .name_prefix("libp2p-dns-")
+            .create()?;
+
+        trace!("Created a DNS thread pool");
+
+        Ok(ThreadPoolResolver {
+            thread_pool,
+            nameserver: wire::system_nameservers().into_iter().next(),
+            timeout: Duration::from_secs(5),
+        })
+    }
+
+    /// Sets the nameserver that TXT records are queried from.
+    ///
+    /// Defaults to the first nameserver of `/etc/resolv.conf` on Unix platforms.
+    pub fn with_nameserver(mut self, nameserver: SocketAddr) -> Self {
+        self.nameserver = Some(nameserver);
+        self
+    }
+
+    /// Runs `f` on the thread pool.
+    fn spawn<F, R>(&self, f: F) -> BoxFuture<'static, Result<R, io::Error>>
+    where
+        F: FnOnce() -> Result<R, io::Error> + Send + 'static,
+        R: Send + 'static
+    {
+        let (tx, rx) = oneshot::channel();
+        self.thread_pool.spawn_ok(async move {
+            let _ = tx.send(f());
+        });
+        rx.map(|result| match result {
+            Ok(result) => result,
+            Err(_) => {
+                error!("DNS resolver crashed");
+                Err(io::Error::new(io::ErrorKind::Other, "DNS resolver crashed"))
+            }
+        }).boxed()
+    }
+}
+
+impl Resolver for ThreadPoolResolver {
+    fn lookup_ip(&self, name: &str) -> BoxFuture<'static, Result<Vec<IpAddr>, io::Error>> {
+        let to_resolve = format!("{}:0", name);
+        self.spawn(move || {
+            to_resolve[..].to_socket_addrs().map(|list| list.map(|s| s.ip()).collect())
+        })
+    }
+
+    fn lookup_txt(&self, name: &str) -> BoxFuture<'static, Result<Vec<String>, io::Error>> {
+        let nameserver = match self.nameserver {
+            Some(nameserver) => nameserver,
+            None => {
+                let err = io::Error::new(io::ErrorKind::Other, "no nameserver configured");
+                return future::err(err).boxed()
+            }
+        };
+        let name = name.to_string();
+        let timeout = self.timeout;
+        self.spawn(move || {
+            let answer = wire::query_blocking(nameserver, &name, RecordType::Txt, timeout)?;
+            Ok(txt_records(answer))
+        })
+    }
+}
+
+pub(crate) fn txt_records(answer: Answer) -> Vec<String> {
+    answer.records.into_iter()
+        .filter_map(|record| match record {
+            Record::Txt(text) => Some(text),
+            Record::Ip(_) => None
+        })
+        .collect()
+}
@@ -0,0 +1,373 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Encoding of DNS queries and decoding of the answers to them.
+
+use std::{
+    io::{self, Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket},
+    time::Duration
+};
+
+/// Maximum size of the DNS messages we receive over UDP, advertised to the nameservers with an
+/// EDNS0 `OPT` record. Larger answers are truncated and must be queried again over TCP.
+pub(crate) const MAX_MESSAGE_SIZE: usize = 4096;
+
+/// Type of the records to query.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(not(feature = "async-std"), allow(dead_code))]
+pub(crate) enum RecordType {
+    A,
+    Aaaa,
+    Txt,
+}
+
+impl RecordType {
+    fn code(self) -> u16 {
+        match self {
+            RecordType::A => 1,
+            RecordType::Aaaa => 28,
+            RecordType::Txt => 16,
+        }
+    }
+}
+
+/// Data of a record of an answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Record {
+    Ip(IpAddr),
+    /// The concatenation of the strings of a TXT record.
+    Txt(String),
+}
+
+/// Outcome of decoding a response to a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Response {
+    /// The records found in the answer.
+    Answer(Answer),
+    /// The answer did not fit in a UDP message, and the query must be sent again over TCP.
+    Truncated,
+}
+
+/// The records of the queried type found in an answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Answer {
+    pub(crate) records: Vec<Record>,
+    /// The smallest TTL of the records, or `None` if there are none.
+    pub(crate) ttl: Option<Duration>,
+}
+
+/// Sends a query for the records of `name` to `nameserver` over UDP and waits for the answer,
+/// querying again over TCP if the answer is truncated.
+///
+/// A name that does not exist has no records.
+pub(crate) fn query_blocking(nameserver: SocketAddr, name: &str, ty: RecordType, timeout: Duration)
+    -> io::Result<Answer>
+{
+    let socket = UdpSocket::bind(unspecified(&nameserver))?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.connect(nameserver)?;
+
+    let id = rand::random();
+    socket.send(&encode_query(id, name, ty)?)?;
+
+    let mut buf = [0u8; MAX_MESSAGE_SIZE];
+    loop {
+        let n = socket.recv(&mut buf).map_err(timed_out)?;
+        match decode_response(id, ty, &buf[.. n])? {
+            Some(Response::Answer(answer)) => return Ok(answer),
+            Some(Response::Truncated) => break,
+            None => {}
+        }
+    }
+
+    let mut stream = TcpStream::connect_timeout(&nameserver, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    let id = rand::random();
+    stream.write_all(&encode_tcp(encode_query(id, name, ty)?))?;
+    let mut len = [0u8; 2];
+    stream.read_exact(&mut len).map_err(timed_out)?;
+    let mut msg = vec![0u8; usize::from(u16::from_be_bytes(len))];
+    stream.read_exact(&mut msg).map_err(timed_out)?;
+    decode_tcp_response(id, ty, &msg)
+}
+
+/// Reports the timeouts of blocking sockets as such.
+fn timed_out(err: io::Error) -> io::Error {
+    match err.kind() {
+        io::ErrorKind::WouldBlock => io::ErrorKind::TimedOut.into(),
+        _ => err
+    }
+}
+
+/// Returns the unspecified address of the IP version of `addr`, for binding to.
+pub(crate) fn unspecified(addr: &SocketAddr) -> SocketAddr {
+    match addr {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    }
+}
+
+/// Returns the nameservers configured in `/etc/resolv.conf`.
+pub(crate) fn system_nameservers() -> Vec<SocketAddr> {
+    if !cfg!(unix) {
+        return Vec::new()
+    }
+    let conf = match std::fs::read_to_string("/etc/resolv.conf") {
+        Ok(conf) => conf,
+        Err(_) => return Vec::new()
+    };
+    conf.lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            if words.next()? != "nameserver" {
+                return None
+            }
+            words.next()?.parse::<IpAddr>().ok()
+        })
+        .map(|ip| SocketAddr::new(ip, 53))
+        .collect()
+}
+
+/// Encodes a recursive query for the records of type `ty` of `name`.
+///
+/// The query carries an EDNS0 `OPT` record advertising `MAX_MESSAGE_SIZE` as the size of the
+/// UDP messages we accept, as answers are otherwise truncated to 512 bytes.
+pub(crate) fn encode_query(id: u16, name: &str, ty: RecordType) -> io::Result<Vec<u8>> {
+    let mut msg = Vec::with_capacity(29 + name.len());
+    msg.extend_from_slice(&id.to_be_bytes());
+    // Flags: standard query, recursion desired.
+    msg.extend_from_slice(&0x0100u16.to_be_bytes());
+    // One question, no answer or authority records, one additional record.
+    msg.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 1]);
+    for label in name.split('.').filter(|l| !l.is_empty()) {
+        if label.len() > 63 {
+            let msg = format!("DNS label too long: {:?}", label);
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg))
+        }
+        msg.push(label.len() as u8);
+        msg.extend_from_slice(label.as_bytes());
+    }
+    msg.push(0);
+    msg.extend_from_slice(&ty.code().to_be_bytes());
+    // Class `IN`.
+    msg.extend_from_slice(&1u16.to_be_bytes());
+    // `OPT` record: root name, type 41, UDP payload size as class, no extended flags, no data.
+    msg.push(0);
+    msg.extend_from_slice(&41u16.to_be_bytes());
+    msg.extend_from_slice(&(MAX_MESSAGE_SIZE as u16).to_be_bytes());
+    msg.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+    Ok(msg)
+}
+
+/// Prefixes a DNS message with its length, as it is sent over TCP.
+pub(crate) fn encode_tcp(msg: Vec<u8>) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(2 + msg.len());
+    framed.extend_from_slice(&(msg.len() as u16).to_be_bytes());
+    framed.extend_from_slice(&msg);
+    framed
+}
+
+/// Decodes the records of type `ty` of a response received over TCP to the query with the
+/// given `id`.
+pub(crate) fn decode_tcp_response(id: u16, ty: RecordType, msg: &[u8]) -> io::Result<Answer> {
+    match decode_response(id, ty, msg)? {
+        Some(Response::Answer(answer)) => Ok(answer),
+        Some(Response::Truncated) | None => {
+            Err(io::Error::new(io::ErrorKind::InvalidData, "invalid DNS response over TCP"))
+        }
+    }
+}
+
+/// Decodes the records of type `ty` of a response to the query with the given `id`.
+///
+/// Returns `None` if the message is not a response to that query.
+pub(crate) fn decode_response(id: u16, ty: RecordType, msg: &[u8]) -> io::Result<Option<Response>> {
+    let mut r = Reader { buf: msg };
+    if r.u16()? != id {
+        return Ok(None)
+    }
+    let flags = r.u16()?;
+    if flags & 0x8000 == 0 {
+        return Ok(None)
+    }
+    if flags & 0x0200 != 0 {
+        return Ok(Some(Response::Truncated))
+    }
+    match flags & 0x000f {
+        0 => {}
+        // NXDOMAIN
+        3 => return Ok(Some(Response::Answer(Answer { records: Vec::new(), ttl: None }))),
+        code => {
+            let msg = format!("DNS server responded with error code {}", code);
+            return Err(io::Error::new(io::ErrorKind::Other, msg))
+        }
+    }
+
+    let questions = r.u16()?;
+    let answers = r.u16()?;
+    r.take(4)?;
+    for _ in 0 .. questions {
+        r.skip_name()?;
+        r.take(4)?;
+    }
+
+    let mut answer = Answer { records: Vec::new(), ttl: None };
+    for _ in 0 .. answers {
+        r.skip_name()?;
+        let record_ty = r.u16()?;
+        r.take(2)?;
+        let ttl = r.u32()?;
+        let len = r.u16()? as usize;
+        let data = r.take(len)?;
+        // Other records, such as the CNAMEs leading to the queried records, are ignored.
+        if record_ty != ty.code() {
+            continue
+        }
+        let record = match ty {
+            RecordType::A if data.len() == 4 => {
+                Record::Ip(Ipv4Addr::new(data[0], data[1], data[2], data[3]).into())
+            }
+            RecordType::Aaaa if data.len() == 16 => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(data);
+                Record::Ip(Ipv6Addr::from(octets).into())
+            }
+            RecordType::Txt => match decode_txt(data)? {
+                Some(text) => Record::Txt(text),
+                None => continue
+            },
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid address record"))
+        };
+        answer.records.push(record);
+        let ttl = Duration::from_secs(u64::from(ttl));
+        answer.ttl = Some(answer.ttl.map_or(ttl, |t| t.min(ttl)));
+    }
+
+    Ok(Some(Response::Answer(answer)))
+}
+
+/// Concatenates the strings of a TXT record, returning `None` if it is not UTF-8.
+fn decode_txt(mut data: &[u8]) -> io::Result<Option<String>> {
+    let mut text = Vec::with_capacity(data.len());
+    while let Some((&n, rest)) = data.split_first() {
+        if rest.len() < n as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid TXT record"))
+        }
+        text.extend_from_slice(&rest[.. n as usize]);
+        data = &rest[n as usize ..];
+    }
+    Ok(String::from_utf8(text).ok())
+}
+
+/// Reads the fields of a DNS message.
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.buf.len() < n {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "truncated DNS message"))
+        }
+        let (data, rest) = self.buf.split_at(n);
+        self.buf = rest;
+        Ok(data)
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let data = self.take(2)?;
+        Ok(u16::from_be_bytes([data[0], data[1]]))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let data = self.take(4)?;
+        Ok(u32::from_be_bytes([data[0], data[1], data[2], data[3]]))
+    }
+
+    fn skip_name(&mut self) -> io::Result<()> {
+        loop {
+            let len = self.take(1)?[0];
+            if len == 0 {
+                return Ok(())
+            }
+            // A compression pointer ends the name.
+            if len & 0xc0 == 0xc0 {
+                self.take(1)?;
+                return Ok(())
+            }
+            self.take(len as usize)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Answer, Record, RecordType, Response, decode_response, encode_query};
+    use std::time::Duration;
+
+    #[test]
+    fn decode_records() {
+        let query = encode_query(0x1234, "_dnsaddr.example.com", RecordType::Txt).unwrap();
+        // Remove the `OPT` record, and set the response flag and answer count.
+        let mut response = query[.. query.len() - 11].to_vec();
+        response[2] |= 0x80;
+        response[7] = 3;
+        response[11] = 0;
+        for (ty, ttl, data) in &[
+            (16u8, 60u8, &b"\x1adnsaddr=/ip4/1.2.3.4/tcp/1"[..]),
+            (5, 10, &b"\x03foo\xc0\x0c"[..]),
+            (16, 30, &b"\x02ot\x03her"[..]),
+        ] {
+            response.extend_from_slice(&[0xc0, 12, 0, *ty, 0, 1, 0, 0, 0, *ttl]);
+            response.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            response.extend_from_slice(data);
+        }
+
+        assert_eq!(decode_response(0x4321, RecordType::Txt, &response).unwrap(), None);
+        assert_eq!(decode_response(0x1234, RecordType::Txt, &query).unwrap(), None);
+        assert_eq!(
+            decode_response(0x1234, RecordType::Txt, &response).unwrap(),
+            Some(Response::Answer(Answer {
+                records: vec![
+                    Record::Txt("dnsaddr=/ip4/1.2.3.4/tcp/1".to_string()),
+                    Record::Txt("other".to_string()),
+                ],
+                ttl: Some(Duration::from_secs(30)),
+            }))
+        );
+        assert_eq!(
+            decode_response(0x1234, RecordType::A, &response).unwrap(),
+            Some(Response::Answer(Answer { records: Vec::new(), ttl: None }))
+        );
+
+        // Truncated answers are reported as such.
+        response[2] |= 0x02;
+        assert_eq!(decode_response(0x1234, RecordType::Txt, &response).unwrap(), Some(Response::Truncated));
+    }
+
+    #[test]
+    fn query_advertises_udp_payload_size() {
+        let query = encode_query(0x1234, "example.com", RecordType::A).unwrap();
+        assert_eq!(&query[10 .. 12], &[0, 1]);
+        assert_eq!(&query[query.len() - 11 ..], &[0, 0, 41, 0x10, 0, 0, 0, 0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,522 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! # libp2p-socks5
+//!
+//! This crate provides the type `Socks5Config` that dials addresses through a SOCKS5 proxy,
+//! such as the one of a Tor client.
+//!
+//! ## Usage
+//!
+//! Create a `Socks5Config` with the transport used to reach the proxy (typically TCP) and the
+//! address of the proxy. Dialing an address of the form `/ip4/<ip>/tcp/<port>`,
+//! `/ip6/<ip>/tcp/<port>`, `/dns/<name>/tcp/<port>` (or `/dns4/` and `/dns6/`),
+//! `/onion/<hash>:<port>` or `/onion3/<hash>:<port>` connects to the proxy and asks it to
+//! connect to that destination. Names are resolved by the proxy, which is what Tor requires to
+//! avoid leaking DNS queries.
+//!
+//! Listening is passed through to the underlying transport.
+//!
+//! ## Stream isolation
+//!
+//! With `stream_isolation(true)`, every connection authenticates with unique credentials. Tor
+//! uses separate circuits for connections with different credentials, so that they cannot be
+//! linked to each other by the exit relays.
+//!
+
+use data_encoding::BASE32;
+use futures::{prelude::*, future::BoxFuture};
+use libp2p_core::{
+    Transport,
+    multiaddr::{Protocol, Multiaddr},
+    transport::{ListenerEvent, TransportError}
+};
+use log::{debug, trace};
+use std::{error, fmt, io, net::IpAddr};
+
+/// Version of the SOCKS protocol.
+const SOCKS_VERSION: u8 = 5;
+/// Version of the username/password authentication (RFC 1929).
+const AUTH_VERSION: u8 = 1;
+
+const METHOD_NO_AUTH: u8 = 0;
+const METHOD_USERNAME_PASSWORD: u8 = 2;
+const METHOD_NONE_ACCEPTABLE: u8 = 0xff;
+
+const COMMAND_CONNECT: u8 = 1;
+
+const ADDR_IPV4: u8 = 1;
+const ADDR_DOMAIN: u8 = 3;
+const ADDR_IPV6: u8 = 4;
+
+/// Represents the configuration for dialing through a SOCKS5 proxy.
+///
+/// This struct implements the `Transport` trait and holds an underlying transport, which is used
+/// to connect to the proxy.
+#[derive(Debug, Clone)]
+pub struct Socks5Config<T> {
+    /// Underlying transport to use to connect to the proxy.
+    inner: T,
+    /// Address of the proxy.
+    proxy: Multiaddr,
+    /// Username and password to authenticate with, if any.
+    credentials: Option<(String, String)>,
+    /// Whether every connection uses unique credentials.
+    stream_isolation: bool,
+}
+
+impl<T> Socks5Config<T> {
+    /// Creates a new configuration dialing through the proxy at `proxy`, which is reached with
+    /// the `inner` transport.
+    pub fn new(inner: T, proxy: Multiaddr) -> Self {
+        Socks5Config {
+            inner,
+            proxy,
+            credentials: None,
+            stream_isolation: false,
+        }
+    }
+
+    /// Sets the username and password to authenticate with.
+    pub fn with_credentials(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.credentials = Some((username.into(), password.into()));
+        self
+    }
+
+    /// Enables or disables stream isolation.
+    ///
+    /// When enabled, every connection authenticates with unique random credentials instead of
+    /// the ones set with `with_credentials`.
+    pub fn stream_isolation(mut self, value: bool) -> Self {
+        self.stream_isolation = value;
+        self
+    }
+
+    /// Returns the credentials to use for a new connection.
+    fn connection_credentials(&self) -> Option<(String, String)> {
+        if self.stream_isolation {
+            let id: u64 = rand::random();
+            Some((format!("libp2p-{:016x}", id), "libp2p".to_string()))
+        } else {
+            self.credentials.clone()
+        }
+    }
+}
+
+impl<T> Transport for Socks5Config<T>
+where
+    T: Transport + Send + 'static,
+    T::Output: AsyncRead + AsyncWrite + Send + Unpin,
+    T::Error: Send,
+    T::Dial: Send
+{
+    type Output = T::Output;
+    type Error = Socks5Error<T::Error>;
+    type Listener = stream::MapErr<
+        stream::MapOk<T::Listener,
+            fn(ListenerEvent<T::ListenerUpgrade>) -> ListenerEvent<Self::ListenerUpgrade>>,
+        fn(T::Error) -> Self::Error>;
+    type ListenerUpgrade = future::MapErr<T::ListenerUpgrade, fn(T::Error) -> Self::Error>;
+    type Dial = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+    fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
+        let listener = self.inner.listen_on(addr).map_err(|err| err.map(Socks5Error::Underlying))?;
+        let listener = listener
+            .map_ok::<_, fn(_) -> _>(|event| event.map(|upgr| {
+                upgr.map_err::<_, fn(_) -> _>(Socks5Error::Underlying)
+            }))
+            .map_err::<_, fn(_) -> _>(Socks5Error::Underlying);
+        Ok(listener)
+    }
+
+    fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let target = match Target::from_multiaddr(&addr) {
+            Some(target) => target,
+            None => return Err(TransportError::MultiaddrNotSupported(addr))
+        };
+
+        let credentials = self.connection_credentials();
+        let proxy_dial = match self.inner.dial(self.proxy.clone()) {
+            Ok(dial) => dial,
+            Err(TransportError::MultiaddrNotSupported(proxy)) => {
+                debug!("Proxy address not supported by the underlying transport: {}", proxy);
+                return Err(TransportError::MultiaddrNotSupported(addr))
+            }
+            Err(TransportError::Other(err)) => return Err(TransportError::Other(Socks5Error::Underlying(err)))
+        };
+
+        debug!("Dialing {} through SOCKS5 proxy {}", addr, self.proxy);
+        Ok(async move {
+            let mut stream = proxy_dial.await.map_err(Socks5Error::Underlying)?;
+            handshake(&mut stream, &target, credentials.as_ref()).await?;
+            trace!("SOCKS5 proxy connected to {}", addr);
+            Ok(stream)
+        }.boxed())
+    }
+}
+
+/// Destination to ask the proxy to connect to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Target {
+    Ip(IpAddr, u16),
+    Domain(String, u16),
+}
+
+impl Target {
+    /// Extracts the destination from an address, if supported.
+    fn from_multiaddr(addr: &Multiaddr) -> Option<Target> {
+        let mut iter = addr.iter();
+        let target = match (iter.next()?, iter.next()) {
+            (Protocol::Ip4(ip), Some(Protocol::Tcp(port))) => Target::Ip(ip.into(), port),
+            (Protocol::Ip6(ip), Some(Protocol::Tcp(port))) => Target::Ip(ip.into(), port),
+            (Protocol::Dns(name), Some(Protocol::Tcp(port)))
+            | (Protocol::Dns4(name), Some(Protocol::Tcp(port)))
+            | (Protocol::Dns6(name), Some(Protocol::Tcp(port))) => Target::Domain(name.into_owned(), port),
+            (Protocol::Onion(hash, port), None) => Target::Domain(onion_name(&hash[..]), port),
+            (Protocol::Onion3(addr), None) => Target::Domain(onion_name(&addr.hash()[..]), addr.port()),
+            _ => return None
+        };
+        if iter.next().is_some() {
+            return None
+        }
+        Some(target)
+    }
+}
+
+/// Returns the host name of an onion service.
+fn onion_name(hash: &[u8]) -> String {
+    format!("{}.onion", BASE32.encode(hash).to_lowercase())
+}
+
+/// Performs the SOCKS5 handshake asking the proxy to connect to `target`.
+async fn handshake<S, TErr>(stream: &mut S, target: &Target, credentials: Option<&(String, String)>)
+    -> Result<(), Socks5Error<TErr>>
+where
+    S: AsyncRead + AsyncWrite + Unpin
+{
+    // Method negotiation.
+    let method = if credentials.is_some() { METHOD_USERNAME_PASSWORD } else { METHOD_NO_AUTH };
+    stream.write_all(&[SOCKS_VERSION, 1, method]).await?;
+    stream.flush().await?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != SOCKS_VERSION {
+        return Err(Socks5Error::InvalidResponse)
+    }
+    match reply[1] {
+        m if m == method => {}
+        METHOD_NONE_ACCEPTABLE => return Err(Socks5Error::NoAcceptableMethod),
+        _ => return Err(Socks5Error::InvalidResponse)
+    }
+
+    // Username and password authentication.
+    if let Some((username, password)) = credentials {
+        if username.is_empty() || username.len() > 255 || password.len() > 255 {
+            let err = io::Error::new(io::ErrorKind::InvalidInput, "invalid SOCKS5 credentials");
+            return Err(Socks5Error::Io(err))
+        }
+        let mut msg = Vec::with_capacity(3 + username.len() + password.len());
+        msg.push(AUTH_VERSION);
+        msg.push(username.len() as u8);
+        msg.extend_from_slice(username.as_bytes());
+        msg.push(password.len() as u8);
+        msg.extend_from_slice(password.as_bytes());
+        stream.write_all(&msg).await?;
+        stream.flush().await?;
+        stream.read_exact(&mut reply).await?;
+        if reply[0] != AUTH_VERSION {
+            return Err(Socks5Error::InvalidResponse)
+        }
+        if reply[1] != 0 {
+            return Err(Socks5Error::AuthenticationFailed)
+        }
+    }
+
+    // Connection request.
+    let mut msg = vec![SOCKS_VERSION, COMMAND_CONNECT, 0];
+    let port = match target {
+        Target::Ip(IpAddr::V4(ip), port) => {
+            msg.push(ADDR_IPV4);
+            msg.extend_from_slice(&ip.octets());
+            port
+        }
+        Target::Ip(IpAddr::V6(ip), port) => {
+            msg.push(ADDR_IPV6);
+            msg.extend_from_slice(&ip.octets());
+            port
+        }
+        Target::Domain(name, port) => {
+            if name.len() > 255 {
+                let err = io::Error::new(io::ErrorKind::InvalidInput, "domain name too long");
+                return Err(Socks5Error::Io(err))
+            }
+            msg.push(ADDR_DOMAIN);
+            msg.push(name.len() as u8);
+            msg.extend_from_slice(name.as_bytes());
+            port
+        }
+    };
+    msg.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&msg).await?;
+    stream.flush().await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != SOCKS_VERSION {
+        return Err(Socks5Error::InvalidResponse)
+    }
+    if reply[1] != 0 {
+        return Err(Socks5Error::ConnectFailed(reply[1]))
+    }
+
+    // Skip the address the proxy bound to.
+    let addr_len = match reply[3] {
+        ADDR_IPV4 => 4,
+        ADDR_IPV6 => 16,
+        ADDR_DOMAIN => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len).await?;
+            len[0] as usize
+        }
+        _ => return Err(Socks5Error::InvalidResponse)
+    };
+    let mut bound = vec![0u8; addr_len + 2];
+    stream.read_exact(&mut bound).await?;
+
+    Ok(())
+}
+
+/// Error that can be generated by the SOCKS5 layer.
+#[derive(Debug)]
+pub enum Socks5Error<TErr> {
+    /// Error in the underlying transport layer while connecting to the proxy.
+    Underlying(TErr),
+    /// Error while communicating with the proxy.
+    Io(io::Error),
+    /// The proxy does not support the authentication method we offered.
+    NoAcceptableMethod,
+    /// The proxy rejected our credentials.
+    AuthenticationFailed,
+    /// The proxy failed to connect to the destination, with the given SOCKS5 reply code.
+    ConnectFailed(u8),
+    /// The proxy sent an invalid response.
+    InvalidResponse,
+}
+
+impl<TErr> From<io::Error> for Socks5Error<TErr> {
+    fn from(err: io::Error) -> Self {
+        Socks5Error::Io(err)
+    }
+}
+
+impl<TErr> fmt::Display for Socks5Error<TErr>
+where TErr: fmt::Display
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Socks5Error::Underlying(err) => write!(f, "{}", err),
+            Socks5Error::Io(err) => write!(f, "SOCKS5 I/O error: {}", err),
+            Socks5Error::NoAcceptableMethod => write!(f, "SOCKS5 proxy refused authentication method"),
+            Socks5Error::AuthenticationFailed => write!(f, "SOCKS5 authentication failed"),
+            Socks5Error::ConnectFailed(code) => {
+                let reason = match code {
+                    1 => "general failure",
+                    2 => "connection not allowed by ruleset",
+                    3 => "network unreachable",
+                    4 => "host unreachable",
+                    5 => "connection refused",
+                    6 => "TTL expired",
+                    7 => "command not supported",
+                    8 => "address type not supported",
+                    _ => "unknown error"
+                };
+                write!(f, "SOCKS5 proxy failed to connect: {} ({})", reason, code)
+            }
+            Socks5Error::InvalidResponse => write!(f, "Invalid response from SOCKS5 proxy"),
+        }
+    }
+}
+
+impl<TErr> error::Error for Socks5Error<TErr>
+where TErr: error::Error + 'static
+{
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Socks5Error::Underlying(err) => Some(err),
+            Socks5Error::Io(err) => Some(err),
+            Socks5Error::NoAcceptableMethod => None,
+            Socks5Error::AuthenticationFailed => None,
+            Socks5Error::ConnectFailed(_) => None,
+            Socks5Error::InvalidResponse => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Socks5Config, Socks5Error, Target};
+    use async_std::net::TcpListener;
+    use futures::{channel::mpsc, prelude::*};
+    use libp2p_core::{Transport, multiaddr::Multiaddr};
+    use libp2p_tcp::TcpConfig;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    /// What the stand-in proxy received from a client.
+    #[derive(Debug)]
+    struct Request {
+        credentials: Option<(String, String)>,
+        target: Target,
+    }
+
+    /// Spawns a SOCKS5 proxy that reports the requests it receives and either echoes the data
+    /// of the connection or refuses it with the given reply code.
+    async fn stand_in(reply: u8) -> (Multiaddr, mpsc::UnboundedReceiver<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::unbounded();
+        async_std::task::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 2];
+                socket.read_exact(&mut buf).await.unwrap();
+                let mut methods = vec![0u8; buf[1] as usize];
+                socket.read_exact(&mut methods).await.unwrap();
+
+                let credentials = if methods.contains(&2) {
+                    socket.write_all(&[5, 2]).await.unwrap();
+                    let mut b = [0u8; 2];
+                    socket.read_exact(&mut b).await.unwrap();
+                    let mut username = vec![0u8; b[1] as usize];
+                    socket.read_exact(&mut username).await.unwrap();
+                    socket.read_exact(&mut b[.. 1]).await.unwrap();
+                    let mut password = vec![0u8; b[0] as usize];
+                    socket.read_exact(&mut password).await.unwrap();
+                    socket.write_all(&[1, 0]).await.unwrap();
+                    Some((String::from_utf8(username).unwrap(), String::from_utf8(password).unwrap()))
+                } else {
+                    socket.write_all(&[5, 0]).await.unwrap();
+                    None
+                };
+
+                let mut header = [0u8; 4];
+                socket.read_exact(&mut header).await.unwrap();
+                assert_eq!(&header[.. 3], &[5, 1, 0]);
+                let host = match header[3] {
+                    1 => {
+                        let mut ip = [0u8; 4];
+                        socket.read_exact(&mut ip).await.unwrap();
+                        Err(IpAddr::from(Ipv4Addr::from(ip)))
+                    }
+                    4 => {
+                        let mut ip = [0u8; 16];
+                        socket.read_exact(&mut ip).await.unwrap();
+                        Err(IpAddr::from(Ipv6Addr::from(ip)))
+                    }
+                    3 => {
+                        let mut len = [0u8; 1];
+                        socket.read_exact(&mut len).await.unwrap();
+                        let mut name = vec![0u8; len[0] as usize];
+                        socket.read_exact(&mut name).await.unwrap();
+                        Ok(String::from_utf8(name).unwrap())
+                    }
+                    other => panic!("Unexpected address type {}", other)
+                };
+                let mut port = [0u8; 2];
+                socket.read_exact(&mut port).await.unwrap();
+                let port = u16::from_be_bytes(port);
+                let target = match host {
+                    Ok(name) => Target::Domain(name, port),
+                    Err(ip) => Target::Ip(ip, port)
+                };
+                tx.unbounded_send(Request { credentials, target }).unwrap();
+
+                socket.write_all(&[5, reply, 0, 1, 127, 0, 0, 1, 0, 0]).await.unwrap();
+                if reply == 0 {
+                    let (mut reader, mut writer) = socket.split();
+                    async_std::task::spawn(async move {
+                        let _ = futures::io::copy(&mut reader, &mut writer).await;
+                    });
+                }
+            }
+        });
+        (format!("/ip4/127.0.0.1/tcp/{}", addr.port()).parse().unwrap(), rx)
+    }
+
+    #[test]
+    fn dial_through_proxy() {
+        async_std::task::block_on(async {
+            let (proxy, mut requests) = stand_in(0).await;
+            let transport = Socks5Config::new(TcpConfig::new(), proxy)
+                .with_credentials("alice", "secret");
+
+            let mut stream = transport.clone()
+                .dial("/dns/example.com/tcp/443".parse().unwrap()).unwrap()
+                .await.unwrap();
+            stream.write_all(b"ping").await.unwrap();
+            let mut buf = [0u8; 4];
+            stream.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"ping");
+
+            let request = requests.next().await.unwrap();
+            assert_eq!(request.credentials, Some(("alice".to_string(), "secret".to_string())));
+            assert_eq!(request.target, Target::Domain("example.com".to_string(), 443));
+
+            transport.clone().dial("/ip6/::1/tcp/4001".parse().unwrap()).unwrap().await.unwrap();
+            let request = requests.next().await.unwrap();
+            assert_eq!(request.target, Target::Ip(Ipv6Addr::LOCALHOST.into(), 4001));
+
+            let onion = "/onion3/vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd:1234";
+            transport.dial(onion.parse().unwrap()).unwrap().await.unwrap();
+            let request = requests.next().await.unwrap();
+            assert_eq!(request.target, Target::Domain(
+                "vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd.onion".to_string(), 1234));
+        });
+    }
+
+    #[test]
+    fn stream_isolation() {
+        async_std::task::block_on(async {
+            let (proxy, mut requests) = stand_in(0).await;
+            let transport = Socks5Config::new(TcpConfig::new(), proxy).stream_isolation(true);
+
+            for _ in 0 .. 2 {
+                transport.clone().dial("/ip4/1.2.3.4/tcp/80".parse().unwrap()).unwrap().await.unwrap();
+            }
+            let first = requests.next().await.unwrap().credentials.unwrap();
+            let second = requests.next().await.unwrap().credentials.unwrap();
+            assert_ne!(first, second);
+        });
+    }
+
+    #[test]
+    fn connect_failure() {
+        async_std::task::block_on(async {
+            let (proxy, _requests) = stand_in(5).await;
+            let transport = Socks5Config::new(TcpConfig::new(), proxy);
+
+            match transport.clone().dial("/ip4/1.2.3.4/tcp/80".parse().unwrap()).unwrap().await {
+                Err(Socks5Error::ConnectFailed(5)) => {}
+                other => panic!("Unexpected result: {:?}", other.map(|_| ()))
+            }
+
+            assert!(transport.dial("/ip4/1.2.3.4/udp/80".parse().unwrap()).is_err());
+        });
+    }
+}
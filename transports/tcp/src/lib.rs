@@ -28,29 +28,59 @@
 //! Both the `TcpConfig` and `TokioTcpConfig` structs implement the `Transport` trait of the
 //! `core` library. See the documentation of `core` and of libp2p in general to learn how to
 //! use the `Transport` trait.
+//!
+//! # Port reuse
+//!
+//! By default, every outgoing connection is made from a new ephemeral port, which means that
+//! the address a remote observes for us is not one it can connect back to. With
+//! `port_reuse(true)`, listening sockets are created with `SO_REUSEADDR` (and `SO_REUSEPORT` on
+//! Unix) and outgoing connections are bound to the port of one of the active listeners of the
+//! same configuration, which helps with NAT traversal.
+//!
+//! # Listening on all interfaces
+//!
+//! When listening on an unspecified address (`0.0.0.0` or `::`), the listener reports one
+//! address per network interface of the host. The interfaces are watched for changes (through
+//! netlink on Linux, and by periodically checking them on all platforms) and
+//! `ListenerEvent::NewAddress` and `ListenerEvent::AddressExpired` events are produced as
+//! addresses appear and disappear.
+
+mod watcher;
 
-use futures::{future::{self, Ready}, prelude::*};
+use futures::{future::{self, Either, Ready}, prelude::*};
 use futures_timer::Delay;
 use get_if_addrs::{IfAddr, get_if_addrs};
 use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+use lazy_static::lazy_static;
 use libp2p_core::{
     Transport,
-    multiaddr::{Protocol, Multiaddr},
+    multiaddr::{Pattern, Protocol, Multiaddr},
     transport::{ListenerEvent, TransportError}
 };
 use log::{debug, trace};
+use socket2::{Domain, Socket, Type};
 use std::{
-    collections::VecDeque,
+    collections::{HashSet, VecDeque},
     io,
     iter::{self, FromIterator},
-    net::{IpAddr, SocketAddr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     pin::Pin,
+    sync::{Arc, Mutex},
     task::{Context, Poll},
     time::Duration
 };
+use watcher::IfWatcher;
+
+/// How often the network interfaces are checked for changes when listening on all interfaces.
+const IF_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+lazy_static! {
+    /// The addresses supported by the transport.
+    static ref TCP_PATTERN: Pattern = "/ip4|ip6/*/tcp/*".parse().expect("The pattern is valid; qed");
+}
 
 macro_rules! codegen {
-    ($feature_name:expr, $tcp_config:ident, $tcp_trans_stream:ident, $tcp_listen_stream:ident, $apply_config:ident, $tcp_stream:ty, $tcp_listener:ty) => {
+    ($feature_name:expr, $tcp_config:ident, $tcp_trans_stream:ident, $tcp_listen_stream:ident, $apply_config:ident, $listener_from_std:ident, $connect_bound:ident, $tcp_stream:ty, $tcp_listener:ty) => {
 
 /// Represents the configuration for a TCP/IP transport capability for libp2p.
 ///
@@ -65,6 +95,9 @@ pub struct $tcp_config {
     ttl: Option<u32>,
     /// `TCP_NODELAY` to set for opened sockets, or `None` to keep default.
     nodelay: Option<bool>,
+    /// Listen addresses shared by the clones of this configuration, or `None` if port reuse
+    /// is disabled.
+    port_reuse: Option<PortReuse>,
 }
 
 impl $tcp_config {
@@ -74,6 +107,7 @@ impl $tcp_config {
             sleep_on_error: Duration::from_millis(100),
             ttl: None,
             nodelay: None,
+            port_reuse: None,
         }
     }
 
@@ -88,6 +122,17 @@ impl $tcp_config {
         self.nodelay = Some(value);
         self
     }
+
+    /// Enables or disables port reuse.
+    ///
+    /// When enabled, listeners are created with `SO_REUSEADDR` and `SO_REUSEPORT` and dialed
+    /// connections are bound to the port of an active listener with the same IP version that
+    /// was created from this configuration or one of its clones. If no such listener exists,
+    /// dialing uses an ephemeral port as usual.
+    pub fn port_reuse(mut self, value: bool) -> Self {
+        self.port_reuse = if value { Some(PortReuse::default()) } else { None };
+        self
+    }
 }
 
 impl Transport for $tcp_config {
@@ -108,10 +153,18 @@ impl Transport for $tcp_config {
         async fn do_listen(cfg: $tcp_config, socket_addr: SocketAddr)
             -> Result<impl Stream<Item = Result<ListenerEvent<Ready<Result<$tcp_trans_stream, io::Error>>>, io::Error>>, io::Error>
         {
-            let listener = <$tcp_listener>::bind(&socket_addr).await?;
+            let listener = if cfg.port_reuse.is_some() {
+                $listener_from_std(reuse_listener(&socket_addr)?)?
+            } else {
+                <$tcp_listener>::bind(&socket_addr).await?
+            };
             let local_addr = listener.local_addr()?;
             let port = local_addr.port();
 
+            if let Some(port_reuse) = &cfg.port_reuse {
+                port_reuse.register(local_addr);
+            }
+
             // Determine all our listen addresses which is either a single local IP address
             // or (if a wildcard IP address was used) the addresses of all our interfaces,
             // as reported by `get_if_addrs`.
@@ -144,11 +197,18 @@ impl Transport for $tcp_config {
                 }
             };
 
+            let watcher = match addrs {
+                Addresses::One(_) => None,
+                Addresses::Many(_) => Some(IfWatcher::new(IF_CHECK_INTERVAL))
+            };
+
             let listen_stream = $tcp_listen_stream {
                 stream: listener,
+                watcher,
                 pause: None,
                 pause_duration: cfg.sleep_on_error,
                 port,
+                local_addr,
                 addrs,
                 pending,
                 config: cfg
@@ -175,7 +235,20 @@ impl Transport for $tcp_config {
         debug!("Dialing {}", addr);
 
         async fn do_dial(cfg: $tcp_config, socket_addr: SocketAddr) -> Result<$tcp_trans_stream, io::Error> {
-            let stream = <$tcp_stream>::connect(&socket_addr).await?;
+            let local_addr = cfg.port_reuse.as_ref().and_then(|r| r.local_dial_addr(&socket_addr.ip()));
+            let socket = match local_addr.map(|a| (a, reuse_dial_socket(&a))) {
+                Some((_, Ok(socket))) => Some(socket),
+                Some((a, Err(err))) => {
+                    debug!("Failed to bind dialing socket to {}: {}; using an ephemeral port", a, err);
+                    None
+                }
+                None => None
+            };
+            let stream = if let Some(socket) = socket {
+                $connect_bound(socket, socket_addr).await?
+            } else {
+                <$tcp_stream>::connect(&socket_addr).await?
+            };
             $apply_config(&cfg, &stream)?;
             Ok($tcp_trans_stream { inner: stream })
         }
@@ -189,12 +262,16 @@ impl Transport for $tcp_config {
 pub struct $tcp_listen_stream {
     /// The incoming connections.
     stream: $tcp_listener,
+    /// Watches the network interfaces if we listen on all of them.
+    watcher: Option<IfWatcher>,
     /// The current pause if any.
     pause: Option<Delay>,
     /// How long to pause after an error.
     pause_duration: Duration,
     /// The port which we use as our listen port in listener event addresses.
     port: u16,
+    /// The local address of the listening socket.
+    local_addr: SocketAddr,
     /// The set of known addresses.
     addrs: Addresses,
     /// Temporary buffer of listener events.
@@ -215,8 +292,33 @@ impl $tcp_listen_stream {
                 let _ = pause.await;
             }
 
+            let accepted = match self.watcher.as_mut() {
+                Some(watcher) => {
+                    let accept = self.stream.accept();
+                    futures::pin_mut!(accept);
+                    let change = future::poll_fn(|cx| watcher.poll_change(cx));
+                    match future::select(accept, change).await {
+                        Either::Left((accepted, _)) => Some(accepted),
+                        Either::Right(((), _)) => None
+                    }
+                }
+                None => Some(self.stream.accept().await)
+            };
+
+            let accepted = match accepted {
+                Some(accepted) => accepted,
+                None => {
+                    if let Addresses::Many(ref mut addrs) = self.addrs {
+                        if let Err(err) = update_addresses(self.port, addrs, &mut self.pending) {
+                            debug!("Failed to check network interfaces: {}", err);
+                        }
+                    }
+                    continue
+                }
+            };
+
             // TODO: do we get the peer_addr at the same time?
-            let (sock, _) = match self.stream.accept().await {
+            let (sock, _) = match accepted {
                 Ok(s) => s,
                 Err(e) => {
                     debug!("error accepting incoming connection: {}", e);
@@ -272,6 +374,14 @@ impl $tcp_listen_stream {
     }
 }
 
+impl Drop for $tcp_listen_stream {
+    fn drop(&mut self) {
+        if let Some(port_reuse) = &self.config.port_reuse {
+            port_reuse.unregister(&self.local_addr);
+        }
+    }
+}
+
 /// Wraps around a `TcpStream` and adds logging for important events.
 #[cfg_attr(docsrs, doc(cfg(feature = $feature_name)))]
 #[derive(Debug)]
@@ -306,10 +416,130 @@ fn $apply_config(config: &$tcp_config, socket: &$tcp_stream) -> Result<(), io::E
 }
 
 #[cfg(feature = "async-std")]
-codegen!("async-std", TcpConfig, TcpTransStream, TcpListenStream, apply_config_async_std, async_std::net::TcpStream, async_std::net::TcpListener);
+codegen!("async-std", TcpConfig, TcpTransStream, TcpListenStream, apply_config_async_std, listener_from_std_async_std, connect_bound_async_std, async_std::net::TcpStream, async_std::net::TcpListener);
+
+#[cfg(feature = "tokio")]
+codegen!("tokio", TokioTcpConfig, TokioTcpTransStream, TokioTcpListenStream, apply_config_tokio, listener_from_std_tokio, connect_bound_tokio, tokio::net::TcpStream, tokio::net::TcpListener);
+
+#[cfg(feature = "async-std")]
+fn listener_from_std_async_std(listener: std::net::TcpListener) -> io::Result<async_std::net::TcpListener> {
+    Ok(async_std::net::TcpListener::from(listener))
+}
+
+// Connect the given socket, which has been bound beforehand, to `remote_addr`.
+//
+// async-std has no way of connecting such a socket, so the connection is started in
+// non-blocking mode and the socket is registered with the reactor of async-std until it becomes
+// writable, which happens once the connection has been established or has failed. Dropping the
+// returned future abandons the connection attempt and closes the socket.
+#[cfg(feature = "async-std")]
+async fn connect_bound_async_std(socket: Socket, remote_addr: SocketAddr) -> io::Result<async_std::net::TcpStream> {
+    socket.set_nonblocking(true)?;
+    match socket.connect(&remote_addr.into()) {
+        Ok(()) => {}
+        Err(ref err) if is_connect_in_progress(err) => {}
+        Err(err) => return Err(err)
+    }
+    let stream = async_io::Async::new(socket.into_tcp_stream())?;
+    stream.writable().await?;
+    if let Some(err) = stream.get_ref().take_error()? {
+        return Err(err)
+    }
+    Ok(async_std::net::TcpStream::from(stream.into_inner()?))
+}
+
+#[cfg(feature = "tokio")]
+fn listener_from_std_tokio(listener: std::net::TcpListener) -> io::Result<tokio::net::TcpListener> {
+    tokio::net::TcpListener::from_std(listener)
+}
 
+// Connect the given socket, which has been bound beforehand, to `remote_addr`.
 #[cfg(feature = "tokio")]
-codegen!("tokio", TokioTcpConfig, TokioTcpTransStream, TokioTcpListenStream, apply_config_tokio, tokio::net::TcpStream, tokio::net::TcpListener);
+async fn connect_bound_tokio(socket: Socket, remote_addr: SocketAddr) -> io::Result<tokio::net::TcpStream> {
+    tokio::net::TcpStream::connect_std(socket.into_tcp_stream(), &remote_addr).await
+}
+
+/// The local addresses of the active listeners of a configuration with port reuse enabled.
+#[derive(Debug, Clone, Default)]
+struct PortReuse {
+    listen_addrs: Arc<Mutex<HashSet<SocketAddr>>>
+}
+
+impl PortReuse {
+    /// Registers the local address of a new listener.
+    fn register(&self, addr: SocketAddr) {
+        trace!("Registering for port reuse: {}", addr);
+        self.listen_addrs.lock().expect("lock is never poisoned").insert(addr);
+    }
+
+    /// Unregisters the local address of a listener that has been closed.
+    fn unregister(&self, addr: &SocketAddr) {
+        trace!("Unregistering for port reuse: {}", addr);
+        self.listen_addrs.lock().expect("lock is never poisoned").remove(addr);
+    }
+
+    /// Returns the local address to bind to when dialing `remote_ip`, if any.
+    ///
+    /// Only listeners with the same IP version are considered, and listeners bound to a
+    /// loopback address are only used to dial loopback addresses.
+    fn local_dial_addr(&self, remote_ip: &IpAddr) -> Option<SocketAddr> {
+        let listen_addrs = self.listen_addrs.lock().expect("lock is never poisoned");
+        listen_addrs.iter()
+            .find(|addr| {
+                addr.is_ipv4() == remote_ip.is_ipv4()
+                    && (addr.ip().is_unspecified() || addr.ip().is_loopback() == remote_ip.is_loopback())
+            })
+            .map(|addr| {
+                let ip = match addr.ip() {
+                    IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                    IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+                };
+                SocketAddr::new(ip, addr.port())
+            })
+    }
+}
+
+// Create a TCP socket for the given address with `SO_REUSEADDR` and `SO_REUSEPORT` set.
+fn reuse_socket(addr: &SocketAddr) -> io::Result<Socket> {
+    let domain = if addr.is_ipv4() { Domain::ipv4() } else { Domain::ipv6() };
+    let socket = Socket::new(domain, Type::stream(), Some(socket2::Protocol::tcp()))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    socket.set_reuse_address(true)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+    Ok(socket)
+}
+
+// Create a listening socket for the given address that allows port reuse.
+fn reuse_listener(addr: &SocketAddr) -> io::Result<std::net::TcpListener> {
+    let socket = reuse_socket(addr)?;
+    socket.bind(&(*addr).into())?;
+    socket.listen(1024)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket.into_tcp_listener())
+}
+
+// Create a socket for dialing that is bound to the given local address.
+fn reuse_dial_socket(local_addr: &SocketAddr) -> io::Result<Socket> {
+    let socket = reuse_socket(local_addr)?;
+    socket.bind(&(*local_addr).into())?;
+    Ok(socket)
+}
+
+// Whether the error returned by `connect` on a non-blocking socket means that the connection
+// is being established.
+#[cfg(feature = "async-std")]
+fn is_connect_in_progress(err: &io::Error) -> bool {
+    #[cfg(unix)]
+    {
+        if err.raw_os_error() == Some(libc::EINPROGRESS) {
+            return true
+        }
+    }
+    err.kind() == io::ErrorKind::WouldBlock
+}
 
 #[cfg(feature = "async-std")]
 impl AsyncRead for TcpTransStream {
@@ -355,19 +585,11 @@ impl AsyncWrite for TokioTcpTransStream {
     }
 }
 
-// This type of logic should probably be moved into the multiaddr package
+// Extracts the socket address of `/ip4/*/tcp/*` and `/ip6/*/tcp/*` addresses.
 fn multiaddr_to_socketaddr(addr: &Multiaddr) -> Result<SocketAddr, ()> {
-    let mut iter = addr.iter();
-    let proto1 = iter.next().ok_or(())?;
-    let proto2 = iter.next().ok_or(())?;
-
-    if iter.next().is_some() {
-        return Err(());
-    }
-
-    match (proto1, proto2) {
-        (Protocol::Ip4(ip), Protocol::Tcp(port)) => Ok(SocketAddr::new(ip.into(), port)),
-        (Protocol::Ip6(ip), Protocol::Tcp(port)) => Ok(SocketAddr::new(ip.into(), port)),
+    match TCP_PATTERN.captures(addr).ok_or(())?[..] {
+        [Protocol::Ip4(ip), Protocol::Tcp(port)] => Ok(SocketAddr::new(ip.into(), port)),
+        [Protocol::Ip6(ip), Protocol::Tcp(port)] => Ok(SocketAddr::new(ip.into(), port)),
         _ => Err(()),
     }
 }
@@ -440,25 +662,7 @@ fn check_for_interface_changes<T>(
     // The local IP address of this socket is new to us.
     // We check for changes in the set of host addresses and report new
     // and expired addresses.
-    //
-    // TODO: We do not detect expired addresses unless there is a new address.
-    let old_listen_addrs = std::mem::replace(listen_addrs, host_addresses(listen_port)?);
-
-    // Check for addresses no longer in use.
-    for (ip, _, ma) in old_listen_addrs.iter() {
-        if listen_addrs.iter().find(|(i, ..)| i == ip).is_none() {
-            debug!("Expired listen address: {}", ma);
-            pending.push_back(Ok(ListenerEvent::AddressExpired(ma.clone())));
-        }
-    }
-
-    // Check for new addresses.
-    for (ip, _, ma) in listen_addrs.iter() {
-        if old_listen_addrs.iter().find(|(i, ..)| i == ip).is_none() {
-            debug!("New listen address: {}", ma);
-            pending.push_back(Ok(ListenerEvent::NewAddress(ma.clone())));
-        }
-    }
+    update_addresses(listen_port, listen_addrs, pending)?;
 
     // We should now be able to find the local address, if not something
     // is seriously wrong and we report an error.
@@ -473,14 +677,51 @@ fn check_for_interface_changes<T>(
     Ok(())
 }
 
+// Replace `listen_addrs` with the current host addresses and report new and
+// expired addresses.
+fn update_addresses<T>(
+    listen_port: u16,
+    listen_addrs: &mut Vec<(IpAddr, IpNet, Multiaddr)>,
+    pending: &mut Buffer<T>
+) -> Result<(), io::Error> {
+    let new_listen_addrs = host_addresses(listen_port)?;
+    let old_listen_addrs = std::mem::replace(listen_addrs, new_listen_addrs);
+    diff_addresses(&old_listen_addrs, listen_addrs, pending);
+    Ok(())
+}
+
+// Report the addresses of `new` that are not in `old` as new, and the addresses
+// of `old` that are not in `new` as expired.
+fn diff_addresses<T>(
+    old: &[(IpAddr, IpNet, Multiaddr)],
+    new: &[(IpAddr, IpNet, Multiaddr)],
+    pending: &mut Buffer<T>
+) {
+    // Check for addresses no longer in use.
+    for (ip, _, ma) in old.iter() {
+        if new.iter().find(|(i, ..)| i == ip).is_none() {
+            debug!("Expired listen address: {}", ma);
+            pending.push_back(Ok(ListenerEvent::AddressExpired(ma.clone())));
+        }
+    }
+
+    // Check for new addresses.
+    for (ip, _, ma) in new.iter() {
+        if old.iter().find(|(i, ..)| i == ip).is_none() {
+            debug!("New listen address: {}", ma);
+            pending.push_back(Ok(ListenerEvent::NewAddress(ma.clone())));
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use futures::prelude::*;
     use libp2p_core::{Transport, multiaddr::{Multiaddr, Protocol}, transport::ListenerEvent};
     use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-    use super::multiaddr_to_socketaddr;
+    use super::{Buffer, diff_addresses, ip_to_multiaddr, multiaddr_to_socketaddr};
     #[cfg(feature = "async-std")]
-    use super::TcpConfig;
+    use super::{Addresses, IF_CHECK_INTERVAL, IfWatcher, TcpConfig, TcpListenStream, host_addresses};
 
     #[test]
     #[cfg(feature = "async-std")]
@@ -523,6 +764,73 @@ mod tests {
         async_std::task::block_on(futures::future::join(server, client)).1.unwrap();
     }
 
+    #[test]
+    fn interface_changes_are_reported() {
+        let addr = |ip: &str| {
+            let ip: IpAddr = ip.parse().unwrap();
+            (ip, ipnet::IpNet::from(ip), ip_to_multiaddr(ip, 4001))
+        };
+        let old = vec![addr("127.0.0.1"), addr("192.168.1.2")];
+        let new = vec![addr("127.0.0.1"), addr("10.8.0.5")];
+
+        let mut pending: Buffer<()> = Default::default();
+        diff_addresses(&old, &new, &mut pending);
+
+        let events = pending.into_iter().map(Result::unwrap).collect::<Vec<_>>();
+        assert_eq!(events.len(), 2);
+        match &events[0] {
+            ListenerEvent::AddressExpired(a) => assert_eq!(a, &old[1].2),
+            _ => panic!("Expected an expired address")
+        }
+        match &events[1] {
+            ListenerEvent::NewAddress(a) => assert_eq!(a, &new[1].2),
+            _ => panic!("Expected a new address")
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "async-std")]
+    fn interface_change_notifications_are_reported_by_listener() {
+        let listener = async_std::task::block_on(async_std::net::TcpListener::bind("0.0.0.0:0")).unwrap();
+        let local_addr = listener.local_addr().unwrap();
+        let port = local_addr.port();
+
+        // The listener has missed the appearance of an interface of the host and the
+        // disappearance of another one.
+        let mut addrs = host_addresses(port).unwrap();
+        let added = addrs.remove(0).2;
+        let gone: IpAddr = "192.0.2.1".parse().unwrap();
+        let expired = ip_to_multiaddr(gone, port);
+        addrs.push((gone, ipnet::IpNet::from(gone), expired.clone()));
+
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let listen_stream = TcpListenStream {
+            stream: listener,
+            watcher: Some(IfWatcher::with_receiver(rx, IF_CHECK_INTERVAL)),
+            pause: None,
+            pause_duration: std::time::Duration::from_millis(100),
+            port,
+            local_addr,
+            addrs: Addresses::Many(addrs),
+            pending: Default::default(),
+            config: TcpConfig::new()
+        };
+        let events = stream::unfold(listen_stream, |s| s.next().map(Some));
+        futures::pin_mut!(events);
+
+        tx.unbounded_send(()).unwrap();
+        async_std::task::block_on(async move {
+            match events.next().await.unwrap().unwrap() {
+                ListenerEvent::AddressExpired(a) => assert_eq!(a, expired),
+                _ => panic!("Expected an expired address")
+            }
+            match events.next().await.unwrap().unwrap() {
+                ListenerEvent::NewAddress(a) => assert_eq!(a, added),
+                _ => panic!("Expected a new address")
+            }
+        });
+    }
+
     #[test]
     fn multiaddr_to_tcp_conversion() {
         use std::net::Ipv6Addr;
@@ -650,6 +958,75 @@ mod tests {
         assert!(!new_addr.to_string().contains("tcp/0"));
     }
 
+    #[test]
+    #[cfg(feature = "async-std")]
+    fn port_reuse_dials_from_listen_port() {
+        let tcp = TcpConfig::new().port_reuse(true);
+
+        let mut listener = tcp.clone().listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+        let listen_addr = futures::executor::block_on_stream(listener.by_ref())
+            .next()
+            .expect("some event")
+            .expect("no error")
+            .into_new_address()
+            .expect("listen address");
+        let listen_port = match listen_addr.iter().nth(1) {
+            Some(Protocol::Tcp(port)) => port,
+            _ => panic!("No TCP port in address: {}", listen_addr)
+        };
+
+        async_std::task::block_on(async move {
+            let mut remote = TcpConfig::new()
+                .listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+                .unwrap();
+            let remote_addr = remote.next().await.unwrap().unwrap().into_new_address().unwrap();
+
+            let dial = tcp.clone().dial(remote_addr).unwrap();
+            let (event, socket) = futures::future::join(remote.next(), dial).await;
+            socket.unwrap();
+            match event.unwrap().unwrap() {
+                ListenerEvent::Upgrade { remote_addr, .. } => {
+                    assert_eq!(remote_addr.iter().nth(1), Some(Protocol::Tcp(listen_port)));
+                }
+                _ => panic!("Expected an incoming connection")
+            }
+
+            // Without an active listener, dialing falls back to an ephemeral port.
+            drop(listener);
+            let remote_addr = "/ip4/127.0.0.1/tcp/0".parse::<Multiaddr>().unwrap();
+            let mut remote = TcpConfig::new().listen_on(remote_addr).unwrap();
+            let remote_addr = remote.next().await.unwrap().unwrap().into_new_address().unwrap();
+            let dial = tcp.dial(remote_addr).unwrap();
+            let (event, socket) = futures::future::join(remote.next(), dial).await;
+            socket.unwrap();
+            match event.unwrap().unwrap() {
+                ListenerEvent::Upgrade { remote_addr, .. } => {
+                    assert_ne!(remote_addr.iter().nth(1), Some(Protocol::Tcp(listen_port)));
+                }
+                _ => panic!("Expected an incoming connection")
+            }
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "async-std")]
+    fn port_reuse_dial_failure_is_reported() {
+        let tcp = TcpConfig::new().port_reuse(true);
+
+        let mut listener = tcp.clone().listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+        futures::executor::block_on_stream(listener.by_ref())
+            .next()
+            .expect("some event")
+            .expect("no error");
+
+        // Find a port that nobody listens on.
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let addr = format!("/ip4/127.0.0.1/tcp/{}", port).parse::<Multiaddr>().unwrap();
+
+        let err = async_std::task::block_on(tcp.dial(addr).unwrap()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused);
+    }
+
     #[test]
     #[cfg(feature = "async-std")]
     fn larger_addr_denied() {
@@ -0,0 +1,243 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Notifications about changes of the network interfaces of the host.
+//!
+//! On Linux, a netlink socket subscribed to address changes is read on a background thread,
+//! which is shared by all the watchers and forwards the notifications to each of them.
+//! On all platforms, and in case netlink is unavailable, the interfaces are additionally
+//! re-checked periodically.
+
+use futures::{channel::mpsc, prelude::*};
+use futures_timer::Delay;
+use lazy_static::lazy_static;
+use log::debug;
+use std::{pin::Pin, sync::Mutex, task::{Context, Poll}, time::Duration};
+
+lazy_static! {
+    /// The senders of the notifications to the watchers, if the netlink thread is running.
+    static ref SUBSCRIBERS: Mutex<Option<Vec<mpsc::UnboundedSender<()>>>> = Mutex::new(None);
+}
+
+/// Subscribes to the notifications of the netlink thread, starting the thread if needed.
+fn subscribe() -> Option<mpsc::UnboundedReceiver<()>> {
+    let mut subscribers = SUBSCRIBERS.lock().expect("lock is never poisoned");
+    if subscribers.is_none() {
+        match platform::watch() {
+            Ok(true) => *subscribers = Some(Vec::new()),
+            Ok(false) => return None,
+            Err(err) => {
+                debug!("Failed to watch network interfaces: {}; polling instead", err);
+                return None
+            }
+        }
+    }
+    let (tx, rx) = mpsc::unbounded();
+    subscribers.as_mut().expect("set above if missing; qed").push(tx);
+    Some(rx)
+}
+
+/// Called by the netlink thread, after a change if `changed` is true and periodically otherwise.
+///
+/// Returns false if there are no watchers left, in which case the thread must stop.
+fn notify(changed: bool) -> bool {
+    let mut subscribers = SUBSCRIBERS.lock().expect("lock is never poisoned");
+    let remaining = match subscribers.as_mut() {
+        Some(list) => {
+            if changed {
+                list.retain(|tx| tx.unbounded_send(()).is_ok())
+            } else {
+                list.retain(|tx| !tx.is_closed())
+            }
+            list.len()
+        }
+        None => 0
+    };
+    if remaining == 0 {
+        *subscribers = None;
+        return false
+    }
+    true
+}
+
+/// Called by the netlink thread when it stops because of an error. The watchers fall back to
+/// checking the interfaces periodically.
+fn stopped() {
+    *SUBSCRIBERS.lock().expect("lock is never poisoned") = None
+}
+
+/// Signals that the network interfaces of the host may have changed.
+pub(crate) struct IfWatcher {
+    /// Notifications from the netlink thread, if any.
+    netlink: Option<mpsc::UnboundedReceiver<()>>,
+    /// Timer for the next periodic check.
+    delay: Delay,
+    /// Interval between periodic checks.
+    interval: Duration,
+}
+
+impl IfWatcher {
+    /// Creates a new watcher that checks for changes at least every `interval`.
+    pub(crate) fn new(interval: Duration) -> Self {
+        IfWatcher {
+            netlink: subscribe(),
+            delay: Delay::new(interval),
+            interval,
+        }
+    }
+
+    /// Creates a new watcher that is notified of changes through `rx`.
+    #[cfg(test)]
+    pub(crate) fn with_receiver(rx: mpsc::UnboundedReceiver<()>, interval: Duration) -> Self {
+        IfWatcher {
+            netlink: Some(rx),
+            delay: Delay::new(interval),
+            interval,
+        }
+    }
+
+    /// Resolves when the network interfaces may have changed.
+    pub(crate) fn poll_change(&mut self, cx: &mut Context) -> Poll<()> {
+        if let Some(rx) = self.netlink.as_mut() {
+            match rx.poll_next_unpin(cx) {
+                Poll::Ready(Some(())) => {
+                    // Coalesce the notifications that have already arrived.
+                    while let Poll::Ready(Some(())) = rx.poll_next_unpin(cx) {}
+                    self.delay.reset(self.interval);
+                    return Poll::Ready(())
+                }
+                Poll::Ready(None) => {
+                    debug!("Network interface notifications stopped; polling instead");
+                    self.netlink = None
+                }
+                Poll::Pending => {}
+            }
+        }
+
+        if Pin::new(&mut self.delay).poll(cx).is_ready() {
+            self.delay.reset(self.interval);
+            return Poll::Ready(())
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use log::debug;
+    use std::{io, mem, thread};
+
+    /// Closes the file descriptor when dropped.
+    struct Fd(libc::c_int);
+
+    impl Drop for Fd {
+        fn drop(&mut self) {
+            unsafe { libc::close(self.0); }
+        }
+    }
+
+    /// Spawns a thread reading address change notifications from a netlink socket and
+    /// forwarding them to the watchers.
+    ///
+    /// The thread stops shortly after the last watcher has been dropped.
+    pub(super) fn watch() -> io::Result<bool> {
+        let fd = unsafe {
+            libc::socket(libc::AF_NETLINK, libc::SOCK_RAW | libc::SOCK_CLOEXEC, libc::NETLINK_ROUTE)
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        let fd = Fd(fd);
+
+        let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        addr.nl_groups = (libc::RTMGRP_IPV4_IFADDR | libc::RTMGRP_IPV6_IFADDR) as u32;
+        let ret = unsafe {
+            libc::bind(
+                fd.0,
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error())
+        }
+
+        // Wake up regularly to notice when the watchers are gone.
+        let timeout = libc::timeval { tv_sec: 1, tv_usec: 0 };
+        let ret = unsafe {
+            libc::setsockopt(
+                fd.0,
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                &timeout as *const libc::timeval as *const libc::c_void,
+                mem::size_of::<libc::timeval>() as libc::socklen_t
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error())
+        }
+
+        thread::Builder::new()
+            .name("libp2p-tcp-if-watch".into())
+            .spawn(move || {
+                let mut buf = [0u8; 4096];
+                loop {
+                    let n = unsafe {
+                        libc::recv(fd.0, buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0)
+                    };
+                    if n < 0 {
+                        let err = io::Error::last_os_error();
+                        match err.kind() {
+                            io::ErrorKind::WouldBlock
+                            | io::ErrorKind::TimedOut
+                            | io::ErrorKind::Interrupted => {
+                                if !super::notify(false) {
+                                    break
+                                }
+                                continue
+                            }
+                            _ => {
+                                debug!("Failed to read from netlink socket: {}", err);
+                                super::stopped();
+                                break
+                            }
+                        }
+                    }
+                    if !super::notify(true) {
+                        break
+                    }
+                }
+            })?;
+
+        Ok(true)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use std::io;
+
+    /// Interface change notifications are not supported on this platform.
+    pub(super) fn watch() -> io::Result<bool> {
+        Ok(false)
+    }
+}
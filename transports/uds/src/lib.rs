@@ -26,7 +26,16 @@
 //!
 //! # Usage
 //!
-//! The `UdsConfig` transport supports multiaddresses of the form `/unix//tmp/foo`.
+//! The `UdsConfig` transport supports multiaddresses of the form `/unix//tmp/foo`. On Linux,
+//! addresses of the form `/unix/@foo` designate the socket `foo` of the abstract namespace,
+//! which is not bound to a file.
+//!
+//! When listening on a path, a socket file left behind by a listener that no longer exists is
+//! removed first. The permissions of the socket file can be configured with `socket_mode`, and
+//! are applied before the socket file is created at that path.
+//!
+//! The credentials of the process on the other side of a connection can be obtained with
+//! [`peer_credentials`], for instance to authorise local clients.
 //!
 //! The `UdsConfig` structs implements the `Transport` trait of the `core` library. See the
 //! documentation of `core` and of libp2p in general to learn how to use the `Transport` trait.
@@ -42,21 +51,54 @@ use libp2p_core::{
     transport::{ListenerEvent, TransportError}
 };
 use log::debug;
-use std::{io, path::PathBuf};
+use std::{
+    fs,
+    io,
+    os::unix::{fs::{DirBuilderExt, FileTypeExt, PermissionsExt}, io::AsRawFd, net},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering}
+};
 
 macro_rules! codegen {
-    ($feature_name:expr, $uds_config:ident, $build_listener:expr, $unix_stream:ty, $($mut_or_not:tt)*) => {
+    ($feature_name:expr, $uds_config:ident, $listener_from_std:expr, $stream_from_std:expr, $unix_stream:ty, $($mut_or_not:tt)*) => {
 
 /// Represents the configuration for a Unix domain sockets transport capability for libp2p.
 #[cfg_attr(docsrs, doc(cfg(feature = $feature_name)))]
 #[derive(Debug, Clone)]
 pub struct $uds_config {
+    /// Permissions to set on the socket files of listeners.
+    socket_mode: Option<u32>,
+    /// Whether to remove stale socket files before listening.
+    remove_stale_socket: bool,
 }
 
 impl $uds_config {
     /// Creates a new configuration object for Unix domain sockets.
     pub fn new() -> $uds_config {
-        $uds_config {}
+        $uds_config {
+            socket_mode: None,
+            remove_stale_socket: true,
+        }
+    }
+
+    /// Sets the permissions of the socket files created when listening, e.g. `0o600` to only
+    /// allow processes of the same user to connect.
+    ///
+    /// Defaults to the permissions derived from the umask of the process.
+    pub fn socket_mode(mut self, mode: u32) -> Self {
+        self.socket_mode = Some(mode);
+        self
+    }
+
+    /// Sets whether a socket file that no listener accepts connections on anymore is removed
+    /// before listening on its path. Enabled by default.
+    ///
+    /// Whether a listener still accepts connections is checked by connecting to the socket, so
+    /// a listener that is still running receives a connection that is closed right away.
+    pub fn remove_stale_socket(mut self, value: bool) -> Self {
+        self.remove_stale_socket = value;
+        self
     }
 }
 
@@ -68,8 +110,11 @@ impl Transport for $uds_config {
     type Dial = BoxFuture<'static, Result<Self::Output, Self::Error>>;
 
     fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
-        if let Ok(path) = multiaddr_to_path(&addr) {
-            Ok(async move { $build_listener(&path).await }
+        if let Ok(uds_addr) = multiaddr_to_uds_addr(&addr) {
+            Ok(async move {
+                    let listener = bind(&uds_addr, self.socket_mode, self.remove_stale_socket)?;
+                    $listener_from_std(listener)
+                }
                 .map_ok(move |listener| {
                     stream::once({
                         let addr = addr.clone();
@@ -102,11 +147,16 @@ impl Transport for $uds_config {
     }
 
     fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
-        if let Ok(path) = multiaddr_to_path(&addr) {
-            debug!("Dialing {}", addr);
-            Ok(async move { <$unix_stream>::connect(&path).await }.boxed())
-        } else {
-            Err(TransportError::MultiaddrNotSupported(addr))
+        match multiaddr_to_uds_addr(&addr) {
+            Ok(UdsAddr::Path(path)) => {
+                debug!("Dialing {}", addr);
+                Ok(async move { <$unix_stream>::connect(&path).await }.boxed())
+            }
+            Ok(UdsAddr::Abstract(name)) => {
+                debug!("Dialing {}", addr);
+                Ok(async move { $stream_from_std(connect_abstract(&name)?) }.boxed())
+            }
+            Err(()) => Err(TransportError::MultiaddrNotSupported(addr))
         }
     }
 }
@@ -118,18 +168,41 @@ impl Transport for $uds_config {
 codegen!(
     "async-std",
     UdsConfig,
-    |addr| async move { async_std::os::unix::net::UnixListener::bind(addr).await },
+    |listener: net::UnixListener| -> io::Result<_> { Ok(async_std::os::unix::net::UnixListener::from(listener)) },
+    |stream: net::UnixStream| -> io::Result<_> { Ok(async_std::os::unix::net::UnixStream::from(stream)) },
     async_std::os::unix::net::UnixStream,
 );
 #[cfg(feature = "tokio")]
 codegen!(
     "tokio",
     TokioUdsConfig,
-    |addr| async move { tokio::net::UnixListener::bind(addr) },
+    tokio::net::UnixListener::from_std,
+    tokio::net::UnixStream::from_std,
     tokio::net::UnixStream,
     mut
 );
 
+/// Address of a Unix domain socket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum UdsAddr {
+    /// A socket bound to a file.
+    Path(PathBuf),
+    /// A socket of the abstract namespace of Linux.
+    Abstract(Vec<u8>),
+}
+
+/// Turns a `Multiaddr` containing a single `Unix` component into a socket address.
+fn multiaddr_to_uds_addr(addr: &Multiaddr) -> Result<UdsAddr, ()> {
+    if cfg!(any(target_os = "linux", target_os = "android")) {
+        if let Some(Protocol::Unix(path)) = addr.iter().next() {
+            if path.starts_with('@') && addr.iter().nth(1).is_none() {
+                return Ok(UdsAddr::Abstract(path.as_bytes()[1 ..].to_vec()))
+            }
+        }
+    }
+    multiaddr_to_path(addr).map(UdsAddr::Path)
+}
+
 /// Turns a `Multiaddr` containing a single `Unix` component into a path.
 ///
 /// Also returns an error if the path is not absolute, as we don't want to dial/listen on relative
@@ -155,9 +228,233 @@ fn multiaddr_to_path(addr: &Multiaddr) -> Result<PathBuf, ()> {
     Ok(out)
 }
 
+/// Creates a non-blocking listener on the given address.
+fn bind(addr: &UdsAddr, mode: Option<u32>, remove_stale: bool) -> io::Result<net::UnixListener> {
+    let listener = match addr {
+        UdsAddr::Path(path) => {
+            if remove_stale {
+                remove_stale_socket(path)?;
+            }
+            match mode {
+                Some(mode) => bind_with_mode(path, mode)?,
+                None => net::UnixListener::bind(path)?
+            }
+        }
+        UdsAddr::Abstract(name) => bind_abstract(name)?
+    };
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
+
+/// Binds a listener to the socket file `path` with the permissions `mode`.
+///
+/// The socket is first bound in a directory next to `path` that only we can access, and only
+/// linked at `path` once its permissions are set, so that it is never reachable with the
+/// permissions derived from the umask.
+fn bind_with_mode(path: &Path, mode: u32) -> io::Result<net::UnixListener> {
+    static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+    let parent = path.parent()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "socket path has no parent"))?;
+    let dir = parent.join(format!(".libp2p-uds-{}-{}", process::id(), NEXT_DIR.fetch_add(1, Ordering::Relaxed)));
+    fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    let tmp = dir.join("socket");
+
+    let result = (|| {
+        let listener = net::UnixListener::bind(&tmp)?;
+        fs::set_permissions(&tmp, fs::Permissions::from_mode(mode))?;
+        // Unlike `fs::rename`, this does not replace an existing file.
+        fs::hard_link(&tmp, path).map_err(|err| match err.kind() {
+            io::ErrorKind::AlreadyExists => io::ErrorKind::AddrInUse.into(),
+            _ => err
+        })?;
+        Ok(listener)
+    })();
+
+    let _ = fs::remove_file(&tmp);
+    let _ = fs::remove_dir(&dir);
+    result
+}
+
+/// Removes the socket file at `path` if nothing accepts connections on it.
+///
+/// Only a refused connection proves that the socket is stale. If a listener is still running,
+/// it receives the connection used for this check.
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => match connect_nonblocking(path) {
+            Err(ref e) if e.kind() == io::ErrorKind::ConnectionRefused => {
+                debug!("Removing stale socket {}", path.display());
+                fs::remove_file(path)
+            }
+            _ => Ok(())
+        }
+        _ => Ok(())
+    }
+}
+
+/// Connects a non-blocking socket to the socket file `path`.
+///
+/// Connecting a blocking socket waits for room in the backlog of the listener, which may take
+/// arbitrarily long if the listener doesn't accept connections. A non-blocking socket fails
+/// with `WouldBlock` instead.
+fn connect_nonblocking(path: &Path) -> io::Result<net::UnixStream> {
+    use std::os::unix::{ffi::OsStrExt, io::FromRawFd};
+
+    let mut addr: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+    let path = path.as_os_str().as_bytes();
+    // The path is followed by a null byte.
+    if path.len() >= addr.sun_path.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "socket path too long"))
+    }
+    for (dst, src) in addr.sun_path.iter_mut().zip(path) {
+        *dst = *src as libc::c_char;
+    }
+
+    let fd = unsafe { libc::socket(libc::AF_UNIX, libc::SOCK_STREAM, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error())
+    }
+    let stream = unsafe { net::UnixStream::from_raw_fd(fd) };
+    if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } < 0 {
+        return Err(io::Error::last_os_error())
+    }
+    stream.set_nonblocking(true)?;
+    let addr_ptr = &addr as *const libc::sockaddr_un as *const libc::sockaddr;
+    let len = std::mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
+    if unsafe { libc::connect(fd, addr_ptr, len) } < 0 {
+        return Err(io::Error::last_os_error())
+    }
+    Ok(stream)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn abstract_sockaddr(name: &[u8]) -> io::Result<(libc::sockaddr_un, libc::socklen_t)> {
+    let mut addr: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+    // The name follows a leading null byte.
+    if name.len() >= addr.sun_path.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "abstract socket name too long"))
+    }
+    for (dst, src) in addr.sun_path[1 ..].iter_mut().zip(name) {
+        *dst = *src as libc::c_char;
+    }
+    let len = std::mem::size_of::<libc::sa_family_t>() + 1 + name.len();
+    Ok((addr, len as libc::socklen_t))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn unix_socket() -> io::Result<libc::c_int> {
+    let fd = unsafe { libc::socket(libc::AF_UNIX, libc::SOCK_STREAM | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error())
+    }
+    Ok(fd)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn bind_abstract(name: &[u8]) -> io::Result<net::UnixListener> {
+    use std::os::unix::io::FromRawFd;
+
+    let (addr, len) = abstract_sockaddr(name)?;
+    let listener = unsafe { net::UnixListener::from_raw_fd(unix_socket()?) };
+    let addr_ptr = &addr as *const libc::sockaddr_un as *const libc::sockaddr;
+    if unsafe { libc::bind(listener.as_raw_fd(), addr_ptr, len) } < 0 {
+        return Err(io::Error::last_os_error())
+    }
+    if unsafe { libc::listen(listener.as_raw_fd(), 128) } < 0 {
+        return Err(io::Error::last_os_error())
+    }
+    Ok(listener)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn connect_abstract(name: &[u8]) -> io::Result<net::UnixStream> {
+    use std::os::unix::io::FromRawFd;
+
+    let (addr, len) = abstract_sockaddr(name)?;
+    let stream = unsafe { net::UnixStream::from_raw_fd(unix_socket()?) };
+    let addr_ptr = &addr as *const libc::sockaddr_un as *const libc::sockaddr;
+    // Connecting to a Unix domain socket does not wait for the listener to accept.
+    if unsafe { libc::connect(stream.as_raw_fd(), addr_ptr, len) } < 0 {
+        return Err(io::Error::last_os_error())
+    }
+    stream.set_nonblocking(true)?;
+    Ok(stream)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn bind_abstract(_: &[u8]) -> io::Result<net::UnixListener> {
+    Err(io::Error::new(io::ErrorKind::Other, "abstract sockets are only supported on Linux"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn connect_abstract(_: &[u8]) -> io::Result<net::UnixStream> {
+    Err(io::Error::new(io::ErrorKind::Other, "abstract sockets are only supported on Linux"))
+}
+
+/// Credentials of the process on the other side of a Unix domain socket connection.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PeerCredentials {
+    pid: Option<u32>,
+    uid: u32,
+    gid: u32,
+}
+
+impl PeerCredentials {
+    /// Returns the process ID of the remote, if known. Only available on Linux.
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// Returns the effective user ID of the remote.
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// Returns the effective group ID of the remote.
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+}
+
+/// Returns the credentials of the process on the other side of a connection produced by this
+/// transport, as they were when the connection was established.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn peer_credentials(stream: &impl AsRawFd) -> io::Result<PeerCredentials> {
+    let mut cred: libc::ucred = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error())
+    }
+    Ok(PeerCredentials { pid: Some(cred.pid as u32), uid: cred.uid, gid: cred.gid })
+}
+
+/// Returns the credentials of the process on the other side of a connection produced by this
+/// transport, as they were when the connection was established.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn peer_credentials(stream: &impl AsRawFd) -> io::Result<PeerCredentials> {
+    let mut uid = 0;
+    let mut gid = 0;
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } < 0 {
+        return Err(io::Error::last_os_error())
+    }
+    Ok(PeerCredentials { pid: None, uid, gid })
+}
+
 #[cfg(all(test, feature = "async-std"))]
 mod tests {
-    use super::{multiaddr_to_path, UdsConfig};
+    use super::{connect_nonblocking, multiaddr_to_path, multiaddr_to_uds_addr, peer_credentials, UdsAddr, UdsConfig};
     use futures::{channel::oneshot, prelude::*};
     use std::{self, borrow::Cow, path::Path};
     use libp2p_core::{Transport, multiaddr::{Protocol, Multiaddr}};
@@ -218,6 +515,102 @@ mod tests {
         });
     }
 
+    #[test]
+    fn socket_mode_and_stale_socket_removal() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let socket = temp_dir.path().join("socket");
+        let addr = Multiaddr::from(Protocol::Unix(Cow::Owned(socket.to_string_lossy().into_owned())));
+
+        // Leave a socket file behind.
+        drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+
+        async_std::task::block_on(async move {
+            let mut listener = UdsConfig::new().remove_stale_socket(false).listen_on(addr.clone()).unwrap();
+            let err = listener.try_next().await.unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+
+            let mut listener = UdsConfig::new().socket_mode(0o600).listen_on(addr.clone()).unwrap();
+            listener.try_next().await.unwrap().expect("some event").into_new_address().expect("listen address");
+            let mode = std::fs::metadata(&socket).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+            assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+            UdsConfig::new().dial(addr.clone()).unwrap().await.unwrap();
+
+            // The socket of an active listener is not removed.
+            let mut other = UdsConfig::new().listen_on(addr.clone()).unwrap();
+            assert!(other.try_next().await.is_err());
+            let mut other = UdsConfig::new().socket_mode(0o600).remove_stale_socket(false).listen_on(addr).unwrap();
+            assert_eq!(other.try_next().await.unwrap_err().kind(), std::io::ErrorKind::AddrInUse);
+        });
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn stale_socket_check_does_not_block_on_full_backlog() {
+        use std::os::unix::io::AsRawFd;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let socket = temp_dir.path().join("socket");
+        let addr = Multiaddr::from(Protocol::Unix(Cow::Owned(socket.to_string_lossy().into_owned())));
+
+        // A listener that doesn't accept connections, whose backlog is full.
+        let listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+        assert_eq!(unsafe { libc::listen(listener.as_raw_fd(), 0) }, 0);
+        let mut pending = Vec::new();
+        loop {
+            match connect_nonblocking(&socket) {
+                Ok(stream) => pending.push(stream),
+                Err(err) => {
+                    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+                    break
+                }
+            }
+        }
+
+        async_std::task::block_on(async move {
+            let mut other = UdsConfig::new().listen_on(addr).unwrap();
+            let result = async_std::future::timeout(std::time::Duration::from_secs(5), other.try_next()).await;
+            assert_eq!(result.expect("not blocked").unwrap_err().kind(), std::io::ErrorKind::AddrInUse);
+        });
+        assert!(socket.exists());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn abstract_socket_and_peer_credentials() {
+        let name = format!("/unix/@libp2p-uds-test-{}", std::process::id());
+        let addr = name.parse::<Multiaddr>().unwrap();
+        assert_eq!(multiaddr_to_uds_addr(&addr), Ok(UdsAddr::Abstract(name[7 ..].as_bytes().to_vec())));
+
+        async_std::task::block_on(async move {
+            let mut listener = UdsConfig::new().listen_on(addr).unwrap();
+            let listen_addr = listener.try_next().await.unwrap()
+                .expect("some event")
+                .into_new_address()
+                .expect("listen address");
+
+            let mut socket = UdsConfig::new().dial(listen_addr).unwrap().await.unwrap();
+            socket.write_all(&[1, 2, 3]).await.unwrap();
+
+            let (sock, _addr) = listener.try_filter_map(|e| future::ok(e.into_upgrade()))
+                .try_next()
+                .await
+                .unwrap()
+                .expect("some event");
+            let mut sock = sock.await.unwrap();
+            let mut buf = [0u8; 3];
+            sock.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, [1, 2, 3]);
+
+            let cred = peer_credentials(&sock).unwrap();
+            assert_eq!(cred.pid(), Some(std::process::id()));
+            assert_eq!(cred.uid(), unsafe { libc::getuid() });
+            assert_eq!(cred.gid(), unsafe { libc::getgid() });
+        });
+    }
+
     #[test]
     #[ignore]       // TODO: for the moment unix addresses fail to parse
     fn larger_addr_denied() {
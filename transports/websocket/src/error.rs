@@ -31,6 +31,8 @@ pub enum Error<E> {
     Tls(tls::Error),
     /// Websocket handshake error.
     Handshake(Box<dyn error::Error + Send + Sync>),
+    /// The HTTP proxy failed to connect to the remote.
+    Proxy(Box<dyn error::Error + Send + Sync>),
     /// The configured maximum of redirects have been made.
     TooManyRedirects,
     /// A multi-address is not supported.
@@ -47,6 +49,7 @@ impl<E: fmt::Display> fmt::Display for Error<E> {
             Error::Transport(err) => write!(f, "{}", err),
             Error::Tls(err) => write!(f, "{}", err),
             Error::Handshake(err) => write!(f, "{}", err),
+            Error::Proxy(err) => write!(f, "proxy error: {}", err),
             Error::InvalidMultiaddr(ma) => write!(f, "invalid multi-address: {}", ma),
             Error::TooManyRedirects => f.write_str("too many redirects"),
             Error::InvalidRedirectLocation => f.write_str("invalid redirect location"),
@@ -61,6 +64,7 @@ impl<E: error::Error + 'static> error::Error for Error<E> {
             Error::Transport(err) => Some(err),
             Error::Tls(err) => Some(err),
             Error::Handshake(err) => Some(&**err),
+            Error::Proxy(err) => Some(&**err),
             Error::Base(err) => Some(&**err),
             Error::InvalidMultiaddr(_)
             | Error::TooManyRedirects
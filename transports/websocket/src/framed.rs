@@ -20,9 +20,10 @@
 
 use async_tls::{client, server};
 use bytes::BytesMut;
-use crate::{error::Error, tls};
+use crate::{error::Error, proxy::{self, ProxyConfig}, tls};
 use either::Either;
 use futures::{future::BoxFuture, prelude::*, ready, stream::BoxStream};
+use futures_timer::Delay;
 use libp2p_core::{
     Transport,
     either::EitherOutput,
@@ -31,12 +32,15 @@ use libp2p_core::{
 };
 use log::{debug, trace};
 use soketto::{connection, data, extension::deflate::Deflate, handshake};
-use std::{convert::TryInto, fmt, io, pin::Pin, task::Context, task::Poll};
+use std::{convert::TryInto, fmt, io, pin::Pin, task::Context, task::Poll, time::Duration};
 use url::Url;
 
 /// Max. number of payload bytes of a single frame.
 const MAX_DATA_SIZE: usize = 256 * 1024 * 1024;
 
+/// Default time to wait for a PONG after sending a PING.
+const PONG_TIMEOUT: Duration = Duration::from_secs(20);
+
 /// A Websocket transport whose output type is a [`Stream`] and [`Sink`] of
 /// frame payloads which does not implement [`AsyncRead`] or
 /// [`AsyncWrite`]. See [`crate::WsConfig`] if you require the latter.
@@ -44,9 +48,11 @@ const MAX_DATA_SIZE: usize = 256 * 1024 * 1024;
 pub struct WsConfig<T> {
     transport: T,
     max_data_size: usize,
-    tls_config: tls::Config,
+    tls_config: tls::Handle,
     max_redirects: u8,
-    use_deflate: bool
+    use_deflate: bool,
+    proxy: ProxyConfig,
+    keep_alive: KeepAliveConfig
 }
 
 impl<T> WsConfig<T> {
@@ -55,9 +61,11 @@ impl<T> WsConfig<T> {
         WsConfig {
             transport,
             max_data_size: MAX_DATA_SIZE,
-            tls_config: tls::Config::client(),
+            tls_config: tls::Handle::new(tls::Config::client()),
             max_redirects: 0,
-            use_deflate: false
+            use_deflate: false,
+            proxy: ProxyConfig::new(),
+            keep_alive: KeepAliveConfig { interval: None, timeout: PONG_TIMEOUT }
         }
     }
 
@@ -84,8 +92,36 @@ impl<T> WsConfig<T> {
     }
 
     /// Set the TLS configuration if TLS support is desired.
+    ///
+    /// This does not affect the clones of this transport, nor the listeners created
+    /// before. Use [`WsConfig::tls_handle`] to replace the configuration of those.
     pub fn set_tls_config(&mut self, c: tls::Config) -> &mut Self {
-        self.tls_config = c;
+        self.tls_config = tls::Handle::new(c);
+        self
+    }
+
+    /// Get a handle to replace the TLS configuration of this transport, its clones
+    /// and its listeners, without dropping existing listeners or connections.
+    ///
+    /// Listening on a `/wss` address requires a server configuration at the time
+    /// `listen_on` is called.
+    pub fn tls_handle(&self) -> tls::Handle {
+        self.tls_config.clone()
+    }
+
+    /// Set the interval at which PINGs are sent to the remote, or `None` to
+    /// disable them (the default).
+    ///
+    /// PINGs are only sent while the connection is being read from.
+    pub fn set_ping_interval(&mut self, interval: Option<Duration>) -> &mut Self {
+        self.keep_alive.interval = interval;
+        self
+    }
+
+    /// Set how long to wait for a PONG after sending a PING before the
+    /// connection fails with a [`io::ErrorKind::TimedOut`] error.
+    pub fn set_pong_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.keep_alive.timeout = timeout;
         self
     }
 
@@ -94,6 +130,12 @@ impl<T> WsConfig<T> {
         self.use_deflate = flag;
         self
     }
+
+    /// Set the HTTP proxies to tunnel dials through.
+    pub fn set_proxy(&mut self, proxy: ProxyConfig) -> &mut Self {
+        self.proxy = proxy;
+        self
+    }
 }
 
 type TlsOrPlain<T> = EitherOutput<EitherOutput<client::TlsStream<T>, server::TlsStream<T>>, T>;
@@ -118,7 +160,7 @@ where
 
         let (use_tls, proto) = match inner_addr.pop() {
             Some(p@Protocol::Wss(_)) =>
-                if self.tls_config.server.is_some() {
+                if self.tls_config.get().server.is_some() {
                     (true, p)
                 } else {
                     debug!("/wss address but TLS server support is not configured");
@@ -132,6 +174,7 @@ where
         };
 
         let tls_config = self.tls_config;
+        let keep_alive = self.keep_alive;
         let max_size = self.max_data_size;
         let use_deflate = self.use_deflate;
         let transport = self.transport.listen_on(inner_addr).map_err(|e| e.map(Error::Transport))?;
@@ -152,7 +195,7 @@ where
                     remote_addr = remote_addr.with(proto.clone());
                     let remote1 = remote_addr.clone(); // used for logging
                     let remote2 = remote_addr.clone(); // used for logging
-                    let tls_config = tls_config.clone();
+                    let tls_config = tls_config.get();
 
                     let upgrade = async move {
                         let stream = upgrade.map_err(Error::Transport).await?;
@@ -160,9 +203,10 @@ where
 
                         let stream =
                             if use_tls { // begin TLS session
-                                let server = tls_config
-                                    .server
-                                    .expect("for use_tls we checked server is not none");
+                                let server = tls_config.server.ok_or_else(|| {
+                                    debug!("TLS server configuration was removed; rejecting {}", remote1);
+                                    Error::Tls(tls::Error::Tls("no TLS server configuration".into()))
+                                })?;
 
                                 trace!("awaiting TLS handshake with {}", remote1);
 
@@ -212,7 +256,7 @@ where
                             let mut builder = server.into_builder();
                             builder.set_max_message_size(max_size);
                             builder.set_max_frame_size(max_size);
-                            Connection::new(builder)
+                            Connection::new(builder, keep_alive)
                         };
 
                         Ok(conn)
@@ -291,20 +335,39 @@ where
                 }
             };
 
-        let dial = self.transport.dial(inner_addr)
+        let proxy = proxy::target(&address)
+            .and_then(|(host, port)| Some((self.proxy.proxy_for(&host, use_tls)?.clone(), host, port)));
+
+        let dial_addr = match &proxy {
+            Some((proxy, ..)) => proxy.addr().clone(),
+            None => inner_addr
+        };
+
+        let dial = self.transport.dial(dial_addr)
             .map_err(|e| match e {
                 TransportError::MultiaddrNotSupported(a) => Error::InvalidMultiaddr(a),
                 TransportError::Other(e) => Error::Transport(e)
             })?;
 
-        let stream = dial.map_err(Error::Transport).await?;
+        let mut stream = dial.map_err(Error::Transport).await?;
+
+        if let Some((proxy, host, port)) = proxy {
+            trace!("connecting to {} through proxy {}", address, proxy.addr());
+            proxy::connect(&mut stream, &proxy, &host, port)
+                .map_err(|e| {
+                    debug!("proxy {} failed to connect to {}: {}", proxy.addr(), address, e);
+                    Error::Proxy(Box::new(e))
+                })
+                .await?;
+        }
+
         trace!("connected to {}", address);
 
         let stream =
             if use_tls { // begin TLS session
                 let dns_name = dns_name.expect("for use_tls we have checked that dns_name is some");
                 trace!("starting TLS handshake with {}", address);
-                let stream = self.tls_config.client.connect(&dns_name, stream)
+                let stream = self.tls_config.get().client.connect(&dns_name, stream)
                     .map_err(|e| {
                         // We should never enter here as we passed a `DNSNameRef` to `connect`.
                         debug!("invalid domain name: {:?}", dns_name);
@@ -341,7 +404,7 @@ where
             }
             handshake::ServerResponse::Accepted { .. } => {
                 trace!("websocket handshake with {} successful", address);
-                Ok(Either::Right(Connection::new(client.into_builder())))
+                Ok(Either::Right(Connection::new(client.into_builder(), self.keep_alive)))
             }
         }
     }
@@ -404,10 +467,39 @@ fn location_to_multiaddr<T>(location: &str) -> Result<Multiaddr, Error<T>> {
     }
 }
 
+/// Configuration of the PINGs sent to keep connections alive.
+#[derive(Debug, Copy, Clone)]
+struct KeepAliveConfig {
+    interval: Option<Duration>,
+    timeout: Duration
+}
+
+/// State of the PINGs sent by a connection.
+struct KeepAlive {
+    interval: Duration,
+    timeout: Duration,
+    /// Fires when the next PING is due, or when waiting for a PONG times out.
+    delay: Delay,
+    state: KeepAliveState
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum KeepAliveState {
+    /// Waiting for the next PING to be due.
+    Idle,
+    /// A PING is due but has not been sent yet.
+    Sending,
+    /// A PING has been sent but not flushed yet.
+    Flushing,
+    /// A PING has been sent and we are waiting for the PONG.
+    AwaitingPong
+}
+
 /// The websocket connection.
 pub struct Connection<T> {
     receiver: BoxStream<'static, Result<data::Incoming, connection::Error>>,
     sender: Pin<Box<dyn Sink<OutgoingData, Error = connection::Error> + Send>>,
+    keep_alive: Option<KeepAlive>,
     _marker: std::marker::PhantomData<T>
 }
 
@@ -461,7 +553,7 @@ impl<T> Connection<T>
 where
     T: AsyncRead + AsyncWrite + Send + Unpin + 'static
 {
-    fn new(builder: connection::Builder<TlsOrPlain<T>>) -> Self {
+    fn new(builder: connection::Builder<TlsOrPlain<T>>, keep_alive: KeepAliveConfig) -> Self {
         let (sender, receiver) = builder.finish();
         let sink = quicksink::make_sink(sender, |mut sender, action| async move {
             match action {
@@ -485,13 +577,56 @@ where
             }
             Ok(sender)
         });
+        let keep_alive = keep_alive.interval.map(|interval| KeepAlive {
+            interval,
+            timeout: keep_alive.timeout,
+            delay: Delay::new(interval),
+            state: KeepAliveState::Idle
+        });
         Connection {
             receiver: connection::into_stream(receiver).boxed(),
             sender: Box::pin(sink),
+            keep_alive,
             _marker: std::marker::PhantomData
         }
     }
 
+    /// Send a PING when due and check that the PONG arrives in time.
+    ///
+    /// Only returns `Poll::Ready` in case of error.
+    fn poll_keep_alive(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        let (keep_alive, sender) = match (&mut self.keep_alive, &mut self.sender) {
+            (Some(k), s) => (k, s),
+            (None, _) => return Poll::Pending
+        };
+        loop {
+            match keep_alive.state {
+                KeepAliveState::Idle => {
+                    ready!(Pin::new(&mut keep_alive.delay).poll(cx));
+                    keep_alive.state = KeepAliveState::Sending
+                }
+                KeepAliveState::Sending => {
+                    ready!(sender.as_mut().poll_ready(cx)).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                    sender.as_mut()
+                        .start_send(OutgoingData::Ping(BytesMut::new()))
+                        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                    keep_alive.state = KeepAliveState::Flushing
+                }
+                KeepAliveState::Flushing => {
+                    ready!(sender.as_mut().poll_flush(cx)).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                    trace!("sent PING");
+                    keep_alive.delay.reset(keep_alive.timeout);
+                    keep_alive.state = KeepAliveState::AwaitingPong
+                }
+                KeepAliveState::AwaitingPong => {
+                    ready!(Pin::new(&mut keep_alive.delay).poll(cx));
+                    debug!("no PONG received within {:?}", keep_alive.timeout);
+                    return Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut, "PONG timeout")))
+                }
+            }
+        }
+    }
+
     /// Send binary application data to the remote.
     pub fn send_data(&mut self, data: impl Into<BytesMut>) -> sink::Send<'_, Self, OutgoingData> {
         self.send(OutgoingData::Binary(data.into()))
@@ -515,7 +650,18 @@ where
     type Item = io::Result<IncomingData>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        if let Poll::Ready(Err(e)) = self.poll_keep_alive(cx) {
+            self.keep_alive = None;
+            return Poll::Ready(Some(Err(e)))
+        }
         let item = ready!(self.receiver.poll_next_unpin(cx));
+        if let (Some(Ok(data)), Some(keep_alive)) = (&item, &mut self.keep_alive) {
+            if data.is_pong() && keep_alive.state == KeepAliveState::AwaitingPong {
+                trace!("received PONG");
+                keep_alive.delay.reset(keep_alive.interval);
+                keep_alive.state = KeepAliveState::Idle
+            }
+        }
         let item = item.map(|result| {
             result.map(IncomingData).map_err(|e| io::Error::new(io::ErrorKind::Other, e))
         });
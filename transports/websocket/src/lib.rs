@@ -22,6 +22,7 @@
 
 pub mod error;
 pub mod framed;
+pub mod proxy;
 pub mod tls;
 
 use bytes::BytesMut;
@@ -35,7 +36,7 @@ use libp2p_core::{
     transport::{map::{MapFuture, MapStream}, ListenerEvent, TransportError}
 };
 use rw_stream_sink::RwStreamSink;
-use std::{io, pin::Pin, task::{Context, Poll}};
+use std::{io, pin::Pin, task::{Context, Poll}, time::Duration};
 
 /// A Websocket transport.
 #[derive(Debug, Clone)]
@@ -72,16 +73,45 @@ impl<T> WsConfig<T> {
     }
 
     /// Set the TLS configuration if TLS support is desired.
+    ///
+    /// This does not affect the clones of this transport, nor the listeners created
+    /// before. Use [`WsConfig::tls_handle`] to replace the configuration of those.
     pub fn set_tls_config(&mut self, c: tls::Config) -> &mut Self {
         self.transport.set_tls_config(c);
         self
     }
 
+    /// Get a handle to replace the TLS configuration of this transport, its clones
+    /// and its listeners, without dropping existing listeners or connections.
+    pub fn tls_handle(&self) -> tls::Handle {
+        self.transport.tls_handle()
+    }
+
+    /// Set the interval at which PINGs are sent to the remote, or `None` to
+    /// disable them (the default).
+    pub fn set_ping_interval(&mut self, interval: Option<Duration>) -> &mut Self {
+        self.transport.set_ping_interval(interval);
+        self
+    }
+
+    /// Set how long to wait for a PONG after sending a PING before the
+    /// connection fails.
+    pub fn set_pong_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.transport.set_pong_timeout(timeout);
+        self
+    }
+
     /// Should the deflate extension (RFC 7692) be used if supported?
     pub fn use_deflate(&mut self, flag: bool) -> &mut Self {
         self.transport.use_deflate(flag);
         self
     }
+
+    /// Set the HTTP proxies to tunnel dials through.
+    pub fn set_proxy(&mut self, proxy: proxy::ProxyConfig) -> &mut Self {
+        self.transport.set_proxy(proxy);
+        self
+    }
 }
 
 impl<T> From<framed::WsConfig<T>> for WsConfig<T> {
@@ -184,11 +214,13 @@ where
 
 #[cfg(test)]
 mod tests {
+    use async_std::net::{TcpListener, TcpStream};
     use libp2p_core::Multiaddr;
     use libp2p_tcp as tcp;
-    use futures::prelude::*;
-    use libp2p_core::{Transport, multiaddr::Protocol};
-    use super::WsConfig;
+    use futures::{channel::mpsc, prelude::*};
+    use libp2p_core::{Transport, multiaddr::Protocol, transport::{ListenerEvent, TransportError}};
+    use std::{io, time::Duration};
+    use super::{WsConfig, error::Error, framed, proxy::{Proxy, ProxyConfig}, tls};
 
     #[test]
     fn dialer_connects_to_listener_ipv4() {
@@ -232,4 +264,249 @@ mod tests {
         let (a, b) = futures::join!(inbound, outbound);
         a.and(b).unwrap();
     }
+
+    #[test]
+    fn dialer_connects_through_proxy() {
+        async_std::task::block_on(async {
+            let (proxy, mut requests) = proxy_stand_in(200).await;
+            let mut ws_config = WsConfig::new(tcp::TcpConfig::new());
+            ws_config.set_proxy(ProxyConfig::all(proxy.with_credentials("alice", "secret")));
+
+            let mut listener = ws_config.clone()
+                .listen_on("/ip4/127.0.0.1/tcp/0/ws".parse().unwrap())
+                .expect("listener");
+
+            let addr = listener.try_next().await
+                .expect("some event")
+                .expect("no error")
+                .into_new_address()
+                .expect("listen address");
+
+            let port = match addr.iter().nth(1) {
+                Some(Protocol::Tcp(port)) => port,
+                _ => panic!("no port in {}", addr)
+            };
+
+            let inbound = async move {
+                let (conn, _addr) = listener.try_filter_map(|e| future::ready(Ok(e.into_upgrade())))
+                    .try_next()
+                    .await
+                    .unwrap()
+                    .unwrap();
+                conn.await
+            };
+
+            let outbound = ws_config.dial(addr).unwrap();
+
+            let (a, b) = futures::join!(inbound, outbound);
+            a.and(b).unwrap();
+
+            let request = requests.next().await.unwrap();
+            assert!(request.starts_with(&format!("CONNECT 127.0.0.1:{} HTTP/1.1\r\n", port)));
+            assert!(request.contains("\r\nProxy-Authorization: Basic YWxpY2U6c2VjcmV0\r\n"));
+        })
+    }
+
+    #[test]
+    fn proxy_refusal_is_reported() {
+        async_std::task::block_on(async {
+            let (proxy, _requests) = proxy_stand_in(407).await;
+            let mut ws_config = WsConfig::new(tcp::TcpConfig::new());
+            ws_config.set_proxy(ProxyConfig::all(proxy));
+
+            match ws_config.dial("/ip4/127.0.0.1/tcp/1/ws".parse().unwrap()).unwrap().await {
+                Err(Error::Proxy(_)) => {}
+                Err(e) => panic!("unexpected error: {}", e),
+                Ok(_) => panic!("proxy refusal was not reported")
+            }
+        })
+    }
+
+    #[test]
+    fn pings_are_answered() {
+        async_std::task::block_on(async {
+            let mut ws_config = framed::WsConfig::new(tcp::TcpConfig::new());
+            ws_config.set_ping_interval(Some(Duration::from_millis(50)));
+            let (mut inbound, mut outbound) = framed_pair(ws_config).await;
+
+            // PINGs are answered while the connection is being read from.
+            async_std::task::spawn(async move {
+                while let Some(Ok(_)) = inbound.next().await {}
+            });
+
+            for _ in 0 .. 3 {
+                let item = outbound.next().await.expect("some item").expect("no error");
+                assert!(item.is_pong());
+            }
+        })
+    }
+
+    #[test]
+    fn missing_pong_fails_the_connection() {
+        async_std::task::block_on(async {
+            let mut ws_config = framed::WsConfig::new(tcp::TcpConfig::new());
+            ws_config.set_ping_interval(Some(Duration::from_millis(50)));
+            ws_config.set_pong_timeout(Duration::from_millis(100));
+            let (_inbound, mut outbound) = framed_pair(ws_config).await;
+
+            match outbound.next().await {
+                Some(Err(e)) => assert_eq!(e.kind(), io::ErrorKind::TimedOut),
+                other => panic!("unexpected item: {:?}", other.map(|r| r.map(|d| d.is_pong())))
+            }
+        })
+    }
+
+    #[test]
+    fn tls_config_is_selected_by_name_and_replaceable() {
+        async_std::task::block_on(async {
+            let certs = ["a.test", "b.test", "c.test"].iter()
+                .map(|name| rcgen::generate_simple_self_signed(vec![name.to_string()]).unwrap())
+                .collect::<Vec<_>>();
+            let cert = |i: usize| tls::Certificate::new(certs[i].serialize_der().unwrap());
+            let key = |i: usize| tls::PrivateKey::new(certs[i].serialize_private_key_der());
+
+            let mut builder = tls::Config::builder();
+            builder.server_for_name("a.test", key(0), vec![cert(0)]).unwrap();
+            builder.server(key(1), vec![cert(1)]).unwrap();
+            let mut ws_config = WsConfig::new(Localhost(tcp::TcpConfig::new()));
+            ws_config.set_tls_config(builder.finish());
+            let handle = ws_config.tls_handle();
+
+            let mut listener = ws_config.listen_on("/ip4/127.0.0.1/tcp/0/wss".parse().unwrap()).unwrap();
+            let addr = listener.try_next().await
+                .expect("some event")
+                .expect("no error")
+                .into_new_address()
+                .expect("listen address");
+            let port = match addr.iter().nth(1) {
+                Some(Protocol::Tcp(port)) => port,
+                _ => panic!("no port in {}", addr)
+            };
+            async_std::task::spawn(async move {
+                while let Some(event) = listener.next().await {
+                    if let Ok(ListenerEvent::Upgrade { upgrade, .. }) = event {
+                        async_std::task::spawn(async move {
+                            let _ = upgrade.await;
+                        });
+                    }
+                }
+            });
+
+            let mut builder = tls::Config::builder();
+            for i in 0 .. certs.len() {
+                builder.add_trust(&cert(i)).unwrap();
+            }
+            let mut dialer = WsConfig::new(Localhost(tcp::TcpConfig::new()));
+            dialer.set_tls_config(builder.finish());
+            let dial = |name: &str| {
+                let addr = format!("/dns4/{}/tcp/{}/wss", name, port).parse().unwrap();
+                dialer.clone().dial(addr).unwrap()
+            };
+
+            assert!(dial("a.test").await.is_ok());
+            assert!(dial("b.test").await.is_ok());
+            assert!(dial("c.test").await.is_err());
+
+            let mut builder = tls::Config::builder();
+            builder.server(key(2), vec![cert(2)]).unwrap();
+            handle.set(builder.finish());
+
+            assert!(dial("c.test").await.is_ok());
+            assert!(dial("a.test").await.is_err());
+        })
+    }
+
+    /// Open a websocket connection over TCP to ourselves, returning the
+    /// listener's and the dialer's side.
+    async fn framed_pair(ws_config: framed::WsConfig<tcp::TcpConfig>)
+        -> (framed::Connection<tcp::TcpTransStream>, framed::Connection<tcp::TcpTransStream>)
+    {
+        let mut listener = ws_config.clone()
+            .listen_on("/ip4/127.0.0.1/tcp/0/ws".parse().unwrap())
+            .expect("listener");
+
+        let addr = listener.try_next().await
+            .expect("some event")
+            .expect("no error")
+            .into_new_address()
+            .expect("listen address");
+
+        let inbound = async move {
+            let (conn, _addr) = listener.try_filter_map(|e| future::ready(Ok(e.into_upgrade())))
+                .try_next()
+                .await
+                .unwrap()
+                .unwrap();
+            conn.await
+        };
+
+        let outbound = ws_config.dial(addr).unwrap();
+
+        let (a, b) = futures::join!(inbound, outbound);
+        (a.unwrap(), b.unwrap())
+    }
+
+    /// TCP transport dialing `127.0.0.1` for all DNS names.
+    #[derive(Clone)]
+    struct Localhost(tcp::TcpConfig);
+
+    impl Transport for Localhost {
+        type Output = <tcp::TcpConfig as Transport>::Output;
+        type Error = <tcp::TcpConfig as Transport>::Error;
+        type Listener = <tcp::TcpConfig as Transport>::Listener;
+        type ListenerUpgrade = <tcp::TcpConfig as Transport>::ListenerUpgrade;
+        type Dial = <tcp::TcpConfig as Transport>::Dial;
+
+        fn listen_on(self, addr: Multiaddr) -> Result<Self::Listener, TransportError<Self::Error>> {
+            self.0.listen_on(addr)
+        }
+
+        fn dial(self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+            match addr.iter().collect::<Vec<_>>().as_slice() {
+                [Protocol::Dns4(_), Protocol::Tcp(port)] =>
+                    self.0.dial(format!("/ip4/127.0.0.1/tcp/{}", port).parse().unwrap()),
+                _ => Err(TransportError::MultiaddrNotSupported(addr))
+            }
+        }
+    }
+
+    /// Spawn an HTTP proxy which reports the requests it receives and answers them with
+    /// the given status code, tunneling the connection if it is 200.
+    async fn proxy_stand_in(status: u16) -> (Proxy, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tx, rx) = mpsc::unbounded();
+        async_std::task::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                while !request.ends_with(b"\r\n\r\n") {
+                    let mut byte = [0u8];
+                    socket.read_exact(&mut byte).await.unwrap();
+                    request.push(byte[0]);
+                }
+                let request = String::from_utf8(request).unwrap();
+                let target = request.split(' ').nth(1).unwrap().to_string();
+                tx.unbounded_send(request).unwrap();
+
+                if status != 200 {
+                    let response = format!("HTTP/1.1 {} Refused\r\nContent-Length: 0\r\n\r\n", status);
+                    socket.write_all(response.as_bytes()).await.unwrap();
+                    continue
+                }
+
+                let remote = TcpStream::connect(target).await.unwrap();
+                socket.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n").await.unwrap();
+                let (mut socket_r, mut socket_w) = socket.split();
+                let (mut remote_r, mut remote_w) = remote.split();
+                async_std::task::spawn(async move {
+                    let _ = futures::io::copy(&mut socket_r, &mut remote_w).await;
+                });
+                async_std::task::spawn(async move {
+                    let _ = futures::io::copy(&mut remote_r, &mut socket_w).await;
+                });
+            }
+        });
+        (Proxy::new(format!("/ip4/127.0.0.1/tcp/{}", port).parse().unwrap()), rx)
+    }
 }
@@ -19,7 +19,8 @@
 // DEALINGS IN THE SOFTWARE.
 
 use async_tls::{TlsConnector, TlsAcceptor};
-use std::{fmt, io, sync::Arc};
+use rustls::{sign::{self, CertifiedKey}, ResolvesServerCert, SignatureScheme};
+use std::{collections::HashMap, fmt, io, sync::{Arc, RwLock}};
 
 /// TLS configuration.
 #[derive(Clone)]
@@ -77,7 +78,7 @@ impl Config {
 
     /// Create a new TLS configuration builder.
     pub fn builder() -> Builder {
-        Builder { client: client_config(), server: None }
+        Builder { client: client_config(), server: None, server_by_name: HashMap::new() }
     }
 }
 
@@ -88,22 +89,59 @@ fn client_config() -> rustls::ClientConfig {
     client
 }
 
+/// Handle to the TLS configuration of a transport.
+///
+/// Replacing the configuration through the handle affects the connections dialed and
+/// accepted afterwards, including those accepted by listeners that already exist.
+#[derive(Debug, Clone)]
+pub struct Handle(Arc<RwLock<Config>>);
+
+impl Handle {
+    pub(crate) fn new(config: Config) -> Self {
+        Handle(Arc::new(RwLock::new(config)))
+    }
+
+    /// Get the current configuration.
+    pub fn get(&self) -> Config {
+        self.0.read().expect("lock is never poisoned").clone()
+    }
+
+    /// Replace the current configuration, e.g. after certificate renewal.
+    pub fn set(&self, config: Config) {
+        *self.0.write().expect("lock is never poisoned") = config
+    }
+}
+
 /// TLS configuration builder.
 pub struct Builder {
     client: rustls::ClientConfig,
-    server: Option<rustls::ServerConfig>
+    server: Option<CertifiedKey>,
+    server_by_name: HashMap<String, CertifiedKey>
 }
 
 impl Builder {
     /// Set server key and certificate chain.
+    ///
+    /// If certificates for specific server names have been added with
+    /// [`Builder::server_for_name`], this one is used for the other names.
     pub fn server<I>(&mut self, key: PrivateKey, certs: I) -> Result<&mut Self, Error>
     where
         I: IntoIterator<Item = Certificate>
     {
-        let mut server = rustls::ServerConfig::new(rustls::NoClientAuth::new());
-        let certs = certs.into_iter().map(|c| c.0).collect();
-        server.set_single_cert(certs, key.0).map_err(|e| Error::Tls(Box::new(e)))?;
-        self.server = Some(server);
+        self.server = Some(certified_key(key, certs)?);
+        Ok(self)
+    }
+
+    /// Set server key and certificate chain to use for connections requesting
+    /// the given server name (SNI).
+    pub fn server_for_name<I>(&mut self, name: &str, key: PrivateKey, certs: I) -> Result<&mut Self, Error>
+    where
+        I: IntoIterator<Item = Certificate>
+    {
+        let key = certified_key(key, certs)?;
+        key.cross_check_end_entity_cert(Some(dns_name_ref(name)?))
+            .map_err(|e| Error::Tls(Box::new(e)))?;
+        self.server_by_name.insert(name.to_ascii_lowercase(), key);
         Ok(self)
     }
 
@@ -115,13 +153,50 @@ impl Builder {
 
     /// Finish configuration.
     pub fn finish(self) -> Config {
+        let server =
+            if self.server.is_some() || !self.server_by_name.is_empty() {
+                let mut server = rustls::ServerConfig::new(rustls::NoClientAuth::new());
+                server.cert_resolver = Arc::new(CertResolver {
+                    default: self.server,
+                    by_name: self.server_by_name
+                });
+                Some(Arc::new(server).into())
+            } else {
+                None
+            };
         Config {
             client: Arc::new(self.client).into(),
-            server: self.server.map(|s| Arc::new(s).into())
+            server
         }
     }
 }
 
+fn certified_key<I>(key: PrivateKey, certs: I) -> Result<CertifiedKey, Error>
+where
+    I: IntoIterator<Item = Certificate>
+{
+    let certs = certs.into_iter().map(|c| c.0).collect();
+    let key = sign::any_supported_type(&key.0).map_err(|()| Error::Tls("invalid private key".into()))?;
+    Ok(CertifiedKey::new(certs, Arc::new(key)))
+}
+
+/// Selects the server certificate by the name requested by the client.
+struct CertResolver {
+    default: Option<CertifiedKey>,
+    by_name: HashMap<String, CertifiedKey>
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, name: Option<webpki::DNSNameRef>, _: &[SignatureScheme]) -> Option<CertifiedKey> {
+        name.and_then(|name| {
+                let name: &str = name.into();
+                self.by_name.get(&name.to_ascii_lowercase())
+            })
+            .or_else(|| self.default.as_ref())
+            .cloned()
+    }
+}
+
 pub(crate) fn dns_name_ref(name: &str) -> Result<webpki::DNSNameRef<'_>, Error> {
     webpki::DNSNameRef::try_from_ascii_str(name).map_err(|_| Error::InvalidDnsName(name.into()))
 }